@@ -0,0 +1,139 @@
+//! Cleanup of documentation text extracted from source comments
+
+/// Turn raw documentation text into Markdown that is safe to embed in a
+/// hover.
+///
+/// Comment delimiters (`/**`, `*/`, `///`, `//!`) and the leading `*` of
+/// JSDoc-style block lines are removed, control characters are dropped, and
+/// constructs that would otherwise swallow or restyle the rest of the hover
+/// are neutralized: raw `<` outside code is escaped so generics like
+/// `List<String>` aren't taken as HTML, lines of only `-`/`=` are escaped
+/// so they don't turn the previous line into a heading, and an unterminated
+/// code fence is closed. Intentional Markdown (emphasis, inline code,
+/// fenced blocks) is left alone.
+pub fn sanitize_doc(doc: &str) -> String {
+    let doc: String = doc
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+
+    let lines = strip_comment_syntax(&doc);
+
+    let mut out = Vec::with_capacity(lines.len());
+    let mut in_fence = false;
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push(line);
+        } else if in_fence {
+            out.push(line);
+        } else {
+            out.push(escape_line(&line));
+        }
+    }
+    if in_fence {
+        out.push("```".to_string());
+    }
+
+    // Drop blank lines left over from the delimiters
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+    let start = out.iter().position(|l| !l.trim().is_empty()).unwrap_or(out.len());
+    out[start..].join("\n")
+}
+
+/// Split into lines with block and line comment syntax removed
+fn strip_comment_syntax(doc: &str) -> Vec<String> {
+    let mut text = doc.trim();
+    let is_block = text.starts_with("/*");
+    let is_line = text.starts_with("//");
+    if is_block {
+        text = text
+            .trim_start_matches("/**")
+            .trim_start_matches("/*!")
+            .trim_start_matches("/*");
+        text = text.strip_suffix("*/").unwrap_or(text);
+        // Text on the opening line, as in `/** Summary. */`
+        text = text.trim_start_matches(' ');
+    }
+
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let body = if is_block {
+                // ` * text` continuation lines; a bare `*` is an empty line
+                trimmed
+                    .strip_prefix('*')
+                    .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            } else if is_line {
+                ["///", "//!", "//"].iter().find_map(|p| trimmed.strip_prefix(p))
+            } else {
+                None
+            };
+            match body {
+                Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+                None => line,
+            }
+            .trim_end()
+            .to_string()
+        })
+        .collect()
+}
+
+/// Escape a line outside code fences, leaving inline code spans as they are
+fn escape_line(line: &str) -> String {
+    let trimmed = line.trim();
+    if trimmed.len() >= 2 && (trimmed.chars().all(|c| c == '-') || trimmed.chars().all(|c| c == '=')) {
+        return format!("\\{}", trimmed);
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    for c in line.chars() {
+        match c {
+            '`' => {
+                in_code = !in_code;
+                out.push(c);
+            }
+            '<' if !in_code => out.push_str("&lt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsdoc_block() {
+        let doc = "/**\n * line1\n * line2\n */";
+        assert_eq!(sanitize_doc(doc), "line1\nline2");
+    }
+
+    #[test]
+    fn test_line_comments_and_indentation() {
+        let doc = "/// Adds numbers.\n///\n///     let x = add(1, 2);";
+        assert_eq!(sanitize_doc(doc), "Adds numbers.\n\n    let x = add(1, 2);");
+        // Plain docstrings keep their text untouched
+        assert_eq!(sanitize_doc("Greet someone.\n\n    name: who"), "Greet someone.\n\n    name: who");
+    }
+
+    #[test]
+    fn test_escapes_stray_markdown() {
+        let doc = "Returns a List<String> or `Option<T>`.\n---\nend\u{7}";
+        assert_eq!(
+            sanitize_doc(doc),
+            "Returns a List&lt;String> or `Option<T>`.\n\\---\nend"
+        );
+        assert_eq!(sanitize_doc("/** Single line. */"), "Single line.");
+    }
+
+    #[test]
+    fn test_closes_open_fence() {
+        let doc = "Example:\n```\nlet a = b < c;";
+        assert_eq!(sanitize_doc(doc), "Example:\n```\nlet a = b < c;\n```");
+    }
+}
@@ -119,10 +119,14 @@ impl Document {
         self.version += 1;
     }
 
-    /// Apply an incremental change
+    /// Apply an incremental change. Positions past the last line clamp to
+    /// the end of the document.
     pub fn apply_change(&mut self, range: Range, text: &str) {
-        let start_offset = self.offset_at(range.start).unwrap_or(0);
-        let end_offset = self.offset_at(range.end).unwrap_or(self.content.len());
+        let start_offset = self.offset_at(range.start).unwrap_or(self.content.len());
+        let end_offset = self
+            .offset_at(range.end)
+            .unwrap_or(self.content.len())
+            .max(start_offset);
 
         let mut new_content = String::with_capacity(
             self.content.len() - (end_offset - start_offset) + text.len(),
@@ -198,4 +202,16 @@ mod tests {
         doc.apply_change(Range::from_coords(0, 6, 0, 11), "rust");
         assert_eq!(doc.content(), "hello rust");
     }
+
+    #[test]
+    fn test_apply_change_past_end_appends() {
+        let mut doc = Document::new(
+            "test.py".to_string(),
+            "python".to_string(),
+            "a\n".to_string(),
+        );
+
+        doc.apply_change(Range::from_coords(2, 0, 2, 0), "b\n");
+        assert_eq!(doc.content(), "a\nb\n");
+    }
 }
\ No newline at end of file
@@ -1,11 +1,17 @@
 //! Logos Core - Core types and interfaces for the language service
 
 pub mod diagnostic;
+pub mod doc;
 pub mod document;
+pub mod line_index;
 pub mod position;
 pub mod symbol;
+pub mod uri;
 
 pub use diagnostic::{Diagnostic, DiagnosticSeverity};
+pub use doc::sanitize_doc;
 pub use document::Document;
+pub use line_index::LineIndex;
 pub use position::{Location, Position, Range};
-pub use symbol::{Symbol, SymbolKind};
\ No newline at end of file
+pub use symbol::{Symbol, SymbolKind};
+pub use uri::{normalize_uri, path_to_uri, uri_to_path};
\ No newline at end of file
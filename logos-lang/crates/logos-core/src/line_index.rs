@@ -0,0 +1,115 @@
+//! Precomputed line starts for fast offset/position conversion
+
+use crate::position::Position;
+use std::ops::Range as ByteRange;
+
+/// Line start offsets of a source string.
+///
+/// Columns are byte offsets within the line, matching tree-sitter points.
+/// Lines end at `\n`; a preceding `\r` is treated as part of the line
+/// terminator, so CRLF and LF sources slice the same way.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Byte offset at which each line starts
+    line_starts: Vec<usize>,
+    /// Length of the indexed text
+    len: usize,
+    /// Whether each line ends with `\r\n`
+    crlf: Vec<bool>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut line_starts = vec![0];
+        let mut crlf = Vec::new();
+        for (i, &b) in bytes.iter().enumerate() {
+            if b == b'\n' {
+                crlf.push(i > 0 && bytes[i - 1] == b'\r');
+                line_starts.push(i + 1);
+            }
+        }
+        crlf.push(false);
+        Self {
+            line_starts,
+            len: text.len(),
+            crlf,
+        }
+    }
+
+    /// Number of lines; a trailing newline starts an (empty) last line
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte range of a line's content, excluding its terminator
+    pub fn line_range(&self, line: u32) -> Option<ByteRange<usize>> {
+        let line = line as usize;
+        let start = *self.line_starts.get(line)?;
+        let end = match self.line_starts.get(line + 1) {
+            Some(&next) => next - if self.crlf[line] { 2 } else { 1 },
+            None => self.len,
+        };
+        Some(start..end)
+    }
+
+    /// Text of a line without its terminator
+    pub fn line<'a>(&self, text: &'a str, line: u32) -> Option<&'a str> {
+        self.line_range(line).map(|range| &text[range])
+    }
+
+    /// Position of a byte offset; offsets past the end clamp to it
+    pub fn position_of(&self, offset: usize) -> Position {
+        let offset = offset.min(self.len);
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        Position::new(line as u32, (offset - self.line_starts[line]) as u32)
+    }
+
+    /// Byte offset of a position. Columns past the end of the line clamp to
+    /// the line's end; lines past the end of the text give `None`.
+    pub fn offset_of(&self, position: Position) -> Option<usize> {
+        let range = self.line_range(position.line)?;
+        Some((range.start + position.column as usize).min(range.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference conversion by scanning from the start of the text
+    fn naive_position(text: &str, offset: usize) -> Position {
+        let before = &text[..offset];
+        let line = before.matches('\n').count();
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Position::new(line as u32, (offset - line_start) as u32)
+    }
+
+    #[test]
+    fn test_conversions_match_naive_scan() {
+        let text = "fn main() {\r\n    let x = 1;\r\n\r\n    println!(\"é\");\n}";
+        let index = LineIndex::new(text);
+
+        for (offset, _) in text.char_indices() {
+            let position = index.position_of(offset);
+            assert_eq!(position, naive_position(text, offset), "offset {}", offset);
+            // Terminator bytes clamp back to the end of the line's content
+            if !matches!(text.as_bytes()[offset], b'\r' | b'\n') {
+                assert_eq!(index.offset_of(position), Some(offset));
+            }
+        }
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(index.line_count(), lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(index.line(text, i as u32), Some(*line));
+        }
+
+        assert_eq!(index.offset_of(Position::new(0, 99)), Some(11));
+        assert_eq!(index.offset_of(Position::new(9, 0)), None);
+        assert_eq!(index.position_of(text.len() + 5), index.position_of(text.len()));
+    }
+}
@@ -0,0 +1,160 @@
+//! Conversion between file paths and `file:` URIs
+
+use std::path::{Path, PathBuf};
+
+/// The `file:` URI for a path, in the three-slash form editors send
+/// (`file:///home/me/a.py`, `file:///C:/Users/me/a.py`). Bytes other than
+/// unreserved characters, `/` and a drive letter's `:` are percent-encoded.
+/// Windows paths may use either separator; UNC paths (`\\server\share`)
+/// become `file://server/share`.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let (authority, path) = if let Some(unc) = path.strip_prefix(r"\\") {
+        let unc = unc.replace('\\', "/");
+        match unc.split_once('/') {
+            Some((host, rest)) => (host.to_string(), format!("/{}", rest)),
+            None => (unc, String::new()),
+        }
+    } else if drive_letter(&path).is_some() {
+        (String::new(), format!("/{}", path.replace('\\', "/")))
+    } else {
+        (String::new(), path.into_owned())
+    };
+
+    // The drive letter's colon in `/C:/...` stays as is
+    let drive_colon = path.starts_with('/') && drive_letter(&path[1..]).is_some();
+    let mut uri = format!("file://{}", authority);
+    for (i, byte) in path.bytes().enumerate() {
+        let keep = byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'/')
+            || (byte == b':' && i == 2 && drive_colon);
+        if keep {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// The path a `file:` URI names, or `None` for other schemes. Percent
+/// escapes are decoded, the slash before a Windows drive letter is dropped
+/// (`file:///c%3A/foo` is `c:/foo`, with `\` separators on Windows), and a
+/// host other than `localhost` gives a UNC path.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, ""),
+    };
+    let path = percent_decode(path);
+
+    let path = if !host.is_empty() && host != "localhost" {
+        format!(r"\\{}{}", host, path.replace('/', "\\"))
+    } else {
+        match path.strip_prefix('/') {
+            Some(windows) if drive_letter(windows).is_some() => {
+                if cfg!(windows) {
+                    windows.replace('/', "\\")
+                } else {
+                    windows.to_string()
+                }
+            }
+            _ => path,
+        }
+    };
+    Some(PathBuf::from(path))
+}
+
+/// The form `path_to_uri` gives for the file a `file:` URI names, so
+/// `file:///c%3A/a.py` and `file:///c:/a.py` map to the same key. Other
+/// URIs are returned unchanged.
+pub fn normalize_uri(uri: &str) -> String {
+    match uri_to_path(uri) {
+        Some(path) => path_to_uri(&path),
+        None => uri.to_string(),
+    }
+}
+
+/// The drive letter of a path like `C:`, `C:\foo` or `c:/foo`
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+    let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    match chars.next() {
+        None | Some('/') | Some('\\') => Some(letter),
+        _ => None,
+    }
+}
+
+/// Decode `%XX` escapes; malformed escapes are kept as written
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_posix_round_trip() {
+        let path = Path::new("/home/me/My Projects/naïve#1.py");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/me/My%20Projects/na%C3%AFve%231.py");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+        assert_eq!(uri_to_path("file://localhost/tmp/a.py").unwrap(), Path::new("/tmp/a.py"));
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn test_windows_round_trip() {
+        let uri = "file:///C:/Users/me/My%20Docs/app.ts";
+        assert_eq!(path_to_uri(Path::new(r"C:\Users\me\My Docs\app.ts")), uri);
+        assert_eq!(path_to_uri(Path::new("C:/Users/me/My Docs/app.ts")), uri);
+
+        let path = uri_to_path(uri).unwrap();
+        let expected = if cfg!(windows) { r"C:\Users\me\My Docs\app.ts" } else { "C:/Users/me/My Docs/app.ts" };
+        assert_eq!(path, Path::new(expected));
+        assert_eq!(path_to_uri(&path), uri);
+
+        // VS Code escapes the drive colon
+        let path = uri_to_path("file:///c%3A/src/main.rs").unwrap();
+        assert_eq!(path_to_uri(&path), "file:///c:/src/main.rs");
+    }
+
+    #[test]
+    fn test_normalize_uri() {
+        assert_eq!(normalize_uri("file:///c%3A/src/main.rs"), "file:///c:/src/main.rs");
+        assert_eq!(normalize_uri("file:///c:/src/main.rs"), "file:///c:/src/main.rs");
+        assert_eq!(normalize_uri("file:///tmp/My Docs/a.py"), "file:///tmp/My%20Docs/a.py");
+        assert_eq!(normalize_uri("untitled:Untitled-1"), "untitled:Untitled-1");
+    }
+
+    #[test]
+    fn test_unc_paths() {
+        let uri = path_to_uri(Path::new(r"\\server\share\a b.txt"));
+        assert_eq!(uri, "file://server/share/a%20b.txt");
+        assert_eq!(uri_to_path(&uri).unwrap(), Path::new(r"\\server\share\a b.txt"));
+    }
+}
@@ -1,7 +1,10 @@
-//! Analysis handlers: TODO items, unused symbols
+//! Analysis handlers: TODO items, unused symbols, scope tree, complexity
 
 use serde_json::{json, Value};
-use logos_index::TodoKind;
+use logos_core::{Symbol, SymbolKind};
+use logos_index::{TodoKind, TodoQuery};
+use logos_semantic::complexity;
+use logos_semantic::scope::ScopeTree;
 
 use crate::protocol::{DocumentSymbolParams, RequestId, Response};
 use crate::state::State;
@@ -46,8 +49,25 @@ pub fn get_todo_items(state: &State, params: &Value, id: Option<RequestId>) -> R
 }
 
 /// Handle logos/getAllTodoItems
-pub fn get_all_todo_items(state: &State, id: Option<RequestId>) -> Response {
-    let todos = state.todo_index.get_all_todos();
+///
+/// Optional `pathPrefix`, `kinds` and `assignee` params narrow the result,
+/// which is then ordered by file and line rather than by priority.
+pub fn get_all_todo_items(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let todos = if params.is_null() {
+        state.todo_index.get_all_todos()
+    } else {
+        let query: TodoQuery = match serde_json::from_value(params.clone()) {
+            Ok(q) => q,
+            Err(e) => {
+                return Response::error(
+                    id,
+                    crate::protocol::error_codes::INVALID_PARAMS,
+                    format!("Invalid getAllTodoItems params: {}", e),
+                );
+            }
+        };
+        state.todo_index.query(&query)
+    };
 
     let items: Vec<_> = todos.iter().map(|(uri, todo)| {
         json!({
@@ -150,6 +170,113 @@ pub fn get_unused_symbols(state: &State, params: &Value, id: Option<RequestId>)
     Response::success(id, json!(items))
 }
 
+/// Handle logos/getScopeTree
+///
+/// Debugging aid: dumps the scope tree built from the document's symbols
+/// and comprehensions, with the names bound in each scope.
+pub fn get_scope_tree(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getScopeTree params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let tree = match (state.get_document(uri), state.document_tree(uri)) {
+        (Some(doc), Some((language, syntax))) => {
+            let symbols = logos_parser::extract_symbols(language, &syntax, doc.content());
+            ScopeTree::from_syntax(&symbols, &syntax, doc.content(), language)
+        }
+        _ => ScopeTree::from_symbols(&[]),
+    };
+
+    match tree.root() {
+        Some(root) => Response::success(id, scope_json(&tree, root)),
+        None => Response::null_result(id),
+    }
+}
+
+fn scope_json(tree: &ScopeTree, scope_id: usize) -> Value {
+    let Some(scope) = tree.get_scope(scope_id) else {
+        return Value::Null;
+    };
+
+    json!({
+        "name": scope.name,
+        "range": {
+            "start": {
+                "line": scope.range.start.line,
+                "character": scope.range.start.column
+            },
+            "end": {
+                "line": scope.range.end.line,
+                "character": scope.range.end.column
+            }
+        },
+        "bindings": scope.bindings,
+        "children": scope.children.iter().map(|&child| scope_json(tree, child)).collect::<Vec<_>>()
+    })
+}
+
+/// Handle logos/getComplexity
+///
+/// Cyclomatic complexity of every function and method in the document.
+pub fn get_complexity(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getComplexity params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let (doc, (language, tree)) = match (state.get_document(uri), state.document_tree(uri)) {
+        (Some(doc), Some(parsed)) => (doc, parsed),
+        _ => return Response::success(id, json!([])),
+    };
+
+    let symbols = logos_parser::extract_symbols(language, &tree, doc.content());
+    let mut functions = Vec::new();
+    collect_functions(&symbols, &mut functions);
+
+    let items: Vec<_> = functions.iter().map(|symbol| {
+        json!({
+            "name": symbol.name,
+            "complexity": complexity::cyclomatic(symbol, &tree, doc.content()),
+            "range": {
+                "start": {
+                    "line": symbol.selection_range.start.line,
+                    "character": symbol.selection_range.start.column
+                },
+                "end": {
+                    "line": symbol.selection_range.end.line,
+                    "character": symbol.selection_range.end.column
+                }
+            }
+        })
+    }).collect();
+
+    Response::success(id, json!(items))
+}
+
+fn collect_functions(symbols: &[Symbol], out: &mut Vec<Symbol>) {
+    for symbol in symbols {
+        if matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor) {
+            out.push(symbol.clone());
+        }
+        collect_functions(&symbol.children, out);
+    }
+}
+
 fn todo_kind_to_string(kind: TodoKind) -> &'static str {
     match kind {
         TodoKind::Todo => "todo",
@@ -162,3 +289,69 @@ fn todo_kind_to_string(kind: TodoKind) -> &'static str {
         TodoKind::Custom => "custom",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_todo_items_path_prefix_filter() {
+        let mut state = State::new();
+        state.open_document(
+            "file:///repo/src/auth/login.py".to_string(),
+            "python".to_string(),
+            "# FIXME: check expiry\n".to_string(),
+        );
+        state.open_document(
+            "file:///repo/src/billing/invoice.py".to_string(),
+            "python".to_string(),
+            "# FIXME: rounding\n".to_string(),
+        );
+
+        let params = json!({ "pathPrefix": "src/auth/", "kinds": ["fixme"] });
+        let result = get_all_todo_items(&state, &params, None).result.unwrap();
+        let items = result.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["uri"], "file:///repo/src/auth/login.py");
+
+        let all = get_all_todo_items(&state, &Value::Null, None).result.unwrap();
+        assert_eq!(all.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_scope_tree_nests_function_locals() {
+        let mut state = State::new();
+        let uri = "file:///calc.py".to_string();
+        let source = "def compute():\n    total = 1\n    return total\n";
+        state.open_document(uri.clone(), "python".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let root = get_scope_tree(&state, &params, None).result.unwrap();
+
+        assert_eq!(root["bindings"], json!(["compute"]));
+        let children = root["children"].as_array().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0]["name"], "compute");
+        assert_eq!(children[0]["bindings"], json!(["total"]));
+        assert_eq!(children[0]["range"]["start"]["line"], 0);
+    }
+
+    #[test]
+    fn test_get_complexity_per_function() {
+        let mut state = State::new();
+        let uri = "file:///shapes.py";
+        let source = "class Shape:\n    def area(self):\n        return 0\n\ndef pick(a, b):\n    if a:\n        return a\n    while b:\n        b -= 1\n    return b\n";
+        state.open_document(uri.to_string(), "python".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = get_complexity(&state, &params, None).result.unwrap();
+        let complexities: Vec<_> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| (f["name"].as_str().unwrap(), f["complexity"].as_u64().unwrap()))
+            .collect();
+
+        assert_eq!(complexities, vec![("area", 1), ("pick", 3)]);
+    }
+}
@@ -1,9 +1,12 @@
 //! Call Hierarchy handler (LSP 3.16+)
 //!
-//! Provides incoming/outgoing call hierarchy for Smart mode.
+//! Provides incoming/outgoing call hierarchy. Smart mode answers across
+//! the project from the call graph; Basic mode scans the open document and
+//! only knows about calls within that file.
 
 use serde_json::{json, Value};
-use logos_core::Position;
+use logos_core::{Position, Range, Symbol, SymbolKind};
+use logos_index::{ProjectIndex, SmartSymbol, SymbolId};
 
 use crate::protocol::{
     CallHierarchyItem, CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams,
@@ -25,9 +28,16 @@ pub fn handle_prepare(state: &State, params: &Value, id: Option<RequestId>) -> R
         }
     };
 
-    // Smart mode required for call hierarchy
+    let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
+
     if !state.is_smart_mode() {
-        return Response::success(id, json!(null));
+        let callables = basic_callables(state, uri);
+        let items: Vec<CallHierarchyItem> = enclosing_callable(&callables, position)
+            .map(|i| basic_item(uri, &callables[i]))
+            .into_iter()
+            .collect();
+        return Response::success(id, json!(items));
     }
 
     let indexer = match state.get_indexer() {
@@ -35,9 +45,6 @@ pub fn handle_prepare(state: &State, params: &Value, id: Option<RequestId>) -> R
         None => return Response::success(id, json!(null)),
     };
 
-    let uri = &params.text_document.uri;
-    let position = Position::new(params.position.line, params.position.character);
-
     // Find symbol at position
     let index = indexer.get_index();
     let symbols = index.symbols.find_by_name(""); // Get all symbols
@@ -58,7 +65,7 @@ pub fn handle_prepare(state: &State, params: &Value, id: Option<RequestId>) -> R
                 uri: s.location.uri.clone(),
                 range: range_to_serializable(&s.location.range),
                 selection_range: range_to_serializable(&s.location.selection_range),
-                data: Some(json!({ "symbolId": s.id.0 })),
+                data: Some(item_data(s)),
             };
             Response::success(id, json!([item]))
         }
@@ -80,7 +87,7 @@ pub fn handle_incoming_calls(state: &State, params: &Value, id: Option<RequestId
     };
 
     if !state.is_smart_mode() {
-        return Response::success(id, json!([]));
+        return Response::success(id, json!(basic_incoming_calls(state, &params.item)));
     }
 
     let indexer = match state.get_indexer() {
@@ -88,22 +95,13 @@ pub fn handle_incoming_calls(state: &State, params: &Value, id: Option<RequestId
         None => return Response::success(id, json!([])),
     };
 
-    // Get symbol ID from item data
-    let symbol_id = params
-        .item
-        .data
-        .as_ref()
-        .and_then(|d| d.get("symbolId"))
-        .and_then(|v| v.as_u64())
-        .map(logos_index::SymbolId);
+    let index = indexer.get_index();
 
-    let symbol_id = match symbol_id {
+    let symbol_id = match item_symbol_id(&index, params.item.data.as_ref()) {
         Some(id) => id,
         None => return Response::success(id, json!([])),
     };
 
-    let index = indexer.get_index();
-
     // Find callers
     let callers = index.call_graph.get_callers(symbol_id);
 
@@ -119,7 +117,7 @@ pub fn handle_incoming_calls(state: &State, params: &Value, id: Option<RequestId
                     uri: caller.location.uri.clone(),
                     range: range_to_serializable(&caller.location.range),
                     selection_range: range_to_serializable(&caller.location.selection_range),
-                    data: Some(json!({ "symbolId": caller.id.0 })),
+                    data: Some(item_data(&caller)),
                 },
                 from_ranges: vec![range_to_serializable(&call_site.location.range)],
             })
@@ -143,7 +141,7 @@ pub fn handle_outgoing_calls(state: &State, params: &Value, id: Option<RequestId
     };
 
     if !state.is_smart_mode() {
-        return Response::success(id, json!([]));
+        return Response::success(id, json!(basic_outgoing_calls(state, &params.item)));
     }
 
     let indexer = match state.get_indexer() {
@@ -151,22 +149,13 @@ pub fn handle_outgoing_calls(state: &State, params: &Value, id: Option<RequestId
         None => return Response::success(id, json!([])),
     };
 
-    // Get symbol ID from item data
-    let symbol_id = params
-        .item
-        .data
-        .as_ref()
-        .and_then(|d| d.get("symbolId"))
-        .and_then(|v| v.as_u64())
-        .map(logos_index::SymbolId);
+    let index = indexer.get_index();
 
-    let symbol_id = match symbol_id {
+    let symbol_id = match item_symbol_id(&index, params.item.data.as_ref()) {
         Some(id) => id,
         None => return Response::success(id, json!([])),
     };
 
-    let index = indexer.get_index();
-
     // Find callees
     let callees = index.call_graph.get_callees(symbol_id);
 
@@ -182,7 +171,7 @@ pub fn handle_outgoing_calls(state: &State, params: &Value, id: Option<RequestId
                     uri: callee.location.uri.clone(),
                     range: range_to_serializable(&callee.location.range),
                     selection_range: range_to_serializable(&callee.location.selection_range),
-                    data: Some(json!({ "symbolId": callee.id.0 })),
+                    data: Some(item_data(&callee)),
                 },
                 from_ranges: vec![range_to_serializable(&call_site.location.range)],
             })
@@ -192,6 +181,125 @@ pub fn handle_outgoing_calls(state: &State, params: &Value, id: Option<RequestId
     Response::success(id, json!(outgoing_calls))
 }
 
+/// Functions and methods of an open document, parents before the
+/// functions nested in them
+fn basic_callables(state: &State, uri: &str) -> Vec<Symbol> {
+    fn collect(symbols: Vec<Symbol>, out: &mut Vec<Symbol>) {
+        for mut symbol in symbols {
+            let children = std::mem::take(&mut symbol.children);
+            if matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor) {
+                out.push(symbol);
+            }
+            collect(children, out);
+        }
+    }
+    let mut callables = Vec::new();
+    collect(state.document_symbols(uri), &mut callables);
+    callables
+}
+
+/// Index of the innermost callable whose body contains `position`
+fn enclosing_callable(callables: &[Symbol], position: Position) -> Option<usize> {
+    // Nested functions come after their parents, so the last match is innermost
+    callables.iter().rposition(|c| c.range.contains(position))
+}
+
+/// Index of the callable an item was prepared from
+fn item_callable(callables: &[Symbol], item: &CallHierarchyItem) -> Option<usize> {
+    let selection = range_from_serializable(&item.selection_range);
+    callables.iter().position(|c| c.name == item.name && c.selection_range == selection)
+}
+
+fn basic_item(uri: &str, symbol: &Symbol) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: symbol.name.clone(),
+        kind: symbol_kind_to_lsp(symbol.kind),
+        detail: symbol.detail.clone(),
+        uri: uri.to_string(),
+        range: range_to_serializable(&symbol.range),
+        selection_range: range_to_serializable(&symbol.selection_range),
+        data: None,
+    }
+}
+
+/// Callers of `item` within its own file, one entry per calling function
+fn basic_incoming_calls(state: &State, item: &CallHierarchyItem) -> Vec<CallHierarchyIncomingCall> {
+    let callables = basic_callables(state, &item.uri);
+    let mut callers: Vec<(usize, Vec<SerializableRange>)> = Vec::new();
+    for (name, range) in state.document_calls(&item.uri) {
+        if name != item.name {
+            continue;
+        }
+        if let Some(caller) = enclosing_callable(&callables, range.start) {
+            match callers.iter_mut().find(|(i, _)| *i == caller) {
+                Some((_, ranges)) => ranges.push(range_to_serializable(&range)),
+                None => callers.push((caller, vec![range_to_serializable(&range)])),
+            }
+        }
+    }
+    callers
+        .into_iter()
+        .map(|(caller, from_ranges)| CallHierarchyIncomingCall {
+            from: basic_item(&item.uri, &callables[caller]),
+            from_ranges,
+        })
+        .collect()
+}
+
+/// Functions in the same file that `item` calls directly (not from
+/// functions nested inside it), one entry per callee
+fn basic_outgoing_calls(state: &State, item: &CallHierarchyItem) -> Vec<CallHierarchyOutgoingCall> {
+    let callables = basic_callables(state, &item.uri);
+    let caller = match item_callable(&callables, item) {
+        Some(i) => i,
+        None => return Vec::new(),
+    };
+    let mut callees: Vec<(usize, Vec<SerializableRange>)> = Vec::new();
+    for (name, range) in state.document_calls(&item.uri) {
+        if enclosing_callable(&callables, range.start) != Some(caller) {
+            continue;
+        }
+        if let Some(callee) = callables.iter().position(|c| c.name == name) {
+            match callees.iter_mut().find(|(i, _)| *i == callee) {
+                Some((_, ranges)) => ranges.push(range_to_serializable(&range)),
+                None => callees.push((callee, vec![range_to_serializable(&range)])),
+            }
+        }
+    }
+    callees
+        .into_iter()
+        .map(|(callee, from_ranges)| CallHierarchyOutgoingCall {
+            to: basic_item(&item.uri, &callables[callee]),
+            from_ranges,
+        })
+        .collect()
+}
+
+/// `data` for a call hierarchy item: the id for this session and a
+/// descriptor that survives reindexing
+fn item_data(symbol: &SmartSymbol) -> Value {
+    json!({ "symbolId": symbol.id.0, "descriptor": symbol.descriptor() })
+}
+
+/// The symbol an item refers to. The id is only trusted while it still
+/// names the symbol the descriptor describes, since ids are reused after
+/// reindexing; otherwise the descriptor is looked up.
+fn item_symbol_id(index: &ProjectIndex, data: Option<&Value>) -> Option<SymbolId> {
+    let data = data?;
+    let descriptor = data.get("descriptor").and_then(|v| v.as_str());
+    let by_id = data
+        .get("symbolId")
+        .and_then(|v| v.as_u64())
+        .map(SymbolId)
+        .filter(|id| {
+            index
+                .symbols
+                .get(*id)
+                .is_some_and(|s| descriptor.is_none_or(|d| s.descriptor() == d))
+        });
+    by_id.or_else(|| index.symbols.find_by_descriptor(descriptor?).map(|s| s.id))
+}
+
 fn range_to_serializable(range: &logos_core::Range) -> SerializableRange {
     SerializableRange {
         start: SerializablePosition {
@@ -205,6 +313,15 @@ fn range_to_serializable(range: &logos_core::Range) -> SerializableRange {
     }
 }
 
+fn range_from_serializable(range: &SerializableRange) -> Range {
+    Range::from_coords(
+        range.start.line,
+        range.start.character,
+        range.end.line,
+        range.end.character,
+    )
+}
+
 fn symbol_kind_to_lsp(kind: logos_core::SymbolKind) -> i32 {
     use logos_core::SymbolKind;
     match kind {
@@ -236,3 +353,66 @@ fn symbol_kind_to_lsp(kind: logos_core::SymbolKind) -> i32 {
         SymbolKind::TypeParameter => 26,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare(state: &State, uri: &str, line: u32, character: u32) -> Value {
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character }
+        });
+        handle_prepare(state, &params, None).result.unwrap()
+    }
+
+    #[test]
+    fn test_basic_mode_same_file_calls() {
+        let mut state = State::new();
+        let uri = "file:///app.py".to_string();
+        let source = "def load():\n    return 1\n\ndef main():\n    load()\n    load()\n    print('done')\n";
+        state.open_document(uri.clone(), "python".to_string(), source.to_string());
+
+        let items = prepare(&state, &uri, 3, 5);
+        assert_eq!(items.as_array().unwrap().len(), 1);
+        assert_eq!(items[0]["name"], "main");
+
+        let params = json!({ "item": items[0] });
+        let outgoing = handle_outgoing_calls(&state, &params, None).result.unwrap();
+        let outgoing = outgoing.as_array().unwrap();
+        // print isn't defined in the file
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0]["to"]["name"], "load");
+        assert_eq!(outgoing[0]["fromRanges"].as_array().unwrap().len(), 2);
+        assert_eq!(outgoing[0]["fromRanges"][0]["start"], json!({ "line": 4, "character": 4 }));
+
+        let load = prepare(&state, &uri, 0, 5);
+        let params = json!({ "item": load[0] });
+        let incoming = handle_incoming_calls(&state, &params, None).result.unwrap();
+        assert_eq!(incoming.as_array().unwrap().len(), 1);
+        assert_eq!(incoming[0]["from"]["name"], "main");
+    }
+
+    #[test]
+    fn test_stale_symbol_id_is_checked_against_descriptor() {
+        use crate::state::{path_to_uri, uri_to_root};
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.py"), "def load():\n    return 1\n\ndef main():\n    load()\n").unwrap();
+        let mut state = State::new();
+        state.add_workspace_root(uri_to_root(&path_to_uri(dir.path())));
+        state.enable_smart_mode().unwrap();
+
+        let index = state.get_indexer().unwrap().get_index();
+        let uri = path_to_uri(&dir.path().join("app.py"));
+        let symbols = index.symbols.get_file_symbols(&uri);
+        let load = symbols.iter().find(|s| s.name == "load").unwrap();
+        let main = symbols.iter().find(|s| s.name == "main").unwrap();
+
+        // An id that now belongs to another symbol is not trusted
+        let data = json!({ "symbolId": load.id.0, "descriptor": main.descriptor() });
+        assert_eq!(item_symbol_id(&index, Some(&data)), Some(main.id));
+        let data = json!({ "symbolId": load.id.0, "descriptor": load.descriptor() });
+        assert_eq!(item_symbol_id(&index, Some(&data)), Some(load.id));
+    }
+}
@@ -1,14 +1,24 @@
 //! Completion handler
 
+use std::collections::HashMap;
+
 use serde_json::{json, Value};
-use logos_core::SymbolKind;
+use logos_core::{Position, SymbolKind};
+use logos_index::{ProjectIndex, SmartSymbol, Visibility};
+use logos_semantic::scope::ScopeTree;
+
+use crate::protocol::{CompletionParams, RequestId, Response};
+use crate::state::{uri_to_path, State};
 
-use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
-use crate::state::State;
+/// A ranked completion candidate; lower rank sorts first
+struct Candidate {
+    rank: usize,
+    item: Value,
+}
 
 /// Handle textDocument/completion
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
-    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+    let params: CompletionParams = match serde_json::from_value(params.clone()) {
         Ok(p) => p,
         Err(e) => {
             return Response::error(
@@ -20,6 +30,7 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     };
 
     let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
 
     let doc = match state.get_document(uri) {
         Some(d) => d,
@@ -28,7 +39,51 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
         }
     };
 
-    let mut completions = Vec::new();
+    // Trigger characters are shared by all languages, so `>` in `a > b` or
+    // `:` in a Python slice only complete when they end a member accessor
+    let trigger = params.context.as_ref().and_then(|c| c.trigger_character.as_deref());
+    if let Some(trigger) = trigger {
+        if !ends_accessor(doc.content(), position, &doc.language_id, trigger) {
+            return Response::success(id, json!({ "isIncomplete": false, "items": [] }));
+        }
+    }
+
+    // `obj.|` (or `Type::|`, `ptr->|`) completes the receiver's members (Smart mode)
+    if let Some(receiver) = member_access_receiver(doc.content(), position, &doc.language_id) {
+        if let Some(indexer) = state.get_indexer() {
+            if let Some(items) = member_completions(&indexer.index, uri, position, &receiver) {
+                return Response::success(id, json!({
+                    "isIncomplete": false,
+                    "items": items
+                }));
+            }
+        }
+    }
+
+    // Candidates keyed by label so the nearest definition wins
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+
+    // File symbols, ranked by distance from the scope at the cursor;
+    // another function's locals are not visible
+    let symbols = state.document_symbols(uri);
+    let tree = ScopeTree::from_symbols(&symbols);
+    let chain = tree.scope_chain(position);
+    tree.visit_visible(&symbols, &chain, &mut |symbol, _, distance| {
+        add_candidate(&mut candidates, &symbol.name, symbol.kind, distance);
+    });
+
+    // Symbols exported by imported files (Smart mode)
+    let imported_rank = chain.len();
+    if let (Some(indexer), Some(path)) = (state.get_indexer(), uri_to_path(uri)) {
+        let index = &indexer.index;
+        for file in index.dependencies.get_imports(&path) {
+            for symbol_id in index.dependencies.get_exports(&file) {
+                if let Some(symbol) = index.symbols.get(symbol_id) {
+                    add_candidate(&mut candidates, &symbol.name, symbol.kind, imported_rank);
+                }
+            }
+        }
+    }
 
     // Add keyword completions based on language
     let keywords = match doc.language_id.as_str() {
@@ -43,22 +98,29 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
         _ => &[],
     };
 
+    let keyword_rank = imported_rank + 1;
     for kw in keywords {
-        completions.push(json!({
-            "label": kw,
-            "kind": 14, // Keyword
-            "detail": "keyword"
-        }));
+        candidates.entry(kw.to_string()).or_insert_with(|| Candidate {
+            rank: keyword_rank,
+            item: json!({
+                "label": kw,
+                "kind": 14, // Keyword
+                "detail": "keyword"
+            }),
+        });
     }
 
-    // Add symbols from index
-    for symbol in state.symbol_index.get_document_symbols(uri) {
-        completions.push(json!({
-            "label": symbol.name,
-            "kind": symbol_kind_to_completion_kind(symbol.kind),
-            "detail": format!("{:?}", symbol.kind)
-        }));
-    }
+    let mut ranked: Vec<(String, Candidate)> = candidates.into_iter().collect();
+    ranked.sort_by(|(a_label, a), (b_label, b)| a.rank.cmp(&b.rank).then_with(|| a_label.cmp(b_label)));
+
+    let completions: Vec<Value> = ranked
+        .into_iter()
+        .map(|(label, mut candidate)| {
+            // sortText keeps the client from reordering our ranking
+            candidate.item["sortText"] = json!(format!("{:02}_{}", candidate.rank, label));
+            candidate.item
+        })
+        .collect();
 
     Response::success(id, json!({
         "isIncomplete": false,
@@ -66,17 +128,341 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     }))
 }
 
+fn add_candidate(
+    candidates: &mut HashMap<String, Candidate>,
+    name: &str,
+    kind: SymbolKind,
+    rank: usize,
+) {
+    if candidates.get(name).is_some_and(|existing| existing.rank <= rank) {
+        return;
+    }
+
+    candidates.insert(name.to_string(), Candidate {
+        rank,
+        item: json!({
+            "label": name,
+            "kind": symbol_kind_to_completion_kind(kind),
+            "detail": format!("{:?}", kind)
+        }),
+    });
+}
+
+/// Tokens that separate a receiver from its member in a language
+fn member_accessors(language_id: &str) -> &'static [&'static str] {
+    match language_id {
+        "rust" => &["::", "."],
+        "cpp" => &["::", "->", "."],
+        "c" => &["->", "."],
+        _ => &["."],
+    }
+}
+
+/// Whether `trigger`, just typed before the cursor, completes one of the
+/// language's member accessors
+fn ends_accessor(content: &str, position: Position, language_id: &str, trigger: &str) -> bool {
+    let Some(line) = content.lines().nth(position.line as usize) else {
+        return false;
+    };
+    let prefix: String = line.chars().take(position.column as usize).collect();
+    member_accessors(language_id)
+        .iter()
+        .any(|accessor| accessor.ends_with(trigger) && prefix.ends_with(accessor))
+}
+
+/// If the cursor follows `receiver.` (or the language's `::`/`->`
+/// analogues, optionally with a partial member name), return the receiver
+/// identifier
+pub(crate) fn member_access_receiver(content: &str, position: Position, language_id: &str) -> Option<String> {
+    let line = content.lines().nth(position.line as usize)?;
+    let prefix: String = line.chars().take(position.column as usize).collect();
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let before_member = prefix.trim_end_matches(is_ident);
+    let before_accessor = member_accessors(language_id)
+        .iter()
+        .find_map(|accessor| before_member.strip_suffix(accessor))?;
+
+    let start = before_accessor
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident(*c))
+        .last()
+        .map(|(i, _)| i)?;
+    Some(before_accessor[start..].to_string())
+}
+
+/// Complete the members of the receiver's type. Returns `None` when the
+/// receiver's type cannot be resolved from the index.
+fn member_completions(
+    index: &ProjectIndex,
+    uri: &str,
+    position: Position,
+    receiver: &str,
+) -> Option<Vec<Value>> {
+    let enclosing = enclosing_type(index, uri, position);
+    let type_symbol = receiver_type(index, uri, position, receiver)?;
+
+    // Private members are only visible inside the type itself, protected
+    // ones also inside its subtypes
+    let inside = enclosing.as_ref().is_some_and(|e| e.id == type_symbol.id);
+    let in_subtype = enclosing
+        .as_ref()
+        .is_some_and(|e| is_subtype_of(index, e.id, type_symbol.id));
+
+    let mut members: Vec<SmartSymbol> = index
+        .symbols
+        .get_members(type_symbol.id)
+        .into_iter()
+        .filter(|m| m.kind != SymbolKind::Constructor)
+        .filter(|m| match m.visibility {
+            Visibility::Private => inside,
+            Visibility::Protected => inside || in_subtype,
+            _ => true,
+        })
+        .collect();
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    members.dedup_by(|a, b| a.name == b.name);
+
+    Some(
+        members
+            .into_iter()
+            .map(|m| {
+                json!({
+                    "label": m.name,
+                    "kind": symbol_kind_to_completion_kind(m.kind),
+                    "detail": format!("{:?}", m.kind),
+                    "sortText": m.name
+                })
+            })
+            .collect(),
+    )
+}
+
+/// The type (or module) whose members `receiver.` at `position` lists:
+/// the enclosing type for `this`/`self`, the receiver itself for types and
+/// modules (`Point::`, `utils::`), else the receiver's declared type
+pub(crate) fn receiver_type(index: &ProjectIndex, uri: &str, position: Position, receiver: &str) -> Option<SmartSymbol> {
+    if receiver == "this" || receiver == "self" {
+        return enclosing_type(index, uri, position);
+    }
+
+    let receiver_symbol = prefer_uri(index.symbols.find_by_name(receiver), uri)?;
+    if is_type_kind(receiver_symbol.kind)
+        || matches!(receiver_symbol.kind, SymbolKind::Module | SymbolKind::Namespace)
+    {
+        return Some(receiver_symbol);
+    }
+
+    let type_expr = &receiver_symbol.type_info.as_ref()?.type_expr;
+    let type_name = type_name_of(type_expr)?;
+    let types = index
+        .symbols
+        .find_by_name(type_name)
+        .into_iter()
+        .filter(|s| is_type_kind(s.kind))
+        .collect();
+    prefer_uri(types, uri)
+}
+
+/// The innermost class-like symbol containing a position
+fn enclosing_type(index: &ProjectIndex, uri: &str, position: Position) -> Option<SmartSymbol> {
+    index
+        .symbols
+        .get_file_symbols(uri)
+        .into_iter()
+        .filter(|s| is_type_kind(s.kind) && s.location.range.contains(position))
+        .max_by_key(|s| s.location.range.start)
+}
+
+fn is_subtype_of(index: &ProjectIndex, child: logos_index::SymbolId, ancestor: logos_index::SymbolId) -> bool {
+    let mut pending = index.type_hierarchy.get_supertypes(child);
+    let mut seen = Vec::new();
+    while let Some(id) = pending.pop() {
+        if id == ancestor {
+            return true;
+        }
+        if !seen.contains(&id) {
+            seen.push(id);
+            pending.extend(index.type_hierarchy.get_supertypes(id));
+        }
+    }
+    false
+}
+
+/// Pick the candidate defined in `uri`, else the first one
+fn prefer_uri(candidates: Vec<SmartSymbol>, uri: &str) -> Option<SmartSymbol> {
+    let local = candidates.iter().position(|s| s.location.uri == uri);
+    candidates.into_iter().nth(local.unwrap_or(0))
+}
+
+fn is_type_kind(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Class | SymbolKind::Interface | SymbolKind::Struct | SymbolKind::Enum
+    )
+}
+
+/// Extract the base type name from a type annotation like `: Foo<Bar> | null`
+fn type_name_of(type_expr: &str) -> Option<&str> {
+    let text = type_expr.trim().trim_start_matches(':').trim();
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(text.len());
+    if end == 0 {
+        None
+    } else {
+        Some(&text[..end])
+    }
+}
+
 fn symbol_kind_to_completion_kind(kind: SymbolKind) -> u32 {
     match kind {
-        SymbolKind::Function | SymbolKind::Method => 3,  // Function
+        SymbolKind::Method => 2,      // Method
+        SymbolKind::Function => 3,    // Function
+        SymbolKind::Constructor => 4, // Constructor
+        SymbolKind::Field => 5,       // Field
+        SymbolKind::Variable => 6,    // Variable
         SymbolKind::Class => 7,       // Class
         SymbolKind::Interface => 8,   // Interface
-        SymbolKind::Variable => 6,    // Variable
-        SymbolKind::Constant => 21,   // Constant
+        SymbolKind::Module | SymbolKind::Namespace | SymbolKind::Package => 9, // Module
+        SymbolKind::Property => 10,   // Property
         SymbolKind::Enum => 13,       // Enum
+        SymbolKind::EnumMember => 20, // EnumMember
+        SymbolKind::Constant => 21,   // Constant
         SymbolKind::Struct => 22,     // Struct
-        SymbolKind::Module => 9,      // Module
-        SymbolKind::Property | SymbolKind::Field => 10, // Property
+        SymbolKind::Event => 23,      // Event
+        SymbolKind::Operator => 24,   // Operator
+        SymbolKind::TypeParameter => 25, // TypeParameter
         _ => 1,                       // Text
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_outranks_global() {
+        let mut state = State::new();
+        let uri = "file:///test.py".to_string();
+        let source = "count = 0\nlimit = 10\n\ndef tick():\n    count = 1\n    return count\n";
+        state.open_document(uri.clone(), "python".to_string(), source.to_string());
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 5, "character": 11 }
+        });
+        let response = handle(&state, &params, None);
+        let result = response.result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        let count: Vec<_> = items.iter().filter(|i| i["label"] == "count").collect();
+        assert_eq!(count.len(), 1);
+        assert_eq!(count[0]["kind"], 6);
+
+        let limit = items.iter().find(|i| i["label"] == "limit").unwrap();
+        assert!(count[0]["sortText"].as_str().unwrap() < limit["sortText"].as_str().unwrap());
+        assert_eq!(items[0]["label"], "count");
+    }
+
+    #[test]
+    fn test_member_access_completion() {
+        use logos_index::{LanguageAdapter, ProjectIndexer, TypeScriptAdapter};
+        use std::sync::Arc;
+
+        let uri = "file:///app.ts".to_string();
+        let source = "class User {\n    private secret: string = '';\n    getName(): string { return ''; }\n    save(): void {}\n}\nfunction helper() {}\nconst u = new User();\nu.\n";
+
+        let mut state = State::new();
+        state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+        let indexer = ProjectIndexer::new();
+        let analysis = TypeScriptAdapter::new().unwrap().analyze(&uri, source);
+        for symbol in analysis.symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 7, "character": 2 }
+        });
+        let response = handle(&state, &params, None);
+        let result = response.result.unwrap();
+        let labels: Vec<_> = result["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["label"].as_str().unwrap().to_string())
+            .collect();
+
+        assert_eq!(labels, vec!["getName", "save"]);
+    }
+
+    #[test]
+    fn test_path_completion_after_double_colon() {
+        use logos_index::{LanguageAdapter, ProjectIndexer, RustAdapter};
+        use std::sync::Arc;
+
+        let uri = "file:///lib.rs".to_string();
+        let source = "mod shapes {\n    pub fn area() {}\n    pub struct Circle {}\n}\n\nfn main() {\n    shapes::\n}\n";
+
+        let mut state = State::new();
+        state.open_document(uri.clone(), "rust".to_string(), source.to_string());
+
+        let indexer = ProjectIndexer::new();
+        for symbol in RustAdapter::new().unwrap().analyze(&uri, source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 6, "character": 12 }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        let labels: Vec<_> = result["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["label"].as_str().unwrap().to_string())
+            .collect();
+
+        // Only the module's members, not keywords or `main`
+        assert_eq!(labels, vec!["Circle", "area"]);
+    }
+
+    #[test]
+    fn test_member_accessors_per_language() {
+        let at_end = |content: &str, language: &str| {
+            member_access_receiver(content, Position::new(0, content.len() as u32), language)
+        };
+        assert_eq!(at_end("node->ne", "c"), Some("node".to_string()));
+        assert_eq!(at_end("std::ve", "cpp"), Some("std".to_string()));
+        assert_eq!(at_end("Vec::", "rust"), Some("Vec".to_string()));
+        assert_eq!(at_end("Vec::", "python"), None);
+    }
+
+    #[test]
+    fn test_trigger_character_outside_accessor_is_ignored() {
+        let mut state = State::new();
+        let uri = "file:///cmp.ts".to_string();
+        let source = "const limit = 1;\nif (limit >\nlimit.\n";
+        state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+        let items_after = |line: u32, character: u32, trigger: &str| {
+            let params = json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+                "context": { "triggerKind": 2, "triggerCharacter": trigger }
+            });
+            handle(&state, &params, None).result.unwrap()["items"].as_array().unwrap().len()
+        };
+        // `>` is a comparison in TypeScript, `.` is member access
+        assert_eq!(items_after(1, 11, ">"), 0);
+        assert!(items_after(2, 6, ".") > 0);
+    }
+}
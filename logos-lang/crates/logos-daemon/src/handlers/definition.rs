@@ -1,10 +1,13 @@
 //! Definition handler
 
 use serde_json::{json, Value};
-use logos_core::Position;
+use logos_core::{Position, Range, Symbol, SymbolKind};
+use logos_index::{IndexedSymbol, ProjectIndex, SmartSymbol};
+use logos_semantic::scope::ScopeTree;
 
+use crate::handlers::completion::{member_access_receiver, receiver_type};
 use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
-use crate::state::State;
+use crate::state::{uri_to_path, State};
 
 /// Handle textDocument/definition
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -22,22 +25,316 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
 
+    let word = state
+        .get_document(uri)
+        .and_then(|doc| word_at(doc.content(), position));
+
+    // `receiver.member`: the member of the receiver's type, not whatever
+    // else is called `member` (Smart mode)
+    if let (Some(indexer), Some(doc), Some(word)) = (state.get_indexer(), state.get_document(uri), &word) {
+        if state.resolve_member_access {
+            let member = member_access_receiver(doc.content(), position, &doc.language_id)
+                .and_then(|receiver| receiver_type(&indexer.index, uri, position, &receiver))
+                .and_then(|owner| find_member(&indexer.index, &owner, word));
+            if let Some(member) = member {
+                return Response::success(
+                    id,
+                    definition_json(state, &member.location.uri, &member.location.selection_range, &member.name),
+                );
+            }
+        }
+    }
+
+    // Locals shadow everything else, so the enclosing scopes come first
+    if let Some(range) = word
+        .as_deref()
+        .and_then(|word| local_declaration(&state.document_symbols(uri), position, word))
+    {
+        return Response::success(id, location_json(uri, &range));
+    }
+
+    // Smart mode: follow imports (including aliases) to the declaring file
+    if let (Some(indexer), Some(path)) = (state.get_indexer(), uri_to_path(uri)) {
+        if let Some(word) = &word {
+            if let Some(symbol) = indexer.resolve_imported_symbol(&path, word) {
+                return Response::success(
+                    id,
+                    definition_json(state, &symbol.location.uri, &symbol.location.selection_range, &symbol.name),
+                );
+            }
+        }
+    }
+
     if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
-        let definition = json!({
-            "uri": symbol.uri,
-            "range": {
-                "start": {
-                    "line": symbol.range.start.line,
-                    "character": symbol.range.start.column
-                },
-                "end": {
-                    "line": symbol.range.end.line,
-                    "character": symbol.range.end.column
-                }
+        return Response::success(id, definition_json(state, &symbol.uri, &symbol.range, &symbol.name));
+    }
+
+    // Last resort: a declaration with the same name in any open document
+    if let Some(symbol) = word.and_then(|word| best_name_match(state, uri, &word)) {
+        return Response::success(id, definition_json(state, &symbol.uri, &symbol.selection_range, &symbol.name));
+    }
+
+    Response::null_result(id)
+}
+
+/// Selection range of the declaration of `name` in the innermost scope
+/// around `position` that declares it, when that scope is a function or
+/// class body rather than the top level of the file
+fn local_declaration(symbols: &[Symbol], position: Position, name: &str) -> Option<Range> {
+    let tree = ScopeTree::from_symbols(symbols);
+    let chain = tree.scope_chain(position);
+    let mut best: Option<(usize, &Symbol)> = None;
+    tree.visit_visible(symbols, &chain, &mut |symbol, _, distance| {
+        if symbol.name == name && best.is_none_or(|(nearest, _)| distance < nearest) {
+            best = Some((distance, symbol));
+        }
+    });
+    // The last scope in the chain is the file; its symbols are left to the
+    // index lookups below
+    best.filter(|(distance, _)| *distance + 1 < chain.len())
+        .map(|(_, symbol)| symbol.selection_range)
+}
+
+/// The member `name` of `owner`, declared on the type itself or inherited
+/// from one of its supertypes
+fn find_member(index: &ProjectIndex, owner: &SmartSymbol, name: &str) -> Option<SmartSymbol> {
+    let mut pending = vec![owner.id];
+    let mut seen = Vec::new();
+    while let Some(id) = pending.pop() {
+        if seen.contains(&id) {
+            continue;
+        }
+        seen.push(id);
+        if let Some(member) = index.symbols.get_members(id).into_iter().find(|m| m.name == name) {
+            return Some(member);
+        }
+        pending.extend(index.type_hierarchy.get_supertypes(id));
+        pending.extend(index.type_hierarchy.get_interfaces(id));
+    }
+    None
+}
+
+/// Pick the likeliest declaration of `name` by name alone: one in the
+/// requesting document first, then top-level symbols (the ones another
+/// file could import), then declarations over variables and members
+fn best_name_match<'a>(state: &'a State, uri: &str, name: &str) -> Option<&'a IndexedSymbol> {
+    let kind_rank = |kind: SymbolKind| match kind {
+        SymbolKind::Function
+        | SymbolKind::Class
+        | SymbolKind::Interface
+        | SymbolKind::Struct
+        | SymbolKind::Enum
+        | SymbolKind::Module
+        | SymbolKind::Namespace => 0,
+        SymbolKind::Method | SymbolKind::Constructor | SymbolKind::Constant => 1,
+        _ => 2,
+    };
+    state.symbol_index.find_by_name(name).min_by(|a, b| {
+        let key = |s: &IndexedSymbol| (s.uri != uri, s.container.is_some(), kind_rank(s.kind));
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| a.uri.cmp(&b.uri))
+            .then_with(|| a.selection_range.start.cmp(&b.selection_range.start))
+    })
+}
+
+/// The identifier under the cursor, if any
+fn word_at(content: &str, position: Position) -> Option<String> {
+    let line = content.lines().nth(position.line as usize)?;
+    let chars: Vec<char> = line.chars().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+    let column = (position.column as usize).min(chars.len());
+    let mut start = column;
+    while start > 0 && is_ident(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = column;
+    while end < chars.len() && is_ident(chars[end]) {
+        end += 1;
+    }
+
+    if start == end {
+        None
+    } else {
+        Some(chars[start..end].iter().collect())
+    }
+}
+
+/// The location of the declaration `name` at `range`. With
+/// `followTypeAliases` on, a type alias whose target is declared in the
+/// project gives both locations, the alias first.
+fn definition_json(state: &State, uri: &str, range: &Range, name: &str) -> Value {
+    let location = location_json(uri, range);
+    if !state.follow_type_aliases {
+        return location;
+    }
+    match alias_target(state, uri, range, name) {
+        Some(target) => json!([
+            location,
+            location_json(&target.location.uri, &target.location.selection_range)
+        ]),
+        None => location,
+    }
+}
+
+/// The type aliased by the Smart-mode declaration of `name` at `range`
+fn alias_target(state: &State, uri: &str, range: &Range, name: &str) -> Option<SmartSymbol> {
+    let index = state.get_indexer()?.get_index();
+    let alias = index.symbols.get_file_symbols(uri).into_iter().find(|s| {
+        s.name == name && s.alias_target.is_some() && s.location.range.contains(range.start)
+    })?;
+    index.resolve_alias(&alias)
+}
+
+fn location_json(uri: &str, range: &Range) -> Value {
+    json!({
+        "uri": uri,
+        "range": {
+            "start": {
+                "line": range.start.line,
+                "character": range.start.column
+            },
+            "end": {
+                "line": range.end.line,
+                "character": range.end.column
             }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_mode_falls_back_to_name_match() {
+        let mut state = State::new();
+        state.open_document(
+            "file:///util.ts".to_string(),
+            "typescript".to_string(),
+            "export function formatDate(d) {\n    return d;\n}\n".to_string(),
+        );
+        state.open_document(
+            "file:///app.ts".to_string(),
+            "typescript".to_string(),
+            "import { formatDate } from './util';\nformatDate(now);\n".to_string(),
+        );
+
+        let params = json!({
+            "textDocument": { "uri": "file:///app.ts" },
+            "position": { "line": 1, "character": 3 }
         });
-        return Response::success(id, definition);
+        let result = handle(&state, &params, None).result.unwrap();
+        assert_eq!(result["uri"], "file:///util.ts");
+        assert_eq!(result["range"]["start"], json!({ "line": 0, "character": 16 }));
     }
 
-    Response::null_result(id)
+    #[test]
+    fn test_local_resolves_in_enclosing_function() {
+        let mut state = State::new();
+        state.open_document(
+            "file:///calc.py".to_string(),
+            "python".to_string(),
+            "x = 0\n\ndef first():\n    x = 1\n    return x\n\ndef second():\n    x = 2\n    return x\n".to_string(),
+        );
+
+        let definition_at = |line: u32, character: u32| {
+            let params = json!({
+                "textDocument": { "uri": "file:///calc.py" },
+                "position": { "line": line, "character": character }
+            });
+            handle(&state, &params, None).result.unwrap()
+        };
+        let result = definition_at(8, 11);
+        assert_eq!(result["uri"], "file:///calc.py");
+        assert_eq!(result["range"]["start"], json!({ "line": 7, "character": 4 }));
+        assert_eq!(definition_at(4, 11)["range"]["start"], json!({ "line": 3, "character": 4 }));
+    }
+
+    #[test]
+    fn test_follow_type_aliases_adds_target() {
+        use logos_index::ProjectIndexer;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.rs");
+        let source = "pub struct User {\n    id: u64,\n}\n\npub type Account = User;\n\nfn load(account: Account) {}\n";
+        std::fs::write(&path, source).unwrap();
+        let uri = format!("file://{}", path.display());
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&path).unwrap();
+        let mut state = State::new();
+        state.open_document(uri.clone(), "rust".to_string(), source.to_string());
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 6, "character": 20 }
+        });
+        let alias = json!({ "line": 4, "character": 9 });
+
+        let result = handle(&state, &params, None).result.unwrap();
+        assert_eq!(result["range"]["start"], alias);
+
+        state.follow_type_aliases = true;
+        let result = handle(&state, &params, None).result.unwrap();
+        let locations = result.as_array().unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0]["range"]["start"], alias);
+        assert_eq!(locations[1]["uri"], uri);
+        assert_eq!(locations[1]["range"]["start"], json!({ "line": 0, "character": 11 }));
+    }
+
+    #[test]
+    fn test_member_access_resolves_on_receiver_type() {
+        let mut state = State::new();
+        state.enable_smart_mode().unwrap();
+        state.open_document(
+            "file:///model.ts".to_string(),
+            "typescript".to_string(),
+            "export class Person {\n    wave() {}\n}\nexport class User extends Person {\n    greet() {}\n}\n".to_string(),
+        );
+        state.open_document(
+            "file:///app.ts".to_string(),
+            "typescript".to_string(),
+            "function greet() {}\nconst user: User = new User();\nuser.greet();\nuser.wave();\n".to_string(),
+        );
+
+        let definition_at = |state: &State, line: u32, character: u32| {
+            let params = json!({
+                "textDocument": { "uri": "file:///app.ts" },
+                "position": { "line": line, "character": character }
+            });
+            handle(state, &params, None).result.unwrap()
+        };
+
+        let result = definition_at(&state, 2, 7);
+        assert_eq!(result["uri"], "file:///model.ts");
+        assert_eq!(result["range"]["start"], json!({ "line": 4, "character": 4 }));
+        // Inherited members are found on the supertype
+        assert_eq!(definition_at(&state, 3, 6)["range"]["start"], json!({ "line": 1, "character": 4 }));
+
+        state.resolve_member_access = false;
+        assert_eq!(definition_at(&state, 2, 7)["uri"], "file:///app.ts");
+    }
+
+    #[test]
+    fn test_no_match_returns_null() {
+        let mut state = State::new();
+        state.open_document(
+            "file:///app.ts".to_string(),
+            "typescript".to_string(),
+            "missing();\n".to_string(),
+        );
+
+        let params = json!({
+            "textDocument": { "uri": "file:///app.ts" },
+            "position": { "line": 0, "character": 2 }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        assert!(result.is_null());
+    }
 }
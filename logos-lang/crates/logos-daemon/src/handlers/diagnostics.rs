@@ -1,9 +1,21 @@
 //! Diagnostics handler
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use logos_core::{Diagnostic, DiagnosticSeverity, SymbolKind};
+use logos_index::{ProjectIndex, ProjectIndexer};
+use logos_parser::LanguageId;
+use logos_semantic::{conditions, empty_handlers, FunctionSignature, Type, TypeChecker, UnusedDetector};
 use serde_json::{json, Value};
 
-use crate::protocol::{DocumentSymbolParams, RequestId, Response};
-use crate::state::State;
+use crate::protocol::{DocumentSymbolParams, RequestId, Response, WorkspaceDiagnosticParams};
+use crate::state::{path_to_uri, State};
+
+/// Most file reports in one workspace/diagnostic response
+const MAX_WORKSPACE_REPORTS: usize = 1000;
 
 /// Handle textDocument/diagnostic
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -18,12 +30,581 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
         }
     };
 
-    let _uri = &params.text_document.uri;
+    let uri = &params.text_document.uri;
+    let mut diagnostics = Vec::new();
+    if let (Some(doc), Some((language, tree))) = (state.get_document(uri), state.document_tree(uri)) {
+        let source = doc.content();
+        diagnostics.extend(logos_parser::extract_parse_errors(&tree, source));
+
+        let config = &state.analysis_config;
+        if config.detect_unused {
+            let mut detector = UnusedDetector::new();
+            if config.report_unused {
+                let symbols = logos_parser::extract_symbols(language, &tree, source);
+                diagnostics.extend(detector.analyze_to_diagnostics(&symbols, source));
+            }
+            diagnostics.extend(
+                detector
+                    .detect_unreachable(&tree, source)
+                    .iter()
+                    .map(|item| item.to_diagnostic()),
+            );
+        }
+        diagnostics.extend(conditions::assignment_in_condition(&tree, language));
+        diagnostics.extend(empty_handlers::empty_handlers(&tree, source));
+
+        let mut checker = TypeChecker::with_config(config.type_check_config());
+        checker.check_returns(&tree, source);
+        if let Some(indexer) = state.get_indexer() {
+            checker.check_calls(&tree, source, |name| {
+                indexed_signature(&indexer.index, name, language)
+            });
+        }
+        diagnostics.extend(checker.diagnostics());
+    }
+    diagnostics.extend(state.todo_index.document_diagnostics(uri));
+    state.analysis_config.apply_severity_overrides(&mut diagnostics);
+    let items: Vec<Value> = diagnostics.iter().map(diagnostic_json).collect();
 
-    // For now, return empty diagnostics
-    // Future: integrate with parser errors and semantic analysis
     Response::success(id, json!({
         "kind": "full",
-        "items": []
+        "items": items
     }))
 }
+
+/// Handle workspace/diagnostic: project-wide findings (import cycles,
+/// duplicate and unused exports) as one report per file. A file whose
+/// findings still match the client's previous result id gets an
+/// "unchanged" report. Changed files are reported before unchanged ones, so
+/// a client that pulls again with the result ids it got receives the files
+/// a `limit` cut off. Smart mode only.
+pub fn workspace(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: WorkspaceDiagnosticParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid workspace diagnostic params: {}", e),
+            );
+        }
+    };
+
+    let Some(indexer) = state.get_indexer() else {
+        return Response::success(id, json!({ "items": [] }));
+    };
+
+    let mut findings: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+    for (path, diagnostic) in import_cycle_diagnostics(indexer)
+        .into_iter()
+        .chain(export_diagnostics(indexer))
+    {
+        findings.entry(path_to_uri(&path)).or_default().push(diagnostic);
+    }
+
+    // Files reported last time that are clean now get an empty report
+    let previous: HashMap<&str, &str> = params
+        .previous_result_ids
+        .iter()
+        .map(|p| (p.uri.as_str(), p.value.as_str()))
+        .collect();
+    for uri in previous.keys() {
+        findings.entry(uri.to_string()).or_default();
+    }
+
+    let limit = params.limit.unwrap_or(MAX_WORKSPACE_REPORTS).min(MAX_WORKSPACE_REPORTS);
+    let (mut changed, unchanged): (Vec<Value>, Vec<Value>) = findings
+        .into_iter()
+        .map(|(uri, mut diagnostics)| {
+            state.analysis_config.apply_severity_overrides(&mut diagnostics);
+            let items: Vec<Value> = diagnostics.iter().map(diagnostic_json).collect();
+            let result_id = result_id(&items);
+            if previous.get(uri.as_str()) == Some(&result_id.as_str()) {
+                json!({ "kind": "unchanged", "uri": uri, "version": null, "resultId": result_id })
+            } else {
+                json!({ "kind": "full", "uri": uri, "version": null, "resultId": result_id, "items": items })
+            }
+        })
+        .partition(|report| report["kind"] == "full");
+    changed.extend(unchanged);
+    let reports: Vec<Value> = changed.into_iter().take(limit).collect();
+
+    Response::success(id, json!({ "items": reports }))
+}
+
+/// A warning on every import that takes part in an import cycle
+fn import_cycle_diagnostics(indexer: &ProjectIndexer) -> Vec<(PathBuf, Diagnostic)> {
+    let deps = &indexer.index.dependencies;
+    let mut diagnostics = Vec::new();
+    for cycle in deps.find_cycles() {
+        let members: HashSet<&PathBuf> = cycle.iter().collect();
+        for file in &cycle {
+            for import in deps.get_file_imports(file) {
+                let Some(target) = indexer.resolve_import(file, &import.module_path) else {
+                    continue;
+                };
+                if !members.contains(&target) {
+                    continue;
+                }
+                let chain: Vec<String> = std::iter::once(file)
+                    .chain(&cycle_path(indexer, &target, file, &members))
+                    .map(|path| file_name(path))
+                    .collect();
+                let message = format!(
+                    "Import of '{}' is part of an import cycle: {}",
+                    import.module_path,
+                    chain.join(" -> ")
+                );
+                diagnostics.push((
+                    file.clone(),
+                    Diagnostic::warning(import.location, message)
+                        .with_code("import-cycle".to_string())
+                        .with_source("logos-index".to_string()),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Shortest import path from `from` to `to` through files in the cycle,
+/// both ends included
+fn cycle_path(indexer: &ProjectIndexer, from: &PathBuf, to: &PathBuf, members: &HashSet<&PathBuf>) -> Vec<PathBuf> {
+    let deps = &indexer.index.dependencies;
+    let mut came_from: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut queue = VecDeque::from([from.clone()]);
+    while let Some(file) = queue.pop_front() {
+        if &file == to {
+            break;
+        }
+        for next in deps.get_imports(&file) {
+            if members.contains(&next) && &next != from && !came_from.contains_key(&next) {
+                came_from.insert(next.clone(), file.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut path = vec![to.clone()];
+    while let Some(previous) = came_from.get(path.last().unwrap()) {
+        path.push(previous.clone());
+    }
+    if path.last() != Some(from) {
+        path.push(from.clone());
+    }
+    path.reverse();
+    path
+}
+
+/// Exports declared twice in a file, and exports nothing imports. Files
+/// no other file imports are taken to be entry points, so their exports
+/// are never reported as unused.
+fn export_diagnostics(indexer: &ProjectIndexer) -> Vec<(PathBuf, Diagnostic)> {
+    let deps = &indexer.index.dependencies;
+    let mut diagnostics = Vec::new();
+    for file in deps.exporting_files() {
+        let exports = deps.get_file_exports(&file);
+        let exported_name = |export: &logos_index::ExportInfo| {
+            if export.is_default { "default".to_string() } else { export.name.clone() }
+        };
+
+        let mut seen = HashSet::new();
+        for export in exports.iter().filter(|e| e.name != "*") {
+            if !seen.insert(exported_name(export)) {
+                diagnostics.push((
+                    file.clone(),
+                    Diagnostic::error(export.location, format!("Duplicate export '{}'", export.name))
+                        .with_code("duplicate-export".to_string())
+                        .with_source("logos-index".to_string()),
+                ));
+            }
+        }
+
+        let importers = deps.get_importers(&file);
+        if importers.is_empty() {
+            continue;
+        }
+        let Some(used) = imported_names(indexer, &file, &importers) else {
+            continue;
+        };
+        for export in exports.iter().filter(|e| e.name != "*") {
+            if !used.contains(&exported_name(export)) {
+                diagnostics.push((
+                    file.clone(),
+                    Diagnostic::hint(export.location, format!("'{}' is exported but never imported", export.name))
+                        .with_code("unused-export".to_string())
+                        .with_source("logos-index".to_string()),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Names that `importers` import or re-export from `file`, or `None` if
+/// one of them takes the whole module (`import * as`, `export *`, or a
+/// bare side-effect import)
+fn imported_names(indexer: &ProjectIndexer, file: &PathBuf, importers: &[PathBuf]) -> Option<HashSet<String>> {
+    let deps = &indexer.index.dependencies;
+    let resolves_to_file =
+        |importer: &PathBuf, module: &str| indexer.resolve_import(importer, module).as_ref() == Some(file);
+
+    let mut names = HashSet::new();
+    for importer in importers {
+        for import in deps.get_file_imports(importer) {
+            if !resolves_to_file(importer, &import.module_path) {
+                continue;
+            }
+            if import.items.is_empty() || import.items.iter().any(|item| item.name == "*") {
+                return None;
+            }
+            names.extend(import.items.into_iter().map(|item| item.name));
+        }
+        for export in deps.get_file_exports(importer) {
+            let Some(module) = &export.from_module else {
+                continue;
+            };
+            if !resolves_to_file(importer, module) {
+                continue;
+            }
+            if export.name == "*" {
+                return None;
+            }
+            names.insert(export.original_name.unwrap_or(export.name));
+        }
+    }
+    Some(names)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Result id for a report: a hash of its diagnostics, so an unchanged
+/// report gets the same id
+fn result_id(items: &[Value]) -> String {
+    let mut hasher = DefaultHasher::new();
+    Value::Array(items.to_vec()).to_string().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Signature of the indexed function `name`, if exactly one function of
+/// that name exists in the document's language and its parameter types
+/// are known
+fn indexed_signature(index: &ProjectIndex, name: &str, language: LanguageId) -> Option<FunctionSignature> {
+    let mut candidates = index.symbols.find_by_name(name).into_iter().filter(|s| {
+        s.kind == SymbolKind::Function
+            && s.location.uri.rsplit('.').next().and_then(LanguageId::from_extension) == Some(language)
+    });
+    let symbol = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+
+    // Adapters that don't record parameters leave `param_types` empty
+    let type_info = symbol.type_info.as_ref().filter(|t| !t.param_types.is_empty())?;
+    let mut params = Vec::new();
+    let mut variadic = false;
+    let mut optional_params = 0;
+    for param in &type_info.param_types {
+        if param.type_expr.starts_with("...") {
+            variadic = true;
+            break;
+        }
+        params.push((String::new(), Type::from_annotation(&param.type_expr)));
+        // Only a trailing run of optional parameters can be left out
+        optional_params = if param.nullable { optional_params + 1 } else { 0 };
+    }
+
+    Some(FunctionSignature {
+        name: name.to_string(),
+        params,
+        return_type: type_info
+            .return_type
+            .as_ref()
+            .map(|r| Type::from_annotation(&r.type_expr))
+            .unwrap_or_default(),
+        variadic,
+        optional_params,
+    })
+}
+
+/// Convert a diagnostic to its LSP JSON form
+fn diagnostic_json(diagnostic: &Diagnostic) -> Value {
+    let severity = match diagnostic.severity {
+        DiagnosticSeverity::Error => 1,
+        DiagnosticSeverity::Warning => 2,
+        DiagnosticSeverity::Information => 3,
+        DiagnosticSeverity::Hint => 4,
+    };
+
+    let mut item = json!({
+        "range": {
+            "start": {
+                "line": diagnostic.range.start.line,
+                "character": diagnostic.range.start.column
+            },
+            "end": {
+                "line": diagnostic.range.end.line,
+                "character": diagnostic.range.end.column
+            }
+        },
+        "severity": severity,
+        "message": diagnostic.message
+    });
+    if let Some(source) = &diagnostic.source {
+        item["source"] = json!(source);
+    }
+    if let Some(code) = &diagnostic.code {
+        item["code"] = json!(code);
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unterminated_string_is_a_syntax_error() {
+        let mut state = State::new();
+        let uri = "file:///broken.py".to_string();
+        let source = "x = 1\ny = \"unterminated\nz = 2\n";
+        state.open_document(uri.clone(), "python".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        assert!(!items.is_empty(), "expected a syntax error, got none");
+        let error = &items[0];
+        assert_eq!(error["severity"], 1);
+        assert_eq!(error["source"], "logos-parser");
+        assert_eq!(error["message"], "Syntax error near '\"'");
+        // The stray opening quote on line 1
+        assert_eq!(error["range"]["start"], json!({ "line": 1, "character": 4 }));
+        assert_eq!(error["range"]["end"], json!({ "line": 1, "character": 5 }));
+    }
+
+    #[test]
+    fn test_clean_document_has_no_diagnostics() {
+        let mut state = State::new();
+        let uri = "file:///ok.py".to_string();
+        state.open_document(uri.clone(), "python".to_string(), "x = 1\n".to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        assert!(result["items"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_todo_diagnostics_when_enabled() {
+        let mut state = State::new();
+        state.todo_index = logos_index::TodoIndex::with_config(&logos_index::ScannerConfig {
+            report_diagnostics: true,
+            ..Default::default()
+        });
+        let uri = "file:///todo.py".to_string();
+        state.open_document(uri.clone(), "python".to_string(), "x = 1  # TODO: rename\n".to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["severity"], 3);
+        assert_eq!(items[0]["code"], "todo");
+        assert_eq!(items[0]["message"], "TODO: rename");
+    }
+
+    #[test]
+    fn test_unreachable_code_is_a_warning() {
+        let mut state = State::new();
+        let uri = "file:///early.ts".to_string();
+        let source = "function f(): number {\n  return 1;\n  console.log('never');\n}\n";
+        state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["severity"], 2);
+        assert_eq!(items[0]["code"], "unreachable-code");
+        assert_eq!(items[0]["range"]["start"], json!({ "line": 2, "character": 2 }));
+    }
+
+    #[test]
+    fn test_assignment_condition_is_reported() {
+        let mut state = State::new();
+        let uri = "file:///check.js".to_string();
+        state.open_document(uri.clone(), "javascript".to_string(), "if (ready = true) {\n  start();\n}\n".to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["code"], "assignment-in-condition");
+        assert_eq!(items[0]["severity"], 2);
+    }
+
+    #[test]
+    fn test_init_options_toggle_unused_diagnostics() {
+        let source = "function f(): number {\n  return 1;\n  console.log('never');\n}\n";
+        let codes = |options: Value| {
+            let mut state = State::new();
+            let init = json!({ "processId": null, "rootUri": null, "initializationOptions": options });
+            crate::handlers::lifecycle::initialize(&mut state, &init, None);
+            let uri = "file:///early.ts".to_string();
+            state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+            let params = json!({ "textDocument": { "uri": uri } });
+            let result = handle(&state, &params, None).result.unwrap();
+            result["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["code"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(codes(json!({ "detectUnused": false })).is_empty());
+        assert_eq!(codes(json!({ "reportUnused": true })), ["unused-function", "unreachable-code"]);
+    }
+
+    #[test]
+    fn test_severity_overrides_from_init_options() {
+        let mut state = State::new();
+        let init = json!({
+            "processId": null,
+            "rootUri": null,
+            "initializationOptions": {
+                "reportUnused": true,
+                "severityOverrides": { "unused-variable": "error" }
+            }
+        });
+        crate::handlers::lifecycle::initialize(&mut state, &init, None);
+        let uri = "file:///totals.py".to_string();
+        state.open_document(uri.clone(), "python".to_string(), "def total():\n    rate = 2\n    return 1\n".to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let severity_of = |code: &str| {
+            let items = result["items"].as_array().unwrap();
+            items.iter().find(|item| item["code"] == code).unwrap()["severity"].clone()
+        };
+        assert_eq!(severity_of("unused-variable"), 1);
+        assert_eq!(severity_of("unused-function"), 4);
+    }
+
+    #[test]
+    fn test_return_type_mismatch_is_reported() {
+        let mut state = State::new();
+        let uri = "file:///count.py".to_string();
+        state.open_document(uri.clone(), "python".to_string(), "def count() -> int:\n    return \"many\"\n".to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["code"], "return-type-mismatch");
+    }
+
+    #[test]
+    fn test_workspace_diagnostics_report_import_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.ts"),
+            "import { b } from './b';\nexport function a() { return b(); }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b.ts"),
+            "import { a } from './a';\nexport function b() { return a(); }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("c.ts"), "export const c = 1;\n").unwrap();
+
+        let mut state = State::new();
+        state.add_workspace_root(dir.path().display().to_string());
+        state.enable_smart_mode().unwrap();
+
+        let result = workspace(&state, &json!({}), None).result.unwrap();
+        let reports = result["items"].as_array().unwrap();
+        let uris: Vec<&str> = reports.iter().map(|r| r["uri"].as_str().unwrap()).collect();
+        let a_uri = format!("file://{}", dir.path().join("a.ts").display());
+        let b_uri = format!("file://{}", dir.path().join("b.ts").display());
+        assert_eq!(uris, vec![a_uri.as_str(), b_uri.as_str()]);
+        for report in reports {
+            assert_eq!(report["kind"], "full");
+            let items = report["items"].as_array().unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0]["code"], "import-cycle");
+            assert_eq!(items[0]["range"]["start"], json!({ "line": 0, "character": 0 }));
+        }
+        assert_eq!(
+            reports[0]["items"][0]["message"],
+            "Import of './b' is part of an import cycle: a.ts -> b.ts -> a.ts"
+        );
+
+        // Unchanged findings are not resent
+        let params = json!({
+            "previousResultIds": [
+                { "uri": a_uri, "value": reports[0]["resultId"] },
+                { "uri": b_uri, "value": reports[1]["resultId"] }
+            ]
+        });
+        let result = workspace(&state, &params, None).result.unwrap();
+        let kinds: Vec<&Value> = result["items"].as_array().unwrap().iter().map(|r| &r["kind"]).collect();
+        assert_eq!(kinds, vec!["unchanged", "unchanged"]);
+
+        // The limit caps each pull; pulling again with the result ids
+        // received reports the files it cut off
+        let result = workspace(&state, &json!({ "limit": 1 }), None).result.unwrap();
+        let first = &result["items"][0];
+        assert_eq!(first["uri"], a_uri);
+        let params = json!({
+            "previousResultIds": [{ "uri": a_uri, "value": first["resultId"] }],
+            "limit": 1
+        });
+        let result = workspace(&state, &params, None).result.unwrap();
+        let reports = result["items"].as_array().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["uri"], b_uri);
+        assert_eq!(reports[0]["kind"], "full");
+    }
+
+    #[test]
+    fn test_call_checked_against_indexed_signature() {
+        use logos_index::ProjectIndexer;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("math.ts");
+        std::fs::write(
+            &lib,
+            "export function add(a: number, b: number): number {\n  return a + b;\n}\nexport function pad(s: string, width?: number) {}\n",
+        )
+        .unwrap();
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&lib).unwrap();
+
+        let mut state = State::new();
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+        let uri = "file:///app.ts".to_string();
+        let source = "add(1, \"two\");\nadd(1, 2);\npad(\"x\");\n";
+        state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        assert_eq!(items.len(), 1, "{:?}", items);
+        assert_eq!(items[0]["code"], "type-mismatch");
+        assert_eq!(items[0]["range"]["start"], json!({ "line": 0, "character": 0 }));
+    }
+}
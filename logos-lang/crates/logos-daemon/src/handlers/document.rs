@@ -1,9 +1,12 @@
 //! Document synchronization handlers
 
 use log::debug;
+use logos_core::Range;
 use serde_json::Value;
 
-use crate::protocol::{DidOpenTextDocumentParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams};
+use crate::protocol::{
+    DidOpenTextDocumentParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams, RenameFilesParams,
+};
 use crate::state::State;
 
 /// Handle textDocument/didOpen
@@ -34,10 +37,17 @@ pub fn did_change(state: &mut State, params: &Value) {
 
     debug!("Changing document: {}", params.text_document.uri);
 
-    // We use full sync, so just take the last change
-    if let Some(change) = params.content_changes.last() {
-        state.update_document(&params.text_document.uri, change.text.clone());
-    }
+    let changes = params
+        .content_changes
+        .into_iter()
+        .map(|change| {
+            let range = change.range.map(|r| {
+                Range::from_coords(r.start.line, r.start.character, r.end.line, r.end.character)
+            });
+            (range, change.text)
+        })
+        .collect();
+    state.change_document(&params.text_document.uri, changes);
 }
 
 /// Handle textDocument/didClose
@@ -54,3 +64,115 @@ pub fn did_close(state: &mut State, params: &Value) {
 
     state.close_document(&params.text_document.uri);
 }
+
+/// Handle workspace/didRenameFiles
+pub fn did_rename_files(state: &mut State, params: &Value) {
+    let params: RenameFilesParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            log::warn!("Invalid didRenameFiles params: {}", e);
+            return;
+        }
+    };
+
+    for file in &params.files {
+        debug!("Renaming file: {} -> {}", file.old_uri, file.new_uri);
+        state.rename_file(&file.old_uri, &file.new_uri);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_incremental_changes_apply_in_order() {
+        let mut state = State::new();
+        let uri = "file:///edit.py";
+        did_open(&mut state, &json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "python",
+                "version": 1,
+                "text": "def add(a, b):\n    return a + b\n"
+            }
+        }));
+
+        // The second range is relative to the content after the first edit
+        did_change(&mut state, &json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [
+                {
+                    "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 7 } },
+                    "text": "plus"
+                },
+                {
+                    "range": { "start": { "line": 1, "character": 15 }, "end": { "line": 1, "character": 16 } },
+                    "text": "c"
+                }
+            ]
+        }));
+
+        let doc = state.get_document(uri).unwrap();
+        assert_eq!(doc.content(), "def plus(a, b):\n    return a + c\n");
+        assert_eq!(state.document_symbols(uri)[0].name, "plus");
+    }
+
+    #[test]
+    fn test_renamed_file_is_reindexed_under_new_uri() {
+        use crate::state::{path_to_uri, uri_to_root};
+        use std::fs;
+
+        let dir = tempfile::tempdir().unwrap();
+        let util = dir.path().join("util.ts");
+        let app = dir.path().join("app.ts");
+        fs::write(&util, "export function helper() {}\n").unwrap();
+        fs::write(&app, "import { helper } from './util';\nhelper();\n").unwrap();
+
+        let mut state = State::new();
+        state.add_workspace_root(uri_to_root(&path_to_uri(dir.path())));
+        state.enable_smart_mode().unwrap();
+        let index = state.get_indexer().unwrap().get_index();
+        assert_eq!(index.dependencies.get_importers(&util), vec![app.clone()]);
+
+        let helpers = dir.path().join("helpers.ts");
+        fs::rename(&util, &helpers).unwrap();
+        let (old_uri, new_uri) = (path_to_uri(&util), path_to_uri(&helpers));
+        did_rename_files(&mut state, &json!({ "files": [{ "oldUri": old_uri, "newUri": new_uri }] }));
+
+        let index = state.get_indexer().unwrap().get_index();
+        assert!(index.symbols.get_file_symbols(&old_uri).is_empty());
+        let names: Vec<String> = index.symbols.get_file_symbols(&new_uri).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["helper"]);
+        assert!(index.dependencies.get_importers(&util).is_empty());
+        assert!(!index.dependencies.get_imports(&app).contains(&util));
+        assert_eq!(index.dependencies.get_exports(&helpers).len(), 1);
+    }
+
+    #[test]
+    fn test_escaped_drive_colon_uses_the_same_index_key() {
+        use crate::state::{path_to_uri, uri_to_root};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = State::new();
+        state.add_workspace_root(uri_to_root(&path_to_uri(dir.path())));
+        state.enable_smart_mode().unwrap();
+
+        let (escaped, plain) = ("file:///c%3A/src/app.ts", "file:///c:/src/app.ts");
+        did_open(&mut state, &json!({
+            "textDocument": { "uri": escaped, "languageId": "typescript", "version": 1, "text": "function main() {}\n" }
+        }));
+        assert!(state.get_document(plain).is_some());
+        let index = state.get_indexer().unwrap().get_index();
+        let names: Vec<String> = index.symbols.get_file_symbols(plain).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["main"]);
+
+        // Closing with the other spelling drops the same entry
+        did_close(&mut state, &json!({ "textDocument": { "uri": plain } }));
+        let index = state.get_indexer().unwrap().get_index();
+        assert!(index.symbols.get_file_symbols(plain).is_empty());
+        assert!(index.symbols.get_file_symbols(escaped).is_empty());
+        assert!(index.symbols.files().is_empty());
+    }
+}
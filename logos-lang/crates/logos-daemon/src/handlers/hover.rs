@@ -1,10 +1,11 @@
 //! Hover handler
 
 use serde_json::{json, Value};
-use logos_core::Position;
+use logos_core::{sanitize_doc, Position, Range, SymbolKind};
+use logos_index::{ProjectIndex, SmartSymbol, SymbolId};
 
 use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
-use crate::state::State;
+use crate::state::{uri_to_path, State};
 
 /// Handle textDocument/hover
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -22,25 +23,347 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
 
+    // Smart mode: render documentation and signature from the project index
+    if let Some(indexer) = state.get_indexer() {
+        if let Some(symbol) = indexer.index.symbols.find_at_position(uri, position) {
+            let language_id = state
+                .get_document(uri)
+                .map(|doc| doc.language_id.as_str())
+                .unwrap_or("");
+            let supertypes = supertype_names(&indexer.index, &symbol);
+            let hover = json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": render_smart_hover(&symbol, &supertypes, language_id)
+                },
+                "range": range_to_json(&symbol.location.selection_range)
+            });
+            return Response::success(id, hover);
+        }
+
+        // A use of a name: hover its declaration, or every candidate when
+        // the name can't be narrowed down to one
+        if let Some((range, name)) = state.identifier_at(uri, position) {
+            let candidates = match uri_to_path(uri).and_then(|path| indexer.resolve_imported_symbol(&path, &name)) {
+                Some(symbol) => vec![symbol],
+                None => hover_candidates(&indexer.index, uri, position, &name),
+            };
+            if !candidates.is_empty() {
+                let language_id = state
+                    .get_document(uri)
+                    .map(|doc| doc.language_id.as_str())
+                    .unwrap_or("");
+                let sections: Vec<String> = candidates
+                    .iter()
+                    .map(|symbol| {
+                        let supertypes = supertype_names(&indexer.index, symbol);
+                        let mut section = render_smart_hover(symbol, &supertypes, language_id);
+                        if candidates.len() > 1 {
+                            section.push_str(&format!("\n\n_Defined in `{}`_", symbol.location.uri));
+                        }
+                        section
+                    })
+                    .collect();
+                let hover = json!({
+                    "contents": {
+                        "kind": "markdown",
+                        "value": sections.join("\n\n---\n\n")
+                    },
+                    "range": range_to_json(&range)
+                });
+                return Response::success(id, hover);
+            }
+        }
+    }
+
     if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
         let hover = json!({
             "contents": {
                 "kind": "markdown",
                 "value": format!("**{}** ({})", symbol.name, format!("{:?}", symbol.kind))
             },
-            "range": {
-                "start": {
-                    "line": symbol.selection_range.start.line,
-                    "character": symbol.selection_range.start.column
-                },
-                "end": {
-                    "line": symbol.selection_range.end.line,
-                    "character": symbol.selection_range.end.column
-                }
-            }
+            "range": range_to_json(&symbol.selection_range)
         });
         return Response::success(id, hover);
     }
 
     Response::null_result(id)
 }
+
+/// Declarations `name` at `position` in `uri` could refer to. Declarations
+/// in the same file win over other files; among those, the ones in the
+/// innermost scope around the position win, and declarations local to a
+/// scope that doesn't contain the position are skipped. Whatever is left
+/// is still ambiguous, e.g. overloads.
+fn hover_candidates(index: &ProjectIndex, uri: &str, position: Position, name: &str) -> Vec<SmartSymbol> {
+    let all = index.symbols.find_by_name(name);
+    if !all.iter().any(|s| s.location.uri == uri) {
+        return all;
+    }
+
+    // Start of the enclosing scope the symbol is declared in, `None` at the
+    // top level, or no entry at all for a scope that doesn't enclose the
+    // position
+    let scope_start = |symbol: &SmartSymbol| -> Option<Option<Position>> {
+        match symbol.parent.and_then(|p| index.symbols.get(p)) {
+            Some(parent) if parent.location.range.contains(position) => Some(Some(parent.location.range.start)),
+            Some(_) => None,
+            None => Some(None),
+        }
+    };
+    let scoped: Vec<(Option<Position>, SmartSymbol)> = all
+        .into_iter()
+        .filter(|s| s.location.uri == uri)
+        .filter_map(|s| Some((scope_start(&s)?, s)))
+        .collect();
+    let innermost = scoped.iter().map(|(start, _)| *start).max();
+    scoped
+        .into_iter()
+        .filter(|(start, _)| Some(*start) == innermost)
+        .map(|(_, s)| s)
+        .collect()
+}
+
+/// Names of the types a type symbol extends and implements
+#[derive(Debug, Default)]
+struct Supertypes {
+    extends: Vec<String>,
+    implements: Vec<String>,
+}
+
+/// Resolve a type's supertypes from the type hierarchy back to names
+fn supertype_names(index: &ProjectIndex, symbol: &SmartSymbol) -> Supertypes {
+    let names = |ids: Vec<SymbolId>| -> Vec<String> {
+        ids.into_iter()
+            .filter_map(|id| index.symbols.get(id).map(|s| s.name))
+            .collect()
+    };
+    Supertypes {
+        extends: names(index.type_hierarchy.get_supertypes(symbol.id)),
+        implements: names(index.type_hierarchy.get_interfaces(symbol.id)),
+    }
+}
+
+/// Render hover Markdown for a Smart mode symbol: a fenced signature,
+/// the qualified name, and the documentation if any
+fn render_smart_hover(symbol: &SmartSymbol, supertypes: &Supertypes, language_id: &str) -> String {
+    let signature = render_type_header(symbol, supertypes).unwrap_or_else(|| render_signature(symbol));
+    let mut value = format!("```{}\n{}\n```", language_id, signature);
+
+    if !symbol.qualified_name.is_empty() && symbol.qualified_name != symbol.name {
+        value.push_str(&format!("\n\n`{}`", symbol.qualified_name));
+    }
+
+    if let Some(doc) = symbol.documentation.as_deref().map(sanitize_doc) {
+        if !doc.is_empty() {
+            value.push_str("\n\n---\n\n");
+            value.push_str(&doc);
+        }
+    }
+
+    value
+}
+
+/// `class Dog extends Animal implements Pet` for types with known supertypes
+fn render_type_header(symbol: &SmartSymbol, supertypes: &Supertypes) -> Option<String> {
+    let keyword = match symbol.kind {
+        SymbolKind::Class => "class",
+        SymbolKind::Interface => "interface",
+        SymbolKind::Struct => "struct",
+        _ => return None,
+    };
+    if supertypes.extends.is_empty() && supertypes.implements.is_empty() {
+        return None;
+    }
+
+    let mut header = format!("{} {}", keyword, symbol.name);
+    if !supertypes.extends.is_empty() {
+        header.push_str(&format!(" extends {}", supertypes.extends.join(", ")));
+    }
+    if !supertypes.implements.is_empty() {
+        header.push_str(&format!(" implements {}", supertypes.implements.join(", ")));
+    }
+    Some(header)
+}
+
+/// Render a one-line signature from the symbol's type info
+pub(crate) fn render_signature(symbol: &SmartSymbol) -> String {
+    let type_info = match &symbol.type_info {
+        Some(ti) => ti,
+        None => return format!("({:?}) {}", symbol.kind, symbol.name),
+    };
+
+    let params = match render_parameters(symbol) {
+        Some(params) => params,
+        None => return format!("{}: {}", symbol.name, strip_annotation(&type_info.type_expr)),
+    };
+
+    let type_params = if type_info.type_params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_info.type_params.join(", "))
+    };
+
+    let mut signature = format!("{}{}{}", symbol.name, type_params, params);
+    if let Some(ret) = &type_info.return_type {
+        signature.push_str(&format!(": {}", strip_annotation(&ret.type_expr)));
+    }
+    signature
+}
+
+/// The parenthesized parameter list of a function, method or constructor,
+/// as Basic mode's symbol detail shows it; `None` for other symbols
+pub(crate) fn render_parameters(symbol: &SmartSymbol) -> Option<String> {
+    let type_info = symbol.type_info.as_ref()?;
+    let is_callable = matches!(
+        symbol.kind,
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor
+    );
+    if !is_callable {
+        return None;
+    }
+
+    // "(params) => ret" keeps parameter names; per-parameter types don't
+    let params = type_info
+        .type_expr
+        .rsplit_once(" => ")
+        .map(|(params, _)| params.trim())
+        .filter(|params| params.starts_with('(') && params.ends_with(')'))
+        .map(|params| params[1..params.len() - 1].to_string())
+        .unwrap_or_else(|| {
+            type_info
+                .param_types
+                .iter()
+                .map(|p| strip_annotation(&p.type_expr))
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+    Some(format!("({})", params))
+}
+
+/// Strip a leading `:` or `->` that some grammars include in annotations
+fn strip_annotation(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix(':')
+        .or_else(|| text.strip_prefix("->"))
+        .unwrap_or(text)
+        .trim()
+}
+
+fn range_to_json(range: &Range) -> Value {
+    json!({
+        "start": {
+            "line": range.start.line,
+            "character": range.start.column
+        },
+        "end": {
+            "line": range.end.line,
+            "character": range.end.column
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smart_hover_documented_function() {
+        let mut state = State::new();
+        state.enable_smart_mode().unwrap();
+        let source = "/** Greets a user by name. */\nfunction greet(name: string): string {\n    return name;\n}\n";
+        state.open_document("file:///greet.ts".to_string(), "typescript".to_string(), source.to_string());
+
+        let params = json!({
+            "textDocument": { "uri": "file:///greet.ts" },
+            "position": { "line": 1, "character": 11 }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        assert_eq!(result["contents"]["kind"], "markdown");
+        assert_eq!(result["range"]["start"], json!({ "line": 1, "character": 9 }));
+        let value = result["contents"]["value"].as_str().unwrap();
+        assert!(value.contains("```typescript\ngreet(name: string): string\n```"), "{}", value);
+        assert!(value.contains("Greets a user by name."), "{}", value);
+    }
+
+    #[test]
+    fn test_hover_shows_base_classes() {
+        use logos_index::ProjectIndexer;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pets.py");
+        std::fs::write(&path, "class Animal:\n    pass\n\nclass Dog(Animal):\n    pass\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&path).unwrap();
+        let mut state = State::new();
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+
+        let params = json!({
+            "textDocument": { "uri": format!("file://{}", path.display()) },
+            "position": { "line": 3, "character": 7 }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        let value = result["contents"]["value"].as_str().unwrap();
+        assert!(value.contains("class Dog extends Animal"), "{}", value);
+    }
+
+    #[test]
+    fn test_hover_prefers_same_file_declaration() {
+        let mut state = State::new();
+        state.enable_smart_mode().unwrap();
+        state.open_document(
+            "file:///dates.ts".to_string(),
+            "typescript".to_string(),
+            "function format(d: Date): string {\n    return '';\n}\nformat(today);\n".to_string(),
+        );
+        state.open_document(
+            "file:///money.ts".to_string(),
+            "typescript".to_string(),
+            "function format(cents: number): string {\n    return '';\n}\n".to_string(),
+        );
+        state.open_document(
+            "file:///report.ts".to_string(),
+            "typescript".to_string(),
+            "format(total);\n".to_string(),
+        );
+
+        let hover_at = |uri: &str, line: u32| -> String {
+            let params = json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": 2 }
+            });
+            let result = handle(&state, &params, None).result.unwrap();
+            result["contents"]["value"].as_str().unwrap().to_string()
+        };
+
+        let value = hover_at("file:///dates.ts", 3);
+        assert!(value.contains("format(d: Date)"), "{}", value);
+        assert!(!value.contains("cents"), "{}", value);
+
+        // Nothing to narrow by: both declarations, one section each
+        let value = hover_at("file:///report.ts", 0);
+        let sections: Vec<&str> = value.split("\n\n---\n\n").collect();
+        assert_eq!(sections.len(), 2, "{}", value);
+        assert!(sections[0].contains("format(d: Date)") && sections[0].contains("file:///dates.ts"));
+        assert!(sections[1].contains("format(cents: number)") && sections[1].contains("file:///money.ts"));
+    }
+
+    #[test]
+    fn test_hover_prefers_enclosing_class_member() {
+        let mut state = State::new();
+        state.enable_smart_mode().unwrap();
+        let source = "class Draft:\n    def save(self, path):\n        pass\n\nclass Post:\n    def save(self, force):\n        self.save(True)\n";
+        state.open_document("file:///posts.py".to_string(), "python".to_string(), source.to_string());
+
+        let params = json!({
+            "textDocument": { "uri": "file:///posts.py" },
+            "position": { "line": 6, "character": 14 }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        let value = result["contents"]["value"].as_str().unwrap();
+        assert!(value.contains("`Post.save`") && !value.contains("Draft"), "{}", value);
+    }
+}
@@ -2,9 +2,12 @@
 
 use log::info;
 use serde_json::{json, Value};
+use logos_index::{ScannerConfig, TodoIndex};
 
-use crate::protocol::{InitializeParams, RequestId, Response};
-use crate::state::State;
+use crate::protocol::{
+    DidChangeWorkspaceFoldersParams, InitializationOptions, InitializeParams, RequestId, Response,
+};
+use crate::state::{uri_to_root, State};
 
 /// Handle initialize request
 pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> Response {
@@ -24,7 +27,27 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
     info!("  Root path: {:?}", params.root_path);
     info!("  Root URI: {:?}", params.root_uri);
 
-    state.root_path = params.root_path.or(params.root_uri);
+    // Prefer workspace folders; fall back to the single root for older clients
+    state.root_paths = match params.workspace_folders {
+        Some(folders) if !folders.is_empty() => folders
+            .iter()
+            .map(|folder| {
+                info!("  Workspace folder: {} ({})", folder.name, folder.uri);
+                uri_to_root(&folder.uri)
+            })
+            .collect(),
+        _ => params
+            .root_path
+            .or(params.root_uri.as_deref().map(uri_to_root))
+            .into_iter()
+            .collect(),
+    };
+    if let Some(options) = params.initialization_options {
+        match serde_json::from_value::<InitializationOptions>(options) {
+            Ok(options) => apply_options(state, options),
+            Err(e) => log::warn!("Invalid initializationOptions, using defaults: {}", e),
+        }
+    }
     state.initialized = true;
 
     // Return server capabilities
@@ -32,13 +55,15 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
         "capabilities": {
             "textDocumentSync": {
                 "openClose": true,
-                "change": 1,  // Full sync
+                "change": 2,  // Incremental sync
                 "save": {
                     "includeText": false
                 }
             },
             "completionProvider": {
-                "triggerCharacters": [".", ":", "<", "\"", "'", "/", "@", "{", "("],
+                // The last character of `.`, `::` and `->`; the handler
+                // checks the accessor exists in the document's language
+                "triggerCharacters": [".", ":", ">"],
                 "resolveProvider": false
             },
             "hoverProvider": true,
@@ -46,12 +71,23 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
             "referencesProvider": true,
             "documentSymbolProvider": true,
             "workspaceSymbolProvider": true,
+            "workspace": {
+                "workspaceFolders": {
+                    "supported": true,
+                    "changeNotifications": true
+                },
+                "fileOperations": {
+                    "didRename": {
+                        "filters": [{ "scheme": "file", "pattern": { "glob": "**/*" } }]
+                    }
+                }
+            },
             "renameProvider": {
                 "prepareProvider": true
             },
             "diagnosticProvider": {
-                "interFileDependencies": false,
-                "workspaceDiagnostics": false
+                "interFileDependencies": true,
+                "workspaceDiagnostics": true
             }
         },
         "serverInfo": {
@@ -63,8 +99,88 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
     Response::success(id, capabilities)
 }
 
+/// Override the analysis defaults with the options the client set
+fn apply_options(state: &mut State, options: InitializationOptions) {
+    let config = &mut state.analysis_config;
+    if let Some(enabled) = options.detect_unused {
+        config.detect_unused = enabled;
+    }
+    if let Some(enabled) = options.report_unused {
+        config.report_unused = enabled;
+    }
+    if let Some(enabled) = options.strict_types {
+        config.strict_types = enabled;
+    }
+    if let Some(enabled) = options.null_safety {
+        config.null_safety = enabled;
+    }
+    if let Some(overrides) = options.severity_overrides {
+        config.severity_overrides = overrides;
+    }
+    info!("  Analysis config: {:?}", config);
+    if let Some(size) = options.workspace_symbol_cache_size {
+        state.workspace_symbol_cache.lock().unwrap().set_capacity(size);
+    }
+    if let Some(kinds) = options.excluded_symbol_kinds {
+        state.excluded_symbol_kinds = kinds;
+    }
+    if let Some(enabled) = options.follow_type_aliases {
+        state.follow_type_aliases = enabled;
+    }
+    if let Some(enabled) = options.resolve_member_access {
+        state.resolve_member_access = enabled;
+    }
+    if let Some(enabled) = options.todo_diagnostics {
+        state.todo_index = TodoIndex::with_config(&ScannerConfig {
+            report_diagnostics: enabled,
+            ..ScannerConfig::default()
+        });
+    }
+}
+
 /// Handle initialized notification
 pub fn initialized(state: &mut State) {
     info!("Client initialized, server is ready");
     state.initialized = true;
 }
+
+/// Handle workspace/didChangeWorkspaceFolders notification
+pub fn did_change_workspace_folders(state: &mut State, params: &Value) {
+    let params: DidChangeWorkspaceFoldersParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            log::warn!("Invalid didChangeWorkspaceFolders params: {}", e);
+            return;
+        }
+    };
+
+    for folder in &params.event.removed {
+        info!("Removing workspace folder: {}", folder.uri);
+        state.remove_workspace_root(&uri_to_root(&folder.uri));
+    }
+    for folder in &params.event.added {
+        info!("Adding workspace folder: {}", folder.uri);
+        state.add_workspace_root(uri_to_root(&folder.uri));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_options_fall_back_to_defaults() {
+        let mut state = State::new();
+        let params = json!({
+            "processId": null,
+            "rootUri": null,
+            "initializationOptions": { "detectUnused": "yes", "strictTypes": true }
+        });
+        let response = initialize(&mut state, &params, None);
+
+        assert!(response.error.is_none());
+        assert!(response.result.unwrap()["capabilities"].is_object());
+        assert!(state.initialized);
+        assert_eq!(state.analysis_config.strict_types, State::new().analysis_config.strict_types);
+    }
+}
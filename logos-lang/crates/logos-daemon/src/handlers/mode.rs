@@ -56,7 +56,9 @@ pub fn handle_get_mode(state: &State, _params: &Value, id: Option<RequestId>) ->
     Response::success(id, json!({ "mode": mode }))
 }
 
-/// Handle logos/getIndexStats
+/// Handle logos/getIndexStats. `generation` changes whenever the project
+/// index does, so clients can tell when cached symbol data is stale; it is
+/// null in Basic mode, which has no project index.
 pub fn handle_get_index_stats(state: &State, _params: &Value, id: Option<RequestId>) -> Response {
     if let Some(indexer) = state.get_indexer() {
         let index = indexer.get_index();
@@ -66,6 +68,8 @@ pub fn handle_get_index_stats(state: &State, _params: &Value, id: Option<Request
                 "symbolCount": index.symbols.len(),
                 "callSiteCount": index.call_graph.len(),
                 "fileCount": index.dependencies.file_count(),
+                "adapterErrors": indexer.adapter_errors(),
+                "generation": index.generation(),
             }),
         )
     } else {
@@ -75,6 +79,8 @@ pub fn handle_get_index_stats(state: &State, _params: &Value, id: Option<Request
                 "symbolCount": state.symbol_index.symbol_count(),
                 "callSiteCount": 0,
                 "fileCount": 0,
+                "adapterErrors": [],
+                "generation": null,
             }),
         )
     }
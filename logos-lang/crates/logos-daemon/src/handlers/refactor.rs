@@ -1,6 +1,8 @@
 //! Refactoring handlers
 
 use serde_json::{json, Value};
+use logos_core::Location;
+use logos_index::ProjectIndex;
 
 use crate::protocol::{RefactorParams, ExtractVariableParams, ExtractMethodParams, RequestId, Response};
 use crate::state::State;
@@ -237,25 +239,29 @@ pub fn can_safe_delete(state: &State, params: &Value, id: Option<RequestId>) ->
 
     match logos_refactor::safe_delete::analyze(&ctx) {
         Ok(analysis) => {
-            let usages: Vec<_> = analysis.usages.iter().map(|loc| {
-                json!({
-                    "uri": loc.uri,
-                    "range": {
-                        "start": {
-                            "line": loc.range.start.line,
-                            "character": loc.range.start.column
-                        },
-                        "end": {
-                            "line": loc.range.end.line,
-                            "character": loc.range.end.column
-                        }
+            let usages: Vec<_> = analysis.usages.iter().map(location_json).collect();
+
+            // In-document usages plus, in Smart mode, references and call
+            // sites recorded in the project index
+            let mut references = analysis.usages.clone();
+            if let Some(indexer) = state.get_indexer() {
+                for location in index_references(&indexer.index, uri, selection) {
+                    let duplicate = references
+                        .iter()
+                        .any(|r| r.uri == location.uri && r.range.start == location.range.start);
+                    if !duplicate {
+                        references.push(location);
                     }
-                })
-            }).collect();
+                }
+            }
+            let safe = references.is_empty();
 
             Response::success(id, json!({
-                "canDelete": analysis.can_delete,
+                "canDelete": safe,
+                "safe": safe,
                 "symbolName": analysis.symbol_name,
+                "referenceCount": references.len(),
+                "references": references.iter().map(location_json).collect::<Vec<_>>(),
                 "usages": usages,
                 "warnings": analysis.warnings
             }))
@@ -269,6 +275,61 @@ pub fn can_safe_delete(state: &State, params: &Value, id: Option<RequestId>) ->
     }
 }
 
+/// Locations outside the selected symbol's own declaration that reference
+/// or call it, according to the project index
+fn index_references(index: &ProjectIndex, uri: &str, selection: logos_core::Range) -> Vec<Location> {
+    let symbol = match index.symbols.find_at_position(uri, selection.start).or_else(|| {
+        index
+            .symbols
+            .get_file_symbols(uri)
+            .into_iter()
+            .find(|s| s.location.selection_range.overlaps(&selection))
+    }) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+
+    let is_external = |location: &logos_index::SymbolLocation| {
+        location.uri != symbol.location.uri
+            || !(symbol.location.range.start <= location.range.start
+                && location.range.end <= symbol.location.range.end)
+    };
+
+    let references = index
+        .symbols
+        .get_references(symbol.id)
+        .into_iter()
+        .filter(|r| !r.is_definition && is_external(&r.location))
+        .map(|r| r.location);
+    let calls = index
+        .call_graph
+        .get_callers(symbol.id)
+        .into_iter()
+        .filter(|c| c.caller != symbol.id && is_external(&c.location))
+        .map(|c| c.location);
+
+    references
+        .chain(calls)
+        .map(|location| Location::new(location.uri, location.range))
+        .collect()
+}
+
+fn location_json(location: &Location) -> Value {
+    json!({
+        "uri": location.uri,
+        "range": {
+            "start": {
+                "line": location.range.start.line,
+                "character": location.range.start.column
+            },
+            "end": {
+                "line": location.range.end.line,
+                "character": location.range.end.column
+            }
+        }
+    })
+}
+
 /// Handle logos/safeDelete
 pub fn safe_delete(state: &State, params: &Value, id: Option<RequestId>) -> Response {
     let params: RefactorParams = match serde_json::from_value(params.clone()) {
@@ -349,3 +410,81 @@ pub fn safe_delete(state: &State, params: &Value, id: Option<RequestId>) -> Resp
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::IntelligenceMode;
+    use logos_index::{
+        make_location, CallSite, CallType, LanguageAdapter, ProjectIndexer, SymbolReference,
+        TypeScriptAdapter,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_can_safe_delete_reports_index_references() {
+        let uri = "file:///lib.ts".to_string();
+        let source = "export function helper() {}\nexport function unused() {}\n";
+
+        let mut state = State::new();
+        state.open_document(uri.clone(), "typescript".to_string(), source.to_string());
+
+        let indexer = ProjectIndexer::new();
+        for symbol in TypeScriptAdapter::new().unwrap().analyze(&uri, source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        let main_source = "import { helper } from './lib';\nfunction main() { helper(); }\n";
+        for symbol in TypeScriptAdapter::new().unwrap().analyze("file:///main.ts", main_source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        let helper = indexer.index.symbols.find_by_name("helper").remove(0);
+        let main = indexer.index.symbols.find_by_name("main").remove(0);
+        let call_location = make_location(
+            "file:///main.ts",
+            logos_core::Range::from_coords(1, 18, 1, 26),
+            logos_core::Range::from_coords(1, 18, 1, 24),
+        );
+        indexer.index.symbols.add_reference(SymbolReference {
+            symbol_id: helper.id,
+            location: make_location(
+                "file:///main.ts",
+                logos_core::Range::from_coords(0, 9, 0, 15),
+                logos_core::Range::from_coords(0, 9, 0, 15),
+            ),
+            is_definition: false,
+            is_write: false,
+        });
+        indexer.index.call_graph.add_call(CallSite {
+            caller: main.id,
+            callee: helper.id,
+            location: call_location,
+            call_type: CallType::Direct,
+        });
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = IntelligenceMode::Smart;
+
+        let params = |start: u32, end: u32| {
+            json!({
+                "textDocument": { "uri": uri },
+                "range": {
+                    "start": { "line": start, "character": 16 },
+                    "end": { "line": start, "character": end }
+                }
+            })
+        };
+
+        let result = can_safe_delete(&state, &params(0, 22), None).result.unwrap();
+        assert_eq!(result["safe"], false);
+        assert_eq!(result["referenceCount"], 2);
+        let references = result["references"].as_array().unwrap();
+        assert!(references.iter().all(|r| r["uri"] == "file:///main.ts"));
+        assert!(references
+            .iter()
+            .any(|r| r["range"]["start"]["line"] == 1 && r["range"]["start"]["character"] == 18));
+
+        let result = can_safe_delete(&state, &params(1, 22), None).result.unwrap();
+        assert_eq!(result["safe"], true);
+        assert_eq!(result["canDelete"], true);
+        assert!(result["references"].as_array().unwrap().is_empty());
+    }
+}
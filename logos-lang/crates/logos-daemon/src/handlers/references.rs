@@ -1,14 +1,18 @@
 //! References handler
 
+use std::collections::HashSet;
+
 use serde_json::{json, Value};
-use logos_core::Position;
+use logos_core::{Position, Range};
+use logos_index::scan_references;
+use logos_parser::LanguageId;
 
-use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
+use crate::protocol::{ReferenceParams, RequestId, Response};
 use crate::state::State;
 
 /// Handle textDocument/references
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
-    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+    let params: ReferenceParams = match serde_json::from_value(params.clone()) {
         Ok(p) => p,
         Err(e) => {
             return Response::error(
@@ -21,34 +25,117 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
 
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
+    // Clients that leave out the context get the declaration too
+    let include_declaration = params.context.is_none_or(|c| c.include_declaration);
+
+    // Smart mode: use the references recorded in the project index
+    if let Some(indexer) = state.get_indexer() {
+        if let Some(symbol) = indexer.index.symbols.find_at_position(uri, position) {
+            let recorded = indexer.index.symbols.get_references(symbol.id);
+            if !recorded.is_empty() {
+                let mut locations: Vec<(String, Range)> = recorded
+                    .iter()
+                    .filter(|r| include_declaration || !r.is_definition)
+                    .map(|r| (r.location.uri.clone(), r.location.selection_range))
+                    .collect();
+                if include_declaration && !recorded.iter().any(|r| r.is_definition) {
+                    locations.insert(0, (symbol.location.uri.clone(), symbol.location.selection_range));
+                }
+                return Response::success(id, locations_json(&locations));
+            }
+        }
+    }
 
-    // Find the symbol at the given position
-    let symbol = match state.symbol_index.find_at_position(uri, position) {
-        Some(s) => s,
+    // Basic mode: every identifier with the same name in open documents
+    let name = match state
+        .identifier_at(uri, position)
+        .map(|(_, name)| name)
+        .or_else(|| state.symbol_index.find_at_position(uri, position).map(|s| s.name.clone()))
+    {
+        Some(name) => name,
         None => return Response::success(id, json!([])),
     };
 
-    let symbol_name = symbol.name.clone();
+    let declarations: HashSet<(&str, Range)> = state
+        .symbol_index
+        .find_by_name(&name)
+        .map(|s| (s.uri.as_str(), s.selection_range))
+        .collect();
+
+    let mut uris: Vec<&String> = state.documents.keys().collect();
+    uris.sort();
+    let mut locations = Vec::new();
+    for doc_uri in uris {
+        let doc = &state.documents[doc_uri];
+        let Some(language) = LanguageId::from_str(&doc.language_id) else {
+            continue;
+        };
+        for range in scan_references(doc.content(), &name, language) {
+            if include_declaration || !declarations.contains(&(doc_uri.as_str(), range)) {
+                locations.push((doc_uri.clone(), range));
+            }
+        }
+    }
+
+    Response::success(id, locations_json(&locations))
+}
 
-    // Search for all occurrences of this symbol name
-    let references: Vec<_> = state.symbol_index.search(&symbol_name)
+fn locations_json(locations: &[(String, Range)]) -> Value {
+    let locations: Vec<Value> = locations
         .iter()
-        .map(|s| {
+        .map(|(uri, range)| {
             json!({
-                "uri": s.uri,
+                "uri": uri,
                 "range": {
                     "start": {
-                        "line": s.selection_range.start.line,
-                        "character": s.selection_range.start.column
+                        "line": range.start.line,
+                        "character": range.start.column
                     },
                     "end": {
-                        "line": s.selection_range.end.line,
-                        "character": s.selection_range.end.column
+                        "line": range.end.line,
+                        "character": range.end.column
                     }
                 }
             })
         })
         .collect();
+    json!(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference_lines(include_declaration: bool) -> Vec<(u64, u64)> {
+        let mut state = State::new();
+        state.open_document(
+            "file:///greet.ts".to_string(),
+            "typescript".to_string(),
+            "function greet() {}\ngreet();\nconst greeting = greet;\n".to_string(),
+        );
+
+        let params = json!({
+            "textDocument": { "uri": "file:///greet.ts" },
+            "position": { "line": 1, "character": 2 },
+            "context": { "includeDeclaration": include_declaration }
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| (l["range"]["start"]["line"].as_u64().unwrap(), l["range"]["start"]["character"].as_u64().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_exclude_declaration() {
+        // `greeting` is not an occurrence of `greet`
+        assert_eq!(reference_lines(false), vec![(1, 0), (2, 17)]);
+    }
 
-    Response::success(id, json!(references))
+    #[test]
+    fn test_include_declaration() {
+        assert_eq!(reference_lines(true), vec![(0, 9), (1, 0), (2, 17)]);
+    }
 }
@@ -3,6 +3,8 @@
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use logos_core::Position;
+use logos_index::scan_references;
+use logos_parser::LanguageId;
 
 use crate::protocol::{TextDocumentPositionParams, RenameParams, RequestId, Response};
 use crate::state::State;
@@ -23,24 +25,26 @@ pub fn prepare(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
 
-    if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
-        let result = json!({
+    match state.identifier_at(uri, position) {
+        Some((range, name)) => Response::success(id, json!({
             "range": {
                 "start": {
-                    "line": symbol.selection_range.start.line,
-                    "character": symbol.selection_range.start.column
+                    "line": range.start.line,
+                    "character": range.start.column
                 },
                 "end": {
-                    "line": symbol.selection_range.end.line,
-                    "character": symbol.selection_range.end.column
+                    "line": range.end.line,
+                    "character": range.end.column
                 }
             },
-            "placeholder": symbol.name
-        });
-        return Response::success(id, result);
+            "placeholder": name
+        })),
+        None => Response::error(
+            id,
+            crate::protocol::error_codes::REQUEST_FAILED,
+            "The element at this position can't be renamed".to_string(),
+        ),
     }
-
-    Response::null_result(id)
 }
 
 /// Handle textDocument/rename
@@ -60,35 +64,42 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let position = Position::new(params.position.line, params.position.character);
     let new_name = &params.new_name;
 
-    // Find the symbol at the given position
-    let symbol = match state.symbol_index.find_at_position(uri, position) {
-        Some(s) => s,
-        None => return Response::null_result(id),
+    // Resolve the identifier under the cursor the same way prepareRename
+    // does, so usages can be renamed as well as declarations
+    let old_name = match state.identifier_at(uri, position) {
+        Some((_, name)) => name,
+        None => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::REQUEST_FAILED,
+                "The element at this position can't be renamed".to_string(),
+            );
+        }
     };
 
-    let old_name = symbol.name.clone();
-
-    // Find all references to this symbol
-    let references = state.symbol_index.search(&old_name);
-
     // Group edits by document URI
     let mut changes: HashMap<String, Vec<Value>> = HashMap::new();
 
-    for s in references {
-        let edit = json!({
-            "range": {
-                "start": {
-                    "line": s.selection_range.start.line,
-                    "character": s.selection_range.start.column
+    for (doc_uri, doc) in &state.documents {
+        let Some(language) = LanguageId::from_str(&doc.language_id) else {
+            continue;
+        };
+        for range in scan_references(doc.content(), &old_name, language) {
+            let edit = json!({
+                "range": {
+                    "start": {
+                        "line": range.start.line,
+                        "character": range.start.column
+                    },
+                    "end": {
+                        "line": range.end.line,
+                        "character": range.end.column
+                    }
                 },
-                "end": {
-                    "line": s.selection_range.end.line,
-                    "character": s.selection_range.end.column
-                }
-            },
-            "newText": new_name
-        });
-        changes.entry(s.uri.clone()).or_default().push(edit);
+                "newText": new_name
+            });
+            changes.entry(doc_uri.clone()).or_default().push(edit);
+        }
     }
 
     let workspace_edit = json!({
@@ -97,3 +108,62 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
 
     Response::success(id, workspace_edit)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare_at(state: &State, uri: &str, line: u32, character: u32) -> Response {
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character }
+        });
+        prepare(state, &params, None)
+    }
+
+    #[test]
+    fn test_prepare_rename_ranges() {
+        let mut state = State::new();
+        let uri = "file:///greet.py";
+        let source = "def greet(name):\n    return \"hi \" + name\n";
+        state.open_document(uri.to_string(), "python".to_string(), source.to_string());
+
+        // The parameter reference on line 1, not the enclosing function
+        let result = prepare_at(&state, uri, 1, 22).result.unwrap();
+        assert_eq!(result["placeholder"], "name");
+        assert_eq!(result["range"]["start"], json!({ "line": 1, "character": 19 }));
+        assert_eq!(result["range"]["end"], json!({ "line": 1, "character": 23 }));
+
+        // Keyword, string literal
+        for (line, character) in [(0, 1), (1, 6), (1, 13)] {
+            let response = prepare_at(&state, uri, line, character);
+            assert!(response.result.is_none(), "{}:{} should not be renameable", line, character);
+            assert_eq!(
+                response.error.unwrap().code,
+                crate::protocol::error_codes::REQUEST_FAILED
+            );
+        }
+    }
+
+    #[test]
+    fn test_rename_from_usage_site() {
+        let mut state = State::new();
+        let uri = "file:///greet.ts";
+        let source = "function greet() {}\ngreet();\nconst greeting = greet;\n";
+        state.open_document(uri.to_string(), "typescript".to_string(), source.to_string());
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 1, "character": 2 },
+            "newName": "hello"
+        });
+        let result = handle(&state, &params, None).result.unwrap();
+        let edits = result["changes"][uri].as_array().unwrap();
+        let starts: Vec<(u64, u64)> = edits
+            .iter()
+            .map(|e| (e["range"]["start"]["line"].as_u64().unwrap(), e["range"]["start"]["character"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(starts, vec![(0, 9), (1, 0), (2, 17)]);
+        assert!(edits.iter().all(|e| e["newText"] == "hello"));
+    }
+}
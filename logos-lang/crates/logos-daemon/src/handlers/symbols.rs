@@ -1,9 +1,14 @@
 //! Symbol handlers
 
+use std::collections::HashMap;
+
 use serde_json::{json, Value};
+use logos_core::{Range, Symbol, SymbolKind};
+use logos_index::{glob_match, is_glob_pattern, SmartSymbol, SymbolId, SymbolTable};
 
+use crate::handlers::hover::render_parameters;
 use crate::protocol::{DocumentSymbolParams, WorkspaceSymbolParams, RequestId, Response};
-use crate::state::State;
+use crate::state::{uri_to_path, State};
 
 /// Handle textDocument/documentSymbol
 pub fn document_symbols(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -20,37 +25,128 @@ pub fn document_symbols(state: &State, params: &Value, id: Option<RequestId>) ->
 
     let uri = &params.text_document.uri;
 
-    let symbols: Vec<_> = state.symbol_index.get_document_symbols(uri)
-        .iter()
+    // Hierarchical DocumentSymbol[] so outlines nest members under types
+    let outline = smart_outline(state, uri).unwrap_or_else(|| state.document_symbols(uri));
+    let symbols = document_symbols_json(&outline, &state.excluded_symbol_kinds);
+
+    Response::success(id, json!(symbols))
+}
+
+/// In Smart mode, the outline from the language adapter run over the open
+/// buffer, nested through each symbol's `parent`
+fn smart_outline(state: &State, uri: &str) -> Option<Vec<Symbol>> {
+    let indexer = state.get_indexer()?;
+    let doc = state.get_document(uri)?;
+    let result = indexer.analyze_source(&uri_to_path(uri)?, doc.content())?;
+
+    let mut by_parent: HashMap<Option<SymbolId>, Vec<&SmartSymbol>> = HashMap::new();
+    for symbol in &result.symbols {
+        // A parent the adapter didn't emit leaves the symbol at the top level
+        let parent = symbol.parent.filter(|p| result.symbols.iter().any(|s| s.id == *p));
+        by_parent.entry(parent).or_default().push(symbol);
+    }
+    Some(outline_children(&by_parent, None))
+}
+
+fn outline_children(by_parent: &HashMap<Option<SymbolId>, Vec<&SmartSymbol>>, parent: Option<SymbolId>) -> Vec<Symbol> {
+    by_parent
+        .get(&parent)
+        .into_iter()
+        .flatten()
+        .map(|s| Symbol {
+            name: s.name.clone(),
+            kind: s.kind,
+            range: s.location.range,
+            selection_range: s.location.selection_range,
+            detail: s.type_info.as_ref().map(|t| t.type_expr.clone()),
+            children: outline_children(by_parent, Some(s.id)),
+        })
+        .collect()
+}
+
+/// Map symbols to nested LSP `DocumentSymbol`s, ordered by source position.
+/// Symbols of an excluded kind are dropped and their children move up to
+/// the nearest kept ancestor.
+fn document_symbols_json(symbols: &[Symbol], excluded: &[SymbolKind]) -> Vec<Value> {
+    let mut ordered: Vec<&Symbol> = Vec::new();
+    collect_kept(symbols, excluded, &mut ordered);
+    ordered.sort_by_key(|s| s.range.start);
+
+    ordered
+        .into_iter()
         .map(|s| {
-            json!({
+            let mut symbol = json!({
                 "name": s.name,
                 "kind": s.kind.to_monaco_kind(),
-                "range": {
-                    "start": {
-                        "line": s.range.start.line,
-                        "character": s.range.start.column
-                    },
-                    "end": {
-                        "line": s.range.end.line,
-                        "character": s.range.end.column
-                    }
-                },
-                "selectionRange": {
-                    "start": {
-                        "line": s.selection_range.start.line,
-                        "character": s.selection_range.start.column
-                    },
-                    "end": {
-                        "line": s.selection_range.end.line,
-                        "character": s.selection_range.end.column
-                    }
-                }
-            })
+                "range": range_json(&s.range),
+                "selectionRange": range_json(&s.selection_range),
+                "children": document_symbols_json(&s.children, excluded)
+            });
+            if let Some(detail) = &s.detail {
+                symbol["detail"] = json!(detail);
+            }
+            symbol
         })
-        .collect();
+        .collect()
+}
 
-    Response::success(id, json!(symbols))
+fn collect_kept<'a>(symbols: &'a [Symbol], excluded: &[SymbolKind], out: &mut Vec<&'a Symbol>) {
+    for symbol in symbols {
+        if excluded.contains(&symbol.kind) {
+            collect_kept(&symbol.children, excluded, out);
+        } else {
+            out.push(symbol);
+        }
+    }
+}
+
+fn symbol_information_json(
+    name: &str,
+    kind: SymbolKind,
+    uri: &str,
+    range: &Range,
+    container: Option<&str>,
+    detail: Option<&str>,
+) -> Value {
+    let mut symbol = json!({
+        "name": name,
+        "kind": kind.to_monaco_kind(),
+        "location": {
+            "uri": uri,
+            "range": range_json(range)
+        }
+    });
+    if let Some(container) = container {
+        symbol["containerName"] = json!(container);
+    }
+    if let Some(detail) = detail {
+        symbol["detail"] = json!(detail);
+    }
+    symbol
+}
+
+/// Name of the symbol's parent, or failing that the prefix of its
+/// qualified name (`geo::Point` for `geo::Point::norm`)
+fn smart_container(symbols: &SymbolTable, symbol: &SmartSymbol) -> Option<String> {
+    if let Some(parent) = symbol.parent.and_then(|id| symbols.get(id)) {
+        return Some(parent.name);
+    }
+    let qualified = symbol.qualified_name.strip_suffix(symbol.name.as_str())?;
+    let prefix = qualified.strip_suffix("::").or_else(|| qualified.strip_suffix('.'))?;
+    (!prefix.is_empty()).then(|| prefix.to_string())
+}
+
+fn range_json(range: &Range) -> Value {
+    json!({
+        "start": {
+            "line": range.start.line,
+            "character": range.start.column
+        },
+        "end": {
+            "line": range.end.line,
+            "character": range.end.column
+        }
+    })
 }
 
 /// Handle workspace/symbol
@@ -66,28 +162,264 @@ pub fn workspace_symbols(state: &State, params: &Value, id: Option<RequestId>) -
         }
     };
 
-    let results: Vec<_> = state.symbol_index.search(&params.query)
-        .iter()
-        .map(|s| {
-            json!({
-                "name": s.name,
-                "kind": s.kind.to_monaco_kind(),
-                "location": {
-                    "uri": s.uri,
-                    "range": {
-                        "start": {
-                            "line": s.range.start.line,
-                            "character": s.range.start.column
-                        },
-                        "end": {
-                            "line": s.range.end.line,
-                            "character": s.range.end.column
-                        }
-                    }
-                }
-            })
-        })
-        .collect();
+    let query = &params.query;
+    let cache_key = (query.clone(), state.mode);
+    let generation = state.symbols_generation();
+    {
+        let mut cache = state.workspace_symbol_cache.lock().unwrap();
+        if let Some(results) = cache.get(&cache_key, generation) {
+            log::debug!("workspace/symbol cache hit for {:?} ({} hits)", query, cache.hits());
+            return Response::success(id, json!(results));
+        }
+    }
 
+    // `*Controller` / `get*` style queries match whole names; anything else is a substring search
+    let results: Vec<Value> = if is_glob_pattern(query) {
+        match state.get_indexer() {
+            Some(indexer) => {
+                let symbols = &indexer.get_index().symbols;
+                symbols
+                    .search_glob(query)
+                    .iter()
+                    .map(|s| {
+                        let container = smart_container(symbols, s);
+                        let detail = render_parameters(s);
+                        symbol_information_json(
+                            &s.name,
+                            s.kind,
+                            &s.location.uri,
+                            &s.location.range,
+                            container.as_deref(),
+                            detail.as_deref(),
+                        )
+                    })
+                    .collect()
+            }
+            None => {
+                let pattern = query.to_lowercase();
+                state
+                    .symbol_index
+                    .documents()
+                    .flat_map(|uri| state.symbol_index.get_document_symbols(uri))
+                    .filter(|s| glob_match(&pattern, &s.name.to_lowercase()))
+                    .map(|s| symbol_information_json(&s.name, s.kind, &s.uri, &s.range, s.container.as_deref(), s.detail.as_deref()))
+                    .collect()
+            }
+        }
+    } else {
+        state
+            .symbol_index
+            .search(query)
+            .iter()
+            .map(|s| symbol_information_json(&s.name, s.kind, &s.uri, &s.range, s.container.as_deref(), s.detail.as_deref()))
+            .collect()
+    };
+
+    state
+        .workspace_symbol_cache
+        .lock()
+        .unwrap()
+        .insert(cache_key, generation, results.clone());
     Response::success(id, json!(results))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_symbols_are_nested() {
+        let mut state = State::new();
+        let uri = "file:///shapes.py".to_string();
+        let source = "class Shape:\n    def area(self):\n        return 0\n\n    def name(self):\n        return 'shape'\n";
+        state.open_document(uri.clone(), "python".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = document_symbols(&state, &params, None).result.unwrap();
+        let symbols = result.as_array().unwrap();
+
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0]["name"], "Shape");
+        let children = symbols[0]["children"].as_array().unwrap();
+        let names: Vec<_> = children.iter().map(|c| c["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["area", "name"]);
+        assert_eq!(children[0]["selectionRange"]["start"]["line"], 1);
+    }
+
+    #[test]
+    fn test_document_symbols_exclude_kinds() {
+        let mut state = State::new();
+        state.excluded_symbol_kinds = vec![SymbolKind::Variable, SymbolKind::Field];
+        let uri = "file:///Config.java".to_string();
+        let source = "class Config {\n    int port;\n    String host;\n}\n\nclass Server {\n    int count;\n    void start() {}\n}\n";
+        state.open_document(uri.clone(), "java".to_string(), source.to_string());
+
+        let params = json!({ "textDocument": { "uri": uri } });
+        let result = document_symbols(&state, &params, None).result.unwrap();
+        let symbols = result.as_array().unwrap();
+
+        let names: Vec<_> = symbols.iter().map(|s| s["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Config", "Server"]);
+        assert!(symbols[0]["children"].as_array().unwrap().is_empty());
+        let members: Vec<_> = symbols[1]["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(members, vec!["start"]);
+    }
+
+    #[test]
+    fn test_smart_outline_matches_basic_for_struct_fields() {
+        let mut state = State::new();
+        let uri = "file:///model/user.go".to_string();
+        let source = "package model\n\ntype User struct {\n\tName string\n\tAge int\n}\n";
+        state.open_document(uri.clone(), "go".to_string(), source.to_string());
+        let params = json!({ "textDocument": { "uri": uri } });
+
+        let outline = |state: &State| -> Vec<(String, Vec<Value>)> {
+            let result = document_symbols(state, &params, None).result.unwrap();
+            result
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| {
+                    let fields = s["children"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|c| json!([c["name"], c["kind"], c["selectionRange"]]))
+                        .collect();
+                    (s["name"].as_str().unwrap().to_string(), fields)
+                })
+                .collect()
+        };
+        let basic = outline(&state);
+        state.enable_smart_mode().unwrap();
+        let smart = outline(&state);
+
+        assert_eq!(basic.len(), 1);
+        assert_eq!(basic[0].1.len(), 2);
+        assert_eq!(smart, basic);
+    }
+
+    #[test]
+    fn test_c_struct_outline_has_fields() {
+        let mut state = State::new();
+        let uri = "file:///point.c".to_string();
+        state.open_document(uri.clone(), "c".to_string(), "struct Point { int x, y; };\n".to_string());
+        let params = json!({ "textDocument": { "uri": uri } });
+
+        for smart in [false, true] {
+            if smart {
+                state.enable_smart_mode().unwrap();
+            }
+            let result = document_symbols(&state, &params, None).result.unwrap();
+            assert_eq!(result[0]["name"], "Point");
+            let fields: Vec<_> = result[0]["children"].as_array().unwrap().iter().map(|c| c["name"].clone()).collect();
+            assert_eq!(fields, vec!["x", "y"], "smart mode: {}", smart);
+        }
+    }
+
+    fn workspace_symbol_names(state: &State, query: &str) -> Vec<String> {
+        let result = workspace_symbols(state, &json!({ "query": query }), None).result.unwrap();
+        let mut names: Vec<String> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_workspace_symbols_glob_queries() {
+        let mut state = State::new();
+        let source = "class UserService:\n    pass\n\nclass ServiceRegistry:\n    pass\n\ndef handle_click():\n    pass\n\ndef rehandle():\n    pass\n";
+        state.open_document("file:///app.py".to_string(), "python".to_string(), source.to_string());
+
+        assert_eq!(workspace_symbol_names(&state, "*Service"), vec!["UserService"]);
+        assert_eq!(workspace_symbol_names(&state, "handle*"), vec!["handle_click"]);
+        // Plain queries are still substring matches
+        assert_eq!(workspace_symbol_names(&state, "Service"), vec!["ServiceRegistry", "UserService"]);
+    }
+
+    #[test]
+    fn test_workspace_symbols_carry_container_names() {
+        let mut state = State::new();
+        let source = "class User:\n    def save(self):\n        pass\n\nclass Order:\n    def save(self):\n        pass\n";
+        state.open_document("file:///models.py".to_string(), "python".to_string(), source.to_string());
+
+        let result = workspace_symbols(&state, &json!({ "query": "save" }), None).result.unwrap();
+        let mut containers: Vec<&str> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["containerName"].as_str().unwrap())
+            .collect();
+        containers.sort();
+        assert_eq!(containers, vec!["Order", "User"]);
+    }
+
+    #[test]
+    fn test_workspace_symbols_carry_signature_detail() {
+        let mut state = State::new();
+        let source = "def transfer(source, target, amount=0):\n    pass\n";
+        state.open_document("file:///bank.py".to_string(), "python".to_string(), source.to_string());
+
+        let result = workspace_symbols(&state, &json!({ "query": "transfer" }), None).result.unwrap();
+        assert_eq!(result[0]["detail"], "(source, target, amount=0)");
+    }
+
+    #[test]
+    fn test_repeated_workspace_query_is_cached() {
+        let mut state = State::new();
+        let uri = "file:///app.py".to_string();
+        state.open_document(uri.clone(), "python".to_string(), "def render():\n    pass\n".to_string());
+        let hits = |state: &State| state.workspace_symbol_cache.lock().unwrap().hits();
+
+        assert_eq!(workspace_symbol_names(&state, "ren"), vec!["render"]);
+        assert_eq!(workspace_symbol_names(&state, "ren"), vec!["render"]);
+        assert_eq!(hits(&state), 1);
+
+        state.change_document(&uri, vec![(None, "def render():\n    pass\n\ndef rename():\n    pass\n".to_string())]);
+        assert_eq!(workspace_symbol_names(&state, "ren"), vec!["rename", "render"]);
+        assert_eq!(hits(&state), 1);
+    }
+
+    #[test]
+    fn test_workspace_symbols_glob_uses_project_index() {
+        use logos_index::{LanguageAdapter, ProjectIndexer, PythonAdapter};
+        use std::sync::Arc;
+
+        let mut state = State::new();
+        let indexer = ProjectIndexer::new();
+        let source = "class OrderService:\n    pass\n\ndef handle_order():\n    pass\n";
+        for symbol in PythonAdapter::new().unwrap().analyze("file:///orders.py", source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        state.project_indexer = Some(Arc::new(indexer));
+        state.mode = crate::state::IntelligenceMode::Smart;
+
+        assert_eq!(workspace_symbol_names(&state, "*Service"), vec!["OrderService"]);
+        assert_eq!(workspace_symbol_names(&state, "handle*"), vec!["handle_order"]);
+
+        let indexer = state.get_indexer().unwrap();
+        let source = "class Cart:\n    def save_cart(self):\n        pass\n";
+        for symbol in PythonAdapter::new().unwrap().analyze("file:///cart.py", source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        let result = workspace_symbols(&state, &json!({ "query": "save*" }), None).result.unwrap();
+        assert_eq!(result[0]["containerName"], "Cart");
+
+        let source = "export function saveOrder(order: Order, force: boolean): void {}\n";
+        for symbol in logos_index::TypeScriptAdapter::new().unwrap().analyze("file:///orders.ts", source).symbols {
+            indexer.index.symbols.add_symbol(symbol);
+        }
+        let result = workspace_symbols(&state, &json!({ "query": "saveO*" }), None).result.unwrap();
+        // Same detail as Basic mode: the parameter list without the name
+        assert_eq!(result[0]["detail"], "(order: Order, force: boolean)");
+    }
+}
@@ -3,6 +3,7 @@
 //! Communicates via stdio using JSON-RPC 2.0 protocol with LSP-style headers.
 
 mod protocol;
+mod query_cache;
 mod server;
 mod state;
 mod handlers;
@@ -1,11 +1,22 @@
 //! JSON-RPC 2.0 protocol types
+//!
+//! These mirror the wire format, so not every field is consumed by a handler.
 
-use serde::{Deserialize, Serialize};
+use logos_core::{DiagnosticSeverity, SymbolKind};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+
+/// Deserialize a document URI in the form the index keys files by, so
+/// `file:///c%3A/a.ts` and `file:///c:/a.ts` name the same document
+fn normalized_uri<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    String::deserialize(deserializer).map(|uri| logos_core::normalize_uri(&uri))
+}
 
 /// JSON-RPC request
 #[derive(Debug, Deserialize)]
 pub struct Request {
+    #[allow(dead_code)]
     pub jsonrpc: String,
     pub id: Option<RequestId>,
     pub method: String,
@@ -78,29 +89,14 @@ pub struct ResponseError {
 /// Standard JSON-RPC error codes
 pub mod error_codes {
     pub const PARSE_ERROR: i32 = -32700;
+    #[allow(dead_code)]
     pub const INVALID_REQUEST: i32 = -32600;
     pub const METHOD_NOT_FOUND: i32 = -32601;
     pub const INVALID_PARAMS: i32 = -32602;
     pub const INTERNAL_ERROR: i32 = -32603;
+    pub const REQUEST_FAILED: i32 = -32803;
 }
 
-/// JSON-RPC notification (no id, no response expected)
-#[derive(Debug, Serialize)]
-pub struct Notification {
-    pub jsonrpc: &'static str,
-    pub method: String,
-    pub params: Value,
-}
-
-impl Notification {
-    pub fn new(method: impl Into<String>, params: Value) -> Self {
-        Self {
-            jsonrpc: "2.0",
-            method: method.into(),
-            params,
-        }
-    }
-}
 
 // LSP-like types for parameters
 
@@ -111,27 +107,91 @@ pub struct InitializeParams {
     pub root_path: Option<String>,
     pub root_uri: Option<String>,
     #[serde(default)]
-    pub capabilities: Value,
+    pub workspace_folders: Option<Vec<WorkspaceFolder>>,
+    /// Parsed into `InitializationOptions` separately, so a bad setting
+    /// can't fail the whole request
+    #[serde(default)]
+    pub initialization_options: Option<Value>,
+}
+
+/// Client settings passed in `initializationOptions`; unset fields keep
+/// the server defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializationOptions {
+    pub detect_unused: Option<bool>,
+    pub strict_types: Option<bool>,
+    pub null_safety: Option<bool>,
+    pub report_unused: Option<bool>,
+    pub workspace_symbol_cache_size: Option<usize>,
+    pub todo_diagnostics: Option<bool>,
+    pub excluded_symbol_kinds: Option<Vec<SymbolKind>>,
+    pub follow_type_aliases: Option<bool>,
+    pub resolve_member_access: Option<bool>,
+    pub severity_overrides: Option<HashMap<String, DiagnosticSeverity>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceFolder {
+    pub uri: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeWorkspaceFoldersParams {
+    pub event: WorkspaceFoldersChangeEvent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceFoldersChangeEvent {
+    #[serde(default)]
+    pub added: Vec<WorkspaceFolder>,
+    #[serde(default)]
+    pub removed: Vec<WorkspaceFolder>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameFilesParams {
+    pub files: Vec<FileRename>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRename {
+    #[serde(deserialize_with = "normalized_uri")]
+    pub old_uri: String,
+    #[serde(deserialize_with = "normalized_uri")]
+    pub new_uri: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TextDocumentIdentifier {
+    #[serde(deserialize_with = "normalized_uri")]
     pub uri: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VersionedTextDocumentIdentifier {
+    #[serde(deserialize_with = "normalized_uri")]
     pub uri: String,
+    #[allow(dead_code)]
     pub version: i64,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TextDocumentItem {
+    #[serde(deserialize_with = "normalized_uri")]
     pub uri: String,
     pub language_id: String,
+    #[allow(dead_code)]
     pub version: i64,
     pub text: String,
 }
@@ -184,6 +244,55 @@ pub struct TextDocumentPositionParams {
     pub position: Position,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    #[serde(default)]
+    pub context: Option<ReferenceContext>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceContext {
+    pub include_declaration: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    #[serde(default)]
+    pub context: Option<CompletionContext>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionContext {
+    #[serde(default)]
+    pub trigger_character: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDiagnosticParams {
+    #[serde(default)]
+    pub previous_result_ids: Vec<PreviousResultId>,
+    /// Most file reports to return (a Logos extension)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviousResultId {
+    #[serde(deserialize_with = "normalized_uri")]
+    pub uri: String,
+    pub value: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RenameParams {
@@ -244,6 +353,7 @@ pub struct CallHierarchyItem {
     pub name: String,
     pub kind: i32, // SymbolKind
     pub detail: Option<String>,
+    #[serde(deserialize_with = "normalized_uri")]
     pub uri: String,
     pub range: SerializableRange,
     pub selection_range: SerializableRange,
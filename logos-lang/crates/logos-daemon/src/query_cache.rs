@@ -0,0 +1,102 @@
+//! Small LRU cache for repeated queries
+//!
+//! Entries are tagged with the generation of the data they were computed
+//! from; looking up with a newer generation drops every entry.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Least-recently-used cache of query results
+pub struct QueryCache<K, V, G> {
+    capacity: usize,
+    generation: Option<G>,
+    /// Value and the tick it was last used at
+    entries: HashMap<K, (u64, V)>,
+    tick: u64,
+    hits: u64,
+}
+
+impl<K: Eq + Hash, V: Clone, G: PartialEq> QueryCache<K, V, G> {
+    /// A cache holding at most `capacity` results; 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            generation: None,
+            entries: HashMap::new(),
+            tick: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict_oldest();
+        }
+    }
+
+    /// The cached result for `key`, if it was computed at `generation`
+    pub fn get(&mut self, key: &K, generation: G) -> Option<V> {
+        self.sync(generation);
+        self.tick += 1;
+        let (last_used, value) = self.entries.get_mut(key)?;
+        *last_used = self.tick;
+        self.hits += 1;
+        Some(value.clone())
+    }
+
+    /// Cache a result computed at `generation`, evicting the least recently
+    /// used entry if the cache is full
+    pub fn insert(&mut self, key: K, generation: G, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.sync(generation);
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict_oldest();
+        }
+        self.tick += 1;
+        self.entries.insert(key, (self.tick, value));
+    }
+
+    /// Number of lookups answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Drop everything computed at an older generation
+    fn sync(&mut self, generation: G) {
+        if self.generation.as_ref() != Some(&generation) {
+            self.entries.clear();
+            self.generation = Some(generation);
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        // Ticks are unique, so this removes exactly one entry
+        if let Some(oldest) = self.entries.values().map(|(last_used, _)| *last_used).min() {
+            self.entries.retain(|_, (last_used, _)| *last_used != oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache: QueryCache<&str, u32, u64> = QueryCache::new(2);
+        cache.insert("a", 0, 1);
+        cache.insert("b", 0, 2);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+        cache.insert("c", 0, 3);
+
+        assert_eq!(cache.get(&"b", 0), None);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+        assert_eq!(cache.get(&"c", 0), Some(3));
+        // A new generation starts empty
+        assert_eq!(cache.get(&"a", 1), None);
+        assert_eq!(cache.hits(), 3);
+    }
+}
@@ -67,6 +67,10 @@ impl Server {
                 info!("Shutdown requested");
                 Response::null_result(id)
             }
+            "workspace/didChangeWorkspaceFolders" => {
+                handlers::lifecycle::did_change_workspace_folders(&mut self.state, &request.params);
+                Response::null_result(id)
+            }
             "exit" => {
                 self.should_exit = true;
                 info!("Exit requested");
@@ -86,6 +90,10 @@ impl Server {
                 handlers::document::did_close(&mut self.state, &request.params);
                 Response::null_result(id)
             }
+            "workspace/didRenameFiles" => {
+                handlers::document::did_rename_files(&mut self.state, &request.params);
+                Response::null_result(id)
+            }
 
             // Language features
             "textDocument/completion" => {
@@ -117,6 +125,9 @@ impl Server {
             "textDocument/diagnostic" => {
                 handlers::diagnostics::handle(&self.state, &request.params, id)
             }
+            "workspace/diagnostic" => {
+                handlers::diagnostics::workspace(&self.state, &request.params, id)
+            }
 
             // Refactoring
             "logos/getRefactorActions" => {
@@ -140,7 +151,7 @@ impl Server {
                 handlers::analysis::get_todo_items(&self.state, &request.params, id)
             }
             "logos/getAllTodoItems" => {
-                handlers::analysis::get_all_todo_items(&self.state, id)
+                handlers::analysis::get_all_todo_items(&self.state, &request.params, id)
             }
             "logos/getTodoStats" => {
                 handlers::analysis::get_todo_stats(&self.state, id)
@@ -148,6 +159,12 @@ impl Server {
             "logos/getUnusedSymbols" => {
                 handlers::analysis::get_unused_symbols(&self.state, &request.params, id)
             }
+            "logos/getScopeTree" => {
+                handlers::analysis::get_scope_tree(&self.state, &request.params, id)
+            }
+            "logos/getComplexity" => {
+                handlers::analysis::get_complexity(&self.state, &request.params, id)
+            }
 
             // Call Hierarchy (Smart mode)
             "textDocument/prepareCallHierarchy" => {
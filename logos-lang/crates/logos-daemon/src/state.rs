@@ -2,25 +2,34 @@
 
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
-use logos_core::Document;
+use std::sync::{Arc, Mutex};
+use logos_core::{Document, LineIndex, Position, Range, Symbol, SymbolKind};
 use logos_index::{ProjectIndexer, SymbolIndex, TodoIndex};
+use logos_parser::{LanguageId, LanguageParser, Tree};
+use logos_semantic::AnalysisConfig;
+use serde_json::Value;
+
+use crate::query_cache::QueryCache;
+
+pub(crate) use logos_core::{path_to_uri, uri_to_path};
+
+/// Default number of workspace/symbol results kept for repeated queries
+pub const WORKSPACE_SYMBOL_CACHE_SIZE: usize = 64;
+
+/// Cached workspace/symbol results by query and mode, tagged with
+/// [`State::symbols_generation`]
+pub type WorkspaceSymbolCache = QueryCache<(String, IntelligenceMode), Vec<Value>, (u64, Option<u64>)>;
 
 /// Intelligence mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum IntelligenceMode {
     /// Basic mode - lightweight LSP
+    #[default]
     Basic,
     /// Smart mode - full project indexing
     Smart,
 }
 
-impl Default for IntelligenceMode {
-    fn default() -> Self {
-        Self::Basic
-    }
-}
-
 /// Global state for the language service daemon
 pub struct State {
     /// Open documents by URI
@@ -35,8 +44,21 @@ pub struct State {
     pub mode: IntelligenceMode,
     /// Whether the server has been initialized
     pub initialized: bool,
-    /// Root path of the workspace
-    pub root_path: Option<String>,
+    /// Root paths of the workspace folders
+    pub root_paths: Vec<String>,
+    /// Semantic checks to run, as configured by the client
+    pub analysis_config: AnalysisConfig,
+    /// Symbol kinds left out of document outlines
+    pub excluded_symbol_kinds: Vec<SymbolKind>,
+    /// Whether go-to-definition on a type alias also offers the aliased type
+    pub follow_type_aliases: bool,
+    /// Whether go-to-definition on `receiver.member` looks the member up on
+    /// the receiver's type
+    pub resolve_member_access: bool,
+    /// Results of recent workspace/symbol queries
+    pub workspace_symbol_cache: Mutex<WorkspaceSymbolCache>,
+    /// Bumped whenever open documents, workspace folders or the mode change
+    generation: u64,
 }
 
 impl State {
@@ -48,10 +70,27 @@ impl State {
             project_indexer: None,
             mode: IntelligenceMode::Basic,
             initialized: false,
-            root_path: None,
+            root_paths: Vec::new(),
+            // Unused symbol detection is name-based and flags every public
+            // function that the file itself doesn't call, so editors opt in
+            analysis_config: AnalysisConfig {
+                report_unused: false,
+                ..AnalysisConfig::default()
+            },
+            excluded_symbol_kinds: Vec::new(),
+            follow_type_aliases: false,
+            resolve_member_access: true,
+            workspace_symbol_cache: Mutex::new(QueryCache::new(WORKSPACE_SYMBOL_CACHE_SIZE)),
+            generation: 0,
         }
     }
 
+    /// Version of the symbol data behind queries: changes whenever a
+    /// document, workspace folder or the project index changes
+    pub fn symbols_generation(&self) -> (u64, Option<u64>) {
+        (self.generation, self.get_indexer().map(|i| i.index.generation()))
+    }
+
     /// Switch to Smart mode and start indexing
     pub fn enable_smart_mode(&mut self) -> Result<(), String> {
         if self.mode == IntelligenceMode::Smart {
@@ -59,37 +98,64 @@ impl State {
         }
 
         let indexer = ProjectIndexer::new();
+        for error in indexer.adapter_errors() {
+            log::error!("Language adapter unavailable: {}", error);
+        }
 
-        // Index the workspace if root path is set
-        if let Some(ref root) = self.root_path {
-            let root_path = PathBuf::from(root);
-            if root_path.exists() {
-                log::info!("Starting Smart mode indexing for: {}", root);
-                match indexer.index_directory(&root_path) {
-                    Ok(stats) => {
-                        log::info!(
-                            "Indexed {} files, {} symbols, {} imports",
-                            stats.files_indexed,
-                            stats.symbols_found,
-                            stats.imports_found
-                        );
-                    }
-                    Err(e) => {
-                        log::warn!("Indexing error: {}", e);
-                    }
-                }
-            }
+        // Index every workspace folder
+        for root in &self.root_paths {
+            index_root(&indexer, root);
         }
 
         self.project_indexer = Some(Arc::new(indexer));
         self.mode = IntelligenceMode::Smart;
+        self.generation += 1;
         Ok(())
     }
 
+    /// Add a workspace folder, indexing it if Smart mode is active
+    pub fn add_workspace_root(&mut self, root: String) {
+        if self.root_paths.contains(&root) {
+            return;
+        }
+        if let Some(ref indexer) = self.project_indexer {
+            index_root(indexer, &root);
+        }
+        self.root_paths.push(root);
+        self.generation += 1;
+    }
+
+    /// Remove a workspace folder and drop its files from the project index
+    pub fn remove_workspace_root(&mut self, root: &str) {
+        let before = self.root_paths.len();
+        self.root_paths.retain(|r| r != root);
+        if self.root_paths.len() == before {
+            return;
+        }
+        self.generation += 1;
+
+        if let Some(ref indexer) = self.project_indexer {
+            let index = indexer.get_index();
+            let files = index.symbols.files();
+            let removed: Vec<&str> = files
+                .iter()
+                .filter(|uri| {
+                    let Some(path) = uri_to_path(uri) else { return false };
+                    // Files can still belong to a remaining (enclosing) folder
+                    let still_rooted = self.root_paths.iter().any(|r| path.starts_with(r));
+                    path.starts_with(root) && !still_rooted
+                })
+                .map(|uri| uri.as_str())
+                .collect();
+            index.remove_files(&removed);
+        }
+    }
+
     /// Switch to Basic mode
     pub fn enable_basic_mode(&mut self) {
         self.project_indexer = None;
         self.mode = IntelligenceMode::Basic;
+        self.generation += 1;
     }
 
     /// Check if Smart mode is active
@@ -104,32 +170,53 @@ impl State {
 
     /// Open a document
     pub fn open_document(&mut self, uri: String, language_id: String, content: String) {
+        // Index symbols for Basic mode features
+        let symbols = parse_symbols(&language_id, &content);
+        self.symbol_index.index_document(&uri, &symbols);
+        self.generation += 1;
+
         let doc = Document::new(uri.clone(), language_id, content.clone());
         self.documents.insert(uri.clone(), doc);
         // Index TODOs
         self.todo_index.index_document(&uri, &content);
 
-        // Re-index in Smart mode
+        // Re-index in Smart mode from the buffer, which may be unsaved
         if let Some(ref indexer) = self.project_indexer {
-            if let Some(path) = uri_to_path(&uri) {
-                let _ = indexer.reindex_file(&path);
-            }
+            let _ = indexer.index_source(&uri, &content);
         }
     }
 
-    /// Update a document
-    pub fn update_document(&mut self, uri: &str, content: String) {
-        if let Some(doc) = self.documents.get_mut(uri) {
-            doc.set_content(content.clone());
+    /// Apply content changes to an open document in order. A change without
+    /// a range replaces the whole content.
+    pub fn change_document(&mut self, uri: &str, changes: Vec<(Option<Range>, String)>) {
+        let content = match self.documents.get_mut(uri) {
+            Some(doc) => {
+                for (range, text) in changes {
+                    match range {
+                        Some(range) => doc.apply_change(range, &text),
+                        None => doc.set_content(text),
+                    }
+                }
+                doc.content().to_string()
+            }
+            None => return,
+        };
+        self.reindex_document(uri, &content);
+    }
+
+    /// Refresh the symbol, TODO and project indexes after a document changed
+    fn reindex_document(&mut self, uri: &str, content: &str) {
+        if let Some(doc) = self.documents.get(uri) {
+            let symbols = parse_symbols(&doc.language_id, content);
+            self.symbol_index.index_document(uri, &symbols);
         }
+        self.generation += 1;
         // Re-index TODOs
-        self.todo_index.index_document(uri, &content);
+        self.todo_index.index_document(uri, content);
 
         // Re-index in Smart mode
         if let Some(ref indexer) = self.project_indexer {
-            if let Some(path) = uri_to_path(uri) {
-                let _ = indexer.reindex_file(&path);
-            }
+            let _ = indexer.index_source(uri, content);
         }
     }
 
@@ -138,6 +225,70 @@ impl State {
         self.documents.remove(uri);
         self.symbol_index.remove_document(uri);
         self.todo_index.remove_document(uri);
+        self.generation += 1;
+
+        // Unsaved changes are dropped, so the project index goes back to
+        // what is on disk
+        if let Some(ref indexer) = self.project_indexer {
+            match uri_to_path(uri) {
+                Some(path) if path.exists() => {
+                    let _ = indexer.reindex_file(&path);
+                }
+                _ => indexer.get_index().remove_file(uri),
+            }
+        }
+    }
+
+    /// Move a file or directory's project index entries from `old_uri` to
+    /// `new_uri` after it was renamed on disk. Files that imported the old
+    /// path are re-indexed so their import edges resolve against the new
+    /// layout.
+    pub fn rename_file(&mut self, old_uri: &str, new_uri: &str) {
+        let indexer = match self.project_indexer {
+            Some(ref indexer) => indexer,
+            None => return,
+        };
+        let (old_path, new_path) = match (uri_to_path(old_uri), uri_to_path(new_uri)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return,
+        };
+        self.generation += 1;
+
+        let index = indexer.get_index();
+        let moved: Vec<(String, PathBuf)> = index
+            .symbols
+            .files()
+            .into_iter()
+            .filter_map(|uri| {
+                let path = uri_to_path(&uri)?;
+                let relative = path.strip_prefix(&old_path).ok()?;
+                // Joining an empty path would add a trailing slash
+                let moved = if relative.as_os_str().is_empty() { new_path.clone() } else { new_path.join(relative) };
+                Some((uri, moved))
+            })
+            .collect();
+
+        let mut importers = Vec::new();
+        for (uri, path) in &moved {
+            let old = uri_to_path(uri).unwrap_or_default();
+            importers.extend(index.dependencies.get_importers(&old));
+            index.remove_file(uri);
+            let _ = indexer.reindex_file(path);
+        }
+
+        importers.sort();
+        importers.dedup();
+        for importer in importers.iter().filter(|path| !path.starts_with(&old_path)) {
+            let uri = path_to_uri(importer);
+            match self.documents.get(&uri) {
+                Some(doc) => {
+                    let _ = indexer.index_source(&uri, doc.content());
+                }
+                None => {
+                    let _ = indexer.reindex_file(importer);
+                }
+            }
+        }
     }
 
     /// Get a document by URI
@@ -145,7 +296,44 @@ impl State {
         self.documents.get(uri)
     }
 
+    /// Parse an open document once, for handlers that run several passes
+    /// over the same syntax tree
+    pub fn document_tree(&self, uri: &str) -> Option<(LanguageId, Tree)> {
+        let doc = self.get_document(uri)?;
+        parse_source(&doc.language_id, doc.content())
+    }
+
+    /// Parse an open document and return its symbol tree
+    pub fn document_symbols(&self, uri: &str) -> Vec<Symbol> {
+        self.get_document(uri)
+            .map(|doc| parse_symbols(&doc.language_id, doc.content()))
+            .unwrap_or_default()
+    }
+
+    /// Parse an open document and return its call sites: the called name
+    /// and the range of the call
+    pub fn document_calls(&self, uri: &str) -> Vec<(String, Range)> {
+        self.get_document(uri)
+            .and_then(|doc| {
+                let (_, tree) = parse_source(&doc.language_id, doc.content())?;
+                Some(logos_parser::extract_calls(&tree, doc.content()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Range and text of the identifier under `position` in an open document
+    pub fn identifier_at(&self, uri: &str, position: Position) -> Option<(Range, String)> {
+        let doc = self.get_document(uri)?;
+        let (_, tree) = parse_source(&doc.language_id, doc.content())?;
+        let range = logos_parser::identifier_range_at(&tree, position)?;
+        let index = LineIndex::new(doc.content());
+        let start = index.offset_of(range.start)?;
+        let end = index.offset_of(range.end)?;
+        Some((range, doc.content()[start..end].to_string()))
+    }
+
     /// Get all open document URIs
+    #[allow(dead_code)]
     pub fn get_open_documents(&self) -> Vec<String> {
         self.documents.keys().cloned().collect()
     }
@@ -157,11 +345,115 @@ impl Default for State {
     }
 }
 
-/// Convert a file URI to a path
-fn uri_to_path(uri: &str) -> Option<PathBuf> {
-    if uri.starts_with("file://") {
-        Some(PathBuf::from(&uri[7..]))
-    } else {
-        None
+/// Index one workspace folder, logging rather than failing on errors
+fn index_root(indexer: &ProjectIndexer, root: &str) {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() {
+        return;
+    }
+
+    log::info!("Starting Smart mode indexing for: {}", root);
+    match indexer.index_directory(&root_path) {
+        Ok(stats) => {
+            log::info!(
+                "Indexed {} files, {} symbols, {} imports",
+                stats.files_indexed,
+                stats.symbols_found,
+                stats.imports_found
+            );
+        }
+        Err(e) => {
+            log::warn!("Indexing error: {}", e);
+        }
+    }
+}
+
+/// Convert a workspace folder URI (or plain path) to a root path
+pub(crate) fn uri_to_root(uri: &str) -> String {
+    uri_to_path(uri)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|| uri.to_string())
+}
+
+/// Parse source with the grammar for `language_id`, if it is supported
+fn parse_source(language_id: &str, content: &str) -> Option<(LanguageId, Tree)> {
+    let language = LanguageId::from_str(language_id)?;
+
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(content, None).ok()?;
+    Some((language, tree))
+}
+
+/// Parse source and extract its symbols, or nothing if the language is unsupported
+fn parse_symbols(language_id: &str, content: &str) -> Vec<Symbol> {
+    parse_source(language_id, content)
+        .map(|(language, tree)| logos_parser::extract_symbols(language, &tree, content))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn search_names(state: &State, query: &str) -> Vec<String> {
+        state
+            .get_indexer()
+            .unwrap()
+            .get_index()
+            .symbols
+            .search(query)
+            .into_iter()
+            .map(|s| s.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_smart_mode_indexes_every_workspace_folder() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("alpha.py"), "def alpha_handler():\n    pass\n").unwrap();
+        fs::write(second.path().join("beta.py"), "def beta_handler():\n    pass\n").unwrap();
+
+        let mut state = State::new();
+        state.root_paths = vec![
+            first.path().to_string_lossy().into_owned(),
+            second.path().to_string_lossy().into_owned(),
+        ];
+        state.enable_smart_mode().unwrap();
+
+        assert!(search_names(&state, "alpha_handler").contains(&"alpha_handler".to_string()));
+        assert!(search_names(&state, "beta_handler").contains(&"beta_handler".to_string()));
+
+        // Dropping a folder removes only its files from the index
+        let second_root = second.path().to_string_lossy().into_owned();
+        state.remove_workspace_root(&second_root);
+        assert!(search_names(&state, "beta_handler").is_empty());
+        assert!(!search_names(&state, "alpha_handler").is_empty());
+
+        // Adding it back while in Smart mode reindexes it
+        state.add_workspace_root(second_root);
+        assert!(!search_names(&state, "beta_handler").is_empty());
+    }
+
+    #[test]
+    fn test_unsaved_buffer_is_indexed_until_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.py");
+        fs::write(&path, "def saved_job():\n    pass\n").unwrap();
+        let uri = format!("file://{}", path.display());
+
+        let mut state = State::new();
+        state.root_paths = vec![dir.path().to_string_lossy().into_owned()];
+        state.enable_smart_mode().unwrap();
+
+        state.open_document(uri.clone(), "python".to_string(), "def draft_job():\n    pass\n".to_string());
+        assert_eq!(search_names(&state, "draft_job"), vec!["draft_job"]);
+        assert!(search_names(&state, "saved_job").is_empty());
+
+        state.close_document(&uri);
+        assert!(search_names(&state, "draft_job").is_empty());
+        assert_eq!(search_names(&state, "saved_job"), vec!["saved_job"]);
     }
 }
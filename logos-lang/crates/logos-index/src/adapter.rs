@@ -4,10 +4,12 @@
 //! Each language implements this trait to provide Smart Mode indexing.
 
 use crate::symbol_table::{
-    SmartSymbol, SymbolId, SymbolLocation, SymbolReference, TypeInfo, Visibility,
+    Attribute, SmartSymbol, SymbolId, SymbolLocation, SymbolReference, TypeInfo, Visibility,
 };
 use logos_core::{Range, SymbolKind};
 use std::path::Path;
+use std::sync::Mutex;
+use tree_sitter::{Language, LanguageError, Node, Parser, Tree};
 
 /// Import information extracted from source
 #[derive(Debug, Clone)]
@@ -61,6 +63,22 @@ pub struct CallInfo {
     pub location: Range,
     /// Whether this is a constructor call (new)
     pub is_constructor: bool,
+    /// Declared type of the receiver in `recv.method()`, when the adapter
+    /// can tell it from an annotation or the enclosing type
+    pub receiver_type: Option<ReceiverType>,
+    /// Whether a bare callee names a parameter of the caller or of a
+    /// function enclosing it, i.e. a callback passed in by value
+    pub callee_is_parameter: bool,
+}
+
+/// The declared type of a method call's receiver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiverType {
+    /// A named type, looked up in the index to tell classes, interfaces
+    /// and structs apart
+    Named(String),
+    /// A trait object (`dyn Trait`), always dispatched through a vtable
+    TraitObject(String),
 }
 
 /// Type relationship (extends or implements)
@@ -91,6 +109,52 @@ pub struct AnalysisResult {
     pub type_relations: Vec<TypeRelation>,
     /// References to symbols
     pub references: Vec<SymbolReference>,
+    /// Problems that didn't stop the analysis, e.g. nesting too deep to walk
+    pub warnings: Vec<String>,
+}
+
+/// Deepest syntax tree nesting the adapters walk into. Anything below is
+/// skipped so deeply nested (usually generated) code can't overflow the
+/// stack.
+pub const MAX_NESTING_DEPTH: usize = 1000;
+
+/// Tracks how deep an adapter's `analyze_node` recursion is
+#[derive(Debug, Default)]
+pub(crate) struct NestingDepth {
+    current: usize,
+    exceeded: bool,
+}
+
+impl NestingDepth {
+    /// Step into a node; false if that would exceed `MAX_NESTING_DEPTH`,
+    /// in which case the node must be skipped (and `leave` not called)
+    pub(crate) fn enter(&mut self) -> bool {
+        if self.current >= MAX_NESTING_DEPTH {
+            self.exceeded = true;
+            return false;
+        }
+        self.current += 1;
+        true
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.current -= 1;
+    }
+
+    /// Record a warning if part of the tree was skipped
+    pub(crate) fn report(&self, result: &mut AnalysisResult) {
+        if self.exceeded {
+            result.warnings.push(format!(
+                "nesting deeper than {} levels was not analyzed",
+                MAX_NESTING_DEPTH
+            ));
+        }
+    }
+}
+
+/// `node`'s parent, grandparent and so on
+pub(crate) fn ancestors<'a>(node: &Node<'a>) -> impl Iterator<Item = Node<'a>> {
+    std::iter::successors(node.parent(), |n| n.parent())
 }
 
 /// Language adapter trait for Smart Mode indexing
@@ -137,6 +201,42 @@ pub trait LanguageAdapter: Send + Sync {
     }
 }
 
+/// Parsers for one grammar, shared by concurrent `analyze` calls. Each
+/// parse borrows an idle parser, or creates one when all are busy, and
+/// hands it back afterwards; the lock is only held to take or return it.
+pub struct ParserPool {
+    language: Language,
+    idle: Mutex<Vec<Parser>>,
+}
+
+impl ParserPool {
+    pub fn new(language: Language) -> Result<Self, LanguageError> {
+        let mut parser = Parser::new();
+        parser.set_language(&language)?;
+        Ok(Self {
+            language,
+            idle: Mutex::new(vec![parser]),
+        })
+    }
+
+    pub fn parse(&self, source: &str) -> Option<Tree> {
+        let idle = self.idle.lock().ok()?.pop();
+        let mut parser = match idle {
+            Some(parser) => parser,
+            None => {
+                let mut parser = Parser::new();
+                parser.set_language(&self.language).ok()?;
+                parser
+            }
+        };
+        let tree = parser.parse(source, None);
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.push(parser);
+        }
+        tree
+    }
+}
+
 /// Helper to create a SymbolLocation
 pub fn make_location(uri: &str, range: Range, selection_range: Range) -> SymbolLocation {
     SymbolLocation {
@@ -167,6 +267,7 @@ impl SymbolBuilder {
                 attributes: Vec::new(),
                 exported: false,
                 qualified_name: String::new(),
+                alias_target: None,
             },
         }
     }
@@ -201,7 +302,92 @@ impl SymbolBuilder {
         self
     }
 
+    pub fn alias_target(mut self, target: impl Into<String>) -> Self {
+        self.symbol.alias_target = Some(target.into());
+        self
+    }
+
+    pub fn attributes(mut self, attributes: Vec<Attribute>) -> Self {
+        self.symbol.attributes = attributes;
+        self
+    }
+
     pub fn build(self) -> SmartSymbol {
         self.symbol
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::python_adapter::PythonAdapter;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const SOURCE: &str = "class Repo:\n    def load(self):\n        return fetch()\n\ndef fetch():\n    return 1\n";
+
+    /// Run `work` on four threads at once, returning how long they took
+    fn run_concurrently(work: impl Fn() + Send + Sync + 'static) -> Duration {
+        let work = Arc::new(work);
+        let start = Instant::now();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let work = Arc::clone(&work);
+                std::thread::spawn(move || work())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        start.elapsed()
+    }
+
+    #[test]
+    fn test_concurrent_analyze() {
+        let adapter = Arc::new(PythonAdapter::new().unwrap());
+        let expected = adapter.analyze("file:///repo.py", SOURCE).symbols.len();
+
+        let shared = Arc::clone(&adapter);
+        run_concurrently(move || {
+            for i in 0..50 {
+                let result = shared.analyze(&format!("file:///repo{}.py", i), SOURCE);
+                assert_eq!(result.symbols.len(), expected);
+                assert_eq!(result.calls.len(), 1);
+            }
+        });
+    }
+
+    /// A timing comparison, so it only runs on request (`--ignored`) on an
+    /// otherwise idle machine; `test_concurrent_analyze` covers correctness
+    #[test]
+    #[ignore = "timing benchmark"]
+    fn test_pool_parses_in_parallel() {
+        // One core can't show the difference
+        if std::thread::available_parallelism().map_or(1, |n| n.get()) < 2 {
+            return;
+        }
+        let language: Language = tree_sitter_python::LANGUAGE.into();
+        let source = SOURCE.repeat(200);
+
+        let pool = Arc::new(ParserPool::new(language.clone()).unwrap());
+        let pooled = {
+            let source = source.clone();
+            run_concurrently(move || {
+                for _ in 0..20 {
+                    assert!(pool.parse(&source).is_some());
+                }
+            })
+        };
+
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+        let single = Arc::new(Mutex::new(parser));
+        let serialized = run_concurrently(move || {
+            for _ in 0..20 {
+                assert!(single.lock().unwrap().parse(&source, None).is_some());
+            }
+        });
+
+        assert!(pooled < serialized, "pool {:?} vs single parser {:?}", pooled, serialized);
+    }
+}
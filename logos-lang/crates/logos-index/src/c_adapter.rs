@@ -6,30 +6,45 @@
 //! - Exports: treated as public for non-static (best-effort)
 //! - Calls: call_expression nodes (best-effort)
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
-use crate::symbol_table::Visibility;
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool, SymbolBuilder, make_location};
+use crate::comments::attach_leading_docs;
+use crate::symbol_table::{SymbolId, Visibility};
 use logos_core::{Position, Range, SymbolKind};
-use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use logos_parser::LanguageId;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Node, Tree};
 
 pub struct CAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
+    include_dirs: Vec<PathBuf>,
+    angle_includes: bool,
 }
 
 impl CAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_c::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_c::LANGUAGE.into())
             .map_err(|e| format!("Failed to set C language: {}", e))?;
         Ok(Self {
-            parser: std::sync::Mutex::new(parser),
+            parsers,
+            include_dirs: Vec::new(),
+            angle_includes: false,
         })
     }
 
+    /// Search these directories (like `-I`) for includes not found next to the including file
+    pub fn with_include_dirs(mut self, include_dirs: Vec<PathBuf>) -> Self {
+        self.include_dirs = include_dirs;
+        self
+    }
+
+    /// Also resolve `#include <...>` through the include directories
+    pub fn with_angle_includes(mut self, enabled: bool) -> Self {
+        self.angle_includes = enabled;
+        self
+    }
+
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
+        self.parsers.parse(source)
     }
 }
 
@@ -52,31 +67,51 @@ impl LanguageAdapter for CAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
+        ctx.depth.report(&mut ctx.result);
+        attach_leading_docs(&mut ctx.result.symbols, source, LanguageId::C);
         ctx.result
     }
 
-    fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<std::path::PathBuf> {
-        // For `#include "x.h"` try relative to file dir
-        if !(import_path.starts_with('"') && import_path.ends_with('"')) {
+    fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<PathBuf> {
+        resolve_include(from_file, import_path, &self.include_dirs, self.angle_includes)
+    }
+}
+
+/// Resolve an `#include` target. Quoted includes are looked up next to the
+/// including file, then in `include_dirs`; `<...>` includes only in
+/// `include_dirs`, and only when `angle_includes` is set.
+pub(crate) fn resolve_include(
+    from_file: &Path,
+    import_path: &str,
+    include_dirs: &[PathBuf],
+    angle_includes: bool,
+) -> Option<PathBuf> {
+    let (inner, local) = if let Some(inner) = import_path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        (inner, from_file.parent())
+    } else if let Some(inner) = import_path.strip_prefix('<').and_then(|p| p.strip_suffix('>')) {
+        if !angle_includes {
             return None;
         }
-        let inner = import_path.trim_matches('"');
-        let parent = from_file.parent()?;
-        let resolved = parent.join(inner);
-        if resolved.exists() {
-            return Some(resolved);
-        }
-        None
-    }
+        (inner, None)
+    } else {
+        return None;
+    };
+    local
+        .into_iter()
+        .chain(include_dirs.iter().map(PathBuf::as_path))
+        .map(|dir| dir.join(inner))
+        .find(|candidate| candidate.is_file())
 }
 
 struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
 }
 
 impl<'a> AnalysisContext<'a> {
@@ -86,10 +121,25 @@ impl<'a> AnalysisContext<'a> {
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         "preproc_include" => analyze_include(node, ctx),
         "function_definition" => analyze_function(node, ctx),
         "declaration" => analyze_declaration(node, ctx),
+        "type_definition" => analyze_typedef(node, ctx),
+        "struct_specifier" | "union_specifier" => {
+            analyze_struct(node, ctx);
+        }
+        "enum_specifier" => {
+            analyze_enum(node, ctx);
+        }
         "call_expression" => analyze_call(node, ctx),
         _ => {
             for i in 0..node.named_child_count() {
@@ -155,8 +205,12 @@ fn analyze_declaration(node: &Node, ctx: &mut AnalysisContext) {
         if let Some(ch) = node.named_child(i) {
             match ch.kind() {
                 "type_definition" => analyze_typedef(&ch, ctx),
-                "struct_specifier" => analyze_struct(&ch, ctx),
-                "enum_specifier" => analyze_enum(&ch, ctx),
+                "struct_specifier" | "union_specifier" => {
+                    analyze_struct(&ch, ctx);
+                }
+                "enum_specifier" => {
+                    analyze_enum(&ch, ctx);
+                }
                 _ => {}
             }
         }
@@ -164,43 +218,112 @@ fn analyze_declaration(node: &Node, ctx: &mut AnalysisContext) {
 }
 
 fn analyze_typedef(node: &Node, ctx: &mut AnalysisContext) {
-    if let Some(name_node) = node.child_by_field_name("declarator")
-        .and_then(find_identifier_in_declarator) {
-        let name = ctx.get_text(&name_node);
-        let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
-        ctx.result.symbols.push(
-            // logos-core 没有 TypeAlias：这里用 Class 表示 typedef
-            SymbolBuilder::new(name.clone(), SymbolKind::Class, location)
-                .exported(true)
-                .visibility(Visibility::Public)
-                .build()
-        );
+    let name_node = match node.child_by_field_name("declarator").and_then(find_identifier_in_declarator) {
+        Some(n) => n,
+        None => return,
+    };
+    let name = ctx.get_text(&name_node);
+    let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
+
+    // `typedef struct { ... } Name;` has no tag, so the members belong to
+    // the typedef; a tagged struct gets its own symbol
+    let body_of_anonymous = node
+        .child_by_field_name("type")
+        .filter(|t| t.child_by_field_name("name").is_none())
+        .and_then(|t| Some((t.kind(), t.child_by_field_name("body")?)));
+    let kind = match body_of_anonymous {
+        Some(("struct_specifier" | "union_specifier", _)) => SymbolKind::Struct,
+        Some(("enum_specifier", _)) => SymbolKind::Enum,
+        // logos-core 没有 TypeAlias：这里用 Class 表示 typedef
+        _ => SymbolKind::Class,
+    };
+    let mut builder = SymbolBuilder::new(name, kind, location)
+        .exported(true)
+        .visibility(Visibility::Public);
+    if kind == SymbolKind::Class {
+        // `typedef struct Node Node_t;` aliases the tag, not the keyword
+        if let Some(target) = node.child_by_field_name("type") {
+            let target = target.child_by_field_name("name").unwrap_or(target);
+            builder = builder.alias_target(ctx.get_text(&target));
+        }
     }
-}
+    let symbol = builder.build();
+    let id = symbol.id;
+    ctx.result.symbols.push(symbol);
 
-fn analyze_struct(node: &Node, ctx: &mut AnalysisContext) {
-    if let Some(name_node) = node.child_by_field_name("name") {
-        let name = ctx.get_text(&name_node);
-        let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
-        ctx.result.symbols.push(
-            SymbolBuilder::new(name.clone(), SymbolKind::Struct, location)
-                .exported(true)
-                .visibility(Visibility::Public)
-                .build()
-        );
+    match body_of_anonymous {
+        Some((_, body)) if kind != SymbolKind::Class => analyze_members(&body, id, ctx),
+        _ => {
+            if let Some(specifier) = node.child_by_field_name("type") {
+                analyze_node(&specifier, ctx);
+            }
+        }
     }
 }
 
-fn analyze_enum(node: &Node, ctx: &mut AnalysisContext) {
-    if let Some(name_node) = node.child_by_field_name("name") {
-        let name = ctx.get_text(&name_node);
-        let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
-        ctx.result.symbols.push(
-            SymbolBuilder::new(name.clone(), SymbolKind::Enum, location)
-                .exported(true)
-                .visibility(Visibility::Public)
-                .build()
-        );
+/// Index a struct or union definition and its fields. Declarations that
+/// only name the type (`struct Point p;`) have no body and are skipped.
+fn analyze_struct(node: &Node, ctx: &mut AnalysisContext) -> Option<()> {
+    let name_node = node.child_by_field_name("name")?;
+    let body = node.child_by_field_name("body")?;
+    let name = ctx.get_text(&name_node);
+    let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
+    let symbol = SymbolBuilder::new(name, SymbolKind::Struct, location)
+        .exported(true)
+        .visibility(Visibility::Public)
+        .build();
+    let id = symbol.id;
+    ctx.result.symbols.push(symbol);
+    analyze_members(&body, id, ctx);
+    Some(())
+}
+
+/// Index an enum definition and its enumerators
+fn analyze_enum(node: &Node, ctx: &mut AnalysisContext) -> Option<()> {
+    let name_node = node.child_by_field_name("name")?;
+    let body = node.child_by_field_name("body")?;
+    let name = ctx.get_text(&name_node);
+    let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
+    let symbol = SymbolBuilder::new(name, SymbolKind::Enum, location)
+        .exported(true)
+        .visibility(Visibility::Public)
+        .build();
+    let id = symbol.id;
+    ctx.result.symbols.push(symbol);
+    analyze_members(&body, id, ctx);
+    Some(())
+}
+
+/// Fields of a struct/union body or enumerators of an enum body, as
+/// children of `parent`
+fn analyze_members(body: &Node, parent: SymbolId, ctx: &mut AnalysisContext) {
+    for i in 0..body.named_child_count() {
+        let member = match body.named_child(i) {
+            Some(m) => m,
+            None => continue,
+        };
+        let (kind, name_nodes) = match member.kind() {
+            "field_declaration" => {
+                let mut cursor = member.walk();
+                let names: Vec<Node> = member
+                    .children_by_field_name("declarator", &mut cursor)
+                    .filter_map(find_identifier_in_declarator)
+                    .collect();
+                (SymbolKind::Field, names)
+            }
+            "enumerator" => (SymbolKind::EnumMember, member.child_by_field_name("name").into_iter().collect()),
+            _ => continue,
+        };
+        for name_node in name_nodes {
+            let location = make_location(&ctx.uri, node_to_range(&member), node_to_range(&name_node));
+            ctx.result.symbols.push(
+                SymbolBuilder::new(ctx.get_text(&name_node), kind, location)
+                    .parent(parent)
+                    .exported(true)
+                    .visibility(Visibility::Public)
+                    .build(),
+            );
+        }
     }
 }
 
@@ -212,13 +335,15 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
             qualified_name: None,
             location: node_to_range(node),
             is_constructor: false,
+            receiver_type: None,
+            callee_is_parameter: false,
         });
     }
 }
 
 fn find_identifier_in_declarator<'a>(node: Node<'a>) -> Option<Node<'a>> {
-    // Walk down to find first "identifier"
-    if node.kind() == "identifier" {
+    // Walk down to find the first identifier (type and field names included)
+    if matches!(node.kind(), "identifier" | "type_identifier" | "field_identifier") {
         return Some(node);
     }
     for i in 0..node.named_child_count() {
@@ -250,6 +375,34 @@ fn node_to_range(node: &Node) -> Range {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_includes_through_include_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let include = dir.path().join("include");
+        std::fs::create_dir_all(include.join("net")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(include.join("foo.h"), "int foo(void);\n").unwrap();
+        std::fs::write(include.join("net/socket.h"), "int open_socket(void);\n").unwrap();
+        std::fs::write(dir.path().join("src/local.h"), "int local(void);\n").unwrap();
+        let main = dir.path().join("src/main.c");
+
+        let adapter = CAdapter::new().unwrap().with_include_dirs(vec![include.clone()]);
+        assert_eq!(adapter.resolve_import(&main, "\"local.h\""), Some(dir.path().join("src/local.h")));
+        assert_eq!(adapter.resolve_import(&main, "\"net/socket.h\""), Some(include.join("net/socket.h")));
+        // Angle-bracket includes are opt-in
+        assert_eq!(adapter.resolve_import(&main, "<foo.h>"), None);
+
+        let adapter = adapter.with_angle_includes(true);
+        assert_eq!(adapter.resolve_import(&main, "<foo.h>"), Some(include.join("foo.h")));
+        assert_eq!(adapter.resolve_import(&main, "<local.h>"), None);
+
+        let cpp = crate::cpp_adapter::CppAdapter::new()
+            .unwrap()
+            .with_include_dirs(vec![include.clone()])
+            .with_angle_includes(true);
+        assert_eq!(cpp.resolve_import(&dir.path().join("src/main.cpp"), "<foo.h>"), Some(include.join("foo.h")));
+    }
+
     #[test]
     fn c_basic_symbols_imports_calls() {
         let adapter = CAdapter::new().unwrap();
@@ -268,9 +421,31 @@ int greet(User* u) {
 }
 "#;
         let result = adapter.analyze("file:///test.c", src);
-        assert!(result.imports.len() >= 1);
+        assert!(!result.imports.is_empty());
         assert!(result.symbols.iter().any(|s| s.name == "greet"));
-        assert!(result.calls.len() >= 1);
+        assert!(!result.calls.is_empty());
+    }
+
+    #[test]
+    fn c_struct_fields_and_enumerators_are_children() {
+        let adapter = CAdapter::new().unwrap();
+        let src = "struct Point { int x, y; };\ntypedef struct { int id; } User;\nenum Color { RED, GREEN };\nstruct Point origin;\n";
+        let result = adapter.analyze("file:///shapes.c", src);
+
+        let children = |parent: &str| -> Vec<(&str, SymbolKind)> {
+            let parent = result.symbols.iter().find(|s| s.name == parent).unwrap();
+            result
+                .symbols
+                .iter()
+                .filter(|s| s.parent == Some(parent.id))
+                .map(|s| (s.name.as_str(), s.kind))
+                .collect()
+        };
+        assert_eq!(children("Point"), vec![("x", SymbolKind::Field), ("y", SymbolKind::Field)]);
+        assert_eq!(children("User"), vec![("id", SymbolKind::Field)]);
+        assert_eq!(children("Color"), vec![("RED", SymbolKind::EnumMember), ("GREEN", SymbolKind::EnumMember)]);
+        // `struct Point origin;` uses the type, it doesn't define it
+        assert_eq!(result.symbols.iter().filter(|s| s.name == "Point").count(), 1);
     }
 }
 
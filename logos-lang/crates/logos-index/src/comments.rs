@@ -2,10 +2,13 @@
 //!
 //! Scans source code for TODO, FIXME, HACK, XXX, NOTE and other comment markers.
 
-use logos_core::Range;
+use crate::symbol_table::SmartSymbol;
+use logos_core::{uri_to_path, Diagnostic, DiagnosticSeverity, LineIndex, Range};
+use logos_parser::LanguageId;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// The kind of TODO comment marker
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -48,6 +51,15 @@ impl TodoKind {
             TodoKind::Custom => 1,
         }
     }
+
+    /// Severity when reported as a diagnostic: markers that flag broken or
+    /// questionable code are warnings, the rest are informational
+    pub fn diagnostic_severity(&self) -> DiagnosticSeverity {
+        match self {
+            TodoKind::Fixme | TodoKind::Hack | TodoKind::Bug | TodoKind::Xxx => DiagnosticSeverity::Warning,
+            _ => DiagnosticSeverity::Information,
+        }
+    }
 }
 
 /// A TODO item found in source code
@@ -67,6 +79,64 @@ pub struct TodoItem {
     pub line: u32,
 }
 
+impl TodoItem {
+    /// Convert to a diagnostic covering the marker and its text
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let message = if self.text.is_empty() {
+            self.kind.as_str().to_string()
+        } else {
+            format!("{}: {}", self.kind.as_str(), self.text)
+        };
+        Diagnostic {
+            range: self.range,
+            severity: self.kind.diagnostic_severity(),
+            code: Some(self.kind.as_str().to_lowercase()),
+            source: Some("logos-index".to_string()),
+            message,
+            related_information: Vec::new(),
+        }
+    }
+}
+
+/// Filters for [`TodoIndex::query`]; unset fields match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TodoQuery {
+    /// Absolute path or URI prefix, or a relative directory such as
+    /// `src/auth/` matched at any path segment
+    pub path_prefix: Option<String>,
+    /// Only these kinds of marker
+    pub kinds: Option<Vec<TodoKind>>,
+    /// Author named in `TODO(name):`, compared case-insensitively
+    pub assignee: Option<String>,
+}
+
+impl TodoQuery {
+    fn matches_path(&self, uri: &str) -> bool {
+        let Some(prefix) = self.path_prefix.as_deref() else {
+            return true;
+        };
+        let prefix = uri_to_path(prefix).unwrap_or_else(|| PathBuf::from(prefix));
+        let path = uri_to_path(uri).unwrap_or_else(|| PathBuf::from(uri));
+        // Whole components only, so `src/auth` doesn't match `src/authz`
+        if prefix.has_root() {
+            return path.starts_with(&prefix);
+        }
+        let components: Vec<_> = path.components().collect();
+        (0..components.len()).any(|i| components[i..].iter().collect::<PathBuf>().starts_with(&prefix))
+    }
+
+    fn matches_item(&self, item: &TodoItem) -> bool {
+        let kind_ok = self.kinds.as_ref().is_none_or(|kinds| kinds.contains(&item.kind));
+        let assignee_ok = self.assignee.as_ref().is_none_or(|assignee| {
+            item.author
+                .as_ref()
+                .is_some_and(|author| author.eq_ignore_ascii_case(assignee))
+        });
+        kind_ok && assignee_ok
+    }
+}
+
 /// Configuration for the comment scanner
 #[derive(Debug, Clone)]
 pub struct ScannerConfig {
@@ -74,6 +144,8 @@ pub struct ScannerConfig {
     pub custom_patterns: Vec<String>,
     /// Whether to scan inside multi-line comments
     pub scan_multiline: bool,
+    /// Whether [`TodoIndex::document_diagnostics`] reports found items
+    pub report_diagnostics: bool,
 }
 
 impl Default for ScannerConfig {
@@ -81,6 +153,7 @@ impl Default for ScannerConfig {
         Self {
             custom_patterns: Vec::new(),
             scan_multiline: true,
+            report_diagnostics: false,
         }
     }
 }
@@ -200,6 +273,104 @@ impl CommentScanner {
     }
 }
 
+/// Collect the documentation comment directly above a symbol, with the
+/// comment markers stripped.
+///
+/// Rust takes `///` and `/** */`, TypeScript/JavaScript and Java take
+/// `/** */`, and C, C++ and Go take `//` and `/* */` blocks. Attribute and
+/// decorator lines between the comment and the symbol are skipped. Python
+/// documents symbols with docstrings, which the Python adapter reads itself.
+pub fn extract_leading_doc(source: &str, symbol_range: Range, language: LanguageId) -> Option<String> {
+    leading_doc(source, &LineIndex::new(source), symbol_range.start.line, language)
+}
+
+/// Fill in `documentation` from leading comments for symbols that have none
+pub(crate) fn attach_leading_docs(symbols: &mut [SmartSymbol], source: &str, language: LanguageId) {
+    let index = LineIndex::new(source);
+    for symbol in symbols.iter_mut().filter(|s| s.documentation.is_none()) {
+        symbol.documentation = leading_doc(source, &index, symbol.location.range.start.line, language);
+    }
+}
+
+fn leading_doc(source: &str, index: &LineIndex, symbol_line: u32, language: LanguageId) -> Option<String> {
+    if language == LanguageId::Python {
+        return None;
+    }
+    let line_text = |line: u32| index.line(source, line).unwrap_or("").trim();
+
+    // Skip `#[derive(..)]` / `@Override` lines sitting between doc and symbol
+    let mut line = symbol_line.checked_sub(1)?;
+    while line_text(line).starts_with("#[") || line_text(line).starts_with('@') {
+        line = line.checked_sub(1)?;
+    }
+
+    let last = line_text(line);
+    let lines: Vec<String> = if last.ends_with("*/") {
+        // Walk up to the line that opens the block. The opener must start
+        // its own line: `int count; /* number */` is a trailing comment on
+        // code, and passing another comment's `*/` means we left the block.
+        let mut start = line;
+        loop {
+            let text = line_text(start);
+            if text.starts_with("/*") {
+                break;
+            }
+            if text.contains("/*") || (start != line && text.contains("*/")) {
+                return None;
+            }
+            start = start.checked_sub(1)?;
+        }
+        let opener = line_text(start);
+        let is_doc_block = match language {
+            LanguageId::C | LanguageId::Cpp | LanguageId::Go => true,
+            _ => opener.starts_with("/**") && !opener.starts_with("/**/"),
+        };
+        if !is_doc_block {
+            return None;
+        }
+        (start..=line).map(|l| strip_block_comment_line(line_text(l))).collect()
+    } else {
+        let marker = match language {
+            LanguageId::Rust => "///",
+            LanguageId::C | LanguageId::Cpp | LanguageId::Go => "//",
+            _ => return None,
+        };
+        let is_doc_line = |text: &str| {
+            // `////` is a divider, not a doc comment
+            text.starts_with(marker) && !(marker == "///" && text.starts_with("////"))
+        };
+        if !is_doc_line(last) {
+            return None;
+        }
+        let mut start = line;
+        while start > 0 && is_doc_line(line_text(start - 1)) {
+            start -= 1;
+        }
+        (start..=line)
+            .map(|l| {
+                let text = &line_text(l)[marker.len()..];
+                text.strip_prefix(' ').unwrap_or(text).to_string()
+            })
+            .collect()
+    };
+
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Strip `/**`, `*/` and a leading ` * ` gutter from one line of a block comment
+fn strip_block_comment_line(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("/**")
+        .or_else(|| text.strip_prefix("/*"))
+        .unwrap_or(text);
+    let text = text.strip_suffix("*/").unwrap_or(text).trim_end();
+    let text = text.trim_start();
+    let text = text.strip_prefix('*').unwrap_or(text);
+    text.strip_prefix(' ').unwrap_or(text).to_string()
+}
+
 /// Index for storing and querying TODO items across a project
 #[derive(Debug, Default)]
 pub struct TodoIndex {
@@ -207,6 +378,8 @@ pub struct TodoIndex {
     by_document: HashMap<String, Vec<TodoItem>>,
     /// Scanner instance
     scanner: CommentScanner,
+    /// Report TODOs as diagnostics
+    report_diagnostics: bool,
 }
 
 impl TodoIndex {
@@ -218,6 +391,7 @@ impl TodoIndex {
         Self {
             by_document: HashMap::new(),
             scanner: CommentScanner::new(config),
+            report_diagnostics: config.report_diagnostics,
         }
     }
 
@@ -241,6 +415,15 @@ impl TodoIndex {
         self.by_document.get(uri).map(|v| v.as_slice()).unwrap_or(&[])
     }
 
+    /// TODOs in a document as diagnostics; empty unless the index was
+    /// configured with `report_diagnostics`
+    pub fn document_diagnostics(&self, uri: &str) -> Vec<Diagnostic> {
+        if !self.report_diagnostics {
+            return Vec::new();
+        }
+        self.get_document_todos(uri).iter().map(TodoItem::to_diagnostic).collect()
+    }
+
     /// Get all TODOs across all indexed documents
     pub fn get_all_todos(&self) -> Vec<(&str, &TodoItem)> {
         let mut todos = Vec::new();
@@ -266,6 +449,19 @@ impl TodoIndex {
             .collect()
     }
 
+    /// Get TODOs matching `opts`, sorted by file then line
+    pub fn query(&self, opts: &TodoQuery) -> Vec<(&str, &TodoItem)> {
+        let mut todos: Vec<(&str, &TodoItem)> = self
+            .by_document
+            .iter()
+            .filter(|(uri, _)| opts.matches_path(uri))
+            .flat_map(|(uri, items)| items.iter().map(move |item| (uri.as_str(), item)))
+            .filter(|(_, item)| opts.matches_item(item))
+            .collect();
+        todos.sort_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.line.cmp(&b.1.line)));
+        todos
+    }
+
     /// Get total count of TODOs
     pub fn todo_count(&self) -> usize {
         self.by_document.values().map(|v| v.len()).sum()
@@ -341,4 +537,130 @@ fn main() {
         assert_eq!(index.get_document_todos("a.rs").len(), 2);
         assert_eq!(index.get_document_todos("b.rs").len(), 1);
     }
+
+    #[test]
+    fn test_todo_diagnostics() {
+        let source = "fn main() {\n    // FIXME: handle the error\n}\n";
+        let mut index = TodoIndex::new();
+        index.index_document("a.rs", source);
+        assert!(index.document_diagnostics("a.rs").is_empty());
+
+        let mut index = TodoIndex::with_config(&ScannerConfig {
+            custom_patterns: vec!["REVIEW".to_string()],
+            report_diagnostics: true,
+            ..Default::default()
+        });
+        index.index_document("a.rs", source);
+        let diagnostics = index.document_diagnostics("a.rs");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[0].message, "FIXME: handle the error");
+        assert_eq!(diagnostics[0].range, Range::from_coords(1, 7, 1, 30));
+
+        index.index_document("b.rs", "// TODO: later\n// REVIEW: naming\n");
+        let severities: Vec<_> = index.document_diagnostics("b.rs").iter().map(|d| d.severity).collect();
+        assert_eq!(severities, vec![DiagnosticSeverity::Information, DiagnosticSeverity::Information]);
+    }
+
+    #[test]
+    fn test_query_by_path_prefix_kind_and_assignee() {
+        let mut index = TodoIndex::new();
+        index.index_document(
+            "file:///repo/src/auth/login.rs",
+            "// FIXME(ana): check expiry\n// TODO: log attempts\n// FIXME: rate limit\n",
+        );
+        index.index_document("file:///repo/src/billing/invoice.rs", "// FIXME: rounding\n");
+
+        let under_auth = TodoQuery {
+            path_prefix: Some("src/auth/".to_string()),
+            ..Default::default()
+        };
+        let todos = index.query(&under_auth);
+        assert_eq!(todos.len(), 3);
+        assert!(todos.iter().all(|(uri, _)| uri.contains("/src/auth/")));
+        let lines: Vec<u32> = todos.iter().map(|(_, t)| t.line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+
+        let fixmes = TodoQuery {
+            path_prefix: Some("/repo/src/auth".to_string()),
+            kinds: Some(vec![TodoKind::Fixme]),
+            ..Default::default()
+        };
+        assert_eq!(index.query(&fixmes).len(), 2);
+
+        let assigned = TodoQuery {
+            assignee: Some("Ana".to_string()),
+            ..Default::default()
+        };
+        let todos = index.query(&assigned);
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].1.text, "check expiry");
+
+        assert_eq!(index.query(&TodoQuery::default()).len(), 4);
+
+        // Prefixes match whole path segments
+        index.index_document("file:///repo/src/authz/policy.rs", "// TODO: deny by default\n");
+        for prefix in ["src/auth", "/repo/src/auth", "file:///repo/src/auth"] {
+            let query = TodoQuery {
+                path_prefix: Some(prefix.to_string()),
+                ..Default::default()
+            };
+            let todos = index.query(&query);
+            assert_eq!(todos.len(), 3, "{}", prefix);
+            assert!(todos.iter().all(|(uri, _)| uri.contains("/src/auth/")), "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_leading_rustdoc() {
+        let source = "use std::fmt;\n\n/// Adds two numbers.\n///\n/// Wraps on overflow.\n#[inline]\npub fn add(a: u8, b: u8) -> u8 {\n    a.wrapping_add(b)\n}\n";
+        let range = Range::from_coords(6, 0, 8, 1);
+        assert_eq!(
+            extract_leading_doc(source, range, LanguageId::Rust).as_deref(),
+            Some("Adds two numbers.\n\nWraps on overflow.")
+        );
+
+        // A plain `//` comment is not rustdoc
+        let source = "// helper\nfn helper() {}\n";
+        assert_eq!(extract_leading_doc(source, Range::from_coords(1, 0, 1, 14), LanguageId::Rust), None);
+    }
+
+    #[test]
+    fn test_leading_jsdoc() {
+        let source = "/**\n * Formats a user's name.\n * @param user the user\n */\nexport function format(user) {}\n";
+        let range = Range::from_coords(4, 7, 4, 30);
+        assert_eq!(
+            extract_leading_doc(source, range, LanguageId::TypeScript).as_deref(),
+            Some("Formats a user's name.\n@param user the user")
+        );
+
+        // Plain block comments are not JSDoc
+        let source = "/* internal */\nfunction f() {}\n";
+        assert_eq!(extract_leading_doc(source, Range::from_coords(1, 0, 1, 15), LanguageId::TypeScript), None);
+    }
+
+    #[test]
+    fn test_trailing_block_comment_is_not_a_doc() {
+        let source = "int total(void);\nint count; /* number */\nint main(void) {}\n";
+        assert_eq!(extract_leading_doc(source, Range::from_coords(2, 0, 2, 17), LanguageId::C), None);
+
+        // The same holds when the trailing comment spans lines
+        let source = "/* header */\nsetup(); /* starts\n   here */\nint main(void) {}\n";
+        assert_eq!(extract_leading_doc(source, Range::from_coords(3, 0, 3, 17), LanguageId::C), None);
+
+        let source = "int count;\n/* Entry point */\nint main(void) {}\n";
+        assert_eq!(
+            extract_leading_doc(source, Range::from_coords(2, 0, 2, 17), LanguageId::C).as_deref(),
+            Some("Entry point")
+        );
+    }
+
+    #[test]
+    fn test_adapters_attach_leading_docs() {
+        use crate::adapter::LanguageAdapter;
+        let adapter = crate::rust_adapter::RustAdapter::new().unwrap();
+        let result = adapter.analyze("file:///lib.rs", "/// A point.\n#[derive(Debug)]\npub struct Point {}\n");
+        let point = result.symbols.iter().find(|s| s.name == "Point").unwrap();
+        assert_eq!(point.documentation.as_deref(), Some("A point."));
+    }
 }
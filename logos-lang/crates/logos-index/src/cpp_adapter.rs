@@ -5,30 +5,46 @@
 //! - Imports: #include directives
 //! - Calls: call_expression nodes (best-effort)
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool, SymbolBuilder, make_location};
+use crate::c_adapter::resolve_include;
+use crate::comments::attach_leading_docs;
 use crate::symbol_table::Visibility;
 use logos_core::{Position, Range, SymbolKind};
-use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use logos_parser::LanguageId;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Node, Tree};
 
 pub struct CppAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
+    include_dirs: Vec<PathBuf>,
+    angle_includes: bool,
 }
 
 impl CppAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_cpp::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_cpp::LANGUAGE.into())
             .map_err(|e| format!("Failed to set C++ language: {}", e))?;
         Ok(Self {
-            parser: std::sync::Mutex::new(parser),
+            parsers,
+            include_dirs: Vec::new(),
+            angle_includes: false,
         })
     }
 
+    /// Search these directories (like `-I`) for includes not found next to the including file
+    pub fn with_include_dirs(mut self, include_dirs: Vec<PathBuf>) -> Self {
+        self.include_dirs = include_dirs;
+        self
+    }
+
+    /// Also resolve `#include <...>` through the include directories
+    pub fn with_angle_includes(mut self, enabled: bool) -> Self {
+        self.angle_includes = enabled;
+        self
+    }
+
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
+        self.parsers.parse(source)
     }
 }
 
@@ -51,25 +67,18 @@ impl LanguageAdapter for CppAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
+        ctx.depth.report(&mut ctx.result);
+        attach_leading_docs(&mut ctx.result.symbols, source, LanguageId::Cpp);
         ctx.result
     }
 
-    fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<std::path::PathBuf> {
-        // For `#include "x.h"` try relative to file dir
-        if !(import_path.starts_with('"') && import_path.ends_with('"')) {
-            return None;
-        }
-        let inner = import_path.trim_matches('"');
-        let parent = from_file.parent()?;
-        let resolved = parent.join(inner);
-        if resolved.exists() {
-            return Some(resolved);
-        }
-        None
+    fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<PathBuf> {
+        resolve_include(from_file, import_path, &self.include_dirs, self.angle_includes)
     }
 }
 
@@ -77,6 +86,7 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
 }
 
@@ -105,6 +115,14 @@ impl<'a> AnalysisContext<'a> {
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         "preproc_include" => analyze_include(node, ctx),
         "function_definition" => analyze_function(node, ctx),
@@ -344,10 +362,6 @@ fn analyze_field_declaration_list(node: &Node, ctx: &mut AnalysisContext, defaul
     }
 }
 
-fn analyze_field(node: &Node, ctx: &mut AnalysisContext) {
-    analyze_field_with_visibility(node, ctx, Visibility::Private);
-}
-
 fn analyze_field_with_visibility(node: &Node, ctx: &mut AnalysisContext, visibility: Visibility) {
     // field_declaration 结构：type + declarator (field_identifier)
     // 查找 field_identifier 或 identifier
@@ -368,10 +382,6 @@ fn analyze_field_with_visibility(node: &Node, ctx: &mut AnalysisContext, visibil
     }
 }
 
-fn analyze_method(node: &Node, ctx: &mut AnalysisContext) {
-    analyze_method_with_visibility(node, ctx, Visibility::Private);
-}
-
 fn analyze_method_with_visibility(node: &Node, ctx: &mut AnalysisContext, visibility: Visibility) {
     // function_definition 在类中：function_declarator 包含 field_identifier 或 identifier
     let name_node = node
@@ -496,6 +506,8 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
             qualified_name: if text.contains("::") || text.contains('.') { Some(text) } else { None },
             location: node_to_range(node),
             is_constructor: false,
+            receiver_type: None,
+            callee_is_parameter: false,
         });
     }
 }
@@ -550,7 +562,7 @@ namespace demo {
 int greet() { return 0; }
 "#;
         let result = adapter.analyze("file:///test.cpp", src);
-        assert!(result.imports.len() >= 1, "Should have includes");
+        assert!(!result.imports.is_empty(), "Should have includes");
         assert!(result.symbols.iter().any(|s| s.name == "demo"), "Should have namespace");
         assert!(result.symbols.iter().any(|s| s.name == "User" && s.kind == SymbolKind::Class), "Should have class User");
         assert!(result.symbols.iter().any(|s| s.name == "name" && s.kind == SymbolKind::Field), "Should have field name");
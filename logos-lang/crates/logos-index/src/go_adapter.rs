@@ -2,34 +2,33 @@
 //!
 //! Implements the LanguageAdapter trait for Go files.
 //! This is a pragmatic (not fully semantic) indexer:
-//! - Symbols: functions, methods, types, vars/consts (package-level)
+//! - Symbols: functions, methods, types, vars/consts (package-level);
+//!   typed `iota` const blocks become enum members of their type
 //! - Imports: import specs
 //! - Exports: inferred from Go export rule (Capitalized identifiers)
 //! - Calls: call expressions
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
-use crate::symbol_table::{SymbolId, Visibility};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool, SymbolBuilder, make_location};
+use crate::comments::attach_leading_docs;
+use crate::symbol_table::{SymbolId, TypeInfo, Visibility};
 use logos_core::{Position, Range, SymbolKind};
-use tree_sitter::{Node, Parser, Tree};
+use logos_parser::LanguageId;
+use std::collections::HashMap;
+use tree_sitter::{Node, Tree};
 
 pub struct GoAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
 }
 
 impl GoAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_go::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_go::LANGUAGE.into())
             .map_err(|e| format!("Failed to set Go language: {}", e))?;
-        Ok(Self {
-            parser: std::sync::Mutex::new(parser),
-        })
+        Ok(Self { parsers })
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
+        self.parsers.parse(source)
     }
 }
 
@@ -52,10 +51,16 @@ impl LanguageAdapter for GoAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
+            iota_groups: Vec::new(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
+        ctx.depth.report(&mut ctx.result);
+        attach_iota_groups(&mut ctx);
+        resolve_package_calls(&mut ctx.result);
+        attach_leading_docs(&mut ctx.result.symbols, source, LanguageId::Go);
         ctx.result
     }
 }
@@ -64,7 +69,15 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
+    /// Typed `iota` const blocks: the type name and the constants declared
+    iota_groups: Vec<IotaGroup>,
+}
+
+struct IotaGroup {
+    type_name: String,
+    members: Vec<SymbolId>,
 }
 
 struct ScopeInfo {
@@ -92,6 +105,14 @@ impl<'a> AnalysisContext<'a> {
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         // imports
         "import_declaration" => analyze_import(node, ctx),
@@ -266,14 +287,57 @@ fn analyze_type_declaration(node: &Node, ctx: &mut AnalysisContext) {
                 .visibility(visibility)
                 .qualified_name(ctx.qualified_name(&name))
                 .build();
+            let symbol_id = symbol.id;
 
             ctx.result.symbols.push(symbol);
+
+            if let Some(fields) = spec
+                .child_by_field_name("type")
+                .filter(|t| t.kind() == "struct_type")
+                .and_then(|t| t.named_child(0))
+                .filter(|list| list.kind() == "field_declaration_list")
+            {
+                analyze_struct_fields(&fields, symbol_id, &name, ctx);
+            }
+        }
+    }
+}
+
+/// Named struct fields as children of the struct. Embedded fields have no
+/// name of their own and are skipped.
+fn analyze_struct_fields(fields: &Node, struct_id: SymbolId, struct_name: &str, ctx: &mut AnalysisContext) {
+    for i in 0..fields.named_child_count() {
+        let field = match fields.named_child(i) {
+            Some(f) if f.kind() == "field_declaration" => f,
+            _ => continue,
+        };
+        let type_info = field
+            .child_by_field_name("type")
+            .map(|t| TypeInfo::simple(ctx.get_text(&t)));
+        let mut cursor = field.walk();
+        let names: Vec<Node> = field.children_by_field_name("name", &mut cursor).collect();
+        for name_node in names {
+            let name = ctx.get_text(&name_node);
+            let exported = is_exported_go(&name);
+            let location = make_location(&ctx.uri, node_to_range(&field), node_to_range(&name_node));
+            let mut builder = SymbolBuilder::new(name.clone(), SymbolKind::Field, location)
+                .parent(struct_id)
+                .exported(exported)
+                .visibility(if exported { Visibility::Public } else { Visibility::Private })
+                .qualified_name(format!("{}.{}", ctx.qualified_name(struct_name), name));
+            if let Some(type_info) = type_info.clone() {
+                builder = builder.type_info(type_info);
+            }
+            ctx.result.symbols.push(builder.build());
         }
     }
 }
 
 fn analyze_value_declaration(node: &Node, ctx: &mut AnalysisContext) {
     let is_const = node.kind() == "const_declaration";
+    let iota_type = if is_const { iota_type_name(node, ctx) } else { None };
+    let mut members = Vec::new();
+
     for i in 0..node.named_child_count() {
         if let Some(spec) = node.named_child(i) {
             if spec.kind() != "const_spec" && spec.kind() != "var_spec" {
@@ -291,17 +355,80 @@ fn analyze_value_declaration(node: &Node, ctx: &mut AnalysisContext) {
                     .visibility(visibility)
                     .qualified_name(ctx.qualified_name(&name))
                     .build();
+                members.push(symbol.id);
                 ctx.result.symbols.push(symbol);
             }
         }
     }
+
+    if let Some(type_name) = iota_type {
+        ctx.iota_groups.push(IotaGroup { type_name, members });
+    }
+}
+
+/// The declared type of a const block enumerated with `iota`, e.g. `Color`
+/// in `const ( Red Color = iota; Green )`
+fn iota_type_name(node: &Node, ctx: &AnalysisContext) -> Option<String> {
+    let mut cursor = node.walk();
+    let specs: Vec<Node> = node
+        .named_children(&mut cursor)
+        .filter(|spec| spec.kind() == "const_spec")
+        .collect();
+
+    let uses_iota = specs.iter().any(|spec| {
+        spec.child_by_field_name("value")
+            .map(|value| contains_iota(value, ctx))
+            .unwrap_or(false)
+    });
+    if !uses_iota {
+        return None;
+    }
+    specs
+        .iter()
+        .find_map(|spec| spec.child_by_field_name("type"))
+        .map(|t| ctx.get_text(&t))
+}
+
+fn contains_iota(node: Node, ctx: &AnalysisContext) -> bool {
+    if node.kind() == "iota" || (node.kind() == "identifier" && ctx.get_text(&node) == "iota") {
+        return true;
+    }
+    let mut cursor = node.walk();
+    let found = node.named_children(&mut cursor).any(|child| contains_iota(child, ctx));
+    found
+}
+
+/// Turn typed `iota` constants into enum members of their type, when the
+/// type is declared in the same file. The type itself becomes an enum.
+fn attach_iota_groups(ctx: &mut AnalysisContext) {
+    for group in std::mem::take(&mut ctx.iota_groups) {
+        let enum_id = match ctx
+            .result
+            .symbols
+            .iter_mut()
+            .find(|s| s.name == group.type_name && s.parent.is_none() && s.kind == SymbolKind::Class)
+        {
+            Some(symbol) => {
+                symbol.kind = SymbolKind::Enum;
+                symbol.id
+            }
+            None => continue,
+        };
+
+        for symbol in ctx.result.symbols.iter_mut() {
+            if group.members.contains(&symbol.id) {
+                symbol.kind = SymbolKind::EnumMember;
+                symbol.parent = Some(enum_id);
+            }
+        }
+    }
 }
 
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     // call_expression: function + arguments
     if let Some(func) = node.child_by_field_name("function") {
         let text = ctx.get_text(&func);
-        let (callee_name, qualified_name) = if let Some(last) = text.split('.').last() {
+        let (callee_name, qualified_name) = if let Some(last) = text.split('.').next_back() {
             (last.to_string(), if text.contains('.') { Some(text) } else { None })
         } else {
             (text.clone(), None)
@@ -312,6 +439,8 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
             qualified_name,
             location: node_to_range(node),
             is_constructor: false,
+            receiver_type: None,
+            callee_is_parameter: false,
         });
     }
 
@@ -323,6 +452,33 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// Rewrite `alias.Func` calls to use the imported package path, e.g.
+/// `s.ToUpper` becomes `strings.ToUpper` under `import s "strings"`
+fn resolve_package_calls(result: &mut AnalysisResult) {
+    let mut packages: HashMap<String, String> = HashMap::new();
+    for import in &result.imports {
+        for item in &import.items {
+            let name = match item.alias.as_deref() {
+                // Blank and dot imports don't introduce a package name
+                Some("_") | Some(".") => continue,
+                Some(alias) => alias,
+                None => import.module_path.rsplit('/').next().unwrap_or(&import.module_path),
+            };
+            packages.insert(name.to_string(), import.module_path.clone());
+        }
+    }
+
+    for call in &mut result.calls {
+        if let Some(qualified) = &mut call.qualified_name {
+            if let Some((receiver, rest)) = qualified.split_once('.') {
+                if let Some(path) = packages.get(receiver) {
+                    *qualified = format!("{}.{}", path, rest);
+                }
+            }
+        }
+    }
+}
+
 fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
     let end = node.end_position();
@@ -370,5 +526,95 @@ func helper() {}
         assert!(result.symbols.iter().any(|s| s.name == "helper"));
         assert!(result.calls.len() >= 2);
     }
+
+    #[test]
+    fn go_struct_fields_are_children() {
+        let adapter = GoAdapter::new().unwrap();
+        let src = "package model\n\ntype User struct {\n  Name, email string\n  Age int\n  Base\n}\n";
+        let result = adapter.analyze("file:///user.go", src);
+
+        let user = result.symbols.iter().find(|s| s.name == "User").unwrap();
+        let fields: Vec<_> = result
+            .symbols
+            .iter()
+            .filter(|s| s.parent == Some(user.id))
+            .map(|s| (s.name.as_str(), s.kind, s.exported))
+            .collect();
+        assert_eq!(fields, vec![
+            ("Name", SymbolKind::Field, true),
+            ("email", SymbolKind::Field, false),
+            ("Age", SymbolKind::Field, true),
+        ]);
+        let age = result.symbols.iter().find(|s| s.name == "Age").unwrap();
+        assert_eq!(age.qualified_name, "User.Age");
+        assert_eq!(age.type_info.as_ref().unwrap().type_expr, "int");
+    }
+
+    #[test]
+    fn go_package_calls_resolve_import_aliases() {
+        let adapter = GoAdapter::new().unwrap();
+        let src = r#"
+package main
+
+import (
+  "encoding/json"
+  s "strings"
+)
+
+func main() {
+  s.ToUpper("x")
+  json.Marshal(nil)
+  u.Name()
 }
+"#;
+        let result = adapter.analyze("file:///main.go", src);
+        let qualified = |callee: &str| {
+            result.calls.iter().find(|c| c.callee_name == callee).unwrap().qualified_name.clone()
+        };
+        assert_eq!(qualified("ToUpper").as_deref(), Some("strings.ToUpper"));
+        assert_eq!(qualified("Marshal").as_deref(), Some("encoding/json.Marshal"));
+        // Receivers that aren't packages are left alone
+        assert_eq!(qualified("Name").as_deref(), Some("u.Name"));
+    }
 
+    #[test]
+    fn go_iota_constants_are_enum_members() {
+        let adapter = GoAdapter::new().unwrap();
+        let src = r#"
+package colors
+
+const (
+  Red Color = iota
+  Green
+  Blue
+)
+
+type Color int
+
+const (
+  First = iota
+  Second
+)
+"#;
+        let result = adapter.analyze("file:///colors.go", src);
+        let color = result.symbols.iter().find(|s| s.name == "Color").unwrap();
+        assert_eq!(color.kind, SymbolKind::Enum);
+
+        let members: Vec<_> = result
+            .symbols
+            .iter()
+            .filter(|s| s.parent == Some(color.id))
+            .map(|s| (s.name.as_str(), s.kind))
+            .collect();
+        assert_eq!(members, vec![
+            ("Red", SymbolKind::EnumMember),
+            ("Green", SymbolKind::EnumMember),
+            ("Blue", SymbolKind::EnumMember),
+        ]);
+
+        // Untyped iota blocks have no enum to belong to
+        let first = result.symbols.iter().find(|s| s.name == "First").unwrap();
+        assert_eq!(first.kind, SymbolKind::Constant);
+        assert_eq!(first.parent, None);
+    }
+}
@@ -2,18 +2,87 @@
 //!
 //! Coordinates language adapters and the project index to index entire projects.
 
-use crate::adapter::{AnalysisResult, LanguageAdapter};
+use crate::adapter::{AnalysisResult, CallInfo, LanguageAdapter, ReceiverType};
 use crate::c_adapter::CAdapter;
 use crate::cpp_adapter::CppAdapter;
 use crate::go_adapter::GoAdapter;
 use crate::java_adapter::JavaAdapter;
 use crate::python_adapter::PythonAdapter;
 use crate::rust_adapter::RustAdapter;
-use crate::symbol_table::{CallSite, CallType, ProjectIndex};
+use crate::symbol_table::{CallSite, CallType, ProjectIndex, SmartSymbol, SymbolId};
 use crate::typescript_adapter::TypeScriptAdapter;
+use logos_core::{normalize_uri, path_to_uri, uri_to_path, Range, SymbolKind};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Files larger than this are skipped unless overridden with
+/// [`ProjectIndexer::with_max_file_bytes`]
+pub const DEFAULT_MAX_FILE_BYTES: usize = 2 * 1024 * 1024;
+
+/// How many files [`IndexingStats::slowest_files`] keeps
+const SLOWEST_FILES_LIMIT: usize = 10;
+
+/// Why a file or directory couldn't be indexed.
+///
+/// I/O failures carry the path that failed: `ReadFile` and `ReadDir` take
+/// the place of a bare `Io(std::io::Error)` variant, and the underlying
+/// error is their `source`.
+#[derive(Debug, Error)]
+pub enum IndexError {
+    #[error("No adapter found for {0}")]
+    NoAdapter(IndexTarget),
+    #[error("Failed to read file {path:?}: {source}")]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to read directory {path:?}: {source}")]
+    ReadDir {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The adapter failed on the source, e.g. by panicking
+    #[error("{0}")]
+    Parse(String),
+    #[error("{} is {len} bytes, above the {limit} byte indexing limit", .target.subject())]
+    TooLarge { target: IndexTarget, len: u64, limit: usize },
+}
+
+/// What an `IndexError` is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexTarget {
+    /// A file read from disk
+    File(PathBuf),
+    /// An in-memory buffer passed to `index_source`, by URI
+    Buffer(String),
+}
+
+impl IndexTarget {
+    /// The target at the start of a sentence
+    fn subject(&self) -> String {
+        match self {
+            IndexTarget::File(path) => format!("File {:?}", path),
+            IndexTarget::Buffer(uri) => uri.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for IndexTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexTarget::File(path) => write!(f, "{:?}", path),
+            IndexTarget::Buffer(uri) => f.write_str(uri),
+        }
+    }
+}
 
 /// Project indexer that coordinates language adapters
 pub struct ProjectIndexer {
@@ -21,6 +90,14 @@ pub struct ProjectIndexer {
     pub index: Arc<ProjectIndex>,
     /// Available language adapters
     adapters: Vec<Box<dyn LanguageAdapter>>,
+    /// File extension -> index into `adapters`
+    adapters_by_extension: HashMap<String, usize>,
+    /// Files above this size (generated bundles and the like) are not indexed
+    max_file_bytes: usize,
+    /// Adapters that could not be constructed, and why
+    adapter_errors: Vec<String>,
+    /// Whether directory indexing records how long each file took
+    timing: bool,
 }
 
 impl ProjectIndexer {
@@ -28,59 +105,113 @@ impl ProjectIndexer {
         let mut indexer = Self {
             index: Arc::new(ProjectIndex::new()),
             adapters: Vec::new(),
+            adapters_by_extension: HashMap::new(),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            adapter_errors: Vec::new(),
+            timing: false,
         };
 
-        // Register built-in adapters
-        if let Ok(ts_adapter) = TypeScriptAdapter::new() {
-            indexer.register_adapter(Box::new(ts_adapter));
-        }
-        if let Ok(py_adapter) = PythonAdapter::new() {
-            indexer.register_adapter(Box::new(py_adapter));
-        }
-        if let Ok(go_adapter) = GoAdapter::new() {
-            indexer.register_adapter(Box::new(go_adapter));
-        }
-        if let Ok(rust_adapter) = RustAdapter::new() {
-            indexer.register_adapter(Box::new(rust_adapter));
-        }
-        if let Ok(c_adapter) = CAdapter::new() {
-            indexer.register_adapter(Box::new(c_adapter));
-        }
-        if let Ok(cpp_adapter) = CppAdapter::new() {
-            indexer.register_adapter(Box::new(cpp_adapter));
-        }
-        if let Ok(java_adapter) = JavaAdapter::new() {
-            indexer.register_adapter(Box::new(java_adapter));
-        }
+        // Register built-in adapters. C++ is registered after C so that it
+        // claims the shared `.h` extension.
+        indexer.try_register_adapter(TypeScriptAdapter::new());
+        indexer.try_register_adapter(PythonAdapter::new());
+        indexer.try_register_adapter(GoAdapter::new());
+        indexer.try_register_adapter(RustAdapter::new());
+        indexer.try_register_adapter(CAdapter::new());
+        indexer.try_register_adapter(CppAdapter::new());
+        indexer.try_register_adapter(JavaAdapter::new());
 
         indexer
     }
 
-    /// Register a language adapter
+    /// Set the size above which files are skipped
+    pub fn with_max_file_bytes(mut self, max_file_bytes: usize) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self
+    }
+
+    /// Record per-file indexing time in [`IndexingStats::slowest_files`]
+    pub fn with_timing(mut self, timing: bool) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Size of `path` if it exceeds the configured limit
+    fn oversized(&self, path: &Path) -> Option<u64> {
+        let len = fs::metadata(path).ok()?.len();
+        (len > self.max_file_bytes as u64).then_some(len)
+    }
+
+    /// Register a language adapter.
+    ///
+    /// When two adapters handle the same extension, the one registered
+    /// last wins, so registering an adapter overrides the built-in choice.
     pub fn register_adapter(&mut self, adapter: Box<dyn LanguageAdapter>) {
+        let index = self.adapters.len();
+        for ext in adapter.file_extensions() {
+            self.adapters_by_extension.insert(ext.to_string(), index);
+        }
         self.adapters.push(adapter);
     }
 
+    /// Register an adapter if it was constructed, otherwise record the error
+    pub fn try_register_adapter<A: LanguageAdapter + 'static>(&mut self, adapter: Result<A, String>) {
+        match adapter {
+            Ok(adapter) => self.register_adapter(Box::new(adapter)),
+            Err(e) => self.adapter_errors.push(e),
+        }
+    }
+
+    /// Errors from adapters that failed to construct; their languages are not indexed
+    pub fn adapter_errors(&self) -> &[String] {
+        &self.adapter_errors
+    }
+
     /// Find an adapter for a file
     fn find_adapter(&self, path: &Path) -> Option<&dyn LanguageAdapter> {
-        self.adapters
-            .iter()
-            .find(|a| a.can_handle(path))
-            .map(|a| a.as_ref())
+        let ext = path.extension()?.to_str()?;
+        self.adapters_by_extension
+            .get(ext)
+            .map(|&index| self.adapters[index].as_ref())
     }
 
     /// Index a single file
-    pub fn index_file(&self, path: &Path) -> Result<AnalysisResult, String> {
+    pub fn index_file(&self, path: &Path) -> Result<AnalysisResult, IndexError> {
+        let (adapter, result) = self.analyze_file(path, &mut Vec::new())?;
+        let _update = self.index.begin_update();
+        self.add_analysis(adapter, &path_to_uri(path), path, &result);
+        Ok(result)
+    }
+
+    /// Read and analyze a file without touching the index, so no update is
+    /// held while the file is parsed. Problems that don't stop the file from
+    /// being indexed are pushed onto `warnings`.
+    fn analyze_file(
+        &self,
+        path: &Path,
+        warnings: &mut Vec<String>,
+    ) -> Result<(&dyn LanguageAdapter, AnalysisResult), IndexError> {
         let adapter = self
             .find_adapter(path)
-            .ok_or_else(|| format!("No adapter found for {:?}", path))?;
+            .ok_or_else(|| IndexError::NoAdapter(IndexTarget::File(path.to_path_buf())))?;
 
-        let content = fs::read_to_string(path)
-            .map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
+        if let Some(len) = self.oversized(path) {
+            return Err(IndexError::TooLarge {
+                target: IndexTarget::File(path.to_path_buf()),
+                len,
+                limit: self.max_file_bytes,
+            });
+        }
 
-        let uri = path_to_uri(path);
-        let result = adapter.analyze(&uri, &content);
+        let content = read_source(path, warnings)?;
+        let result = analyze_catching_panics(adapter, &path_to_uri(path), &content)?;
+        warnings.extend(result.warnings.iter().map(|w| format!("{:?}: {}", path, w)));
+        Ok((adapter, result))
+    }
 
+    /// Add the analysis of `uri`, whose file path is `path`, to the index.
+    /// Callers hold an update.
+    fn add_analysis(&self, adapter: &dyn LanguageAdapter, uri: &str, path: &Path, result: &AnalysisResult) {
         // Add symbols to the index
         for symbol in &result.symbols {
             self.index.symbols.add_symbol(symbol.clone());
@@ -90,25 +221,18 @@ impl ProjectIndexer {
         for call in &result.calls {
             // For now, we create placeholder symbol IDs
             // In a full implementation, we'd resolve the callee to an actual symbol
-            if let Some(caller_scope) = result.symbols.iter().find(|s| {
-                s.location.range.start.line <= call.location.start.line
-                    && s.location.range.end.line >= call.location.end.line
-            }) {
+            if let Some(caller_scope) = enclosing_caller(&result.symbols, call.location) {
                 // We'd need to resolve call.callee_name to a SymbolId
                 // For now, this is a placeholder showing the structure
                 let call_site = CallSite {
                     caller: caller_scope.id,
                     callee: caller_scope.id, // Placeholder - should be resolved
                     location: crate::symbol_table::SymbolLocation {
-                        uri: uri.clone(),
+                        uri: uri.to_string(),
                         range: call.location,
                         selection_range: call.location,
                     },
-                    call_type: if call.is_constructor {
-                        CallType::Constructor
-                    } else {
-                        CallType::Direct
-                    },
+                    call_type: self.classify_call(call, &result.symbols),
                 };
                 self.index.call_graph.add_call(call_site);
             }
@@ -142,9 +266,17 @@ impl ProjectIndexer {
         let file_path = path.to_path_buf();
         for import in &result.imports {
             if let Some(resolved) = adapter.resolve_import(path, &import.module_path) {
-                self.index.dependencies.add_import(file_path.clone(), resolved);
+                self.index
+                    .dependencies
+                    .add_import(file_path.clone(), normalize_path(&resolved));
             }
         }
+        self.index
+            .dependencies
+            .set_file_imports(file_path.clone(), result.imports.clone());
+        self.index
+            .dependencies
+            .set_file_exports(file_path.clone(), result.exports.clone());
 
         // Set exports
         let export_symbols: Vec<_> = result
@@ -154,22 +286,114 @@ impl ProjectIndexer {
             .map(|s| s.id)
             .collect();
         self.index.dependencies.set_exports(file_path, export_symbols);
+    }
 
-        Ok(result)
+    /// Classify a call by how its target is dispatched.
+    ///
+    /// `recv.method()` is `Virtual` when the adapter typed the receiver as a
+    /// class or a trait object and `Interface` when typed as an interface;
+    /// struct receivers dispatch statically. A bare call to a parameter of
+    /// the caller or of a function enclosing it is a `Callback`.
+    fn classify_call(&self, call: &CallInfo, file_symbols: &[SmartSymbol]) -> CallType {
+        if call.is_constructor {
+            return CallType::Constructor;
+        }
+        if call.callee_is_parameter {
+            return CallType::Callback;
+        }
+
+        let type_name = match &call.receiver_type {
+            Some(ReceiverType::TraitObject(_)) => return CallType::Virtual,
+            Some(ReceiverType::Named(name)) => name,
+            None => return CallType::Direct,
+        };
+        let type_kind = file_symbols
+            .iter()
+            .find(|s| s.name == *type_name && is_type_kind(s.kind))
+            .map(|s| s.kind)
+            .or_else(|| {
+                self.index
+                    .symbols
+                    .find_by_name(type_name)
+                    .into_iter()
+                    .find(|s| is_type_kind(s.kind))
+                    .map(|s| s.kind)
+            });
+
+        match type_kind {
+            Some(SymbolKind::Interface) => CallType::Interface,
+            Some(SymbolKind::Class) => CallType::Virtual,
+            _ => CallType::Direct,
+        }
     }
 
     /// Index a directory recursively
-    pub fn index_directory(&self, dir: &Path) -> Result<IndexingStats, String> {
+    pub fn index_directory(&self, dir: &Path) -> Result<IndexingStats, IndexError> {
+        self.index_directory_with_progress(dir, &mut |_| {})
+    }
+
+    /// Index a directory recursively, reporting progress after each file.
+    ///
+    /// The files are collected up front so `files_total` is known from the
+    /// first event.
+    pub fn index_directory_with_progress(
+        &self,
+        dir: &Path,
+        progress: &mut dyn FnMut(IndexProgress),
+    ) -> Result<IndexingStats, IndexError> {
         let mut stats = IndexingStats::default();
 
-        self.index_directory_recursive(dir, &mut stats)?;
+        let mut files = Vec::new();
+        self.collect_files(dir, &mut files)?;
+        let files_total = files.len();
+
+        for (done, path) in files.into_iter().enumerate() {
+            if let Some(len) = self.oversized(&path) {
+                stats.skipped_large.push((path.clone(), len));
+            } else {
+                let started = self.timing.then(Instant::now);
+                let indexed = self.analyze_file(&path, &mut stats.warnings).map(|(adapter, result)| {
+                    let _update = self.index.begin_update();
+                    self.add_analysis(adapter, &path_to_uri(&path), &path, &result);
+                    result
+                });
+                if let Some(started) = started {
+                    stats.slowest_files.push((path.clone(), started.elapsed()));
+                }
+                match indexed {
+                    Ok(result) => {
+                        stats.files_indexed += 1;
+                        stats.symbols_found += result.symbols.len();
+                        stats.imports_found += result.imports.len();
+                        stats.exports_found += result.exports.len();
+                        stats.calls_found += result.calls.len();
+                        stats.type_relations_found += result.type_relations.len();
+                    }
+                    Err(e) => {
+                        stats.errors.push(format!("{:?}: {}", path, e));
+                    }
+                }
+            }
+
+            progress(IndexProgress {
+                files_done: done + 1,
+                files_total,
+                current_path: path,
+            });
+        }
+
+        stats.slowest_files.sort_by_key(|(_, elapsed)| Reverse(*elapsed));
+        stats.slowest_files.truncate(SLOWEST_FILES_LIMIT);
 
         Ok(stats)
     }
 
-    fn index_directory_recursive(&self, dir: &Path, stats: &mut IndexingStats) -> Result<(), String> {
-        let entries = fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?;
+    /// Collect the files under `dir` that an adapter can index
+    fn collect_files(&self, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), IndexError> {
+        let entries = fs::read_dir(dir).map_err(|source| IndexError::ReadDir {
+            path: dir.to_path_buf(),
+            source,
+        })?;
 
         for entry in entries.flatten() {
             let path = entry.path();
@@ -188,37 +412,194 @@ impl ProjectIndexer {
             }
 
             if path.is_dir() {
-                self.index_directory_recursive(&path, stats)?;
-            } else if path.is_file()
-                && self.find_adapter(&path).is_some() {
-                    match self.index_file(&path) {
-                        Ok(result) => {
-                            stats.files_indexed += 1;
-                            stats.symbols_found += result.symbols.len();
-                            stats.imports_found += result.imports.len();
-                            stats.exports_found += result.exports.len();
-                            stats.calls_found += result.calls.len();
-                            stats.type_relations_found += result.type_relations.len();
-                        }
-                        Err(e) => {
-                            stats.errors.push(format!("{:?}: {}", path, e));
-                        }
-                    }
-                }
+                self.collect_files(&path, files)?;
+            } else if path.is_file() && self.find_adapter(&path).is_some() {
+                files.push(path);
+            }
         }
 
         Ok(())
     }
 
+    /// Run the adapter for `path` over `source` without touching the index,
+    /// e.g. for an editor buffer with unsaved changes
+    pub fn analyze_source(&self, path: &Path, source: &str) -> Option<AnalysisResult> {
+        let adapter = self.find_adapter(path)?;
+        analyze_catching_panics(adapter, &path_to_uri(path), source).ok()
+    }
+
+    /// Index an in-memory buffer, such as an editor's unsaved changes,
+    /// replacing whatever was indexed for `uri` before. The adapter is
+    /// picked by the URI's extension and nothing is read from disk.
+    pub fn index_source(&self, uri: &str, source: &str) -> Result<AnalysisResult, IndexError> {
+        // Key the buffer the same way as files read from disk
+        let uri = &normalize_uri(uri);
+        let path = uri_to_path(uri).unwrap_or_else(|| PathBuf::from(uri));
+        let adapter = self
+            .find_adapter(&path)
+            .ok_or_else(|| IndexError::NoAdapter(IndexTarget::Buffer(uri.clone())))?;
+        if source.len() > self.max_file_bytes {
+            return Err(IndexError::TooLarge {
+                target: IndexTarget::Buffer(uri.clone()),
+                len: source.len() as u64,
+                limit: self.max_file_bytes,
+            });
+        }
+
+        let result = analyze_catching_panics(adapter, uri, source)?;
+        let _update = self.index.begin_update();
+        self.index.remove_file_data(uri);
+        self.add_analysis(adapter, uri, &path, &result);
+        Ok(result)
+    }
+
     /// Re-index a single file (for incremental updates)
-    pub fn reindex_file(&self, path: &Path) -> Result<AnalysisResult, String> {
+    pub fn reindex_file(&self, path: &Path) -> Result<AnalysisResult, IndexError> {
         let uri = path_to_uri(path);
+        let analyzed = self.analyze_file(path, &mut Vec::new());
+
+        // Snapshots see either the old or the new contents, never neither
+        let _update = self.index.begin_update();
 
-        // Remove old data for this file
-        self.index.remove_file(&uri);
+        // Remove old data for this file, even if it can no longer be indexed
+        self.index.remove_file_data(&uri);
 
         // Re-index
-        self.index_file(path)
+        let (adapter, result) = analyzed?;
+        self.add_analysis(adapter, &uri, path, &result);
+        Ok(result)
+    }
+
+    /// Re-index a single file and report which symbols appeared or went away.
+    ///
+    /// Symbols get fresh ids on every reindex, so old and new symbols are
+    /// matched by kind and qualified name; matched symbols are in neither
+    /// list. If the file can no longer be indexed (e.g. it was deleted), all
+    /// of its previous symbols are reported as removed.
+    pub fn reindex_file_diff(&self, path: &Path) -> ReindexDiff {
+        let uri = path_to_uri(path);
+        let before = self.index.symbols.get_file_symbols(&uri);
+        let after = self
+            .reindex_file(path)
+            .map(|_| self.index.symbols.get_file_symbols(&uri))
+            .unwrap_or_default();
+
+        let key = |s: &SmartSymbol| {
+            let name = if s.qualified_name.is_empty() { &s.name } else { &s.qualified_name };
+            (s.kind, name.clone())
+        };
+        let mut unmatched: HashMap<(SymbolKind, String), Vec<SymbolId>> = HashMap::new();
+        for symbol in &before {
+            unmatched.entry(key(symbol)).or_default().push(symbol.id);
+        }
+
+        let mut diff = ReindexDiff::default();
+        for symbol in &after {
+            let matched = unmatched.get_mut(&key(symbol)).and_then(|ids| ids.pop());
+            if matched.is_none() {
+                diff.added.push(symbol.id);
+            }
+        }
+        diff.removed = unmatched.into_values().flatten().collect();
+        diff
+    }
+
+    /// Resolve an import path from a file using the file's language adapter
+    pub fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<PathBuf> {
+        self.find_adapter(from_file)?
+            .resolve_import(from_file, import_path)
+            .map(|p| normalize_path(&p))
+    }
+
+    /// Resolve a name imported into `file` (possibly under an alias) to the
+    /// symbol declared in the imported module
+    pub fn resolve_imported_symbol(&self, file: &Path, local_name: &str) -> Option<SmartSymbol> {
+        let imports = self.index.dependencies.get_file_imports(&file.to_path_buf());
+
+        for import in &imports {
+            for item in &import.items {
+                let local = item.alias.as_deref().unwrap_or(&item.name);
+                if local != local_name || item.name == "*" {
+                    continue;
+                }
+
+                let target = self.resolve_import(file, &import.module_path)?;
+                return self.resolve_export(&target, &item.name, &mut HashSet::new());
+            }
+        }
+
+        None
+    }
+
+    /// Resolve a name exported from `file` to its declaration, following
+    /// re-export chains (`export { a as b } from './x'`, `export * from './x'`)
+    pub fn resolve_export(
+        &self,
+        file: &Path,
+        name: &str,
+        visited: &mut HashSet<(PathBuf, String)>,
+    ) -> Option<SmartSymbol> {
+        // Guard against re-export cycles
+        if !visited.insert((file.to_path_buf(), name.to_string())) {
+            return None;
+        }
+
+        let exports = self.index.dependencies.get_file_exports(&file.to_path_buf());
+
+        for export in &exports {
+            let matches = export.name == name || (name == "default" && export.is_default);
+            if !matches || export.name == "*" {
+                continue;
+            }
+
+            let original = export.original_name.as_deref().unwrap_or(&export.name);
+            match &export.from_module {
+                Some(module) => {
+                    let target = self.resolve_import(file, module)?;
+                    return self.resolve_export(&target, original, visited);
+                }
+                None => {
+                    if let Some(symbol) = self.find_declared_symbol(file, original) {
+                        return Some(symbol);
+                    }
+                }
+            }
+        }
+
+        if let Some(symbol) = self.find_declared_symbol(file, name) {
+            return Some(symbol);
+        }
+
+        // `export * from './x'` forwards every name
+        for export in &exports {
+            if export.name != "*" {
+                continue;
+            }
+            if let Some(target) = export
+                .from_module
+                .as_deref()
+                .and_then(|module| self.resolve_import(file, module))
+            {
+                if let Some(symbol) = self.resolve_export(&target, name, visited) {
+                    return Some(symbol);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Find a top-level symbol declared in a file, preferring exported ones
+    fn find_declared_symbol(&self, file: &Path, name: &str) -> Option<SmartSymbol> {
+        let mut candidates: Vec<SmartSymbol> = self
+            .index
+            .symbols
+            .get_file_symbols(&path_to_uri(file))
+            .into_iter()
+            .filter(|s| s.name == name && s.parent.is_none())
+            .collect();
+        candidates.sort_by_key(|s| !s.exported);
+        candidates.into_iter().next()
     }
 
     /// Get the project index
@@ -233,6 +614,23 @@ impl Default for ProjectIndexer {
     }
 }
 
+/// Symbols added and removed by [`ProjectIndexer::reindex_file_diff`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReindexDiff {
+    /// Ids of symbols new in this version of the file
+    pub added: Vec<SymbolId>,
+    /// Ids (from the previous version) of symbols no longer present
+    pub removed: Vec<SymbolId>,
+}
+
+/// Progress of a directory indexing run, emitted once per file
+#[derive(Debug, Clone)]
+pub struct IndexProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub current_path: PathBuf,
+}
+
 /// Statistics from indexing
 #[derive(Debug, Default)]
 pub struct IndexingStats {
@@ -243,19 +641,163 @@ pub struct IndexingStats {
     pub calls_found: usize,
     pub type_relations_found: usize,
     pub errors: Vec<String>,
+    /// Files that were indexed despite a problem, e.g. invalid UTF-8
+    pub warnings: Vec<String>,
+    /// Files skipped for exceeding the size limit, with their size in bytes
+    pub skipped_large: Vec<(PathBuf, u64)>,
+    /// The files that took longest to parse and analyze, slowest first.
+    /// Only filled in when the indexer was built `with_timing(true)`.
+    pub slowest_files: Vec<(PathBuf, Duration)>,
 }
 
-/// Convert a file path to a URI
-fn path_to_uri(path: &Path) -> String {
-    format!("file://{}", path.to_string_lossy())
+/// Read a source file as text. A leading UTF-8 byte order mark is dropped
+/// so tree-sitter positions line up with the editor's, and a file that
+/// isn't valid UTF-8 is decoded lossily with a warning instead of failing.
+fn read_source(path: &Path, warnings: &mut Vec<String>) -> Result<String, IndexError> {
+    let bytes = fs::read(path).map_err(|source| IndexError::ReadFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text.to_string()),
+        Err(e) => {
+            warnings.push(format!("{:?}: not valid UTF-8 ({}), decoded lossily", path, e));
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        }
+    }
+}
+
+/// Run an adapter, turning a panic into an error so one malformed file
+/// can't abort indexing of the rest. This runs before the file touches the
+/// index, so a panic leaves nothing half-added. This relies on panics
+/// unwinding, which the workspace release profile keeps.
+fn analyze_catching_panics(adapter: &dyn LanguageAdapter, uri: &str, source: &str) -> Result<AnalysisResult, IndexError> {
+    panic::catch_unwind(AssertUnwindSafe(|| adapter.analyze(uri, source))).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        IndexError::Parse(format!("{} adapter panicked: {}", adapter.language_id(), message))
+    })
+}
+
+/// The innermost symbol whose range contains `call`, so calls inside nested
+/// functions and closures are attributed to them rather than the outer
+/// function. Variable-like symbols (`x = g()`) are data, not callers.
+fn enclosing_caller(symbols: &[SmartSymbol], call: Range) -> Option<&SmartSymbol> {
+    symbols
+        .iter()
+        .filter(|s| {
+            !matches!(
+                s.kind,
+                SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Field | SymbolKind::Property
+            )
+        })
+        .filter(|s| s.location.range.start <= call.start && call.end <= s.location.range.end)
+        // Every candidate contains the call, so the latest start is innermost
+        .max_by_key(|s| (s.location.range.start, Reverse(s.location.range.end)))
+}
+
+fn is_type_kind(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Class | SymbolKind::Interface | SymbolKind::Struct
+    )
+}
+
+/// Lexically remove `.` and `..` components so resolved imports match
+/// the paths produced while walking the project
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use logos_core::Position;
     use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tempfile::tempdir;
 
+    /// Indexes `.stub` files, panicking on any that contain "boom"
+    struct PanickingAdapter;
+
+    impl LanguageAdapter for PanickingAdapter {
+        fn language_id(&self) -> &str {
+            "stub"
+        }
+
+        fn file_extensions(&self) -> &[&str] {
+            &["stub"]
+        }
+
+        fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
+            if source.contains("boom") {
+                panic!("malformed input");
+            }
+            let range = Range::from_coords(0, 0, 0, 4);
+            let location = crate::adapter::make_location(uri, range, range);
+            AnalysisResult {
+                symbols: vec![crate::adapter::SymbolBuilder::new(source.trim(), SymbolKind::Function, location).build()],
+                ..Default::default()
+            }
+        }
+    }
+
+    #[test]
+    fn test_adapter_panic_is_recorded_and_indexing_continues() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.stub"), "alpha\n").unwrap();
+        fs::write(dir.path().join("b.stub"), "boom\n").unwrap();
+        fs::write(dir.path().join("c.ts"), "function gamma() {}\n").unwrap();
+
+        let mut indexer = ProjectIndexer::new();
+        indexer.register_adapter(Box::new(PanickingAdapter));
+        let stats = indexer.index_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_indexed, 2);
+        assert_eq!(stats.errors.len(), 1);
+        assert!(stats.errors[0].contains("b.stub"), "{}", stats.errors[0]);
+        assert!(stats.errors[0].contains("stub adapter panicked: malformed input"), "{}", stats.errors[0]);
+        assert_eq!(indexer.index.symbols.find_by_name("alpha").len(), 1);
+        assert_eq!(indexer.index.symbols.find_by_name("gamma").len(), 1);
+
+        // The index is still usable afterwards
+        fs::write(dir.path().join("b.stub"), "beta\n").unwrap();
+        indexer.index_file(&dir.path().join("b.stub")).unwrap();
+        assert_eq!(indexer.index.symbols.find_by_name("beta").len(), 1);
+    }
+
+    #[test]
+    fn test_failed_adapter_is_recorded() {
+        let mut indexer = ProjectIndexer::new();
+        assert!(indexer.adapter_errors().is_empty());
+
+        indexer.try_register_adapter(Err::<PythonAdapter, _>(
+            "Failed to set Python language: incompatible version".to_string(),
+        ));
+        assert_eq!(
+            indexer.adapter_errors(),
+            ["Failed to set Python language: incompatible version".to_string()]
+        );
+    }
+
     #[test]
     fn test_index_single_file() {
         let dir = tempdir().unwrap();
@@ -315,4 +857,468 @@ export class User {
         assert_eq!(stats.files_indexed, 2);
         assert!(stats.symbols_found >= 3);
     }
+
+    #[test]
+    fn test_resolve_aliased_import() {
+        let dir = tempdir().unwrap();
+
+        let lib = dir.path().join("lib.ts");
+        fs::write(&lib, "export function foo(): number {\n    return 1;\n}\n").unwrap();
+
+        let app = dir.path().join("app.ts");
+        fs::write(&app, "import { foo as bar } from './lib';\nbar();\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_directory(dir.path()).unwrap();
+
+        let symbol = indexer.resolve_imported_symbol(&app, "bar").unwrap();
+        assert_eq!(symbol.name, "foo");
+        assert_eq!(symbol.location.uri, path_to_uri(&lib));
+        assert_eq!(symbol.location.selection_range.start.line, 0);
+        assert_eq!(symbol.location.selection_range.start.column, 16);
+
+        assert!(indexer.resolve_imported_symbol(&app, "foo").is_none());
+    }
+
+    #[test]
+    fn test_resolve_through_reexport() {
+        let dir = tempdir().unwrap();
+
+        let user = dir.path().join("user.ts");
+        fs::write(&user, "export class User {\n    name: string = '';\n}\n").unwrap();
+
+        let index = dir.path().join("index.ts");
+        fs::write(&index, "export { User } from './user';\nexport * from './other';\n").unwrap();
+
+        let other = dir.path().join("other.ts");
+        fs::write(&other, "export * from './index';\nexport const version = 1;\n").unwrap();
+
+        let app = dir.path().join("app.ts");
+        fs::write(
+            &app,
+            "import { User, version, missing } from './index';\nconst u = new User();\n",
+        )
+        .unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_directory(dir.path()).unwrap();
+
+        let symbol = indexer.resolve_imported_symbol(&app, "User").unwrap();
+        assert_eq!(symbol.location.uri, path_to_uri(&user));
+        assert_eq!(symbol.location.selection_range.start.line, 0);
+
+        let version = indexer.resolve_imported_symbol(&app, "version").unwrap();
+        assert_eq!(version.location.uri, path_to_uri(&other));
+
+        // index.ts and other.ts re-export each other; this must terminate
+        assert!(indexer.resolve_imported_symbol(&app, "missing").is_none());
+    }
+
+    #[test]
+    fn test_generation_advances_on_index_and_remove() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("util.ts");
+        fs::write(&file, "function helper() {}\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        let initial = indexer.index.generation();
+        indexer.index_file(&file).unwrap();
+        let indexed = indexer.index.generation();
+        assert!(indexed > initial);
+
+        // A file that can't be read changes nothing
+        assert!(indexer.index_file(&dir.path().join("missing.ts")).is_err());
+        assert_eq!(indexer.index.generation(), indexed);
+
+        indexer.index.remove_file(&path_to_uri(&file));
+        assert!(indexer.index.generation() > indexed);
+    }
+
+    #[test]
+    fn test_index_source_without_file() {
+        let indexer = ProjectIndexer::new();
+        let uri = "file:///nowhere/shapes.ts";
+        let source = "export class Shape {}\nexport class Circle extends Shape {\n    area() { return compute(); }\n}\n";
+        indexer.index_source(uri, source).unwrap();
+
+        let index = &indexer.index;
+        let names: Vec<String> = index.symbols.get_file_symbols(uri).into_iter().map(|s| s.name).collect();
+        assert!(names.contains(&"Shape".to_string()) && names.contains(&"Circle".to_string()));
+        let circle = index.symbols.find_by_name("Circle").remove(0);
+        assert_eq!(index.type_hierarchy.get_supertypes(circle.id).len(), 1);
+        assert_eq!(index.dependencies.get_exports(&PathBuf::from("/nowhere/shapes.ts")).len(), 2);
+
+        // Indexing the buffer again replaces the previous contents
+        indexer.index_source(uri, "export class Square {}\n").unwrap();
+        let names: Vec<String> = index.symbols.get_file_symbols(uri).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Square"]);
+        assert!(indexer.index_source("file:///notes.txt", "").is_err());
+    }
+
+    #[test]
+    fn test_index_errors_are_typed() {
+        let dir = tempdir().unwrap();
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "not code\n").unwrap();
+        let indexer = ProjectIndexer::new();
+
+        let err = indexer.index_file(&notes).unwrap_err();
+        assert!(matches!(&err, IndexError::NoAdapter(IndexTarget::File(path)) if *path == notes));
+        assert_eq!(err.to_string(), format!("No adapter found for {:?}", notes));
+
+        let missing = dir.path().join("missing.py");
+        let err = indexer.index_file(&missing).unwrap_err();
+        assert!(err.to_string().starts_with(&format!("Failed to read file {:?}: ", missing)));
+        match err {
+            IndexError::ReadFile { path, source } => {
+                assert_eq!(path, missing);
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("expected a read error, got {:?}", other),
+        }
+
+        let err = indexer.index_directory(&dir.path().join("gone")).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to read directory "));
+
+        // Buffers are named by their URI
+        let err = indexer.index_source("file:///notes.txt", "").unwrap_err();
+        assert_eq!(err.to_string(), "No adapter found for file:///notes.txt");
+    }
+
+    #[test]
+    fn test_symbols_since_generation() {
+        let dir = tempdir().unwrap();
+        let util = dir.path().join("util.ts");
+        let app = dir.path().join("app.ts");
+        fs::write(&util, "function helper() {}\n").unwrap();
+        fs::write(&app, "function main() {}\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&util).unwrap();
+        let since = indexer.index.generation();
+        assert!(indexer.index.symbols.symbols_since(since).is_empty());
+
+        indexer.index_file(&app).unwrap();
+        fs::write(&util, "function helper() {}\nfunction format() {}\n").unwrap();
+        indexer.reindex_file(&util).unwrap();
+
+        let names: Vec<String> = indexer
+            .index
+            .symbols
+            .symbols_since(since)
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["main", "helper", "format"]);
+        assert_eq!(indexer.index.symbols.symbols_since(0).len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_is_unaffected_by_reindex() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("util.ts");
+        fs::write(&file, "function oldHelper() {}\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+        let snapshot = indexer.index.snapshot();
+        assert_eq!(snapshot.generation(), indexer.index.generation());
+
+        fs::write(&file, "function newHelper() {}\n").unwrap();
+        indexer.reindex_file(&file).unwrap();
+
+        assert_eq!(snapshot.symbols().find_by_name("oldHelper").len(), 1);
+        assert!(snapshot.symbols().find_by_name("newHelper").is_empty());
+        assert!(indexer.index.symbols.find_by_name("oldHelper").is_empty());
+        assert_eq!(indexer.index.symbols.find_by_name("newHelper").len(), 1);
+        assert!(indexer.index.generation() > snapshot.generation());
+    }
+
+    #[test]
+    fn test_reindex_does_not_duplicate_supertypes() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("shapes.ts");
+        fs::write(
+            &file,
+            "class Shape {}\nclass Circle extends Shape {}\n",
+        )
+        .unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+        indexer.reindex_file(&file).unwrap();
+
+        let circle = indexer.index.symbols.find_by_name("Circle");
+        assert_eq!(circle.len(), 1);
+        let shape = indexer.index.symbols.find_by_name("Shape");
+        assert_eq!(shape.len(), 1);
+
+        let supertypes = indexer.index.type_hierarchy.get_supertypes(circle[0].id);
+        assert_eq!(supertypes, vec![shape[0].id]);
+        assert_eq!(
+            indexer.index.type_hierarchy.get_subtypes(shape[0].id),
+            vec![circle[0].id]
+        );
+    }
+
+    #[test]
+    fn test_skip_large_files() {
+        let dir = tempdir().unwrap();
+        let small = dir.path().join("small.ts");
+        fs::write(&small, "export const a = 1;\n").unwrap();
+        let large = dir.path().join("bundle.ts");
+        let content = "export const b = 2;\n".repeat(100);
+        fs::write(&large, &content).unwrap();
+
+        let indexer = ProjectIndexer::new().with_max_file_bytes(256);
+        let stats = indexer.index_directory(dir.path()).unwrap();
+
+        assert_eq!(stats.files_indexed, 1);
+        assert_eq!(stats.skipped_large, vec![(large.clone(), content.len() as u64)]);
+        assert!(stats.errors.is_empty());
+        assert!(matches!(indexer.index_file(&large), Err(IndexError::TooLarge { len, .. }) if len == content.len() as u64));
+    }
+
+    #[test]
+    fn test_bom_and_invalid_utf8() {
+        let dir = tempdir().unwrap();
+        let bom = dir.path().join("bom.ts");
+        fs::write(&bom, b"\xEF\xBB\xBFfunction greet() {}\n").unwrap();
+        let latin1 = dir.path().join("latin1.py");
+        fs::write(&latin1, b"# caf\xE9\ndef brew():\n    pass\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        let stats = indexer.index_directory(dir.path()).unwrap();
+        assert_eq!(stats.files_indexed, 2);
+        assert!(stats.errors.is_empty());
+        assert_eq!(stats.warnings.len(), 1);
+        assert!(stats.warnings[0].contains("latin1.py"));
+
+        let greet = indexer.index.symbols.find_by_name("greet").pop().unwrap();
+        assert_eq!(greet.location.range.start, Position::new(0, 0));
+        assert_eq!(greet.location.selection_range.start, Position::new(0, 9));
+        assert_eq!(indexer.index.symbols.find_by_name("brew").len(), 1);
+    }
+
+    #[test]
+    fn test_slowest_files_timing() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.ts"), "export const a = 1;\n").unwrap();
+        fs::write(dir.path().join("b.ts"), "export function b() { return 1; }\n".repeat(200)).unwrap();
+        fs::write(dir.path().join("c.py"), "def c():\n    pass\n").unwrap();
+
+        let stats = ProjectIndexer::new().index_directory(dir.path()).unwrap();
+        assert!(stats.slowest_files.is_empty());
+
+        let stats = ProjectIndexer::new()
+            .with_timing(true)
+            .index_directory(dir.path())
+            .unwrap();
+        assert_eq!(stats.slowest_files.len(), 3);
+        assert!(stats.slowest_files.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn test_index_directory_progress() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.ts"), "export const a = 1;\n").unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("b.py"), "def b():\n    pass\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not source\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        let mut events = Vec::new();
+        let stats = indexer
+            .index_directory_with_progress(dir.path(), &mut |p| events.push(p))
+            .unwrap();
+
+        assert_eq!(stats.files_indexed, 2);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.files_total == 2));
+        assert_eq!(events[0].files_done, 1);
+        let last = events.last().unwrap();
+        assert_eq!(last.files_done, last.files_total);
+    }
+
+    /// Adapter that counts how often it is asked whether it handles a file
+    struct CountingAdapter {
+        can_handle_calls: Arc<AtomicUsize>,
+    }
+
+    impl LanguageAdapter for CountingAdapter {
+        fn language_id(&self) -> &str {
+            "counting"
+        }
+
+        fn file_extensions(&self) -> &[&str] {
+            &["cnt"]
+        }
+
+        fn can_handle(&self, _path: &Path) -> bool {
+            self.can_handle_calls.fetch_add(1, Ordering::SeqCst);
+            false
+        }
+
+        fn analyze(&self, _uri: &str, _source: &str) -> AnalysisResult {
+            AnalysisResult::default()
+        }
+    }
+
+    #[test]
+    fn test_adapter_lookup_by_extension() {
+        let can_handle_calls = Arc::new(AtomicUsize::new(0));
+        let mut indexer = ProjectIndexer::new();
+        indexer.register_adapter(Box::new(CountingAdapter {
+            can_handle_calls: Arc::clone(&can_handle_calls),
+        }));
+
+        let language = |path: &str| indexer.find_adapter(Path::new(path)).map(|a| a.language_id());
+        assert_eq!(language("/src/app.py"), Some("python"));
+        assert_eq!(language("/src/lib.h"), Some("cpp"));
+        assert_eq!(language("/src/README"), None);
+
+        // Lookup goes through the extension map, not each adapter
+        assert_eq!(can_handle_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_classify_calls() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("calls.ts");
+        fs::write(
+            &file,
+            r#"class Shape {
+    area(): number { return 0; }
+}
+interface Drawable {
+    draw(): void;
+}
+function render(shape: Shape, canvas: Drawable, done: () => void) {
+    shape.area();
+    canvas.draw();
+    done();
+    console.log("rendered");
+}
+function later(done: () => void) {
+    const shape = new Shape();
+    function step() {
+        done();
+        shape.area();
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+
+        let call_types = |name: &str| {
+            let caller = indexer.index.symbols.find_by_name(name).remove(0);
+            let mut calls = indexer.index.call_graph.get_callees(caller.id);
+            calls.sort_by_key(|c| c.location.range.start.line);
+            calls.iter().map(|c| c.call_type).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            call_types("render"),
+            vec![
+                CallType::Virtual,
+                CallType::Interface,
+                CallType::Callback,
+                CallType::Direct
+            ]
+        );
+        // A parameter of the enclosing function is still a callback, and a
+        // local's type comes from its `new` initializer
+        assert_eq!(call_types("step"), vec![CallType::Callback, CallType::Virtual]);
+    }
+
+    #[test]
+    fn test_classify_rust_receivers() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("calls.rs");
+        fs::write(
+            &file,
+            r#"struct Point { x: i32 }
+trait Shape { fn area(&self) -> f64; }
+impl Point {
+    fn norm(&self) -> i32 { self.x }
+    fn twice(&self) -> i32 { self.norm() * 2 }
+}
+fn measure(p: &Point, s: &dyn Shape, boxed: Box<dyn Shape>, f: impl Fn()) {
+    p.norm();
+    s.area();
+    boxed.area();
+    f();
+}
+"#,
+        )
+        .unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+
+        let call_types = |name: &str| {
+            let caller = indexer.index.symbols.find_by_name(name).remove(0);
+            let mut calls = indexer.index.call_graph.get_callees(caller.id);
+            calls.sort_by_key(|c| c.location.range.start.line);
+            calls.iter().map(|c| c.call_type).collect::<Vec<_>>()
+        };
+        // Methods on a struct dispatch statically, trait objects don't
+        assert_eq!(
+            call_types("measure"),
+            vec![
+                CallType::Direct,
+                CallType::Virtual,
+                CallType::Virtual,
+                CallType::Callback
+            ]
+        );
+        assert_eq!(call_types("twice"), vec![CallType::Direct]);
+    }
+
+    #[test]
+    fn test_nested_function_is_the_caller() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("nested.py");
+        fs::write(
+            &file,
+            "def g():\n    pass\n\ndef outer():\n    def inner():\n        g()\n    return inner\n",
+        )
+        .unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+
+        let inner = indexer.index.symbols.find_by_name("inner").remove(0);
+        let outer = indexer.index.symbols.find_by_name("outer").remove(0);
+        let inner_calls = indexer.index.call_graph.get_callees(inner.id);
+        assert_eq!(inner_calls.len(), 1);
+        assert_eq!(inner_calls[0].location.range.start.line, 5);
+        assert!(indexer.index.call_graph.get_callees(outer.id).is_empty());
+    }
+
+    #[test]
+    fn test_reindex_file_diff() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("ops.py");
+        fs::write(&file, "def keep():\n    pass\n\ndef old_helper():\n    pass\n").unwrap();
+
+        let indexer = ProjectIndexer::new();
+        indexer.index_file(&file).unwrap();
+        let old_helper = indexer.index.symbols.find_by_name("old_helper").remove(0);
+
+        fs::write(&file, "def keep():\n    pass\n\ndef new_helper():\n    pass\n").unwrap();
+        let diff = indexer.reindex_file_diff(&file);
+
+        let new_helper = indexer.index.symbols.find_by_name("new_helper").remove(0);
+        assert_eq!(diff.added, vec![new_helper.id]);
+        assert_eq!(diff.removed, vec![old_helper.id]);
+
+        // Deleting the file removes everything it declared
+        fs::remove_file(&file).unwrap();
+        let diff = indexer.reindex_file_diff(&file);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed.len(), 2);
+    }
 }
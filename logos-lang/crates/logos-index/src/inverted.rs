@@ -1,32 +1,52 @@
 //! Inverted index for fast symbol lookup
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
+/// Maps each name key to the URIs containing it. Postings are counted per
+/// (key, uri) so a file with several symbols of the same name keeps its
+/// posting until the last of them is removed.
 #[derive(Debug, Default)]
 pub struct InvertedIndex {
-    index: HashMap<String, HashSet<String>>,
+    index: HashMap<String, HashMap<String, usize>>,
 }
 
 impl InvertedIndex {
     pub fn new() -> Self { Self::default() }
 
     pub fn add(&mut self, name: &str, uri: &str) {
-        let name_lower = name.to_lowercase();
-        self.index.entry(name_lower.clone()).or_default().insert(uri.to_string());
-        for i in 2..=name_lower.len() {
-            let prefix = &name_lower[..i];
-            self.index.entry(prefix.to_string()).or_default().insert(uri.to_string());
+        for key in index_keys(&name.to_lowercase()) {
+            *self.index.entry(key).or_default().entry(uri.to_string()).or_insert(0) += 1;
         }
     }
 
+    /// Add many names at once, touching each key's posting list only once
+    pub fn add_bulk<'a>(&mut self, entries: impl IntoIterator<Item = (&'a str, &'a str)>) {
+        let mut postings: HashMap<String, HashMap<&str, usize>> = HashMap::new();
+        for (name, uri) in entries {
+            for key in index_keys(&name.to_lowercase()) {
+                *postings.entry(key).or_default().entry(uri).or_insert(0) += 1;
+            }
+        }
+        for (key, uris) in postings {
+            let entry = self.index.entry(key).or_default();
+            for (uri, count) in uris {
+                *entry.entry(uri.to_string()).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Remove one occurrence of `name` in `uri`
     pub fn remove(&mut self, name: &str, uri: &str) {
-        let name_lower = name.to_lowercase();
-        for i in 2..=name_lower.len() {
-            let prefix = &name_lower[..i];
-            if let Some(uris) = self.index.get_mut(prefix) {
-                uris.remove(uri);
+        for key in index_keys(&name.to_lowercase()) {
+            if let Some(uris) = self.index.get_mut(&key) {
+                if let Some(count) = uris.get_mut(uri) {
+                    *count -= 1;
+                    if *count == 0 {
+                        uris.remove(uri);
+                    }
+                }
                 if uris.is_empty() {
-                    self.index.remove(prefix);
+                    self.index.remove(&key);
                 }
             }
         }
@@ -34,8 +54,50 @@ impl InvertedIndex {
 
     pub fn search(&self, query: &str) -> Vec<String> {
         let query_lower = query.to_lowercase();
-        self.index.get(&query_lower).map(|uris| uris.iter().cloned().collect()).unwrap_or_default()
+        self.index.get(&query_lower).map(|uris| uris.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Number of indexed occurrences under `key` (a lowercased name or
+    /// prefix) in `uri`
+    pub fn count(&self, key: &str, uri: &str) -> usize {
+        self.index.get(key).and_then(|uris| uris.get(uri)).copied().unwrap_or(0)
     }
 
     pub fn clear(&mut self) { self.index.clear(); }
 }
+
+/// Keys a lowercased name is indexed under: the name itself and every
+/// prefix of at least two characters
+fn index_keys(name_lower: &str) -> Vec<String> {
+    let mut keys: Vec<String> = name_lower
+        .char_indices()
+        .skip(2)
+        .map(|(i, _)| name_lower[..i].to_string())
+        .collect();
+    keys.push(name_lower.to_string());
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_keeps_remaining_occurrences() {
+        let mut index = InvertedIndex::new();
+        index.add("foo", "file:///a.py");
+        index.add("foo", "file:///a.py");
+        index.add_bulk([("Foobar", "file:///a.py")]);
+
+        index.remove("foo", "file:///a.py");
+        assert_eq!(index.search("foo"), vec!["file:///a.py".to_string()]);
+
+        index.remove("foo", "file:///a.py");
+        assert_eq!(index.search("foo"), vec!["file:///a.py".to_string()]);
+
+        index.remove("foobar", "file:///a.py");
+        assert!(index.search("foo").is_empty());
+        assert!(index.search("fo").is_empty());
+        assert!(index.index.is_empty());
+    }
+}
@@ -6,30 +6,27 @@
 //! - Exports: public/protected treated as exported (best-effort)
 //! - Calls: method_invocation nodes (best-effort)
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool, SymbolBuilder, make_location};
+use crate::comments::attach_leading_docs;
 use crate::symbol_table::{SymbolId, Visibility};
 use logos_core::{Position, Range, SymbolKind};
+use logos_parser::LanguageId;
 use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Tree};
 
 pub struct JavaAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
 }
 
 impl JavaAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_java::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_java::LANGUAGE.into())
             .map_err(|e| format!("Failed to set Java language: {}", e))?;
-        Ok(Self {
-            parser: std::sync::Mutex::new(parser),
-        })
+        Ok(Self { parsers })
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
+        self.parsers.parse(source)
     }
 }
 
@@ -52,10 +49,13 @@ impl LanguageAdapter for JavaAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
+        ctx.depth.report(&mut ctx.result);
+        attach_leading_docs(&mut ctx.result.symbols, source, LanguageId::Java);
         ctx.result
     }
 
@@ -70,6 +70,7 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
 }
 
@@ -123,6 +124,14 @@ fn visibility_and_export(node: &Node, ctx: &AnalysisContext) -> (Visibility, boo
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         "import_declaration" => analyze_import(node, ctx),
 
@@ -135,6 +144,7 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
         "field_declaration" => analyze_field(node, ctx),
 
         "method_invocation" => analyze_call(node, ctx),
+        "object_creation_expression" => analyze_object_creation(node, ctx),
 
         _ => {
             for i in 0..node.named_child_count() {
@@ -280,9 +290,36 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
         qualified_name: None,
         location: node_to_range(node),
         is_constructor: false,
+        receiver_type: None,
+        callee_is_parameter: false,
     });
 }
 
+fn analyze_object_creation(node: &Node, ctx: &mut AnalysisContext) {
+    // new Foo<T>(args) -> callee `Foo`
+    if let Some(type_node) = node.child_by_field_name("type") {
+        let text = ctx.get_text(&type_node);
+        let callee_name = text.split('<').next().unwrap_or(&text).trim().to_string();
+        ctx.result.calls.push(CallInfo {
+            callee_name: callee_name.clone(),
+            qualified_name: Some(format!("new {}", callee_name)),
+            location: node_to_range(node),
+            is_constructor: true,
+            receiver_type: None,
+            callee_is_parameter: false,
+        });
+    }
+
+    // Arguments and anonymous class bodies can hold further calls
+    for i in 0..node.named_child_count() {
+        if let Some(child) = node.named_child(i) {
+            if child.kind() != "type_identifier" && child.kind() != "generic_type" {
+                analyze_node(&child, ctx);
+            }
+        }
+    }
+}
+
 fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
     let end = node.end_position();
@@ -315,10 +352,27 @@ public class User {
 }
 "#;
         let result = adapter.analyze("file:///User.java", src);
-        assert!(result.imports.len() >= 1);
+        assert!(!result.imports.is_empty());
         assert!(result.symbols.iter().any(|s| s.name == "User"));
         assert!(result.symbols.iter().any(|s| s.name == "greet"));
-        assert!(result.calls.len() >= 1);
+        assert!(!result.calls.is_empty());
     }
-}
 
+    #[test]
+    fn java_constructor_calls() {
+        let adapter = JavaAdapter::new().unwrap();
+        let src = r#"
+public class Shop {
+  public void open() {
+    List<String> items = new ArrayList<String>();
+    items.add("x");
+  }
+}
+"#;
+        let result = adapter.analyze("file:///Shop.java", src);
+        let created = result.calls.iter().find(|c| c.callee_name == "ArrayList").unwrap();
+        assert!(created.is_constructor);
+        let add = result.calls.iter().find(|c| c.callee_name == "add").unwrap();
+        assert!(!add.is_constructor);
+    }
+}
@@ -9,26 +9,31 @@ pub mod incremental;
 pub mod indexer;
 pub mod inverted;
 pub mod java_adapter;
+#[cfg(feature = "lsif")]
+pub mod lsif;
 pub mod python_adapter;
+pub mod reference_scan;
 pub mod rust_adapter;
 pub mod symbol_table;
 pub mod typescript_adapter;
 
 pub use adapter::{
-    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter,
-    SymbolBuilder, TypeRelation, make_location,
+    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter, ParserPool,
+    ReceiverType, SymbolBuilder, TypeRelation, make_location, MAX_NESTING_DEPTH,
 };
-pub use comments::{CommentScanner, ScannerConfig, TodoIndex, TodoItem, TodoKind};
+pub use comments::{CommentScanner, ScannerConfig, TodoIndex, TodoItem, TodoKind, TodoQuery};
 pub use c_adapter::CAdapter;
 pub use cpp_adapter::CppAdapter;
 pub use go_adapter::GoAdapter;
-pub use indexer::{IndexingStats, ProjectIndexer};
+pub use indexer::{IndexError, IndexProgress, IndexTarget, IndexingStats, ProjectIndexer, ReindexDiff, DEFAULT_MAX_FILE_BYTES};
 pub use java_adapter::JavaAdapter;
 pub use python_adapter::PythonAdapter;
+pub use reference_scan::scan_references;
 pub use rust_adapter::RustAdapter;
 pub use symbol_table::{
-    Attribute, CallGraph, CallSite, CallType, DependencyGraph, ProjectIndex, SmartSymbol, SymbolId,
-    SymbolLocation, SymbolReference, SymbolTable, TypeHierarchy, TypeInfo, Visibility,
+    glob_match, is_glob_pattern, qualified_name_parts, Attribute, CallGraph, CallSite, CallType, DependencyGraph,
+    IndexSnapshot, ProjectIndex, SmartSymbol, SymbolId, SymbolLocation, SymbolReference, SymbolTable,
+    TypeHierarchy, TypeInfo, Visibility,
 };
 pub use typescript_adapter::TypeScriptAdapter;
 use logos_core::{Position, Range, Symbol, SymbolKind};
@@ -42,6 +47,7 @@ pub struct IndexedSymbol {
     pub range: Range,
     pub selection_range: Range,
     pub container: Option<String>,
+    pub detail: Option<String>,
 }
 
 impl IndexedSymbol {
@@ -53,6 +59,7 @@ impl IndexedSymbol {
             range: symbol.range,
             selection_range: symbol.selection_range,
             container,
+            detail: symbol.detail.clone(),
         }
     }
 }
@@ -69,18 +76,45 @@ impl SymbolIndex {
     pub fn index_document(&mut self, uri: &str, symbols: &[Symbol]) {
         self.remove_document(uri);
         let mut indexed = Vec::new();
-        self.index_symbols_recursive(uri, symbols, None, &mut indexed);
+        self.index_symbols(uri, symbols, &mut indexed);
         for symbol in &indexed {
             self.inverted.add(&symbol.name, uri);
         }
         self.by_document.insert(uri.to_string(), indexed);
     }
 
-    fn index_symbols_recursive(&self, uri: &str, symbols: &[Symbol], container: Option<&str>, indexed: &mut Vec<IndexedSymbol>) {
-        for symbol in symbols {
+    /// Index many documents in one pass, adding their names to the
+    /// inverted index in bulk
+    pub fn index_documents(&mut self, docs: &[(String, Vec<Symbol>)]) {
+        // A URI listed twice keeps its last symbols, as with repeated calls
+        let mut batch = HashMap::with_capacity(docs.len());
+        for (uri, symbols) in docs {
+            self.remove_document(uri);
+            let mut indexed = Vec::new();
+            self.index_symbols(uri, symbols, &mut indexed);
+            batch.insert(uri.clone(), indexed);
+        }
+        self.inverted.add_bulk(
+            batch
+                .iter()
+                .flat_map(|(uri, indexed)| indexed.iter().map(move |s| (s.name.as_str(), uri.as_str()))),
+        );
+        self.by_document.extend(batch);
+    }
+
+    /// Flatten a symbol tree depth-first. Uses an explicit stack rather
+    /// than recursion so deeply nested generated code can't overflow.
+    fn index_symbols(&self, uri: &str, symbols: &[Symbol], indexed: &mut Vec<IndexedSymbol>) {
+        let mut stack: Vec<(std::slice::Iter<Symbol>, Option<&str>)> = vec![(symbols.iter(), None)];
+        while let Some((siblings, container)) = stack.last_mut() {
+            let container = *container;
+            let Some(symbol) = siblings.next() else {
+                stack.pop();
+                continue;
+            };
             indexed.push(IndexedSymbol::from_symbol(symbol, uri, container.map(String::from)));
             if !symbol.children.is_empty() {
-                self.index_symbols_recursive(uri, &symbol.children, Some(&symbol.name), indexed);
+                stack.push((symbol.children.iter(), Some(&symbol.name)));
             }
         }
     }
@@ -93,29 +127,99 @@ impl SymbolIndex {
         }
     }
 
+    /// Replace or add a single symbol, updating only its own postings.
+    ///
+    /// With `old_name`, the first symbol of that name in the document
+    /// (preferring one at the new symbol's position) is replaced; without
+    /// it, the symbol is appended.
+    pub fn update_symbol(&mut self, uri: &str, old_name: Option<&str>, new: IndexedSymbol) {
+        let symbols = self.by_document.entry(uri.to_string()).or_default();
+        let existing = old_name.and_then(|old| {
+            symbols
+                .iter()
+                .position(|s| s.name == old && s.selection_range.start == new.selection_range.start)
+                .or_else(|| symbols.iter().position(|s| s.name == old))
+        });
+
+        if let Some(index) = existing {
+            let old = std::mem::replace(&mut symbols[index], new);
+            self.inverted.remove(&old.name, uri);
+            self.inverted.add(&symbols[index].name, uri);
+        } else {
+            self.inverted.add(&new.name, uri);
+            symbols.push(new);
+        }
+    }
+
     pub fn get_document_symbols(&self, uri: &str) -> &[IndexedSymbol] {
         self.by_document.get(uri).map(|v| v.as_slice()).unwrap_or(&[])
     }
 
     pub fn search(&self, query: &str) -> Vec<&IndexedSymbol> {
-        let uris = self.inverted.search(query);
-        let mut results = Vec::new();
-        for uri in uris {
-            if let Some(symbols) = self.by_document.get(&uri) {
-                for symbol in symbols {
-                    if symbol.name.to_lowercase().contains(&query.to_lowercase()) {
-                        results.push(symbol);
-                    }
-                }
-            }
-        }
-        results
+        self.search_iter(query).collect()
+    }
+
+    /// Lazily search symbols by query, document by document
+    pub fn search_iter(&self, query: &str) -> impl Iterator<Item = &IndexedSymbol> + '_ {
+        let query_lower = query.to_lowercase();
+        self.inverted
+            .search(query)
+            .into_iter()
+            .filter_map(move |uri| self.by_document.get(&uri))
+            .flat_map(move |symbols| {
+                let query_lower = query_lower.clone();
+                symbols.iter().filter(move |s| s.name.to_lowercase().contains(&query_lower))
+            })
+    }
+
+    /// Symbols named exactly `name` (case-sensitive), across documents
+    pub fn find_by_name(&self, name: &str) -> impl Iterator<Item = &IndexedSymbol> + '_ {
+        let name = name.to_string();
+        self.search_iter(&name).filter(move |s| s.name == name)
     }
 
     pub fn find_at_position(&self, uri: &str, position: Position) -> Option<&IndexedSymbol> {
         self.by_document.get(uri)?.iter().find(|s| s.selection_range.contains(position))
     }
 
+    /// Innermost symbol whose full range contains the position
+    pub fn find_enclosing(&self, uri: &str, position: Position) -> Option<&IndexedSymbol> {
+        self.by_document
+            .get(uri)?
+            .iter()
+            .filter(|s| s.range.contains(position))
+            .reduce(|outer, s| {
+                if s.range.start >= outer.range.start && s.range.end <= outer.range.end { s } else { outer }
+            })
+    }
+
+    /// Symbols enclosing a position, outermost first (e.g. `[Shape, area]`
+    /// in a method body), following `container` links up from the
+    /// innermost enclosing symbol
+    pub fn breadcrumb(&self, uri: &str, position: Position) -> Vec<&IndexedSymbol> {
+        let Some(symbols) = self.by_document.get(uri) else {
+            return Vec::new();
+        };
+        let mut chain: Vec<&IndexedSymbol> = self.find_enclosing(uri, position).into_iter().collect();
+        while let Some(child) = chain.last().copied() {
+            let Some(container) = child.container.as_deref() else {
+                break;
+            };
+            let parent = symbols.iter().find(|s| {
+                s.name == container
+                    && !std::ptr::eq(*s, child)
+                    && s.range.start <= child.range.start
+                    && child.range.end <= s.range.end
+            });
+            match parent {
+                Some(parent) if chain.len() < symbols.len() => chain.push(parent),
+                _ => break,
+            }
+        }
+        chain.reverse();
+        chain
+    }
+
     pub fn documents(&self) -> impl Iterator<Item = &str> {
         self.by_document.keys().map(|s| s.as_str())
     }
@@ -124,3 +228,233 @@ impl SymbolIndex {
         self.by_document.values().map(|v| v.len()).sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_enclosing_prefers_innermost() {
+        let method = Symbol::new(
+            "area".to_string(),
+            SymbolKind::Method,
+            Range::from_coords(1, 4, 3, 5),
+            Range::from_coords(1, 4, 1, 8),
+        );
+        let class = Symbol::new(
+            "Shape".to_string(),
+            SymbolKind::Class,
+            Range::from_coords(0, 0, 5, 1),
+            Range::from_coords(0, 6, 0, 11),
+        )
+        .with_children(vec![method]);
+
+        let mut index = SymbolIndex::new();
+        index.index_document("file:///shape.ts", &[class]);
+
+        // Inside the method body, away from any name
+        let body = Position::new(2, 8);
+        assert!(index.find_at_position("file:///shape.ts", body).is_none());
+        let symbol = index.find_enclosing("file:///shape.ts", body).unwrap();
+        assert_eq!(symbol.name, "area");
+        assert_eq!(symbol.container.as_deref(), Some("Shape"));
+
+        // Inside the class but outside the method
+        let symbol = index.find_enclosing("file:///shape.ts", Position::new(4, 0)).unwrap();
+        assert_eq!(symbol.name, "Shape");
+
+        assert!(index.find_enclosing("file:///shape.ts", Position::new(7, 0)).is_none());
+    }
+
+    #[test]
+    fn test_breadcrumb() {
+        let method = Symbol::new(
+            "area".to_string(),
+            SymbolKind::Method,
+            Range::from_coords(1, 4, 3, 5),
+            Range::from_coords(1, 4, 1, 8),
+        );
+        let class = Symbol::new(
+            "Shape".to_string(),
+            SymbolKind::Class,
+            Range::from_coords(0, 0, 5, 1),
+            Range::from_coords(0, 6, 0, 11),
+        )
+        .with_children(vec![method]);
+        let mut index = SymbolIndex::new();
+        index.index_document("file:///shape.ts", &[class]);
+
+        let names = |position: Position| -> Vec<String> {
+            index
+                .breadcrumb("file:///shape.ts", position)
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+        assert_eq!(names(Position::new(2, 8)), vec!["Shape", "area"]);
+        assert_eq!(names(Position::new(4, 0)), vec!["Shape"]);
+        assert!(names(Position::new(7, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_index_very_deep_nesting() {
+        let depth = 10_000;
+        let mut symbol = Symbol::new(
+            format!("n{}", depth - 1),
+            SymbolKind::Namespace,
+            Range::from_coords(depth - 1, 0, depth - 1, 1),
+            Range::from_coords(depth - 1, 0, depth - 1, 1),
+        );
+        for i in (0..depth - 1).rev() {
+            symbol = Symbol::new(
+                format!("n{}", i),
+                SymbolKind::Namespace,
+                Range::from_coords(i, 0, 2 * depth - i, 1),
+                Range::from_coords(i, 0, i, 1),
+            )
+            .with_children(vec![symbol]);
+        }
+
+        let mut index = SymbolIndex::new();
+        index.index_document("file:///deep.ts", std::slice::from_ref(&symbol));
+        let deepest = index.find_by_name("n9999").next().unwrap();
+        assert_eq!(deepest.container.as_deref(), Some("n9998"));
+        assert_eq!(index.breadcrumb("file:///deep.ts", Position::new(5, 0)).len(), 6);
+    }
+
+    #[test]
+    fn test_index_documents_matches_individual_calls() {
+        let function = |name: &str, line: u32| {
+            Symbol::new(
+                name.to_string(),
+                SymbolKind::Function,
+                Range::from_coords(line, 0, line + 1, 0),
+                Range::from_coords(line, 9, line, 9 + name.len() as u32),
+            )
+        };
+        let docs: Vec<(String, Vec<Symbol>)> = (0..50)
+            .map(|i| {
+                (
+                    format!("file:///src/mod{}.ts", i),
+                    vec![function("render", 0), function(&format!("helper{}", i), 2)],
+                )
+            })
+            .collect();
+
+        let mut one_by_one = SymbolIndex::new();
+        for (uri, symbols) in &docs {
+            one_by_one.index_document(uri, symbols);
+        }
+        let mut batched = SymbolIndex::new();
+        // Re-indexing replaces earlier contents, as with index_document
+        batched.index_document("file:///src/mod0.ts", &[function("stale", 0)]);
+        batched.index_documents(&docs);
+
+        assert_eq!(batched.symbol_count(), one_by_one.symbol_count());
+        assert!(batched.search("stale").is_empty());
+        for query in ["render", "REN", "helper4", "helper42"] {
+            let mut expected: Vec<_> = one_by_one.search(query).iter().map(|s| (&s.uri, &s.name)).collect();
+            let mut actual: Vec<_> = batched.search(query).iter().map(|s| (&s.uri, &s.name)).collect();
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected, "query {}", query);
+        }
+    }
+
+    /// Run with `cargo test --release -- --ignored`; debug builds spend
+    /// most of the time elsewhere and hide the difference
+    #[test]
+    #[ignore = "timing benchmark"]
+    fn test_index_documents_is_faster_than_individual_calls() {
+        use std::time::{Duration, Instant};
+
+        let docs: Vec<(String, Vec<Symbol>)> = (0..2000)
+            .map(|i| {
+                let symbols = (0..20)
+                    .map(|j| {
+                        let name = format!("renderComponent{}", j % 5);
+                        let selection = Range::from_coords(j, 9, j, 9 + name.len() as u32);
+                        Symbol::new(name, SymbolKind::Function, Range::from_coords(j, 0, j + 1, 0), selection)
+                    })
+                    .collect();
+                (format!("file:///src/mod{}.ts", i), symbols)
+            })
+            .collect();
+
+        // Best of several runs, to keep scheduling noise out
+        let best = |run: &dyn Fn()| {
+            (0..5)
+                .map(|_| {
+                    let start = Instant::now();
+                    run();
+                    start.elapsed()
+                })
+                .min()
+                .unwrap_or(Duration::MAX)
+        };
+        let individual = best(&|| {
+            let mut index = SymbolIndex::new();
+            for (uri, symbols) in &docs {
+                index.index_document(uri, symbols);
+            }
+        });
+        let batched = best(&|| SymbolIndex::new().index_documents(&docs));
+
+        assert!(batched < individual, "batch {:?} vs individual {:?}", batched, individual);
+    }
+
+    #[test]
+    fn test_update_symbol_touches_only_its_postings() {
+        let uri = "file:///lib.ts";
+        let function = |name: &str, line: u32| {
+            Symbol::new(
+                name.to_string(),
+                SymbolKind::Function,
+                Range::from_coords(line, 0, line + 1, 0),
+                Range::from_coords(line, 9, line, 9 + name.len() as u32),
+            )
+        };
+        let mut index = SymbolIndex::new();
+        index.index_document(uri, &[function("fooBar", 0), function("helper", 2), function("fooBaz", 4)]);
+
+        let unrelated = ["helper", "he", "hel", "foobaz"];
+        let before: Vec<usize> = unrelated.iter().map(|k| index.inverted.count(k, uri)).collect();
+        assert_eq!(index.inverted.count("foo", uri), 2);
+
+        let renamed = IndexedSymbol::from_symbol(&function("quux", 0), uri, None);
+        index.update_symbol(uri, Some("fooBar"), renamed);
+
+        let after: Vec<usize> = unrelated.iter().map(|k| index.inverted.count(k, uri)).collect();
+        assert_eq!(after, before);
+        // Prefixes shared with fooBaz lose only the renamed occurrence
+        assert_eq!(index.inverted.count("foo", uri), 1);
+        assert_eq!(index.inverted.count("foobar", uri), 0);
+        assert_eq!(index.inverted.count("quux", uri), 1);
+        assert_eq!(index.search("fooBaz").len(), 1);
+        assert_eq!(index.search("quux")[0].range.start.line, 0);
+        assert_eq!(index.symbol_count(), 3);
+
+        index.update_symbol(uri, None, IndexedSymbol::from_symbol(&function("extra", 6), uri, None));
+        assert_eq!(index.search("extra").len(), 1);
+        assert_eq!(index.symbol_count(), 4);
+    }
+
+    #[test]
+    fn test_search_iter_take() {
+        let mut index = SymbolIndex::new();
+        for i in 0..20 {
+            let symbol = Symbol::new(
+                format!("render{}", i),
+                SymbolKind::Function,
+                Range::from_coords(0, 0, 1, 0),
+                Range::from_coords(0, 9, 0, 16),
+            );
+            index.index_document(&format!("file:///view{}.ts", i), &[symbol]);
+        }
+
+        let mut matches = index.search_iter("Render");
+        assert_eq!(matches.by_ref().take(3).count(), 3);
+        assert_eq!(matches.count(), 17);
+        assert_eq!(index.search_iter("render1").count(), 11);
+    }
+}
@@ -0,0 +1,357 @@
+//! LSIF export
+//!
+//! Writes the index as an LSIF dump: a JSON-lines stream of vertices and
+//! edges that code hosts load to serve definitions, references and hovers
+//! without running a language server. The index's byte columns are
+//! converted to the UTF-16 columns LSIF expects by reading each file.
+
+use crate::symbol_table::{ProjectIndex, SmartSymbol, SymbolLocation};
+use logos_core::{path_to_uri, uri_to_path, Document, LineIndex, Position, Range};
+use logos_parser::LanguageId;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// LSIF format version written to the `metaData` vertex
+const LSIF_VERSION: &str = "0.4.3";
+
+/// Write `index` as LSIF JSON lines. The dump's `projectRoot` is the
+/// deepest folder holding every indexed file; use
+/// [`export_lsif_with_root`] to name the workspace folder instead.
+///
+/// Each symbol gets a result set with definition, reference and hover
+/// results. Its references are the recorded `SymbolReference`s plus the
+/// call sites that call it. Files that can't be read keep byte columns.
+pub fn export_lsif(index: &ProjectIndex, out: &mut impl Write) -> io::Result<()> {
+    export_lsif_with_root(index, &common_root(&index.symbols.files()), out)
+}
+
+/// [`export_lsif`] for the project whose root folder has the URI
+/// `project_root`
+pub fn export_lsif_with_root(index: &ProjectIndex, project_root: &str, out: &mut impl Write) -> io::Result<()> {
+    let mut emitter = Emitter { out, next_id: 1, documents: BTreeMap::new(), sources: HashMap::new() };
+    emitter.vertex(
+        "metaData",
+        json!({
+            "version": LSIF_VERSION,
+            "projectRoot": project_root,
+            "positionEncoding": "utf-16",
+            "toolInfo": { "name": "logos", "version": env!("CARGO_PKG_VERSION") }
+        }),
+    )?;
+
+    let mut files = index.symbols.files();
+    files.sort();
+    for uri in &files {
+        emitter.document(uri)?;
+    }
+
+    for uri in &files {
+        let mut symbols = index.symbols.get_file_symbols(uri);
+        symbols.sort_by_key(|s| (s.location.selection_range.start, s.id.0));
+        for symbol in &symbols {
+            export_symbol(&mut emitter, index, symbol)?;
+        }
+    }
+
+    let documents = std::mem::take(&mut emitter.documents);
+    for (id, ranges) in documents.into_values() {
+        if !ranges.is_empty() {
+            emitter.edge("contains", json!({ "outV": id, "inVs": ranges }))?;
+        }
+    }
+    Ok(())
+}
+
+/// URI of the deepest folder containing every file in `uris`, or
+/// `file:///` when they share none (or aren't files)
+fn common_root(uris: &[String]) -> String {
+    let mut root: Option<PathBuf> = None;
+    for uri in uris {
+        let Some(folder) = uri_to_path(uri).and_then(|path| path.parent().map(Path::to_path_buf)) else {
+            return "file:///".to_string();
+        };
+        root = Some(match root {
+            None => folder,
+            Some(root) => root
+                .components()
+                .zip(folder.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    match root {
+        Some(root) if root.has_root() => path_to_uri(&root),
+        _ => "file:///".to_string(),
+    }
+}
+
+fn export_symbol(emitter: &mut Emitter<impl Write>, index: &ProjectIndex, symbol: &SmartSymbol) -> io::Result<()> {
+    let definition = emitter.range(&symbol.location.uri, &symbol.location.selection_range)?;
+    let result_set = emitter.vertex("resultSet", json!({}))?;
+    emitter.edge("next", json!({ "outV": definition, "inV": result_set }))?;
+
+    let definition_result = emitter.vertex("definitionResult", json!({}))?;
+    emitter.edge("textDocument/definition", json!({ "outV": result_set, "inV": definition_result }))?;
+    let document = emitter.document(&symbol.location.uri)?;
+    emitter.edge(
+        "item",
+        json!({ "outV": definition_result, "inVs": [definition], "document": document }),
+    )?;
+
+    let hover_result = emitter.vertex("hoverResult", json!({ "result": hover(symbol) }))?;
+    emitter.edge("textDocument/hover", json!({ "outV": result_set, "inV": hover_result }))?;
+
+    let reference_result = emitter.vertex("referenceResult", json!({}))?;
+    emitter.edge("textDocument/references", json!({ "outV": result_set, "inV": reference_result }))?;
+    emitter.edge(
+        "item",
+        json!({
+            "outV": reference_result,
+            "inVs": [definition],
+            "document": document,
+            "property": "definitions"
+        }),
+    )?;
+
+    // Use sites, grouped by document
+    let mut uses: BTreeMap<String, Vec<Range>> = BTreeMap::new();
+    let references = index
+        .symbols
+        .get_references(symbol.id)
+        .into_iter()
+        .filter(|r| !r.is_definition)
+        .map(|r| r.location);
+    let call_sites = index.call_graph.get_callers(symbol.id).into_iter().map(|c| c.location);
+    for SymbolLocation { uri, selection_range, .. } in references.chain(call_sites) {
+        let ranges = uses.entry(uri).or_default();
+        if !ranges.contains(&selection_range) {
+            ranges.push(selection_range);
+        }
+    }
+    for (uri, ranges) in uses {
+        let mut ids = Vec::with_capacity(ranges.len());
+        for range in &ranges {
+            let id = emitter.range(&uri, range)?;
+            emitter.edge("next", json!({ "outV": id, "inV": result_set }))?;
+            ids.push(id);
+        }
+        let document = emitter.document(&uri)?;
+        emitter.edge(
+            "item",
+            json!({
+                "outV": reference_result,
+                "inVs": ids,
+                "document": document,
+                "property": "references"
+            }),
+        )?;
+    }
+    Ok(())
+}
+
+/// Hover contents: the signature as code, then the documentation
+fn hover(symbol: &SmartSymbol) -> Value {
+    let signature = match &symbol.type_info {
+        Some(type_info) => format!("{}: {}", symbol.name, type_info.type_expr),
+        None => format!("({:?}) {}", symbol.kind, symbol.name),
+    };
+    let language = language_of(&symbol.location.uri).unwrap_or("");
+    let mut contents = vec![json!({ "language": language, "value": signature })];
+    if let Some(doc) = symbol.documentation.as_deref().filter(|d| !d.trim().is_empty()) {
+        contents.push(json!(doc));
+    }
+    json!({ "contents": contents })
+}
+
+fn language_of(uri: &str) -> Option<&'static str> {
+    let ext = uri.rsplit('.').next()?;
+    LanguageId::from_extension(ext).map(|l| l.as_str())
+}
+
+/// Writes vertices and edges with sequential ids and tracks each
+/// document's ranges for its `contains` edge
+struct Emitter<'a, W: Write> {
+    out: &'a mut W,
+    next_id: u64,
+    /// Document URI -> (vertex id, range vertex ids)
+    documents: BTreeMap<String, (u64, Vec<u64>)>,
+    /// Document URI -> its text on disk, for UTF-16 columns
+    sources: HashMap<String, Option<(LineIndex, Document)>>,
+}
+
+impl<W: Write> Emitter<'_, W> {
+    fn emit(&mut self, kind: &str, label: &str, fields: Value) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut element = json!({ "id": id, "type": kind, "label": label });
+        if let (Some(element), Value::Object(fields)) = (element.as_object_mut(), fields) {
+            element.extend(fields);
+        }
+        writeln!(self.out, "{}", element)?;
+        Ok(id)
+    }
+
+    fn vertex(&mut self, label: &str, fields: Value) -> io::Result<u64> {
+        self.emit("vertex", label, fields)
+    }
+
+    fn edge(&mut self, label: &str, fields: Value) -> io::Result<u64> {
+        self.emit("edge", label, fields)
+    }
+
+    /// The document vertex for `uri`, emitted on first use
+    fn document(&mut self, uri: &str) -> io::Result<u64> {
+        if let Some((id, _)) = self.documents.get(uri) {
+            return Ok(*id);
+        }
+        let language = language_of(uri).unwrap_or("");
+        let id = self.vertex("document", json!({ "uri": uri, "languageId": language }))?;
+        self.documents.insert(uri.to_string(), (id, Vec::new()));
+        Ok(id)
+    }
+
+    fn range(&mut self, uri: &str, range: &Range) -> io::Result<u64> {
+        self.document(uri)?;
+        let (start, end) = (self.utf16(uri, range.start), self.utf16(uri, range.end));
+        let id = self.vertex(
+            "range",
+            json!({
+                "start": { "line": start.line, "character": start.column },
+                "end": { "line": end.line, "character": end.column }
+            }),
+        )?;
+        if let Some((_, ranges)) = self.documents.get_mut(uri) {
+            ranges.push(id);
+        }
+        Ok(id)
+    }
+
+    /// `position` with its byte column converted to UTF-16 code units
+    fn utf16(&mut self, uri: &str, position: Position) -> Position {
+        let source = self.sources.entry(uri.to_string()).or_insert_with(|| {
+            let text = fs::read_to_string(uri_to_path(uri)?).ok()?;
+            Some((LineIndex::new(&text), Document::new(uri.to_string(), String::new(), text)))
+        });
+        match source {
+            Some((lines, document)) => match lines.offset_of(position) {
+                Some(offset) => document.position_at(offset),
+                None => position,
+            },
+            None => position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::{make_location, SymbolBuilder};
+    use crate::symbol_table::{CallSite, CallType};
+    use logos_core::SymbolKind;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_export_two_symbols() {
+        let index = ProjectIndex::new();
+        let symbol = |name: &str, line: u32| {
+            let range = Range::from_coords(line, 0, line + 2, 1);
+            let selection = Range::from_coords(line, 9, line, 9 + name.len() as u32);
+            SymbolBuilder::new(name, SymbolKind::Function, make_location("file:///app.ts", range, selection))
+                .documentation(format!("The {} function.", name))
+                .build()
+        };
+        let main = index.symbols.add_symbol(symbol("main", 0));
+        let helper = index.symbols.add_symbol(symbol("helper", 4));
+        index.call_graph.add_call(CallSite {
+            caller: main,
+            callee: helper,
+            location: make_location(
+                "file:///app.ts",
+                Range::from_coords(1, 4, 1, 12),
+                Range::from_coords(1, 4, 1, 10),
+            ),
+            call_type: CallType::Direct,
+        });
+
+        let mut out = Vec::new();
+        export_lsif(&index, &mut out).unwrap();
+        let elements: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let labels: HashSet<&str> = elements.iter().map(|e| e["label"].as_str().unwrap()).collect();
+        for label in [
+            "metaData",
+            "document",
+            "range",
+            "resultSet",
+            "definitionResult",
+            "referenceResult",
+            "hoverResult",
+            "contains",
+            "item",
+            "next",
+        ] {
+            assert!(labels.contains(label), "missing {}", label);
+        }
+
+        // Every edge points at vertices emitted before it
+        let mut seen = HashSet::new();
+        for element in &elements {
+            if element["type"] == "edge" {
+                let targets = element["inVs"].as_array().cloned().unwrap_or_else(|| vec![element["inV"].clone()]);
+                assert!(seen.contains(&element["outV"].as_u64().unwrap()));
+                assert!(targets.iter().all(|t| seen.contains(&t.as_u64().unwrap())));
+            }
+            seen.insert(element["id"].as_u64().unwrap());
+        }
+
+        // Two definitions and one call site
+        let ranges = elements.iter().filter(|e| e["label"] == "range").count();
+        assert_eq!(ranges, 3);
+        assert_eq!(elements[0]["projectRoot"], "file:///");
+    }
+
+    #[test]
+    fn test_project_root_defaults_to_the_common_folder() {
+        let files = |uris: &[&str]| uris.iter().map(|u| u.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            common_root(&files(&["file:///repo/src/a.ts", "file:///repo/src/ui/b.ts", "file:///repo/test/c.ts"])),
+            "file:///repo"
+        );
+        assert_eq!(common_root(&files(&["file:///repo/src/a.ts"])), "file:///repo/src");
+        assert_eq!(common_root(&files(&["untitled:Untitled-1"])), "file:///");
+        assert_eq!(common_root(&[]), "file:///");
+    }
+
+    #[test]
+    fn test_columns_are_utf16() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("greet.ts");
+        // `é` is two bytes but one UTF-16 unit, `😀` four bytes and two units
+        fs::write(&path, "const s = 'é😀'; function greet() {}\n").unwrap();
+        let uri = logos_core::path_to_uri(&path);
+
+        let index = ProjectIndex::new();
+        let selection = Range::from_coords(0, 29, 0, 34);
+        let location = make_location(&uri, Range::from_coords(0, 20, 0, 37), selection);
+        index.symbols.add_symbol(SymbolBuilder::new("greet", SymbolKind::Function, location).build());
+
+        let mut out = Vec::new();
+        export_lsif_with_root(&index, &logos_core::path_to_uri(dir.path()), &mut out).unwrap();
+        let range: Value = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .find(|e| e["label"] == "range")
+            .unwrap();
+        assert_eq!(range["start"], json!({ "line": 0, "character": 26 }));
+        assert_eq!(range["end"], json!({ "line": 0, "character": 31 }));
+    }
+}
@@ -4,40 +4,30 @@
 //! Extracts symbols, imports, exports, and call relationships.
 
 use crate::adapter::{
-    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter,
-    SymbolBuilder, TypeRelation, make_location,
+    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool,
+    ReceiverType, SymbolBuilder, TypeRelation, ancestors, make_location,
 };
-use crate::symbol_table::{SymbolId, TypeInfo, Visibility};
+use crate::symbol_table::{Attribute, SymbolId, TypeInfo, Visibility};
 use logos_core::{Position, Range, SymbolKind};
+use std::collections::HashSet;
 use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Tree};
 
 /// Python language adapter
 pub struct PythonAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
 }
 
 impl PythonAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_python::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_python::LANGUAGE.into())
             .map_err(|e| format!("Failed to set Python language: {}", e))?;
 
-        Ok(Self {
-            parser: std::sync::Mutex::new(parser),
-        })
+        Ok(Self { parsers })
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
-    }
-}
-
-impl Default for PythonAdapter {
-    fn default() -> Self {
-        Self::new().expect("Failed to create Python adapter")
+        self.parsers.parse(source)
     }
 }
 
@@ -60,10 +50,15 @@ impl LanguageAdapter for PythonAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
+            declared_exports: None,
         };
+        context.declared_exports = declared_exports(&tree.root_node(), &context);
 
         analyze_node(&tree.root_node(), &mut context);
+        context.depth.report(&mut context.result);
+        mark_constructor_calls(&mut context.result);
 
         context.result
     }
@@ -74,13 +69,12 @@ impl LanguageAdapter for PythonAdapter {
             return None;
         }
 
-        let parent = from_file.parent()?;
-
-        // Handle relative imports
+        // `.` is the importing file's package directory, and each further
+        // dot moves up one package. Walking past the filesystem root fails.
         let levels = import_path.chars().take_while(|&c| c == '.').count();
-        let mut base = parent.to_path_buf();
+        let mut base = from_file.parent()?;
         for _ in 1..levels {
-            base = base.parent()?.to_path_buf();
+            base = base.parent().filter(|p| !p.as_os_str().is_empty())?;
         }
 
         let module_name = import_path.trim_start_matches('.');
@@ -114,7 +108,10 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
+    /// Names listed in the module's `__all__`, if it has one
+    declared_exports: Option<HashSet<String>>,
 }
 
 struct ScopeInfo {
@@ -139,9 +136,66 @@ impl<'a> AnalysisContext<'a> {
     fn get_text(&self, node: &Node) -> String {
         self.source[node.byte_range()].to_string()
     }
+
+    /// Whether a module-level name is exported: listed in `__all__` when
+    /// the module declares one, otherwise not underscore-prefixed
+    fn is_exported(&self, name: &str) -> bool {
+        match &self.declared_exports {
+            Some(names) => names.contains(name),
+            None => !name.starts_with('_'),
+        }
+    }
+}
+
+/// Collect the names from module-level `__all__ = [...]` (and `+=`)
+/// statements. Returns None if the module doesn't assign `__all__`.
+fn declared_exports(root: &Node, ctx: &AnalysisContext) -> Option<HashSet<String>> {
+    let mut names: Option<HashSet<String>> = None;
+
+    for i in 0..root.named_child_count() {
+        let assignment = match root.named_child(i) {
+            Some(statement) if statement.kind() == "expression_statement" => match statement.named_child(0) {
+                Some(n) if matches!(n.kind(), "assignment" | "augmented_assignment") => n,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let is_all = assignment
+            .child_by_field_name("left")
+            .is_some_and(|left| left.kind() == "identifier" && ctx.get_text(&left) == "__all__");
+        let right = match assignment.child_by_field_name("right") {
+            Some(n) if is_all && matches!(n.kind(), "list" | "tuple") => n,
+            _ => continue,
+        };
+
+        let listed = names.get_or_insert_with(HashSet::new);
+        if assignment.kind() == "assignment" {
+            listed.clear();
+        }
+        for j in 0..right.named_child_count() {
+            if let Some(item) = right.named_child(j).filter(|n| n.kind() == "string") {
+                let content: String = (0..item.named_child_count())
+                    .filter_map(|k| item.named_child(k))
+                    .filter(|part| part.kind() == "string_content")
+                    .map(|part| ctx.get_text(&part))
+                    .collect();
+                listed.insert(content);
+            }
+        }
+    }
+
+    names
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         // Import statements
         "import_statement" => analyze_import(node, ctx),
@@ -158,6 +212,10 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
             if ctx.scope_stack.is_empty() {
                 analyze_assignment(node, ctx);
             }
+            // The right-hand side can contain calls, e.g. `user = User()`
+            if let Some(right) = node.child_by_field_name("right") {
+                analyze_node(&right, ctx);
+            }
         }
 
         // Call expressions
@@ -283,46 +341,9 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext) {
         .map(|n| ctx.get_text(&n))
         .unwrap_or_else(|| "anonymous".to_string());
 
-    // Check if it's a private function (starts with _)
-    let visibility = if name.starts_with("__") && !name.ends_with("__") {
-        Visibility::Private
-    } else if name.starts_with('_') {
-        Visibility::Protected
-    } else {
-        Visibility::Public
-    };
-
-    // Check for decorators
-    let mut is_staticmethod = false;
-    let mut is_classmethod = false;
-    let mut is_property = false;
-
-    if let Some(decorators) = node.child_by_field_name("decorator") {
-        let dec_text = ctx.get_text(&decorators);
-        is_staticmethod = dec_text.contains("staticmethod");
-        is_classmethod = dec_text.contains("classmethod");
-        is_property = dec_text.contains("property");
-    }
-
-    // Also check siblings for decorators
-    if let Some(parent) = node.parent() {
-        for i in 0..parent.named_child_count() {
-            if let Some(sibling) = parent.named_child(i) {
-                if sibling.kind() == "decorator" {
-                    let dec_text = ctx.get_text(&sibling);
-                    if dec_text.contains("staticmethod") {
-                        is_staticmethod = true;
-                    }
-                    if dec_text.contains("classmethod") {
-                        is_classmethod = true;
-                    }
-                    if dec_text.contains("property") {
-                        is_property = true;
-                    }
-                }
-            }
-        }
-    }
+    let visibility = function_visibility(&name);
+    let attributes = decorators(node, ctx);
+    let is_property = attributes.iter().any(|a| a.name.contains("property"));
 
     let kind = if is_property {
         SymbolKind::Property
@@ -353,14 +374,23 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext) {
 
     let mut builder = SymbolBuilder::new(name.clone(), kind, location)
         .visibility(visibility)
-        .qualified_name(ctx.qualified_name(&name));
+        .qualified_name(ctx.qualified_name(&name))
+        .attributes(attributes);
+
+    if let Some(scope) = ctx.current_scope() {
+        builder = builder.parent(scope.symbol_id);
+    }
 
     if let Some(ti) = type_info {
         builder = builder.type_info(ti);
     }
 
+    if let Some(doc) = docstring(node, ctx) {
+        builder = builder.documentation(doc);
+    }
+
     // Module-level functions are exported by default
-    if ctx.scope_stack.is_empty() && !name.starts_with('_') {
+    if ctx.scope_stack.is_empty() && ctx.is_exported(&name) {
         builder = builder.exported(true);
         ctx.result.exports.push(ExportInfo {
             name: name.clone(),
@@ -387,6 +417,94 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// Decorators wrapping a function or class definition, in source order.
+/// `@app.route("/users", methods=["GET"])` becomes `app.route` with its
+/// two arguments as written.
+fn decorators(node: &Node, ctx: &AnalysisContext) -> Vec<Attribute> {
+    let parent = match node.parent() {
+        Some(parent) if parent.kind() == "decorated_definition" => parent,
+        _ => return Vec::new(),
+    };
+    let mut cursor = parent.walk();
+    parent
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "decorator")
+        .filter_map(|decorator| decorator.named_child(0))
+        .map(|expr| match (expr.kind(), expr.child_by_field_name("function")) {
+            ("call", Some(function)) => {
+                let arguments = expr
+                    .child_by_field_name("arguments")
+                    .map(|args| {
+                        let mut cursor = args.walk();
+                        args.named_children(&mut cursor)
+                            .filter(|arg| arg.kind() != "comment")
+                            .map(|arg| ctx.get_text(&arg))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Attribute {
+                    name: ctx.get_text(&function),
+                    arguments,
+                }
+            }
+            _ => Attribute {
+                name: ctx.get_text(&expr),
+                arguments: Vec::new(),
+            },
+        })
+        .collect()
+}
+
+/// Visibility implied by Python naming conventions: dunder methods such
+/// as `__init__` are public, name-mangled `__private` names are private,
+/// and a single leading underscore marks an internal (protected) name
+fn function_visibility(name: &str) -> Visibility {
+    let is_dunder = name.len() > 4 && name.starts_with("__") && name.ends_with("__");
+    if is_dunder {
+        Visibility::Public
+    } else if name.starts_with("__") {
+        Visibility::Private
+    } else if name.starts_with('_') {
+        Visibility::Protected
+    } else {
+        Visibility::Public
+    }
+}
+
+/// The docstring of a function or class: a string literal as the first
+/// statement of its body, unquoted and dedented
+fn docstring(node: &Node, ctx: &AnalysisContext) -> Option<String> {
+    let first = node.child_by_field_name("body")?.named_child(0)?;
+    if first.kind() != "expression_statement" {
+        return None;
+    }
+    let literal = first.named_child(0).filter(|n| n.kind() == "string")?;
+    let text = ctx.get_text(&literal);
+
+    let text = text.trim_start_matches(|c: char| "rRuUbB".contains(c));
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|q| text.starts_with(q) && text.len() >= 2 * q.len())?;
+    let body = &text[quote.len()..text.len() - quote.len()];
+
+    // Like inspect.cleandoc: the first line is taken as-is, later lines
+    // lose their common indentation
+    let mut lines = body.lines();
+    let first_line = lines.next().unwrap_or("").trim();
+    let rest: Vec<&str> = lines.collect();
+    let indent = rest
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut doc = vec![first_line.to_string()];
+    doc.extend(rest.iter().map(|l| l.get(indent..).unwrap_or("").trim_end().to_string()));
+
+    let doc = doc.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
 fn analyze_class(node: &Node, ctx: &mut AnalysisContext) {
     let name_node = node.child_by_field_name("name");
     let name = name_node
@@ -408,10 +526,15 @@ fn analyze_class(node: &Node, ctx: &mut AnalysisContext) {
 
     let mut builder = SymbolBuilder::new(name.clone(), SymbolKind::Class, location)
         .visibility(visibility)
-        .qualified_name(ctx.qualified_name(&name));
+        .qualified_name(ctx.qualified_name(&name))
+        .attributes(decorators(node, ctx));
+
+    if let Some(doc) = docstring(node, ctx) {
+        builder = builder.documentation(doc);
+    }
 
     // Module-level classes are exported by default
-    if ctx.scope_stack.is_empty() && !name.starts_with('_') {
+    if ctx.scope_stack.is_empty() && ctx.is_exported(&name) {
         builder = builder.exported(true);
         ctx.result.exports.push(ExportInfo {
             name: name.clone(),
@@ -495,7 +618,7 @@ fn analyze_assignment(node: &Node, ctx: &mut AnalysisContext) {
                 .qualified_name(ctx.qualified_name(&name));
 
             // Module-level public variables are exported
-            if !name.starts_with('_') {
+            if ctx.is_exported(&name) {
                 builder = builder.exported(true);
                 ctx.result.exports.push(ExportInfo {
                     name: name.clone(),
@@ -538,28 +661,35 @@ fn analyze_assignment(node: &Node, ctx: &mut AnalysisContext) {
 
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     if let Some(function) = node.child_by_field_name("function") {
-        let (callee_name, qualified_name) = match function.kind() {
+        let (callee_name, qualified_name, receiver_type) = match function.kind() {
             "identifier" => {
                 let name = ctx.get_text(&function);
-                (name.clone(), None)
+                (name.clone(), None, None)
             }
             "attribute" => {
-                if let Some(attr) = function.child_by_field_name("attribute") {
+                if let (Some(object), Some(attr)) = (
+                    function.child_by_field_name("object"),
+                    function.child_by_field_name("attribute"),
+                ) {
                     let prop_name = ctx.get_text(&attr);
                     let full_name = ctx.get_text(&function);
-                    (prop_name, Some(full_name))
+                    (prop_name, Some(full_name), receiver_type(&object, ctx))
                 } else {
                     return;
                 }
             }
             _ => return,
         };
+        let callee_is_parameter =
+            qualified_name.is_none() && enclosing_parameter(node, &callee_name, ctx).is_some();
 
         ctx.result.calls.push(CallInfo {
             callee_name,
             qualified_name,
             location: node_to_range(node),
             is_constructor: false,
+            receiver_type,
+            callee_is_parameter,
         });
     }
 
@@ -569,6 +699,74 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// The declared type of a call receiver: the enclosing class for `self`
+/// and `cls`, otherwise the annotation of the parameter it names
+fn receiver_type(object: &Node, ctx: &AnalysisContext) -> Option<ReceiverType> {
+    if object.kind() != "identifier" {
+        return None;
+    }
+    let name = ctx.get_text(object);
+    if name == "self" || name == "cls" {
+        let class = ancestors(object).find(|n| n.kind() == "class_definition")?;
+        let class_name = class.child_by_field_name("name")?;
+        return Some(ReceiverType::Named(ctx.get_text(&class_name)));
+    }
+    let annotation = enclosing_parameter(object, &name, ctx)?.child_by_field_name("type")?;
+    named_type(&annotation, ctx).map(ReceiverType::Named)
+}
+
+/// The parameter called `name` of the innermost function (or lambda)
+/// enclosing `node` that declares one
+fn enclosing_parameter<'a>(node: &Node<'a>, name: &str, ctx: &AnalysisContext) -> Option<Node<'a>> {
+    ancestors(node)
+        .filter(|n| matches!(n.kind(), "function_definition" | "lambda"))
+        .find_map(|function| {
+            let params = function.child_by_field_name("parameters")?;
+            let mut cursor = params.walk();
+            let found = params.named_children(&mut cursor).find(|param| {
+                let name_node = match param.kind() {
+                    "identifier" => Some(*param),
+                    "default_parameter" | "typed_default_parameter" => param.child_by_field_name("name"),
+                    "typed_parameter" => param.named_child(0).filter(|n| n.kind() == "identifier"),
+                    _ => None,
+                };
+                name_node.is_some_and(|n| ctx.get_text(&n) == name)
+            });
+            found
+        })
+}
+
+/// The bare name of an annotation such as `Shape`, `models.Shape` or
+/// `list[Shape]`'s `list`; `None` for other shapes
+fn named_type(node: &Node, ctx: &AnalysisContext) -> Option<String> {
+    match node.kind() {
+        "type" => node.named_child(0).and_then(|t| named_type(&t, ctx)),
+        "identifier" => Some(ctx.get_text(node)),
+        "attribute" => node.child_by_field_name("attribute").map(|n| ctx.get_text(&n)),
+        "generic_type" | "subscript" => node
+            .named_child(0)
+            .or_else(|| node.child_by_field_name("value"))
+            .and_then(|n| named_type(&n, ctx)),
+        _ => None,
+    }
+}
+
+/// Calling a class constructs it. Only classes defined in this file are
+/// known here, and a class may be defined after its first use.
+fn mark_constructor_calls(result: &mut AnalysisResult) {
+    let classes: HashSet<&str> = result
+        .symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Class)
+        .map(|s| s.name.as_str())
+        .collect();
+    for call in &mut result.calls {
+        if call.qualified_name.is_none() && classes.contains(call.callee_name.as_str()) {
+            call.is_constructor = true;
+        }
+    }
+}
+
 fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
     let end = node.end_position();
@@ -603,6 +801,45 @@ def greet(name: str) -> str:
         assert!(result.symbols[0].exported);
     }
 
+    #[test]
+    fn test_async_and_decorated_definitions() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = r#"
+async def fetch(url: str) -> bytes:
+    return await get(url)
+
+@dataclass(frozen=True)
+class Client:
+    @retry(3, backoff=2.0)
+    async def send(self, payload):
+        return await fetch(payload)
+
+    @property
+    def host(self):
+        return "localhost"
+"#;
+        let result = adapter.analyze("file:///client.py", source);
+        let symbol = |name: &str| result.symbols.iter().find(|s| s.name == name).unwrap();
+
+        assert_eq!(symbol("fetch").kind, SymbolKind::Function);
+        assert!(symbol("fetch").exported);
+        assert!(symbol("fetch").attributes.is_empty());
+
+        let client = symbol("Client");
+        assert_eq!(client.attributes[0].name, "dataclass");
+        assert_eq!(client.attributes[0].arguments, vec!["frozen=True"]);
+
+        let send = symbol("send");
+        assert_eq!(send.kind, SymbolKind::Method);
+        assert_eq!(send.parent, Some(client.id));
+        assert_eq!(send.attributes[0].name, "retry");
+        assert_eq!(send.attributes[0].arguments, vec!["3", "backoff=2.0"]);
+
+        assert_eq!(symbol("host").kind, SymbolKind::Property);
+        assert_eq!(symbol("host").attributes[0].name, "property");
+        assert_eq!(result.calls.iter().filter(|c| c.callee_name == "fetch").count(), 1);
+    }
+
     #[test]
     fn test_class_with_methods() {
         let adapter = PythonAdapter::new().unwrap();
@@ -630,6 +867,80 @@ class User:
         assert_eq!(private_sym.visibility, Visibility::Protected);
     }
 
+    #[test]
+    fn test_method_visibility_by_name_shape() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = r#"
+class Account:
+    def __init__(self):
+        pass
+
+    def __str__(self):
+        return "account"
+
+    def __secret(self):
+        pass
+
+    def _helper(self):
+        pass
+"#;
+        let result = adapter.analyze("file:///account.py", source);
+        let visibility = |name: &str| {
+            result.symbols.iter().find(|s| s.name == name).unwrap().visibility
+        };
+
+        assert_eq!(visibility("__init__"), Visibility::Public);
+        assert_eq!(visibility("__str__"), Visibility::Public);
+        assert_eq!(visibility("__secret"), Visibility::Private);
+        assert_eq!(visibility("_helper"), Visibility::Protected);
+        assert_eq!(function_visibility("__"), Visibility::Private);
+    }
+
+    #[test]
+    fn test_constructor_calls() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = r#"
+def build():
+    user = User("ada")
+    return format_name(user)
+
+class User:
+    pass
+"#;
+        let result = adapter.analyze("file:///test.py", source);
+        let user = result.calls.iter().find(|c| c.callee_name == "User").unwrap();
+        assert!(user.is_constructor);
+        let format_name = result.calls.iter().find(|c| c.callee_name == "format_name").unwrap();
+        assert!(!format_name.is_constructor);
+    }
+
+    #[test]
+    fn test_docstrings() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = r#"
+class Greeter:
+    """Says hello."""
+
+    def greet(self, name):
+        """
+        Greet someone.
+
+            name: who to greet
+        """
+        return name
+
+def bare():
+    return 1
+"#;
+        let result = adapter.analyze("file:///test.py", source);
+        let doc = |name: &str| {
+            result.symbols.iter().find(|s| s.name == name).unwrap().documentation.clone()
+        };
+        assert_eq!(doc("Greeter").as_deref(), Some("Says hello."));
+        assert_eq!(doc("greet").as_deref(), Some("Greet someone.\n\n    name: who to greet"));
+        assert_eq!(doc("bare"), None);
+    }
+
     #[test]
     fn test_imports() {
         let adapter = PythonAdapter::new().unwrap();
@@ -695,4 +1006,69 @@ _private_var = 42
         let private_var = result.symbols.iter().find(|s| s.name == "_private_var").unwrap();
         assert_eq!(private_var.visibility, Visibility::Private);
     }
+
+    #[test]
+    fn test_dunder_all_overrides_underscore_heuristic() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = r#"
+__all__ = ["_internal_but_exported", "Public"]
+
+def _internal_but_exported():
+    pass
+
+def helper():
+    pass
+
+class Public:
+    pass
+"#;
+        let result = adapter.analyze("file:///test.py", source);
+
+        let exported = |name: &str| result.symbols.iter().find(|s| s.name == name).unwrap().exported;
+        assert!(exported("_internal_but_exported"));
+        assert!(exported("Public"));
+        assert!(!exported("helper"));
+        assert!(!exported("__all__"));
+
+        let mut export_names: Vec<_> = result.exports.iter().map(|e| e.name.as_str()).collect();
+        export_names.sort();
+        assert_eq!(export_names, vec!["Public", "_internal_but_exported"]);
+    }
+
+    #[test]
+    fn test_resolve_relative_import_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let pkg = root.join("pkg");
+        let sub = pkg.join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        for file in [
+            pkg.join("__init__.py"),
+            pkg.join("mod.py"),
+            sub.join("__init__.py"),
+            sub.join("sibling.py"),
+            sub.join("main.py"),
+        ] {
+            std::fs::write(file, "").unwrap();
+        }
+
+        let adapter = PythonAdapter::new().unwrap();
+        let main = sub.join("main.py");
+
+        // One dot: the current package
+        assert_eq!(adapter.resolve_import(&main, "."), Some(sub.join("__init__.py")));
+        assert_eq!(adapter.resolve_import(&main, ".sibling"), Some(sub.join("sibling.py")));
+        // Two dots: the parent package
+        assert_eq!(adapter.resolve_import(&main, ".."), Some(pkg.join("__init__.py")));
+        assert_eq!(adapter.resolve_import(&main, "..mod"), Some(pkg.join("mod.py")));
+        // Dotted module under an ancestor
+        assert_eq!(adapter.resolve_import(&main, "...pkg.mod"), Some(pkg.join("mod.py")));
+        assert_eq!(adapter.resolve_import(&main, "..sub.sibling"), Some(sub.join("sibling.py")));
+        assert_eq!(adapter.resolve_import(&main, "..missing"), None);
+
+        // Never walks above the filesystem root
+        let dots = ".".repeat(main.components().count() + 2);
+        assert_eq!(adapter.resolve_import(&main, &dots), None);
+        assert_eq!(adapter.resolve_import(Path::new("top.py"), "..x"), None);
+    }
 }
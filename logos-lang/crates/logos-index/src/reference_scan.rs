@@ -0,0 +1,91 @@
+//! Same-name reference scanning for documents without index data
+//!
+//! Finds the identifiers in a file that spell a given name. There is no
+//! binding resolution, so shadowed locals and unrelated symbols of the
+//! same name are included; strings, comments and members of other
+//! objects are not.
+
+use logos_core::Range;
+use logos_parser::{node_to_range, LanguageId, LanguageParser};
+use tree_sitter::Node;
+
+/// Ranges of the identifiers named `name` in `source`.
+///
+/// `name` after a member access (`other.name`) is skipped unless the
+/// object is `this`/`self`, since it refers to some other object's member.
+pub fn scan_references(source: &str, name: &str, language: LanguageId) -> Vec<Range> {
+    let mut parser = LanguageParser::new();
+    if parser.set_language(language).is_err() {
+        return Vec::new();
+    }
+    let Ok(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+
+    let mut ranges = Vec::new();
+    collect(tree.root_node(), source, name, &mut ranges);
+    ranges
+}
+
+fn collect(node: Node, source: &str, name: &str, out: &mut Vec<Range>) {
+    if node.child_count() == 0 {
+        if node.kind().ends_with("identifier")
+            && &source[node.byte_range()] == name
+            && !is_foreign_member(&node, source)
+        {
+            out.push(node_to_range(&node));
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect(child, source, name, out);
+    }
+}
+
+/// Whether `node` is the member side of an access on an object other
+/// than `this`/`self`
+fn is_foreign_member(node: &Node, source: &str) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    let (member_field, object_field) = match parent.kind() {
+        "member_expression" => ("property", "object"),
+        "attribute" => ("attribute", "object"),
+        "field_expression" => ("field", "value"),
+        "selector_expression" => ("field", "operand"),
+        "field_access" => ("field", "object"),
+        "method_invocation" => ("name", "object"),
+        _ => return false,
+    };
+    if parent.child_by_field_name(member_field).map(|m| m.id()) != Some(node.id()) {
+        return false;
+    }
+    match parent.child_by_field_name(object_field) {
+        Some(object) => !matches!(&source[object.byte_range()], "this" | "self" | "Self"),
+        // Java: `name()` without a receiver
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips_strings_and_comments() {
+        let source = "count = 0\ncount += 1\nprint(\"count\", count)  # count\n";
+        let ranges = scan_references(source, "count", LanguageId::Python);
+        let starts: Vec<_> = ranges.iter().map(|r| (r.start.line, r.start.column)).collect();
+        assert_eq!(starts, vec![(0, 0), (1, 0), (2, 15)]);
+    }
+
+    #[test]
+    fn test_member_access_on_other_objects() {
+        let source = "const size = 1;\nthis.size = size;\nother.size = 2;\n";
+        let ranges = scan_references(source, "size", LanguageId::TypeScript);
+        let starts: Vec<_> = ranges.iter().map(|r| (r.start.line, r.start.column)).collect();
+        assert_eq!(starts, vec![(0, 6), (1, 5), (1, 12)]);
+    }
+}
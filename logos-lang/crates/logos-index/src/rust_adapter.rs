@@ -1,35 +1,33 @@
 //! Rust Language Adapter
 //!
 //! Pragmatic indexer for Rust:
-//! - Symbols: fn/struct/enum/trait/type/mod/const/static
+//! - Symbols: fn/struct/enum/trait/type/mod/const/static, with outer attributes;
+//!   struct fields and enum variants as children
 //! - Imports: use declarations (best-effort string extraction)
 //! - Exports: inferred from `pub` visibility (best-effort)
 //! - Calls: call_expression (best-effort)
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
-use crate::symbol_table::{SymbolId, Visibility};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool, ReceiverType, SymbolBuilder, ancestors, make_location};
+use crate::comments::attach_leading_docs;
+use crate::symbol_table::{Attribute, SymbolId, TypeInfo, Visibility};
 use logos_core::{Position, Range, SymbolKind};
+use logos_parser::LanguageId;
 use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Tree};
 
 pub struct RustAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
 }
 
 impl RustAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_rust::LANGUAGE.into())
+        let parsers = ParserPool::new(tree_sitter_rust::LANGUAGE.into())
             .map_err(|e| format!("Failed to set Rust language: {}", e))?;
-        Ok(Self {
-            parser: std::sync::Mutex::new(parser),
-        })
+        Ok(Self { parsers })
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
+        self.parsers.parse(source)
     }
 }
 
@@ -52,10 +50,13 @@ impl LanguageAdapter for RustAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
+        ctx.depth.report(&mut ctx.result);
+        attach_leading_docs(&mut ctx.result.symbols, source, LanguageId::Rust);
         ctx.result
     }
 
@@ -70,6 +71,7 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
 }
 
@@ -110,6 +112,14 @@ fn has_pub_modifier(node: &Node, ctx: &AnalysisContext) -> bool {
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         "use_declaration" => analyze_use(node, ctx),
 
@@ -123,6 +133,7 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
         "static_item" => analyze_static(node, ctx),
 
         "call_expression" => analyze_call(node, ctx),
+        "struct_expression" => analyze_struct_expression(node, ctx),
 
         _ => {
             for i in 0..node.named_child_count() {
@@ -167,12 +178,84 @@ fn push_symbol(ctx: &mut AnalysisContext, name: String, kind: SymbolKind, node:
         .exported(exported)
         .visibility(visibility)
         .qualified_name(ctx.qualified_name(&name))
+        .attributes(outer_attributes(node, ctx))
         .build();
     let id = sym.id;
     ctx.result.symbols.push(sym);
     id
 }
 
+/// Outer attributes (`#[...]`) directly preceding an item, in source order
+fn outer_attributes(node: &Node, ctx: &AnalysisContext) -> Vec<Attribute> {
+    let mut attributes = Vec::new();
+    let mut sibling = node.prev_sibling();
+    while let Some(prev) = sibling {
+        match prev.kind() {
+            "attribute_item" => {
+                if let Some(attribute) = parse_attribute(&ctx.get_text(&prev)) {
+                    attributes.push(attribute);
+                }
+            }
+            // Doc comments may be interleaved with attributes
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = prev.prev_sibling();
+    }
+    attributes.reverse();
+    attributes
+}
+
+/// Parse `#[name(arg, ...)]` or `#[name = value]` into an `Attribute`
+fn parse_attribute(text: &str) -> Option<Attribute> {
+    let inner = text.trim().strip_prefix("#[")?.strip_suffix(']')?.trim();
+    let name_end = inner
+        .find(|c: char| c == '(' || c == '=' || c.is_whitespace())
+        .unwrap_or(inner.len());
+    let name = inner[..name_end].to_string();
+    let rest = inner[name_end..].trim();
+
+    let arguments = if let Some(args) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+        split_top_level(args)
+    } else if let Some(value) = rest.strip_prefix('=') {
+        vec![value.trim().to_string()]
+    } else {
+        Vec::new()
+    };
+
+    Some(Attribute { name, arguments })
+}
+
+/// Split on commas that aren't nested in brackets or strings
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut prev = ' ';
+    for c in text.chars() {
+        match c {
+            '"' if prev != '\\' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                prev = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        prev = c;
+    }
+    parts.push(current);
+    parts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
 fn analyze_fn(node: &Node, ctx: &mut AnalysisContext) {
     let name_node = match node.child_by_field_name("name") {
         Some(n) => n,
@@ -195,7 +278,35 @@ fn analyze_struct(node: &Node, ctx: &mut AnalysisContext) {
     };
     let name = ctx.get_text(&name_node);
     let exported = has_pub_modifier(node, ctx);
-    let _ = push_symbol(ctx, name, SymbolKind::Struct, node, &name_node, exported);
+    let id = push_symbol(ctx, name.clone(), SymbolKind::Struct, node, &name_node, exported);
+
+    // Tuple and unit structs have no named fields
+    let body = match node.child_by_field_name("body") {
+        Some(b) if b.kind() == "field_declaration_list" => b,
+        _ => return,
+    };
+    ctx.scope_stack.push(ScopeInfo { symbol_id: id, name });
+    for i in 0..body.named_child_count() {
+        if let Some(field) = body.named_child(i) {
+            if field.kind() == "field_declaration" {
+                analyze_field(&field, ctx);
+            }
+        }
+    }
+    ctx.scope_stack.pop();
+}
+
+fn analyze_field(node: &Node, ctx: &mut AnalysisContext) {
+    let name_node = match node.child_by_field_name("name") {
+        Some(n) => n,
+        None => return,
+    };
+    let name = ctx.get_text(&name_node);
+    let exported = has_pub_modifier(node, ctx);
+    let _ = push_symbol(ctx, name, SymbolKind::Field, node, &name_node, exported);
+    if let (Some(type_node), Some(field)) = (node.child_by_field_name("type"), ctx.result.symbols.last_mut()) {
+        field.type_info = Some(TypeInfo::simple(&ctx.source[type_node.byte_range()]));
+    }
 }
 
 fn analyze_enum(node: &Node, ctx: &mut AnalysisContext) {
@@ -205,7 +316,24 @@ fn analyze_enum(node: &Node, ctx: &mut AnalysisContext) {
     };
     let name = ctx.get_text(&name_node);
     let exported = has_pub_modifier(node, ctx);
-    let _ = push_symbol(ctx, name, SymbolKind::Enum, node, &name_node, exported);
+    let id = push_symbol(ctx, name.clone(), SymbolKind::Enum, node, &name_node, exported);
+
+    // Variants share the enum's visibility
+    if let Some(body) = node.child_by_field_name("body") {
+        ctx.scope_stack.push(ScopeInfo { symbol_id: id, name });
+        for i in 0..body.named_child_count() {
+            if let Some(variant) = body.named_child(i) {
+                if variant.kind() != "enum_variant" {
+                    continue;
+                }
+                if let Some(variant_name) = variant.child_by_field_name("name") {
+                    let name = ctx.get_text(&variant_name);
+                    let _ = push_symbol(ctx, name, SymbolKind::EnumMember, &variant, &variant_name, exported);
+                }
+            }
+        }
+        ctx.scope_stack.pop();
+    }
 }
 
 fn analyze_trait(node: &Node, ctx: &mut AnalysisContext) {
@@ -227,6 +355,12 @@ fn analyze_type_alias(node: &Node, ctx: &mut AnalysisContext) {
     let exported = has_pub_modifier(node, ctx);
     // logos-core 没有 TypeAlias：这里用 Class 表示 type alias
     let _ = push_symbol(ctx, name, SymbolKind::Class, node, &name_node, exported);
+    if let Some(target) = node.child_by_field_name("type") {
+        let target = ctx.get_text(&target);
+        if let Some(symbol) = ctx.result.symbols.last_mut() {
+            symbol.alias_target = Some(target);
+        }
+    }
 }
 
 fn analyze_mod(node: &Node, ctx: &mut AnalysisContext) {
@@ -271,7 +405,17 @@ fn analyze_static(node: &Node, ctx: &mut AnalysisContext) {
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     if let Some(function) = node.child_by_field_name("function") {
         let text = ctx.get_text(&function);
-        let callee_name = text.split("::").last().unwrap_or(&text).split('.').last().unwrap_or(&text).to_string();
+        let callee_name = text.rsplit("::").next().unwrap_or(&text).split('.').next_back().unwrap_or(&text).to_string();
+        // `Type::new(..)` is the conventional constructor
+        let is_constructor = function.kind() == "scoped_identifier"
+            && function.child_by_field_name("path").is_some()
+            && callee_name == "new";
+        let receiver_type = (function.kind() == "field_expression")
+            .then(|| function.child_by_field_name("value"))
+            .flatten()
+            .and_then(|value| receiver_type(&value, ctx));
+        let callee_is_parameter = function.kind() == "identifier"
+            && enclosing_parameter(node, &callee_name, ctx).is_some();
         let qualified = if text.contains("::") || text.contains('.') {
             Some(text)
         } else {
@@ -281,9 +425,120 @@ fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
             callee_name,
             qualified_name: qualified,
             location: node_to_range(node),
-            is_constructor: false,
+            is_constructor,
+            receiver_type,
+            callee_is_parameter,
+        });
+    }
+
+    // Recurse into arguments for nested calls
+    if let Some(args) = node.child_by_field_name("arguments") {
+        analyze_node(&args, ctx);
+    }
+}
+
+/// A struct literal `Point { x, y }` constructs `Point` without a call
+fn analyze_struct_expression(node: &Node, ctx: &mut AnalysisContext) {
+    if let Some(name_node) = node.child_by_field_name("name") {
+        let text = ctx.get_text(&name_node);
+        let callee_name = text.rsplit("::").next().unwrap_or(&text).to_string();
+        ctx.result.calls.push(CallInfo {
+            callee_name,
+            qualified_name: text.contains("::").then_some(text),
+            location: node_to_range(node),
+            is_constructor: true,
+            receiver_type: None,
+            callee_is_parameter: false,
         });
     }
+
+    if let Some(body) = node.child_by_field_name("body") {
+        analyze_node(&body, ctx);
+    }
+}
+
+/// The declared type of a method call receiver: the `impl`'s self type
+/// (or the trait) for `self`, otherwise the type of the parameter or
+/// `let` binding it names
+fn receiver_type(value: &Node, ctx: &AnalysisContext) -> Option<ReceiverType> {
+    match value.kind() {
+        "self" => {
+            let owner = ancestors(value).find(|n| matches!(n.kind(), "impl_item" | "trait_item"))?;
+            let ty = owner
+                .child_by_field_name("type")
+                .or_else(|| owner.child_by_field_name("name"))?;
+            rust_type(&ty, ctx)
+        }
+        "identifier" => {
+            let name = ctx.get_text(value);
+            let declared = enclosing_parameter(value, &name, ctx)
+                .or_else(|| local_binding(value, &name, ctx))?
+                .child_by_field_name("type")?;
+            rust_type(&declared, ctx)
+        }
+        _ => None,
+    }
+}
+
+/// The receiver type a type expression dispatches on: references and smart
+/// pointers are looked through, and `dyn Trait` is a trait object. `impl
+/// Trait` and generics are resolved statically, so give `None`.
+fn rust_type(node: &Node, ctx: &AnalysisContext) -> Option<ReceiverType> {
+    match node.kind() {
+        "type_identifier" => Some(ReceiverType::Named(ctx.get_text(node))),
+        "scoped_type_identifier" => node
+            .child_by_field_name("name")
+            .map(|n| ReceiverType::Named(ctx.get_text(&n))),
+        "reference_type" | "pointer_type" => node.child_by_field_name("type").and_then(|t| rust_type(&t, ctx)),
+        "dynamic_type" => match rust_type(&node.child_by_field_name("trait")?, ctx)? {
+            ReceiverType::Named(name) | ReceiverType::TraitObject(name) => Some(ReceiverType::TraitObject(name)),
+        },
+        "generic_type" => {
+            let base = rust_type(&node.child_by_field_name("type")?, ctx)?;
+            match &base {
+                ReceiverType::Named(name) if matches!(name.as_str(), "Box" | "Rc" | "Arc") => {
+                    let args = node.child_by_field_name("type_arguments")?;
+                    rust_type(&args.named_child(0)?, ctx)
+                }
+                _ => Some(base),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The parameter called `name` of the innermost function or closure
+/// enclosing `node` that declares one
+fn enclosing_parameter<'a>(node: &Node<'a>, name: &str, ctx: &AnalysisContext) -> Option<Node<'a>> {
+    ancestors(node)
+        .filter(|n| matches!(n.kind(), "function_item" | "closure_expression"))
+        .find_map(|function| {
+            let params = function.child_by_field_name("parameters")?;
+            let mut cursor = params.walk();
+            let found = params.named_children(&mut cursor).find(|param| {
+                let pattern = match param.kind() {
+                    "identifier" => Some(*param),
+                    "parameter" => param.child_by_field_name("pattern"),
+                    _ => None,
+                };
+                pattern.is_some_and(|p| ctx.get_text(&p) == name)
+            });
+            found
+        })
+}
+
+/// The `let` binding of `name` in a block enclosing `node`
+fn local_binding<'a>(node: &Node<'a>, name: &str, ctx: &AnalysisContext) -> Option<Node<'a>> {
+    ancestors(node).filter(|n| n.kind() == "block").find_map(|block| {
+        let mut cursor = block.walk();
+        let found = block.named_children(&mut cursor).find(|statement| {
+            statement.kind() == "let_declaration"
+                && statement
+                    .child_by_field_name("pattern")
+                    .is_some_and(|p| ctx.get_text(&p) == name)
+        });
+        found
+    })
 }
 
 fn node_to_range(node: &Node) -> Range {
@@ -326,10 +581,125 @@ fn helper() {}
 pub const MAX: usize = 10;
 "#;
         let result = adapter.analyze("file:///test.rs", src);
-        assert!(result.imports.len() >= 1);
+        assert!(!result.imports.is_empty());
         assert!(result.symbols.iter().any(|s| s.name == "User" && s.exported));
         assert!(result.symbols.iter().any(|s| s.name == "helper"));
-        assert!(result.calls.len() >= 1);
+        assert!(!result.calls.is_empty());
     }
+
+    #[test]
+    fn rust_struct_fields_are_children() {
+        let adapter = RustAdapter::new().unwrap();
+        let src = r#"
+pub struct User {
+    pub name: String,
+    age: Option<u32>,
 }
 
+pub struct Meters(pub f64);
+"#;
+        let result = adapter.analyze("file:///user.rs", src);
+        let user = result.symbols.iter().find(|s| s.name == "User").unwrap();
+        let fields: Vec<_> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Field)
+            .collect();
+
+        assert_eq!(fields.len(), 2);
+        assert!(fields.iter().all(|f| f.parent == Some(user.id)));
+        assert_eq!(fields[0].name, "name");
+        assert_eq!(fields[0].qualified_name, "User::name");
+        assert_eq!(fields[0].type_info.as_ref().unwrap().type_expr, "String");
+        assert!(fields[0].exported);
+        assert_eq!(fields[1].type_info.as_ref().unwrap().type_expr, "Option<u32>");
+        assert_eq!(fields[1].visibility, Visibility::Private);
+
+        assert!(result.symbols.iter().any(|s| s.name == "Meters" && s.kind == SymbolKind::Struct));
+    }
+
+    #[test]
+    fn rust_enum_variants_are_children() {
+        let adapter = RustAdapter::new().unwrap();
+        let src = r#"
+pub enum Shape {
+    Circle(f64),
+    #[default]
+    Point,
+    Rect { w: f64, h: f64 },
+}
+"#;
+        let result = adapter.analyze("file:///shapes.rs", src);
+        let shape = result.symbols.iter().find(|s| s.name == "Shape").unwrap();
+        let variants: Vec<_> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::EnumMember)
+            .collect();
+
+        let names: Vec<_> = variants.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Circle", "Point", "Rect"]);
+        assert!(variants.iter().all(|s| s.parent == Some(shape.id) && s.exported));
+        assert_eq!(variants[0].qualified_name, "Shape::Circle");
+        assert_eq!(variants[1].attributes[0].name, "default");
+    }
+
+    #[test]
+    fn rust_outer_attributes() {
+        let adapter = RustAdapter::new().unwrap();
+        let src = r#"
+/// A point
+#[derive(Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Point {
+    x: i32,
+}
+
+#[cfg(test)]
+fn only_in_tests() {}
+
+#[inline]
+pub fn fast() {}
+"#;
+        let result = adapter.analyze("file:///attrs.rs", src);
+        let point = result.symbols.iter().find(|s| s.name == "Point").unwrap();
+        assert_eq!(point.attributes.len(), 2);
+        assert_eq!(point.attributes[0].name, "derive");
+        assert_eq!(point.attributes[0].arguments, vec!["Debug", "Clone"]);
+        assert_eq!(point.attributes[1].name, "serde");
+        assert_eq!(
+            point.attributes[1].arguments,
+            vec!["rename_all = \"camelCase\"", "default"]
+        );
+
+        let tests_only = result.symbols.iter().find(|s| s.name == "only_in_tests").unwrap();
+        assert_eq!(tests_only.attributes[0].name, "cfg");
+        assert_eq!(tests_only.attributes[0].arguments, vec!["test"]);
+
+        let fast = result.symbols.iter().find(|s| s.name == "fast").unwrap();
+        assert_eq!(fast.attributes.len(), 1);
+        assert!(fast.attributes[0].arguments.is_empty());
+    }
+
+    #[test]
+    fn rust_constructor_calls() {
+        let adapter = RustAdapter::new().unwrap();
+        let src = r#"
+struct Point { x: i32 }
+
+fn make() {
+    let a = Point::new(1);
+    let b = Point { x: helper() };
+    helper();
+}
+"#;
+        let result = adapter.analyze("file:///test.rs", src);
+        let flagged = |name: &str| {
+            result.calls.iter().find(|c| c.callee_name == name).map(|c| c.is_constructor)
+        };
+        assert_eq!(flagged("new"), Some(true));
+        assert_eq!(flagged("Point"), Some(true));
+        assert!(result.calls.iter().filter(|c| c.callee_name == "helper").all(|c| !c.is_constructor));
+        assert_eq!(result.calls.iter().filter(|c| c.callee_name == "helper").count(), 2);
+    }
+}
@@ -7,13 +7,54 @@
 //! - Call relationships between functions
 //! - Type hierarchy (inheritance, implementations)
 
+use crate::adapter::{ExportInfo, ImportInfo};
 use dashmap::DashMap;
-use logos_core::{Position, Range, SymbolKind};
+use logos_core::{uri_to_path, Position, Range, SymbolKind};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Whether a symbol query uses glob metacharacters
+pub fn is_glob_pattern(query: &str) -> bool {
+    query.contains(['*', '?'])
+}
+
+/// Match `text` against a glob where `*` is any run of characters and `?`
+/// is exactly one. The pattern is anchored at both ends.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it was tried against
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
 
 /// Unique identifier for a symbol
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -119,6 +160,57 @@ pub struct SmartSymbol {
     pub exported: bool,
     /// Full qualified name (e.g., "module.Class.method")
     pub qualified_name: String,
+    /// Type a type alias stands for, as written (`u64` for
+    /// `type UserId = u64`); `None` for anything that isn't an alias
+    #[serde(default)]
+    pub alias_target: Option<String>,
+}
+
+impl SmartSymbol {
+    /// Textual handle for the symbol, `uri#qualified_name:Kind`, that stays
+    /// valid across reindexing and sessions (unlike `SymbolId`) as long as
+    /// the symbol keeps its file, name and kind. Symbols without a
+    /// qualified name use their plain name.
+    pub fn descriptor(&self) -> String {
+        let name = if self.qualified_name.is_empty() { &self.name } else { &self.qualified_name };
+        format!("{}#{}:{:?}", self.location.uri, name, self.kind)
+    }
+
+    /// Segments of the qualified name, independent of the language's
+    /// separator: both `geo::Point::norm` and `geo.Point.norm` give
+    /// `["geo", "Point", "norm"]`
+    pub fn qualified_name_parts(&self) -> Vec<&str> {
+        qualified_name_parts(&self.qualified_name)
+    }
+}
+
+/// Split a qualified name on `::` or `.`, whichever the language uses
+pub fn qualified_name_parts(qualified_name: &str) -> Vec<&str> {
+    qualified_name
+        .split("::")
+        .flat_map(|part| part.split('.'))
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Key of the qualified name index, so lookups match across separators
+fn qualified_key<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    parts.into_iter().collect::<Vec<_>>().join("::")
+}
+
+/// The declared name an alias target refers to: `Vec` for `Vec<User>`,
+/// `Point` for `geo::Point` or `struct Point`, `User` for `&'a mut User`.
+/// Targets that aren't a single named type (`{ id: string }`,
+/// `string | null`, `fn() -> u8`) give `None`.
+fn alias_base_name(target: &str) -> Option<&str> {
+    let head = target.split(['<', '[']).next()?;
+    if head.contains(['{', '|', '(', '&']) && !head.trim_start().starts_with('&') {
+        return None;
+    }
+    let name = head.split_whitespace().last()?.trim_start_matches(['&', '*']);
+    let name = qualified_name_parts(name).pop()?;
+    let is_ident = name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    is_ident.then_some(name)
 }
 
 /// Location of a symbol
@@ -184,21 +276,56 @@ pub struct SymbolTable {
     /// Symbols indexed by name (for quick lookup)
     name_index: DashMap<String, Vec<SymbolId>>,
 
-    /// Symbols indexed by qualified name
-    qualified_name_index: DashMap<String, SymbolId>,
+    /// Symbols indexed by qualified name, with separators normalized to
+    /// `::`; overloads share a key
+    qualified_name_index: DashMap<String, Vec<SymbolId>>,
+
+    /// Lowercased names in sorted order, mapping to the names in `name_index`
+    /// (for prefix range queries)
+    sorted_names: RwLock<BTreeMap<String, BTreeSet<String>>>,
 
     /// All references
     references: DashMap<SymbolId, Vec<SymbolReference>>,
+
+    /// Generation each symbol was added at
+    added_at: DashMap<SymbolId, u64>,
+
+    /// Change counter, shared with the owning `ProjectIndex` if any
+    generation: Arc<AtomicU64>,
+}
+
+impl Clone for SymbolTable {
+    fn clone(&self) -> Self {
+        Self {
+            symbols: self.symbols.clone(),
+            file_symbols: self.file_symbols.clone(),
+            name_index: self.name_index.clone(),
+            qualified_name_index: self.qualified_name_index.clone(),
+            sorted_names: RwLock::new(self.sorted_names.read().unwrap_or_else(PoisonError::into_inner).clone()),
+            references: self.references.clone(),
+            added_at: self.added_at.clone(),
+            generation: Arc::clone(&self.generation),
+        }
+    }
 }
 
 impl SymbolTable {
     pub fn new() -> Self {
+        Self::with_generation(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// A table that tags symbols with the value of `generation` when they
+    /// are added and bumps it on every change
+    pub(crate) fn with_generation(generation: Arc<AtomicU64>) -> Self {
         Self {
             symbols: DashMap::new(),
             file_symbols: DashMap::new(),
             name_index: DashMap::new(),
             qualified_name_index: DashMap::new(),
+            sorted_names: RwLock::new(BTreeMap::new()),
             references: DashMap::new(),
+            added_at: DashMap::new(),
+            generation,
         }
     }
 
@@ -206,46 +333,107 @@ impl SymbolTable {
     pub fn add_symbol(&self, symbol: SmartSymbol) -> SymbolId {
         let id = symbol.id;
         let name = symbol.name.clone();
-        let qualified_name = symbol.qualified_name.clone();
+        let qualified_name = qualified_key(symbol.qualified_name_parts());
         let uri = symbol.location.uri.clone();
 
         // Add to main index
         self.symbols.insert(id, symbol);
+        self.added_at.insert(id, self.generation.load(Ordering::SeqCst));
 
         // Add to file index
         self.file_symbols.entry(uri).or_default().push(id);
 
         // Add to name index
-        self.name_index.entry(name).or_default().push(id);
+        self.name_index.entry(name.clone()).or_default().push(id);
+        self.sorted_names
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.to_lowercase())
+            .or_default()
+            .insert(name);
 
         // Add to qualified name index
-        self.qualified_name_index.insert(qualified_name, id);
+        if !qualified_name.is_empty() {
+            self.qualified_name_index.entry(qualified_name).or_default().push(id);
+        }
 
+        self.generation.fetch_add(1, Ordering::SeqCst);
         id
     }
 
+    /// Symbols added at or after `generation`, as reported by
+    /// `ProjectIndex::generation()` before the changes of interest, ordered
+    /// by file and position. A reindexed file's symbols count as added, so
+    /// this covers modified symbols too; removals aren't reported.
+    pub fn symbols_since(&self, generation: u64) -> Vec<SmartSymbol> {
+        let mut symbols: Vec<SmartSymbol> = self
+            .added_at
+            .iter()
+            .filter(|entry| *entry.value() >= generation)
+            .filter_map(|entry| self.get(*entry.key()))
+            .collect();
+        sort_by_location(&mut symbols);
+        symbols
+    }
+
     /// Get a symbol by ID
     pub fn get(&self, id: SymbolId) -> Option<SmartSymbol> {
         self.symbols.get(&id).map(|s| s.clone())
     }
 
-    /// Find symbols by name
+    /// Find symbols by name, ordered by file and position
     pub fn find_by_name(&self, name: &str) -> Vec<SmartSymbol> {
-        self.name_index
+        let mut symbols: Vec<SmartSymbol> = self
+            .name_index
             .get(name)
             .map(|ids| {
                 ids.iter()
                     .filter_map(|id| self.symbols.get(id).map(|s| s.clone()))
                     .collect()
             })
-            .unwrap_or_default()
+            .unwrap_or_default();
+        sort_by_location(&mut symbols);
+        symbols
     }
 
-    /// Find symbol by qualified name
+    /// Find the first symbol with a qualified name, written with either
+    /// `::` or `.` as separator
     pub fn find_by_qualified_name(&self, qualified_name: &str) -> Option<SmartSymbol> {
         self.qualified_name_index
-            .get(qualified_name)
-            .and_then(|id| self.symbols.get(&id).map(|s| s.clone()))
+            .get(&qualified_key(qualified_name_parts(qualified_name)))
+            .and_then(|ids| ids.first().and_then(|id| self.symbols.get(id).map(|s| s.clone())))
+    }
+
+    /// Find every symbol with a qualified name, e.g. all overloads of a method
+    pub fn find_all_by_qualified_name(&self, qualified_name: &str) -> Vec<SmartSymbol> {
+        self.find_by_qualified_parts(&qualified_name_parts(qualified_name))
+    }
+
+    /// Find every symbol whose qualified name has these segments, whatever
+    /// separator its language uses; ordered by file and position
+    pub fn find_by_qualified_parts(&self, parts: &[&str]) -> Vec<SmartSymbol> {
+        let mut symbols: Vec<SmartSymbol> = self
+            .qualified_name_index
+            .get(&qualified_key(parts.iter().copied()))
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| self.symbols.get(id).map(|s| s.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        sort_by_location(&mut symbols);
+        symbols
+    }
+
+    /// Resolve a `SmartSymbol::descriptor` back to a symbol. Overloads
+    /// share a descriptor; the first one in the file is returned.
+    pub fn find_by_descriptor(&self, descriptor: &str) -> Option<SmartSymbol> {
+        let (uri, _) = descriptor.split_once('#')?;
+        let ids = self.file_symbols.get(uri)?;
+        ids.iter()
+            .filter_map(|id| self.symbols.get(id))
+            .find(|s| s.descriptor() == descriptor)
+            .map(|s| s.clone())
     }
 
     /// Get all symbols in a file
@@ -260,22 +448,66 @@ impl SymbolTable {
             .unwrap_or_default()
     }
 
-    /// Remove all symbols from a file
-    pub fn remove_file(&self, uri: &str) {
-        if let Some((_, ids)) = self.file_symbols.remove(uri) {
-            for id in ids {
-                if let Some((_, symbol)) = self.symbols.remove(&id) {
-                    // Remove from name index
-                    if let Some(mut entry) = self.name_index.get_mut(&symbol.name) {
+    /// Get the members of a symbol (e.g. a class's methods and fields)
+    pub fn get_members(&self, parent: SymbolId) -> Vec<SmartSymbol> {
+        let parent_symbol = match self.get(parent) {
+            Some(s) => s,
+            None => return Vec::new(),
+        };
+
+        // Adapters record nesting through `parent`; `children` may be empty
+        let mut members: Vec<SmartSymbol> = self
+            .get_file_symbols(&parent_symbol.location.uri)
+            .into_iter()
+            .filter(|s| s.parent == Some(parent))
+            .collect();
+        for id in &parent_symbol.children {
+            if !members.iter().any(|m| m.id == *id) {
+                if let Some(child) = self.get(*id) {
+                    members.push(child);
+                }
+            }
+        }
+        members
+    }
+
+    /// Remove all symbols from a file, returning the ids that were removed
+    pub fn remove_file(&self, uri: &str) -> Vec<SymbolId> {
+        let ids = match self.file_symbols.remove(uri) {
+            Some((_, ids)) => ids,
+            None => return Vec::new(),
+        };
+        for &id in &ids {
+            if let Some((_, symbol)) = self.symbols.remove(&id) {
+                // Remove from name index
+                let now_empty = match self.name_index.get_mut(&symbol.name) {
+                    Some(mut entry) => {
                         entry.retain(|i| *i != id);
+                        entry.is_empty()
                     }
-                    // Remove from qualified name index
-                    self.qualified_name_index.remove(&symbol.qualified_name);
-                    // Remove references
-                    self.references.remove(&id);
+                    None => false,
+                };
+                if now_empty
+                    && self
+                        .name_index
+                        .remove_if(&symbol.name, |_, ids| ids.is_empty())
+                        .is_some()
+                {
+                    self.unlink_sorted_name(&symbol.name);
+                }
+                // Remove from qualified name index
+                let key = qualified_key(symbol.qualified_name_parts());
+                if let Some(mut entry) = self.qualified_name_index.get_mut(&key) {
+                    entry.retain(|i| *i != id);
                 }
+                self.qualified_name_index.remove_if(&key, |_, ids| ids.is_empty());
+                // Remove references
+                self.references.remove(&id);
+                self.added_at.remove(&id);
             }
         }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        ids
     }
 
     /// Add a reference to a symbol
@@ -284,6 +516,7 @@ impl SymbolTable {
             .entry(reference.symbol_id)
             .or_default()
             .push(reference);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get all references to a symbol
@@ -294,20 +527,139 @@ impl SymbolTable {
             .unwrap_or_default()
     }
 
-    /// Search symbols by query
+    /// Search symbols by query, ordered by file and position
     pub fn search(&self, query: &str) -> Vec<SmartSymbol> {
+        let mut symbols: Vec<SmartSymbol> = self.search_iter(query).collect();
+        sort_by_location(&mut symbols);
+        symbols
+    }
+
+    /// Lazily search symbols by query, cloning each match as it is yielded.
+    /// Matches come in no particular order.
+    ///
+    /// The iterator holds a read lock on one shard of the table at a time, so
+    /// don't add or remove symbols from the same thread while it is alive.
+    pub fn search_iter(&self, query: &str) -> impl Iterator<Item = SmartSymbol> + '_ {
+        let query_lower = query.to_lowercase();
+        self.symbols
+            .iter()
+            .filter(move |entry| entry.name.to_lowercase().contains(&query_lower))
+            .map(|entry| entry.clone())
+    }
+
+    /// Search symbols by query, stopping after `limit` matches.
+    ///
+    /// Exact name matches come first, then case-insensitive prefix matches
+    /// (a range query over the sorted name index), then substring matches.
+    pub fn search_limited(&self, query: &str, limit: usize) -> Vec<SmartSymbol> {
+        self.search_limited_counting(query, limit).0
+    }
+
+    /// `search_limited`, also returning how many index entries were touched
+    fn search_limited_counting(&self, query: &str, limit: usize) -> (Vec<SmartSymbol>, usize) {
+        let mut results = Vec::new();
+        let mut touched = 0;
+        if limit == 0 {
+            return (results, touched);
+        }
+
+        let mut seen_names = HashSet::new();
+        let mut take_name = |name: &str, results: &mut Vec<SmartSymbol>, touched: &mut usize| {
+            if !seen_names.insert(name.to_string()) {
+                return;
+            }
+            if let Some(ids) = self.name_index.get(name) {
+                for id in ids.iter() {
+                    if results.len() >= limit {
+                        return;
+                    }
+                    *touched += 1;
+                    if let Some(symbol) = self.symbols.get(id) {
+                        results.push(symbol.clone());
+                    }
+                }
+            }
+        };
+
+        // Exact match
+        take_name(query, &mut results, &mut touched);
+        if results.len() >= limit {
+            return (results, touched);
+        }
+
         let query_lower = query.to_lowercase();
+        let sorted_names = self.sorted_names.read().unwrap_or_else(PoisonError::into_inner);
+
+        // Prefix matches: a contiguous range of the sorted index
+        let prefix_range = sorted_names
+            .range::<str, _>((Bound::Included(query_lower.as_str()), Bound::Unbounded))
+            .take_while(|(lower, _)| lower.starts_with(&query_lower));
+        for (_, names) in prefix_range {
+            touched += 1;
+            for name in names {
+                take_name(name, &mut results, &mut touched);
+            }
+            if results.len() >= limit {
+                return (results, touched);
+            }
+        }
+
+        // Substring matches need a full scan of the names
+        for (lower, names) in sorted_names.iter() {
+            touched += 1;
+            if lower.starts_with(&query_lower) || !lower.contains(&query_lower) {
+                continue;
+            }
+            for name in names {
+                take_name(name, &mut results, &mut touched);
+            }
+            if results.len() >= limit {
+                break;
+            }
+        }
+
+        (results, touched)
+    }
+
+    /// Search symbols whose whole name matches a `*`/`?` glob, case-insensitively
+    pub fn search_glob(&self, pattern: &str) -> Vec<SmartSymbol> {
+        let pattern_lower = pattern.to_lowercase();
+        let sorted_names = self.sorted_names.read().unwrap_or_else(PoisonError::into_inner);
         let mut results = Vec::new();
 
-        for entry in self.symbols.iter() {
-            if entry.name.to_lowercase().contains(&query_lower) {
-                results.push(entry.clone());
+        for (lower, names) in sorted_names.iter() {
+            if !glob_match(&pattern_lower, lower) {
+                continue;
+            }
+            for name in names {
+                if let Some(ids) = self.name_index.get(name) {
+                    results.extend(ids.iter().filter_map(|id| self.symbols.get(id).map(|s| s.clone())));
+                }
             }
         }
 
         results
     }
 
+    /// Drop a name from the sorted index once no symbol uses it.
+    ///
+    /// `add_symbol` links a name into `name_index` before `sorted_names`, so
+    /// checking `name_index` under the `sorted_names` lock can't miss a
+    /// symbol added after the caller saw the name unused.
+    fn unlink_sorted_name(&self, name: &str) {
+        let lower = name.to_lowercase();
+        let mut sorted_names = self.sorted_names.write().unwrap_or_else(PoisonError::into_inner);
+        if self.name_index.contains_key(name) {
+            return;
+        }
+        if let Some(names) = sorted_names.get_mut(&lower) {
+            names.remove(name);
+            if names.is_empty() {
+                sorted_names.remove(&lower);
+            }
+        }
+    }
+
     /// Find symbol at a position in a file
     pub fn find_at_position(&self, uri: &str, position: Position) -> Option<SmartSymbol> {
         self.get_file_symbols(uri)
@@ -315,6 +667,29 @@ impl SymbolTable {
             .find(|s| s.location.selection_range.contains(position))
     }
 
+    /// The innermost symbol whose range contains `position`, followed by
+    /// its parent, grandparent and so on up to a top-level symbol. Stops
+    /// early if the parent links loop.
+    pub fn find_at_position_with_ancestors(&self, uri: &str, position: Position) -> Vec<SmartSymbol> {
+        let innermost = self
+            .get_file_symbols(uri)
+            .into_iter()
+            .filter(|s| s.location.range.contains(position))
+            .max_by_key(|s| (s.location.range.start, std::cmp::Reverse(s.location.range.end)));
+
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = innermost;
+        while let Some(symbol) = current {
+            if !seen.insert(symbol.id) {
+                break;
+            }
+            current = symbol.parent.and_then(|id| self.get(id));
+            chain.push(symbol);
+        }
+        chain
+    }
+
     /// Get symbol count
     pub fn len(&self) -> usize {
         self.symbols.len()
@@ -325,12 +700,28 @@ impl SymbolTable {
         self.symbols.is_empty()
     }
 
-    /// Get all file URIs
+    /// Get all file URIs, sorted
     pub fn files(&self) -> Vec<String> {
-        self.file_symbols.iter().map(|e| e.key().clone()).collect()
+        let mut files: Vec<String> = self.file_symbols.iter().map(|e| e.key().clone()).collect();
+        files.sort();
+        files
     }
 }
 
+/// Order symbols by file, then by where they start
+fn sort_by_location(symbols: &mut [SmartSymbol]) {
+    symbols.sort_by(|a, b| {
+        (&a.location.uri, a.location.range.start).cmp(&(&b.location.uri, b.location.range.start))
+    });
+}
+
+/// Order call sites by file, then by position of the call
+fn sort_call_sites(sites: &mut [CallSite]) {
+    sites.sort_by(|a, b| {
+        (&a.location.uri, a.location.range.start).cmp(&(&b.location.uri, b.location.range.start))
+    });
+}
+
 impl Default for SymbolTable {
     fn default() -> Self {
         Self::new()
@@ -339,70 +730,164 @@ impl Default for SymbolTable {
 
 /// Call graph for tracking function calls
 pub struct CallGraph {
-    /// Outgoing calls: caller -> callees
-    callers: DashMap<SymbolId, HashSet<CallSite>>,
-    /// Incoming calls: callee -> callers
-    callees: DashMap<SymbolId, HashSet<CallSite>>,
+    edges: RwLock<CallEdges>,
+    /// Change counter, shared with the owning `ProjectIndex` if any
+    generation: Arc<AtomicU64>,
+}
+
+/// Call sites stored once, with per-symbol indices into them
+#[derive(Clone, Default)]
+struct CallEdges {
+    sites: Vec<CallSite>,
+    /// Outgoing calls: caller -> indices into `sites`
+    callers: HashMap<SymbolId, Vec<usize>>,
+    /// Incoming calls: callee -> indices into `sites`
+    callees: HashMap<SymbolId, Vec<usize>>,
+}
+
+impl CallEdges {
+    fn sites_at<'a>(&'a self, indices: Option<&'a Vec<usize>>) -> impl Iterator<Item = &'a CallSite> {
+        indices.into_iter().flatten().map(|&i| &self.sites[i])
+    }
+
+    /// Keep the call sites matching `keep`, renumbering the survivors
+    fn retain(&mut self, keep: impl Fn(&CallSite) -> bool) {
+        let sites = std::mem::take(&mut self.sites);
+        self.callers.clear();
+        self.callees.clear();
+        for site in sites.into_iter().filter(|site| keep(site)) {
+            self.push(site);
+        }
+    }
+
+    fn push(&mut self, site: CallSite) {
+        let index = self.sites.len();
+        self.callers.entry(site.caller).or_default().push(index);
+        self.callees.entry(site.callee).or_default().push(index);
+        self.sites.push(site);
+    }
 }
 
 impl CallGraph {
     pub fn new() -> Self {
+        Self::with_generation(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// A graph that bumps `generation` on every change
+    pub(crate) fn with_generation(generation: Arc<AtomicU64>) -> Self {
         Self {
-            callers: DashMap::new(),
-            callees: DashMap::new(),
+            edges: RwLock::new(CallEdges::default()),
+            generation,
         }
     }
 
-    /// Add a call relationship
+    /// Add a call relationship; a call already recorded at the same
+    /// location is ignored
     pub fn add_call(&self, call: CallSite) {
-        // Add to caller's outgoing calls
-        self.callers
-            .entry(call.caller)
-            .or_default()
-            .insert(call.clone());
-
-        // Add to callee's incoming calls
-        self.callees
-            .entry(call.callee)
-            .or_default()
-            .insert(call);
+        let mut edges = self.edges.write().unwrap_or_else(PoisonError::into_inner);
+        if edges.sites_at(edges.callers.get(&call.caller)).any(|site| *site == call) {
+            return;
+        }
+        edges.push(call);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
-    /// Get all functions called by a function
+    /// Get all functions called by a function, ordered by call location
     pub fn get_callees(&self, caller: SymbolId) -> Vec<CallSite> {
-        self.callers
-            .get(&caller)
-            .map(|calls| calls.iter().cloned().collect())
-            .unwrap_or_default()
+        let edges = self.edges.read().unwrap_or_else(PoisonError::into_inner);
+        let mut sites: Vec<CallSite> = edges.sites_at(edges.callers.get(&caller)).cloned().collect();
+        sort_call_sites(&mut sites);
+        sites
     }
 
-    /// Get all functions that call a function
+    /// Get all functions that call a function, ordered by call location
     pub fn get_callers(&self, callee: SymbolId) -> Vec<CallSite> {
-        self.callees
-            .get(&callee)
-            .map(|calls| calls.iter().cloned().collect())
-            .unwrap_or_default()
+        let edges = self.edges.read().unwrap_or_else(PoisonError::into_inner);
+        let mut sites: Vec<CallSite> = edges.sites_at(edges.callees.get(&callee)).cloned().collect();
+        sort_call_sites(&mut sites);
+        sites
+    }
+
+    /// Number of distinct functions that call `id`. Several call sites in
+    /// one caller count once, and recursive calls don't count.
+    pub fn fan_in(&self, id: SymbolId) -> usize {
+        let edges = self.edges.read().unwrap_or_else(PoisonError::into_inner);
+        edges
+            .sites_at(edges.callees.get(&id))
+            .map(|call| call.caller)
+            .filter(|caller| *caller != id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Number of distinct functions that `id` calls, not counting itself
+    pub fn fan_out(&self, id: SymbolId) -> usize {
+        let edges = self.edges.read().unwrap_or_else(PoisonError::into_inner);
+        edges
+            .sites_at(edges.callers.get(&id))
+            .map(|call| call.callee)
+            .filter(|callee| *callee != id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Every symbol that is called at least once
+    pub(crate) fn called_symbols(&self) -> Vec<SymbolId> {
+        self.edges.read().unwrap_or_else(PoisonError::into_inner).callees.keys().copied().collect()
     }
 
     /// Remove all calls from a file
     pub fn remove_file(&self, file_uri: &str) {
-        // Remove calls where the location matches the file
-        for mut entry in self.callers.iter_mut() {
-            entry.retain(|call| call.location.uri != file_uri);
-        }
-        for mut entry in self.callees.iter_mut() {
-            entry.retain(|call| call.location.uri != file_uri);
+        self.edges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|call| call.location.uri != file_uri);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Remove the calls made from any of `file_uris` together with every
+    /// call whose caller or callee is one of `ids`, in a single pass over
+    /// the edges
+    pub fn remove_files(&self, file_uris: &[&str], ids: &[SymbolId]) {
+        let files: HashSet<&str> = file_uris.iter().copied().collect();
+        let ids: HashSet<SymbolId> = ids.iter().copied().collect();
+        self.edges.write().unwrap_or_else(PoisonError::into_inner).retain(|call| {
+            !files.contains(call.location.uri.as_str()) && !ids.contains(&call.caller) && !ids.contains(&call.callee)
+        });
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Remove every call whose caller or callee is one of `ids`, so no
+    /// edge is left pointing at a symbol that no longer exists
+    pub fn remove_symbols(&self, ids: &[SymbolId]) {
+        if ids.is_empty() {
+            return;
         }
+        let ids: HashSet<SymbolId> = ids.iter().copied().collect();
+        self.edges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|call| !ids.contains(&call.caller) && !ids.contains(&call.callee));
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get the total number of call sites
     pub fn len(&self) -> usize {
-        self.callers.iter().map(|e| e.len()).sum()
+        self.edges.read().unwrap_or_else(PoisonError::into_inner).sites.len()
     }
 
     /// Check if empty
     pub fn is_empty(&self) -> bool {
-        self.callers.is_empty()
+        self.edges.read().unwrap_or_else(PoisonError::into_inner).sites.is_empty()
+    }
+}
+
+impl Clone for CallGraph {
+    fn clone(&self) -> Self {
+        Self {
+            edges: RwLock::new(self.edges.read().unwrap_or_else(PoisonError::into_inner).clone()),
+            generation: Arc::clone(&self.generation),
+        }
     }
 }
 
@@ -435,6 +920,7 @@ impl PartialEq for CallSite {
 impl Eq for CallSite {}
 
 /// Type hierarchy for tracking inheritance
+#[derive(Clone)]
 pub struct TypeHierarchy {
     /// Supertype relationships: subtype -> supertypes
     supertypes: DashMap<SymbolId, Vec<SymbolId>>,
@@ -444,28 +930,38 @@ pub struct TypeHierarchy {
     implements: DashMap<SymbolId, Vec<SymbolId>>,
     /// Interface implementors: interface -> implementors
     implementors: DashMap<SymbolId, Vec<SymbolId>>,
+    /// Change counter, shared with the owning `ProjectIndex` if any
+    generation: Arc<AtomicU64>,
 }
 
 impl TypeHierarchy {
     pub fn new() -> Self {
+        Self::with_generation(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// A hierarchy that bumps `generation` on every change
+    pub(crate) fn with_generation(generation: Arc<AtomicU64>) -> Self {
         Self {
             supertypes: DashMap::new(),
             subtypes: DashMap::new(),
             implements: DashMap::new(),
             implementors: DashMap::new(),
+            generation,
         }
     }
 
-    /// Add an inheritance relationship
+    /// Add an inheritance relationship (adding an existing edge is a no-op)
     pub fn add_extends(&self, subtype: SymbolId, supertype: SymbolId) {
-        self.supertypes.entry(subtype).or_default().push(supertype);
-        self.subtypes.entry(supertype).or_default().push(subtype);
+        push_unique(&self.supertypes, subtype, supertype);
+        push_unique(&self.subtypes, supertype, subtype);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
-    /// Add an implementation relationship
+    /// Add an implementation relationship (adding an existing edge is a no-op)
     pub fn add_implements(&self, implementor: SymbolId, interface: SymbolId) {
-        self.implements.entry(implementor).or_default().push(interface);
-        self.implementors.entry(interface).or_default().push(implementor);
+        push_unique(&self.implements, implementor, interface);
+        push_unique(&self.implementors, interface, implementor);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get all supertypes of a type
@@ -499,6 +995,39 @@ impl TypeHierarchy {
             .map(|v| v.clone())
             .unwrap_or_default()
     }
+
+    /// Remove the given (file's) symbols from every relationship, both as
+    /// keys and as entries in other types' lists
+    pub fn remove_file(&self, ids: &[SymbolId]) {
+        if ids.is_empty() {
+            return;
+        }
+        let ids: HashSet<SymbolId> = ids.iter().copied().collect();
+        for map in [
+            &self.supertypes,
+            &self.subtypes,
+            &self.implements,
+            &self.implementors,
+        ] {
+            map.retain(|key, related| {
+                if ids.contains(key) {
+                    return false;
+                }
+                related.retain(|id| !ids.contains(id));
+                !related.is_empty()
+            });
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Append `value` to `key`'s list unless it is already there, keeping
+/// insertion order
+fn push_unique(map: &DashMap<SymbolId, Vec<SymbolId>>, key: SymbolId, value: SymbolId) {
+    let mut related = map.entry(key).or_default();
+    if !related.contains(&value) {
+        related.push(value);
+    }
 }
 
 impl Default for TypeHierarchy {
@@ -508,6 +1037,7 @@ impl Default for TypeHierarchy {
 }
 
 /// Dependency graph for tracking file imports
+#[derive(Clone)]
 pub struct DependencyGraph {
     /// File imports: file -> imported files
     imports: DashMap<PathBuf, HashSet<PathBuf>>,
@@ -515,14 +1045,28 @@ pub struct DependencyGraph {
     imported_by: DashMap<PathBuf, HashSet<PathBuf>>,
     /// Exported symbols per file
     exports: DashMap<PathBuf, Vec<SymbolId>>,
+    /// Import statements per file, including per-item aliases
+    file_imports: DashMap<PathBuf, Vec<ImportInfo>>,
+    /// Export statements per file, including re-exports
+    file_exports: DashMap<PathBuf, Vec<ExportInfo>>,
+    /// Change counter, shared with the owning `ProjectIndex` if any
+    generation: Arc<AtomicU64>,
 }
 
 impl DependencyGraph {
     pub fn new() -> Self {
+        Self::with_generation(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// A graph that bumps `generation` on every change
+    pub(crate) fn with_generation(generation: Arc<AtomicU64>) -> Self {
         Self {
             imports: DashMap::new(),
             imported_by: DashMap::new(),
             exports: DashMap::new(),
+            file_imports: DashMap::new(),
+            file_exports: DashMap::new(),
+            generation,
         }
     }
 
@@ -530,27 +1074,70 @@ impl DependencyGraph {
     pub fn add_import(&self, from: PathBuf, to: PathBuf) {
         self.imports.entry(from.clone()).or_default().insert(to.clone());
         self.imported_by.entry(to).or_default().insert(from);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Set exports for a file
     pub fn set_exports(&self, file: PathBuf, symbols: Vec<SymbolId>) {
         self.exports.insert(file, symbols);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Set the import statements of a file
+    pub fn set_file_imports(&self, file: PathBuf, imports: Vec<ImportInfo>) {
+        self.file_imports.insert(file, imports);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Get the import statements of a file
+    pub fn get_file_imports(&self, file: &PathBuf) -> Vec<ImportInfo> {
+        self.file_imports
+            .get(file)
+            .map(|v| v.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the export statements of a file
+    pub fn set_file_exports(&self, file: PathBuf, exports: Vec<ExportInfo>) {
+        self.file_exports.insert(file, exports);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
-    /// Get files imported by a file
+    /// Get the export statements of a file
+    pub fn get_file_exports(&self, file: &PathBuf) -> Vec<ExportInfo> {
+        self.file_exports
+            .get(file)
+            .map(|v| v.clone())
+            .unwrap_or_default()
+    }
+
+    /// Files with recorded export statements, sorted
+    pub fn exporting_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.file_exports.iter().map(|e| e.key().clone()).collect();
+        files.sort();
+        files
+    }
+
+    /// Get files imported by a file, sorted
     pub fn get_imports(&self, file: &PathBuf) -> Vec<PathBuf> {
-        self.imports
+        let mut files: Vec<PathBuf> = self
+            .imports
             .get(file)
             .map(|v| v.iter().cloned().collect())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        files.sort();
+        files
     }
 
-    /// Get files that import a file
+    /// Get files that import a file, sorted
     pub fn get_importers(&self, file: &PathBuf) -> Vec<PathBuf> {
-        self.imported_by
+        let mut files: Vec<PathBuf> = self
+            .imported_by
             .get(file)
             .map(|v| v.iter().cloned().collect())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        files.sort();
+        files
     }
 
     /// Get exported symbols from a file
@@ -583,12 +1170,75 @@ impl DependencyGraph {
 
         // Remove exports
         self.exports.remove(file);
+        self.file_imports.remove(file);
+        self.file_exports.remove(file);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get the number of indexed files
     pub fn file_count(&self) -> usize {
         self.exports.len()
     }
+
+    /// Groups of files that import each other, directly or through other
+    /// files (the strongly connected components of the import graph).
+    /// Files in a group and the groups themselves are sorted; a file that
+    /// imports itself is a group of one.
+    pub fn find_cycles(&self) -> Vec<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = self.imports.iter().map(|e| e.key().clone()).collect();
+        files.sort();
+
+        // Kosaraju: order files by when their depth-first walk finishes...
+        let mut visited = HashSet::new();
+        let mut finished = Vec::new();
+        for file in files {
+            if !visited.insert(file.clone()) {
+                continue;
+            }
+            let imports = self.get_imports(&file).into_iter();
+            let mut stack = vec![(file, imports)];
+            while let Some((file, imports)) = stack.last_mut() {
+                match imports.next() {
+                    Some(target) => {
+                        if visited.insert(target.clone()) {
+                            let imports = self.get_imports(&target).into_iter();
+                            stack.push((target, imports));
+                        }
+                    }
+                    None => {
+                        finished.push(file.clone());
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        // ...then collect what reaches each file, latest finished first
+        let mut assigned = HashSet::new();
+        let mut cycles = Vec::new();
+        for file in finished.into_iter().rev() {
+            if !assigned.insert(file.clone()) {
+                continue;
+            }
+            let imports_itself = self.imports.get(&file).is_some_and(|targets| targets.contains(&file));
+            let mut component = Vec::new();
+            let mut pending = vec![file];
+            while let Some(file) = pending.pop() {
+                for importer in self.get_importers(&file) {
+                    if assigned.insert(importer.clone()) {
+                        pending.push(importer);
+                    }
+                }
+                component.push(file);
+            }
+            if component.len() > 1 || imports_itself {
+                component.sort();
+                cycles.push(component);
+            }
+        }
+        cycles.sort();
+        cycles
+    }
 }
 
 impl Default for DependencyGraph {
@@ -607,23 +1257,211 @@ pub struct ProjectIndex {
     pub type_hierarchy: Arc<TypeHierarchy>,
     /// Dependency graph
     pub dependencies: Arc<DependencyGraph>,
+    /// Held exclusively while a file's data is replaced and shared while a
+    /// snapshot is copied
+    update_lock: RwLock<()>,
+    /// Change counter, bumped by every structure above
+    generation: Arc<AtomicU64>,
+}
+
+/// Exclusive access to a `ProjectIndex` for one update
+pub(crate) struct IndexUpdate<'a> {
+    _guard: std::sync::RwLockWriteGuard<'a, ()>,
+}
+
+/// An immutable copy of a `ProjectIndex`, taken between updates.
+///
+/// Every structure in a snapshot reflects the same set of completed file
+/// updates, so a query that walks several graphs (e.g. call hierarchy
+/// resolving callers through the symbol table) never sees a file half
+/// reindexed. Later updates to the live index don't affect it.
+pub struct IndexSnapshot {
+    generation: u64,
+    symbols: SymbolTable,
+    call_graph: CallGraph,
+    type_hierarchy: TypeHierarchy,
+    dependencies: DependencyGraph,
+}
+
+impl IndexSnapshot {
+    /// Generation of the live index this snapshot was taken at
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn call_graph(&self) -> &CallGraph {
+        &self.call_graph
+    }
+
+    pub fn type_hierarchy(&self) -> &TypeHierarchy {
+        &self.type_hierarchy
+    }
+
+    pub fn dependencies(&self) -> &DependencyGraph {
+        &self.dependencies
+    }
 }
 
 impl ProjectIndex {
     pub fn new() -> Self {
+        let generation = Arc::new(AtomicU64::new(0));
         Self {
-            symbols: Arc::new(SymbolTable::new()),
-            call_graph: Arc::new(CallGraph::new()),
-            type_hierarchy: Arc::new(TypeHierarchy::new()),
-            dependencies: Arc::new(DependencyGraph::new()),
+            symbols: Arc::new(SymbolTable::with_generation(Arc::clone(&generation))),
+            call_graph: Arc::new(CallGraph::with_generation(Arc::clone(&generation))),
+            type_hierarchy: Arc::new(TypeHierarchy::with_generation(Arc::clone(&generation))),
+            dependencies: Arc::new(DependencyGraph::with_generation(Arc::clone(&generation))),
+            update_lock: RwLock::new(()),
+            generation,
+        }
+    }
+
+    /// Counter bumped by every change to the index, whether made through
+    /// `ProjectIndexer` or directly on the public structures. Handlers can
+    /// compare it before and after a query to tell whether the index
+    /// changed underneath them.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Copy the whole index as of the last completed update.
+    ///
+    /// The guarantee covers updates made through `ProjectIndexer` and
+    /// `remove_file`; writes made directly to the public structures are not
+    /// coordinated. Copying is proportional to the index size, so take one
+    /// snapshot per request that needs a coherent view, not per lookup.
+    pub fn snapshot(&self) -> IndexSnapshot {
+        let _guard = self.update_lock.read().unwrap_or_else(PoisonError::into_inner);
+        IndexSnapshot {
+            generation: self.generation(),
+            symbols: (*self.symbols).clone(),
+            call_graph: (*self.call_graph).clone(),
+            type_hierarchy: (*self.type_hierarchy).clone(),
+            dependencies: (*self.dependencies).clone(),
+        }
+    }
+
+    /// Start an update; snapshots wait until the returned guard is dropped
+    pub(crate) fn begin_update(&self) -> IndexUpdate<'_> {
+        IndexUpdate {
+            _guard: self.update_lock.write().unwrap_or_else(PoisonError::into_inner),
         }
     }
 
     /// Remove all data for a file (for incremental updates)
     pub fn remove_file(&self, uri: &str) {
-        self.symbols.remove_file(uri);
+        let _update = self.begin_update();
+        self.remove_file_data(uri);
+    }
+
+    /// `remove_file` for callers that already hold an update
+    pub(crate) fn remove_file_data(&self, uri: &str) {
+        let ids = self.symbols.remove_file(uri);
         self.call_graph.remove_file(uri);
-        self.dependencies.remove_file(&PathBuf::from(uri));
+        // Edges recorded from other files can still name this file's symbols
+        self.call_graph.remove_symbols(&ids);
+        self.type_hierarchy.remove_file(&ids);
+        let path = uri_to_path(uri).unwrap_or_else(|| PathBuf::from(uri));
+        self.dependencies.remove_file(&path);
+    }
+
+    /// Remove all data for several files at once, e.g. when a directory is
+    /// deleted. The call graph is swept once rather than once per file.
+    pub fn remove_files(&self, uris: &[&str]) {
+        let _update = self.begin_update();
+        let mut ids = Vec::new();
+        for uri in uris {
+            let file_ids = self.symbols.remove_file(uri);
+            let path = uri_to_path(uri).unwrap_or_else(|| PathBuf::from(uri));
+            self.dependencies.remove_file(&path);
+            ids.extend(file_ids);
+        }
+        self.call_graph.remove_files(uris, &ids);
+        self.type_hierarchy.remove_file(&ids);
+    }
+
+    /// Symbols carrying an attribute or decorator named `attr_name`, e.g.
+    /// every `#[get]` or `@app.route` handler. A path-qualified attribute
+    /// matches its last segment too, so `get` finds `#[actix_web::get]`.
+    /// Ordered by file and position.
+    pub fn find_by_attribute(&self, attr_name: &str) -> Vec<SmartSymbol> {
+        let matches = |name: &str| {
+            name == attr_name
+                || name
+                    .strip_suffix(attr_name)
+                    .is_some_and(|prefix| prefix.ends_with("::") || prefix.ends_with('.'))
+        };
+        let mut symbols: Vec<SmartSymbol> = self
+            .symbols
+            .symbols
+            .iter()
+            .filter(|entry| entry.attributes.iter().any(|a| matches(&a.name)))
+            .map(|entry| entry.clone())
+            .collect();
+        sort_by_location(&mut symbols);
+        symbols
+    }
+
+    /// The type an alias ultimately stands for, following chains like
+    /// `type A = B; type B = User;` through to `User`. Declarations in the
+    /// alias's own file win over same-named ones elsewhere. `None` when the
+    /// symbol isn't an alias or its target isn't declared in the project
+    /// (`type UserId = u64`).
+    pub fn resolve_alias(&self, alias: &SmartSymbol) -> Option<SmartSymbol> {
+        let mut visited = HashSet::from([alias.id]);
+        let mut current = alias.clone();
+        let mut resolved = None;
+        let is_type = |s: &SmartSymbol| {
+            matches!(
+                s.kind,
+                SymbolKind::Class | SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Interface
+            )
+        };
+        while let Some(name) = current.alias_target.as_deref().and_then(alias_base_name) {
+            let candidates: Vec<SmartSymbol> = self
+                .symbols
+                .find_by_name(name)
+                .into_iter()
+                .filter(|s| is_type(s) && !visited.contains(&s.id))
+                .collect();
+            let next = candidates
+                .iter()
+                .find(|s| s.location.uri == current.location.uri)
+                .or_else(|| candidates.first())
+                .cloned();
+            match next {
+                Some(next) => {
+                    visited.insert(next.id);
+                    resolved = Some(next.clone());
+                    current = next;
+                }
+                None => break,
+            }
+        }
+        resolved
+    }
+
+    /// The `top_n` functions with the highest fan-in, most called first.
+    /// Ties are broken by symbol id so the ranking is stable.
+    pub fn hotspots(&self, top_n: usize) -> Vec<(SymbolId, usize)> {
+        let mut ranked: Vec<(SymbolId, usize)> = self
+            .call_graph
+            .called_symbols()
+            .into_iter()
+            .filter(|id| {
+                self.symbols.get(*id).is_some_and(|s| {
+                    matches!(s.kind, SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor)
+                })
+            })
+            .map(|id| (id, self.call_graph.fan_in(id)))
+            .filter(|(_, fan_in)| *fan_in > 0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0 .0.cmp(&b.0 .0)));
+        ranked.truncate(top_n);
+        ranked
     }
 }
 
@@ -665,6 +1503,7 @@ mod tests {
             attributes: vec![],
             exported: true,
             qualified_name: "test.foo".to_string(),
+            alias_target: None,
         };
 
         let id = table.add_symbol(symbol.clone());
@@ -714,4 +1553,566 @@ mod tests {
         let callers = graph.get_callers(callee);
         assert_eq!(callers.len(), 1);
     }
+
+    #[test]
+    fn test_call_graph_stores_edges_once() {
+        let graph = CallGraph::new();
+        let (main, parse, emit) = (SymbolId::new(), SymbolId::new(), SymbolId::new());
+        let call = |caller: SymbolId, callee: SymbolId, line: u32| CallSite {
+            caller,
+            callee,
+            location: crate::make_location(
+                "file:///main.ts",
+                Range::from_coords(line, 4, line, 12),
+                Range::from_coords(line, 4, line, 9),
+            ),
+            call_type: CallType::Direct,
+        };
+
+        graph.add_call(call(main, parse, 1));
+        graph.add_call(call(main, parse, 1));
+        graph.add_call(call(main, parse, 2));
+        graph.add_call(call(parse, emit, 7));
+        assert_eq!(graph.len(), 3);
+
+        let lines = |sites: Vec<CallSite>| -> Vec<u32> {
+            sites.iter().map(|c| c.location.range.start.line).collect()
+        };
+        assert_eq!(lines(graph.get_callees(main)), vec![1, 2]);
+        assert_eq!(lines(graph.get_callers(parse)), vec![1, 2]);
+        assert_eq!(lines(graph.get_callees(parse)), vec![7]);
+        assert_eq!(lines(graph.get_callers(emit)), vec![7]);
+
+        graph.remove_symbols(&[emit]);
+        assert_eq!(lines(graph.get_callees(main)), vec![1, 2]);
+        assert!(graph.get_callees(parse).is_empty());
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn test_fan_in_and_hotspots() {
+        let index = ProjectIndex::new();
+        let add = |name: &str| index.symbols.add_symbol(named_symbol(name));
+        let log = add("log");
+        let callers: Vec<SymbolId> = ["parse", "render", "save"].iter().map(|n| add(n)).collect();
+        let format = add("format");
+
+        let call = |caller: SymbolId, callee: SymbolId, line: u32| CallSite {
+            caller,
+            callee,
+            location: crate::make_location(
+                "file:///big.ts",
+                Range::from_coords(line, 0, line, 5),
+                Range::from_coords(line, 0, line, 5),
+            ),
+            call_type: CallType::Direct,
+        };
+        for (i, &caller) in callers.iter().enumerate() {
+            // Two call sites per caller still count as one caller
+            index.call_graph.add_call(call(caller, log, i as u32 * 10));
+            index.call_graph.add_call(call(caller, log, i as u32 * 10 + 1));
+        }
+        index.call_graph.add_call(call(callers[0], format, 50));
+        index.call_graph.add_call(call(log, log, 60));
+
+        assert_eq!(index.call_graph.fan_in(log), 3);
+        assert_eq!(index.call_graph.fan_out(callers[0]), 2);
+        assert_eq!(index.call_graph.fan_out(log), 0);
+        assert_eq!(index.call_graph.fan_in(callers[1]), 0);
+
+        assert_eq!(index.hotspots(5), vec![(log, 3), (format, 1)]);
+        assert_eq!(index.hotspots(1), vec![(log, 3)]);
+    }
+
+    fn named_symbol(name: &str) -> SmartSymbol {
+        let location = crate::make_location(
+            "file:///big.ts",
+            Range::from_coords(0, 0, 0, 10),
+            Range::from_coords(0, 0, 0, 10),
+        );
+        crate::SymbolBuilder::new(name, SymbolKind::Function, location).build()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*service", "userservice"));
+        assert!(glob_match("handle*", "handle"));
+        assert!(glob_match("h?ndle", "handle"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("handle*", "rehandle"));
+        assert!(!glob_match("*service", "serviceclient"));
+        assert!(!glob_match("h?ndle", "hndle"));
+    }
+
+    #[test]
+    fn test_search_glob() {
+        let table = SymbolTable::new();
+        for name in ["UserService", "AuthService", "ServiceLocator", "handleClick", "handle", "rehandle"] {
+            table.add_symbol(named_symbol(name));
+        }
+
+        let names = |pattern: &str| -> Vec<String> {
+            table.search_glob(pattern).into_iter().map(|s| s.name).collect()
+        };
+        assert_eq!(names("*Service"), vec!["AuthService", "UserService"]);
+        assert_eq!(names("handle*"), vec!["handle", "handleClick"]);
+        assert!(names("Service").is_empty());
+    }
+
+    #[test]
+    fn test_search_limited_prefix_touches_few_entries() {
+        let table = SymbolTable::new();
+        for i in 0..10_000 {
+            table.add_symbol(named_symbol(&format!("symbol{:05}", i)));
+        }
+        table.add_symbol(named_symbol("renderWidget"));
+        table.add_symbol(named_symbol("renderPanel"));
+        table.add_symbol(named_symbol("prerender"));
+
+        let (results, touched) = table.search_limited_counting("symbol01", 5);
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|s| s.name.starts_with("symbol01")));
+        assert!(touched < 20, "touched {} entries", touched);
+
+        // Prefix matches rank ahead of substring matches
+        let names: Vec<String> = table
+            .search_limited("RENDER", 10)
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["renderPanel", "renderWidget", "prerender"]);
+
+        // Exact hit short-circuits before the range query
+        let (results, touched) = table.search_limited_counting("symbol00042", 1);
+        assert_eq!(results[0].name, "symbol00042");
+        assert_eq!(touched, 1);
+
+        table.remove_file("file:///big.ts");
+        assert!(table.search_limited("render", 10).is_empty());
+        assert!(table.sorted_names.read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_outputs_are_ordered() {
+        let table = SymbolTable::new();
+        let graph = CallGraph::new();
+        let target = named_symbol("target").id;
+        for (i, file) in ["file:///c.ts", "file:///a.ts", "file:///d.ts", "file:///b.ts"].iter().enumerate() {
+            for line in [7, 2] {
+                let location = crate::make_location(
+                    file,
+                    Range::from_coords(line, 0, line, 10),
+                    Range::from_coords(line, 0, line, 10),
+                );
+                let id = table.add_symbol(
+                    crate::SymbolBuilder::new(format!("handler{}", i), SymbolKind::Function, location.clone()).build(),
+                );
+                graph.add_call(CallSite { caller: id, callee: target, location, call_type: CallType::Direct });
+            }
+        }
+
+        let files = table.files();
+        assert_eq!(files, vec!["file:///a.ts", "file:///b.ts", "file:///c.ts", "file:///d.ts"]);
+        assert_eq!(table.files(), files);
+
+        let found: Vec<(String, u32)> = table
+            .search("handler")
+            .into_iter()
+            .map(|s| (s.location.uri, s.location.range.start.line))
+            .collect();
+        assert_eq!(found[..3], [
+            ("file:///a.ts".to_string(), 2),
+            ("file:///a.ts".to_string(), 7),
+            ("file:///b.ts".to_string(), 2),
+        ]);
+
+        let callers: Vec<(String, u32)> = graph
+            .get_callers(target)
+            .into_iter()
+            .map(|c| (c.location.uri, c.location.range.start.line))
+            .collect();
+        assert_eq!(callers, found);
+    }
+
+    #[test]
+    fn test_find_cycles() {
+        let graph = DependencyGraph::new();
+        let path = |name: &str| PathBuf::from(format!("/src/{}.ts", name));
+        // a -> b -> c -> a, c -> d, e -> e
+        graph.add_import(path("a"), path("b"));
+        graph.add_import(path("b"), path("c"));
+        graph.add_import(path("c"), path("a"));
+        graph.add_import(path("c"), path("d"));
+        graph.add_import(path("e"), path("e"));
+
+        assert_eq!(
+            graph.find_cycles(),
+            vec![vec![path("a"), path("b"), path("c")], vec![path("e")]]
+        );
+    }
+
+    #[test]
+    fn test_remove_files_matches_individual_removals() {
+        let build = || {
+            let index = ProjectIndex::new();
+            let uris: Vec<String> = (0..5).map(|i| format!("file:///dir/m{}.ts", i)).collect();
+            let mut ids = Vec::new();
+            for uri in &uris {
+                let location = crate::make_location(uri, Range::from_coords(0, 0, 5, 0), Range::from_coords(0, 0, 0, 5));
+                ids.push(index.symbols.add_symbol(crate::SymbolBuilder::new("run", SymbolKind::Function, location).build()));
+            }
+            // Each file calls into the next one
+            for (i, uri) in uris.iter().enumerate() {
+                index.call_graph.add_call(CallSite {
+                    caller: ids[i],
+                    callee: ids[(i + 1) % ids.len()],
+                    location: crate::make_location(uri, Range::from_coords(1, 4, 1, 9), Range::from_coords(1, 4, 1, 7)),
+                    call_type: CallType::Direct,
+                });
+            }
+            index.type_hierarchy.add_extends(ids[1], ids[0]);
+            index
+                .dependencies
+                .add_import(PathBuf::from("/dir/m1.ts"), PathBuf::from("/dir/m0.ts"));
+            (index, uris)
+        };
+
+        let (batched, uris) = build();
+        let built = batched.generation();
+        let uri_refs: Vec<&str> = uris.iter().map(|u| u.as_str()).collect();
+        batched.remove_files(&uri_refs);
+
+        let (individual, _) = build();
+        for uri in &uris {
+            individual.remove_file(uri);
+        }
+
+        for index in [&batched, &individual] {
+            assert!(index.symbols.is_empty());
+            assert!(index.symbols.files().is_empty());
+            assert!(index.call_graph.is_empty());
+            assert!(index.type_hierarchy.supertypes.is_empty());
+            assert!(index.dependencies.imports.is_empty());
+        }
+        assert!(batched.generation() > built);
+    }
+
+    #[test]
+    fn test_direct_writes_bump_generation() {
+        let index = ProjectIndex::new();
+        let mut last = index.generation();
+        let mut advanced = |index: &ProjectIndex| {
+            let now = index.generation();
+            let changed = now > last;
+            last = now;
+            changed
+        };
+
+        let id = index.symbols.add_symbol(named_symbol("render"));
+        assert!(advanced(&index));
+        index.call_graph.add_call(CallSite {
+            caller: id,
+            callee: id,
+            location: crate::make_location("file:///big.ts", Range::from_coords(1, 0, 1, 8), Range::from_coords(1, 0, 1, 6)),
+            call_type: CallType::Direct,
+        });
+        assert!(advanced(&index));
+        index.type_hierarchy.add_extends(id, id);
+        assert!(advanced(&index));
+        index.dependencies.add_import(PathBuf::from("/a.ts"), PathBuf::from("/b.ts"));
+        assert!(advanced(&index));
+    }
+
+    #[test]
+    fn test_update_after_a_panicking_update() {
+        let index = Arc::new(ProjectIndex::new());
+        let poisoner = Arc::clone(&index);
+        let panicked = std::thread::spawn(move || {
+            let _update = poisoner.begin_update();
+            panic!("adapter bug");
+        })
+        .join();
+        assert!(panicked.is_err());
+
+        // The lock is poisoned, but the index is still usable
+        index.remove_file("file:///a.ts");
+        let snapshot = index.snapshot();
+        assert_eq!(snapshot.generation(), index.generation());
+    }
+
+    #[test]
+    fn test_remove_file_leaves_no_dangling_edges() {
+        let index = ProjectIndex::new();
+        let symbol_in = |uri: &str, name: &str, kind: SymbolKind| {
+            let location = crate::make_location(
+                uri,
+                Range::from_coords(0, 0, 5, 0),
+                Range::from_coords(0, 0, 0, 5),
+            );
+            crate::SymbolBuilder::new(name, kind, location).build()
+        };
+
+        let helper = index
+            .symbols
+            .add_symbol(symbol_in("file:///a.ts", "helper", SymbolKind::Function));
+        let base = index
+            .symbols
+            .add_symbol(symbol_in("file:///a.ts", "Base", SymbolKind::Class));
+        let main = index
+            .symbols
+            .add_symbol(symbol_in("file:///b.ts", "main", SymbolKind::Function));
+        let derived = index
+            .symbols
+            .add_symbol(symbol_in("file:///b.ts", "Derived", SymbolKind::Class));
+
+        // b.ts calls into a.ts; the call site lives in b.ts
+        index.call_graph.add_call(CallSite {
+            caller: main,
+            callee: helper,
+            location: crate::make_location(
+                "file:///b.ts",
+                Range::from_coords(1, 4, 1, 12),
+                Range::from_coords(1, 4, 1, 10),
+            ),
+            call_type: CallType::Direct,
+        });
+        index.type_hierarchy.add_extends(derived, base);
+        index.type_hierarchy.add_implements(derived, base);
+
+        index.remove_file("file:///a.ts");
+
+        let removed = [helper, base];
+        let edges = index.call_graph.edges.read().unwrap();
+        assert!(removed.iter().all(|id| !edges.callers.contains_key(id) && !edges.callees.contains_key(id)));
+        assert!(edges
+            .sites
+            .iter()
+            .all(|c| !removed.contains(&c.caller) && !removed.contains(&c.callee)));
+        drop(edges);
+        assert!(index.call_graph.get_callees(main).is_empty());
+        assert!(index.call_graph.is_empty());
+
+        assert!(index.type_hierarchy.get_supertypes(derived).is_empty());
+        assert!(index.type_hierarchy.get_interfaces(derived).is_empty());
+        assert!(index.type_hierarchy.get_subtypes(base).is_empty());
+        assert!(index.type_hierarchy.supertypes.is_empty());
+        assert!(index.type_hierarchy.implementors.is_empty());
+
+        assert!(index.symbols.get(main).is_some());
+        assert!(index.symbols.get(helper).is_none());
+    }
+
+    #[test]
+    fn test_type_hierarchy_dedupes_edges() {
+        let hierarchy = TypeHierarchy::new();
+        let base = SymbolId::new();
+        let derived = SymbolId::new();
+        let interface = SymbolId::new();
+
+        hierarchy.add_extends(derived, base);
+        hierarchy.add_extends(derived, base);
+        hierarchy.add_implements(derived, interface);
+        hierarchy.add_implements(derived, interface);
+
+        assert_eq!(hierarchy.get_supertypes(derived), vec![base]);
+        assert_eq!(hierarchy.get_subtypes(base), vec![derived]);
+        assert_eq!(hierarchy.get_interfaces(derived), vec![interface]);
+        assert_eq!(hierarchy.get_implementors(interface), vec![derived]);
+    }
+
+    #[test]
+    fn test_overloads_share_a_qualified_name() {
+        use crate::adapter::LanguageAdapter;
+        use crate::java_adapter::JavaAdapter;
+
+        let src = "class Calc {\n    int add(int a, int b) { return a + b; }\n    double add(double a, double b) { return a + b; }\n}\n";
+        let adapter = JavaAdapter::new().unwrap();
+        let table = SymbolTable::new();
+        for uri in ["file:///Calc.java", "file:///other/Calc.java"] {
+            for symbol in adapter.analyze(uri, src).symbols {
+                table.add_symbol(symbol);
+            }
+        }
+
+        let overloads = table.find_all_by_qualified_name("Calc.add");
+        assert_eq!(overloads.len(), 4);
+        assert_ne!(overloads[0].location.range, overloads[1].location.range);
+        assert_eq!(table.find_by_qualified_name("Calc.add").unwrap().id, overloads[0].id);
+
+        // Removing one file keeps the other file's overloads
+        table.remove_file("file:///Calc.java");
+        let remaining = table.find_all_by_qualified_name("Calc.add");
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|s| s.location.uri == "file:///other/Calc.java"));
+
+        table.remove_file("file:///other/Calc.java");
+        assert!(table.qualified_name_index.is_empty());
+    }
+
+    #[test]
+    fn test_find_at_position_with_ancestors() {
+        use crate::adapter::LanguageAdapter;
+        use crate::python_adapter::PythonAdapter;
+
+        let uri = "file:///repo.py";
+        let src = "class Repo:\n    def load(self):\n        return 1\n\ndef main():\n    pass\n";
+        let symbols = PythonAdapter::new().unwrap().analyze(uri, src).symbols;
+        let table = SymbolTable::new();
+        for symbol in symbols.clone() {
+            table.add_symbol(symbol);
+        }
+
+        let names = |table: &SymbolTable, line, column| -> Vec<String> {
+            table
+                .find_at_position_with_ancestors(uri, Position { line, column })
+                .into_iter()
+                .map(|s| s.name)
+                .collect()
+        };
+        assert_eq!(names(&table, 2, 8), vec!["load", "Repo"]);
+        assert_eq!(names(&table, 0, 6), vec!["Repo"]);
+        assert_eq!(names(&table, 5, 4), vec!["main"]);
+        assert!(names(&table, 3, 0).is_empty());
+
+        // A parent cycle ends the chain instead of looping
+        let load_id = symbols.iter().find(|s| s.name == "load").unwrap().id;
+        let looped = SymbolTable::new();
+        for mut symbol in symbols {
+            if symbol.name == "Repo" {
+                symbol.parent = Some(load_id);
+            }
+            looped.add_symbol(symbol);
+        }
+        assert_eq!(names(&looped, 2, 8), vec!["load", "Repo"]);
+    }
+
+    #[test]
+    fn test_find_by_attribute() {
+        use crate::adapter::LanguageAdapter;
+        use crate::rust_adapter::RustAdapter;
+
+        let src = r#"
+#[get("/users")]
+fn list_users() {}
+
+#[actix_web::post("/users")]
+fn create_user() {}
+
+#[inline]
+fn helper() {}
+
+fn plain() {}
+"#;
+        let index = ProjectIndex::new();
+        for symbol in RustAdapter::new().unwrap().analyze("file:///routes.rs", src).symbols {
+            index.symbols.add_symbol(symbol);
+        }
+
+        let names = |attr: &str| -> Vec<String> {
+            index.find_by_attribute(attr).into_iter().map(|s| s.name).collect()
+        };
+        assert_eq!(names("get"), vec!["list_users"]);
+        assert_eq!(names("post"), vec!["create_user"]);
+        assert_eq!(names("actix_web::post"), vec!["create_user"]);
+        assert_eq!(names("inline"), vec!["helper"]);
+        assert!(names("route").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_alias_follows_chains() {
+        use crate::adapter::LanguageAdapter;
+        use crate::rust_adapter::RustAdapter;
+
+        let src = "pub struct User;\npub type Account = User;\npub type Member = Account;\npub type UserId = u64;\npub type Users = Vec<Member>;\n";
+        let index = ProjectIndex::new();
+        for symbol in RustAdapter::new().unwrap().analyze("file:///model.rs", src).symbols {
+            index.symbols.add_symbol(symbol);
+        }
+        let by_name = |name: &str| index.symbols.find_by_name(name).remove(0);
+
+        assert_eq!(by_name("Member").alias_target.as_deref(), Some("Account"));
+        assert_eq!(by_name("User").alias_target, None);
+        let target = |name: &str| index.resolve_alias(&by_name(name)).map(|s| s.name);
+        assert_eq!(target("Account").as_deref(), Some("User"));
+        assert_eq!(target("Member").as_deref(), Some("User"));
+        assert_eq!(target("UserId"), None);
+        assert_eq!(target("User"), None);
+
+        assert_eq!(alias_base_name("Vec<Member>"), Some("Vec"));
+        assert_eq!(alias_base_name("&'a mut geo::Point"), Some("Point"));
+        assert_eq!(alias_base_name("struct Node"), Some("Node"));
+        assert_eq!(alias_base_name("string | null"), None);
+        assert_eq!(alias_base_name("{ id: string }"), None);
+    }
+
+    #[test]
+    fn test_qualified_lookup_ignores_separator() {
+        use crate::adapter::LanguageAdapter;
+        use crate::python_adapter::PythonAdapter;
+        use crate::rust_adapter::RustAdapter;
+
+        let table = SymbolTable::new();
+        let rust_src = "mod a {\n    mod b {\n        fn c() {}\n    }\n}\n";
+        for symbol in RustAdapter::new().unwrap().analyze("file:///a.rs", rust_src).symbols {
+            table.add_symbol(symbol);
+        }
+        let python_src = "class a:\n    class b:\n        def c(self):\n            pass\n";
+        for symbol in PythonAdapter::new().unwrap().analyze("file:///a.py", python_src).symbols {
+            table.add_symbol(symbol);
+        }
+
+        let mut uris: Vec<_> = table
+            .find_by_qualified_parts(&["a", "b", "c"])
+            .into_iter()
+            .map(|s| s.location.uri)
+            .collect();
+        uris.sort();
+        assert_eq!(uris, vec!["file:///a.py", "file:///a.rs"]);
+        assert_eq!(table.find_all_by_qualified_name("a::b::c").len(), 2);
+        assert_eq!(table.find_all_by_qualified_name("a.b.c").len(), 2);
+
+        let rust_c = table.find_by_qualified_name("a.b.c").unwrap();
+        assert_eq!(rust_c.qualified_name_parts(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        use crate::adapter::LanguageAdapter;
+        use crate::rust_adapter::RustAdapter;
+
+        let src = "mod geo {\n    pub struct Point;\n    impl Point {\n        pub fn norm(&self) -> f64 { 0.0 }\n    }\n}\n";
+        let adapter = RustAdapter::new().unwrap();
+        let table = SymbolTable::new();
+        for symbol in adapter.analyze("file:///geo.rs", src).symbols {
+            table.add_symbol(symbol);
+        }
+
+        let point = table.find_by_name("Point").pop().unwrap();
+        let descriptor = point.descriptor();
+        assert_eq!(descriptor, "file:///geo.rs#geo::Point:Struct");
+        assert_eq!(table.find_by_descriptor(&descriptor).unwrap().id, point.id);
+
+        assert!(table.find_by_descriptor("file:///geo.rs#geo::missing:Function").is_none());
+        assert!(table.find_by_descriptor("no-separator").is_none());
+    }
+
+    #[test]
+    fn test_search_iter_is_lazy() {
+        let table = SymbolTable::new();
+        for i in 0..50 {
+            let location = SymbolLocation {
+                uri: "file:///many.ts".to_string(),
+                range: Range::default(),
+                selection_range: Range::default(),
+            };
+            table.add_symbol(crate::adapter::SymbolBuilder::new(format!("handler{}", i), SymbolKind::Function, location).build());
+        }
+
+        let mut matches = table.search_iter("HANDLER");
+        let first: Vec<_> = matches.by_ref().take(3).collect();
+        assert_eq!(first.len(), 3);
+        // The rest are still pending rather than already collected
+        assert_eq!(matches.count(), 47);
+        assert_eq!(table.search("handler").len(), 50);
+    }
 }
@@ -4,40 +4,31 @@
 //! Extracts symbols, imports, exports, and call relationships.
 
 use crate::adapter::{
-    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter,
-    SymbolBuilder, TypeRelation, make_location,
+    AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter, NestingDepth, ParserPool,
+    ReceiverType, SymbolBuilder, TypeRelation, ancestors, make_location,
 };
+use crate::comments::attach_leading_docs;
 use crate::symbol_table::{SymbolId, TypeInfo, Visibility};
 use logos_core::{Position, Range, SymbolKind};
+use logos_parser::LanguageId;
 use std::path::Path;
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Tree};
 
 /// TypeScript/JavaScript language adapter
 pub struct TypeScriptAdapter {
-    parser: std::sync::Mutex<Parser>,
+    parsers: ParserPool,
 }
 
 impl TypeScriptAdapter {
     pub fn new() -> Result<Self, String> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_typescript::LANGUAGE_TSX.into())
+        let parsers = ParserPool::new(tree_sitter_typescript::LANGUAGE_TSX.into())
             .map_err(|e| format!("Failed to set TypeScript language: {}", e))?;
 
-        Ok(Self {
-            parser: std::sync::Mutex::new(parser),
-        })
+        Ok(Self { parsers })
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
-        parser.parse(source, None)
-    }
-}
-
-impl Default for TypeScriptAdapter {
-    fn default() -> Self {
-        Self::new().expect("Failed to create TypeScript adapter")
+        self.parsers.parse(source)
     }
 }
 
@@ -60,11 +51,14 @@ impl LanguageAdapter for TypeScriptAdapter {
             uri: uri.to_string(),
             source,
             result: AnalysisResult::default(),
+            depth: NestingDepth::default(),
             scope_stack: Vec::new(),
             is_exported: false,
         };
 
         analyze_node(&tree.root_node(), &mut context);
+        context.depth.report(&mut context.result);
+        attach_leading_docs(&mut context.result.symbols, source, LanguageId::TypeScript);
 
         context.result
     }
@@ -108,6 +102,7 @@ struct AnalysisContext<'a> {
     uri: String,
     source: &'a str,
     result: AnalysisResult,
+    depth: NestingDepth,
     scope_stack: Vec<ScopeInfo>,
     is_exported: bool,
 }
@@ -137,6 +132,14 @@ impl<'a> AnalysisContext<'a> {
 }
 
 fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
+    if !ctx.depth.enter() {
+        return;
+    }
+    analyze_node_kind(node, ctx);
+    ctx.depth.leave();
+}
+
+fn analyze_node_kind(node: &Node, ctx: &mut AnalysisContext) {
     match node.kind() {
         // Import statements
         "import_statement" => analyze_import(node, ctx),
@@ -149,7 +152,7 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
         "generator_function_declaration" => analyze_function(node, ctx, false),
 
         // Class declarations
-        "class_declaration" => analyze_class(node, ctx),
+        "class_declaration" => analyze_class(node, ctx, false),
 
         // Interface declarations
         "interface_declaration" => analyze_interface(node, ctx),
@@ -286,7 +289,21 @@ fn analyze_export(node: &Node, ctx: &mut AnalysisContext) {
     }
 
     // Check for default export
-    let is_default = node.children(&mut node.walk()).any(|c| ctx.get_text(&c) == "default");
+    let is_default = node.children(&mut node.walk()).any(|c| c.kind() == "default");
+
+    // `export default <expression>` exports a value rather than a declaration
+    if let Some(value) = node.child_by_field_name("value") {
+        ctx.result.exports.push(ExportInfo {
+            name: "default".to_string(),
+            original_name: (value.kind() == "identifier").then(|| ctx.get_text(&value)),
+            from_module: None,
+            is_type_only: false,
+            is_default: true,
+            location: node_to_range(node),
+        });
+        analyze_node(&value, ctx);
+        return;
+    }
 
     // Set export flag and analyze child declarations
     let was_exported = ctx.is_exported;
@@ -298,27 +315,12 @@ fn analyze_export(node: &Node, ctx: &mut AnalysisContext) {
                 "function_declaration" | "generator_function_declaration" => {
                     analyze_function(&child, ctx, is_default);
                 }
-                "class_declaration" => {
-                    analyze_class(&child, ctx);
-                    if is_default {
-                        // Mark last symbol as default export
-                        if let Some(sym) = ctx.result.symbols.last_mut() {
-                            ctx.result.exports.push(ExportInfo {
-                                name: sym.name.clone(),
-                                original_name: None,
-                                from_module: None,
-                                is_type_only: false,
-                                is_default: true,
-                                location: node_to_range(node),
-                            });
-                        }
-                    }
-                }
+                "class_declaration" => analyze_class(&child, ctx, is_default),
                 "interface_declaration" => analyze_interface(&child, ctx),
                 "type_alias_declaration" => analyze_type_alias(&child, ctx),
                 "enum_declaration" => analyze_enum(&child, ctx),
                 "variable_declaration" | "lexical_declaration" => analyze_variable(&child, ctx),
-                "export_clause" => analyze_export_clause(&child, ctx),
+                "export_clause" => analyze_export_clause(&child, ctx, is_type_only_export(node)),
                 _ => analyze_node(&child, ctx),
             }
         }
@@ -327,9 +329,32 @@ fn analyze_export(node: &Node, ctx: &mut AnalysisContext) {
     ctx.is_exported = was_exported;
 }
 
+/// Whether an export statement is `export type { ... }`
+fn is_type_only_export(node: &Node) -> bool {
+    node.children(&mut node.walk()).any(|c| c.kind() == "type")
+}
+
 fn analyze_reexport(node: &Node, ctx: &mut AnalysisContext, source: &Node) {
     let source_text = ctx.get_text(source);
     let from_module = source_text.trim_matches(|c| c == '"' || c == '\'').to_string();
+    let is_type_only = is_type_only_export(node);
+
+    // `export * from 'module'` has no named child for the star
+    let is_star = node.children(&mut node.walk()).any(|c| c.kind() == "*");
+    let has_clause = node
+        .named_children(&mut node.walk())
+        .any(|c| matches!(c.kind(), "export_clause" | "namespace_export"));
+    if is_star && !has_clause {
+        ctx.result.exports.push(ExportInfo {
+            name: "*".to_string(),
+            original_name: None,
+            from_module: Some(from_module),
+            is_type_only: false,
+            is_default: false,
+            location: node_to_range(node),
+        });
+        return;
+    }
 
     for i in 0..node.named_child_count() {
         if let Some(child) = node.named_child(i) {
@@ -351,7 +376,7 @@ fn analyze_reexport(node: &Node, ctx: &mut AnalysisContext, source: &Node) {
                                         name: export_name,
                                         original_name: if alias.is_some() { Some(original_name) } else { None },
                                         from_module: Some(from_module.clone()),
-                                        is_type_only: false,
+                                        is_type_only,
                                         is_default: false,
                                         location: node_to_range(&spec),
                                     });
@@ -361,10 +386,14 @@ fn analyze_reexport(node: &Node, ctx: &mut AnalysisContext, source: &Node) {
                     }
                 }
                 "namespace_export" => {
-                    // export * from 'module'
+                    // export * as ns from 'module'
+                    let name = child
+                        .named_child(0)
+                        .map(|n| ctx.get_text(&n))
+                        .unwrap_or_else(|| "*".to_string());
                     ctx.result.exports.push(ExportInfo {
-                        name: "*".to_string(),
-                        original_name: None,
+                        name,
+                        original_name: Some("*".to_string()),
                         from_module: Some(from_module.clone()),
                         is_type_only: false,
                         is_default: false,
@@ -377,7 +406,7 @@ fn analyze_reexport(node: &Node, ctx: &mut AnalysisContext, source: &Node) {
     }
 }
 
-fn analyze_export_clause(node: &Node, ctx: &mut AnalysisContext) {
+fn analyze_export_clause(node: &Node, ctx: &mut AnalysisContext, is_type_only: bool) {
     for i in 0..node.named_child_count() {
         if let Some(spec) = node.named_child(i) {
             if spec.kind() == "export_specifier" {
@@ -390,7 +419,7 @@ fn analyze_export_clause(node: &Node, ctx: &mut AnalysisContext) {
                         name: export_name,
                         original_name: if alias.is_some() { Some(original_name) } else { None },
                         from_module: None,
-                        is_type_only: false,
+                        is_type_only,
                         is_default: false,
                         location: node_to_range(&spec),
                     });
@@ -412,23 +441,7 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext, is_default_export: b
         name_node.map(|n| node_to_range(&n)).unwrap_or_else(|| node_to_range(node)),
     );
 
-    // Extract parameters for type info
-    let params = node
-        .child_by_field_name("parameters")
-        .map(|p| ctx.get_text(&p))
-        .unwrap_or_default();
-
-    let return_type = node
-        .child_by_field_name("return_type")
-        .map(|r| ctx.get_text(&r));
-
-    let type_info = TypeInfo {
-        type_expr: format!("{} => {}", params, return_type.as_deref().unwrap_or("void")),
-        nullable: false,
-        type_params: extract_type_params(node, ctx),
-        return_type: return_type.map(|r| Box::new(TypeInfo::simple(r))),
-        param_types: Vec::new(), // TODO: extract individual param types
-    };
+    let type_info = function_type_info(node, ctx);
 
     let symbol = SymbolBuilder::new(name.clone(), SymbolKind::Function, location)
         .type_info(type_info)
@@ -463,7 +476,7 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext, is_default_export: b
     }
 }
 
-fn analyze_class(node: &Node, ctx: &mut AnalysisContext) {
+fn analyze_class(node: &Node, ctx: &mut AnalysisContext, is_default_export: bool) {
     let name_node = node.child_by_field_name("name");
     let name = name_node
         .map(|n| ctx.get_text(&n))
@@ -530,7 +543,7 @@ fn analyze_class(node: &Node, ctx: &mut AnalysisContext) {
             original_name: None,
             from_module: None,
             is_type_only: false,
-            is_default: false,
+            is_default: is_default_export,
             location: node_to_range(node),
         });
     }
@@ -599,6 +612,7 @@ fn analyze_method(node: &Node, ctx: &mut AnalysisContext) {
 
     let symbol = SymbolBuilder::new(name.clone(), SymbolKind::Method, location)
         .parent(ctx.current_scope().map(|s| s.symbol_id).unwrap_or(SymbolId(0)))
+        .type_info(function_type_info(node, ctx))
         .visibility(visibility)
         .qualified_name(ctx.qualified_name(&name))
         .build();
@@ -821,7 +835,7 @@ fn analyze_type_alias(node: &Node, ctx: &mut AnalysisContext) {
         .visibility(if ctx.is_exported { Visibility::Public } else { Visibility::Private });
 
     if let Some(ti) = type_value {
-        builder = builder.type_info(ti);
+        builder = builder.alias_target(ti.type_expr.clone()).type_info(ti);
     }
 
     ctx.result.symbols.push(builder.build());
@@ -925,9 +939,23 @@ fn analyze_variable(node: &Node, ctx: &mut AnalysisContext) {
                     name_node.map(|n| node_to_range(&n)).unwrap_or_else(|| node_to_range(node)),
                 );
 
+                // Use the annotation, the function's own signature, or the
+                // class name for `new Foo(...)`
+                let value = declarator.child_by_field_name("value");
                 let type_info = declarator
                     .child_by_field_name("type")
-                    .map(|t| TypeInfo::simple(ctx.get_text(&t)));
+                    .map(|t| TypeInfo::simple(ctx.get_text(&t)))
+                    .or_else(|| {
+                        value
+                            .filter(|_| kind == SymbolKind::Function)
+                            .map(|v| function_type_info(&v, ctx))
+                    })
+                    .or_else(|| {
+                        value
+                            .filter(|v| v.kind() == "new_expression")
+                            .and_then(|v| v.child_by_field_name("constructor"))
+                            .map(|c| TypeInfo::simple(ctx.get_text(&c)))
+                    });
 
                 let mut builder = SymbolBuilder::new(name.clone(), kind, location)
                     .exported(ctx.is_exported)
@@ -962,6 +990,11 @@ fn analyze_variable(node: &Node, ctx: &mut AnalysisContext) {
                             ctx.scope_stack.pop();
                         }
                     }
+                } else if kind != SymbolKind::Class {
+                    // Initializers such as `new User()` or `load()` contain calls
+                    if let Some(value) = declarator.child_by_field_name("value") {
+                        analyze_node(&value, ctx);
+                    }
                 }
             }
         }
@@ -970,28 +1003,35 @@ fn analyze_variable(node: &Node, ctx: &mut AnalysisContext) {
 
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     if let Some(function) = node.child_by_field_name("function") {
-        let (callee_name, qualified_name) = match function.kind() {
+        let (callee_name, qualified_name, receiver_type) = match function.kind() {
             "identifier" => {
                 let name = ctx.get_text(&function);
-                (name.clone(), None)
+                (name.clone(), None, None)
             }
             "member_expression" => {
-                if let Some(property) = function.child_by_field_name("property") {
+                if let (Some(object), Some(property)) = (
+                    function.child_by_field_name("object"),
+                    function.child_by_field_name("property"),
+                ) {
                     let prop_name = ctx.get_text(&property);
                     let full_name = ctx.get_text(&function);
-                    (prop_name, Some(full_name))
+                    (prop_name, Some(full_name), receiver_type(&object, ctx))
                 } else {
                     return;
                 }
             }
             _ => return,
         };
+        let callee_is_parameter =
+            qualified_name.is_none() && enclosing_parameter(node, &callee_name, ctx).is_some();
 
         ctx.result.calls.push(CallInfo {
             callee_name,
             qualified_name,
             location: node_to_range(node),
             is_constructor: false,
+            receiver_type,
+            callee_is_parameter,
         });
     }
 
@@ -1010,6 +1050,8 @@ fn analyze_new_expression(node: &Node, ctx: &mut AnalysisContext) {
             qualified_name: Some(format!("new {}", callee_name)),
             location: node_to_range(node),
             is_constructor: true,
+            receiver_type: None,
+            callee_is_parameter: false,
         });
     }
 
@@ -1019,6 +1061,106 @@ fn analyze_new_expression(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// The declared type of a call receiver: the enclosing class for `this`,
+/// otherwise the annotation of the parameter or local variable it names
+/// (or the class a `new` initializer constructs)
+fn receiver_type(object: &Node, ctx: &AnalysisContext) -> Option<ReceiverType> {
+    match object.kind() {
+        "this" => {
+            let class = ancestors(object).find(|n| {
+                matches!(n.kind(), "class_declaration" | "abstract_class_declaration" | "class")
+            })?;
+            let name = class.child_by_field_name("name")?;
+            Some(ReceiverType::Named(ctx.get_text(&name)))
+        }
+        "identifier" => {
+            let name = ctx.get_text(object);
+            if let Some(param) = enclosing_parameter(object, &name, ctx) {
+                let annotation = param.child_by_field_name("type")?;
+                return named_type(&annotation, ctx).map(ReceiverType::Named);
+            }
+            let declarator = local_declarator(object, &name, ctx)?;
+            if let Some(annotation) = declarator.child_by_field_name("type") {
+                return named_type(&annotation, ctx).map(ReceiverType::Named);
+            }
+            let value = declarator.child_by_field_name("value")?;
+            let constructor = (value.kind() == "new_expression")
+                .then(|| value.child_by_field_name("constructor"))
+                .flatten()?;
+            named_type(&constructor, ctx).map(ReceiverType::Named)
+        }
+        _ => None,
+    }
+}
+
+/// The parameter called `name` of the innermost function enclosing `node`
+/// that declares one
+fn enclosing_parameter<'a>(node: &Node<'a>, name: &str, ctx: &AnalysisContext) -> Option<Node<'a>> {
+    ancestors(node)
+        .filter(|n| {
+            matches!(
+                n.kind(),
+                "function_declaration"
+                    | "generator_function_declaration"
+                    | "function_expression"
+                    | "generator_function"
+                    | "arrow_function"
+                    | "method_definition"
+            )
+        })
+        .find_map(|function| {
+            // `x => ...` has a bare identifier instead of a parameter list
+            if let Some(param) = function.child_by_field_name("parameter") {
+                return (ctx.get_text(&param) == name).then_some(param);
+            }
+            let params = function.child_by_field_name("parameters")?;
+            let mut cursor = params.walk();
+            let found = params.named_children(&mut cursor).find(|param| {
+                param
+                    .child_by_field_name("pattern")
+                    .is_some_and(|pattern| ctx.get_text(&pattern) == name)
+            });
+            found
+        })
+}
+
+/// The `let`/`const`/`var` declarator of `name` in a block enclosing `node`
+fn local_declarator<'a>(node: &Node<'a>, name: &str, ctx: &AnalysisContext) -> Option<Node<'a>> {
+    ancestors(node)
+        .filter(|n| matches!(n.kind(), "statement_block" | "program"))
+        .find_map(|block| {
+            let mut cursor = block.walk();
+            let declarations: Vec<Node<'a>> = block
+                .named_children(&mut cursor)
+                .filter(|n| matches!(n.kind(), "lexical_declaration" | "variable_declaration"))
+                .collect();
+            declarations.into_iter().find_map(|declaration| {
+                let mut cursor = declaration.walk();
+                let found = declaration.named_children(&mut cursor).find(|d| {
+                    d.kind() == "variable_declarator"
+                        && d.child_by_field_name("name")
+                            .is_some_and(|n| ctx.get_text(&n) == name)
+                });
+                found
+            })
+        })
+}
+
+/// The bare name of a type such as `Shape`, `ns.Shape` or `Map<K, V>`;
+/// `None` for unions, literals and other shapes that name no single type
+fn named_type(node: &Node, ctx: &AnalysisContext) -> Option<String> {
+    match node.kind() {
+        "type_annotation" => node.named_child(0).and_then(|t| named_type(&t, ctx)),
+        "type_identifier" | "identifier" => Some(ctx.get_text(node)),
+        "nested_type_identifier" | "member_expression" => node
+            .child_by_field_name("name")
+            .or_else(|| node.child_by_field_name("property"))
+            .map(|n| ctx.get_text(&n)),
+        "generic_type" => node.child_by_field_name("name").and_then(|n| named_type(&n, ctx)),
+        _ => None,
+    }
+}
+
 fn get_member_visibility(node: &Node, ctx: &AnalysisContext) -> Visibility {
     for i in 0..node.child_count() {
         if let Some(child) = node.child(i) {
@@ -1034,6 +1176,71 @@ fn get_member_visibility(node: &Node, ctx: &AnalysisContext) -> Visibility {
     Visibility::Public
 }
 
+/// Signature of a function-like node: `(params) => ret` plus per-parameter
+/// and return types. Unannotated parameters are `any`, optional and
+/// defaulted parameters are nullable, and a rest parameter's type is
+/// prefixed with `...`; a missing return annotation leaves `return_type`
+/// unset.
+fn function_type_info(node: &Node, ctx: &AnalysisContext) -> TypeInfo {
+    let params_node = node.child_by_field_name("parameters");
+    let params = params_node
+        .map(|p| ctx.get_text(&p))
+        .or_else(|| {
+            // `x => x * 2`: a lone identifier parameter
+            node.child_by_field_name("parameter").map(|p| format!("({})", ctx.get_text(&p)))
+        })
+        .unwrap_or_else(|| "()".to_string());
+
+    let mut param_types = Vec::new();
+    if let Some(params_node) = params_node {
+        for i in 0..params_node.named_child_count() {
+            if let Some(param) = params_node.named_child(i) {
+                if !matches!(param.kind(), "required_parameter" | "optional_parameter") {
+                    continue;
+                }
+                // `this: Window` types the receiver; callers don't pass it
+                if param.child_by_field_name("pattern").is_some_and(|p| p.kind() == "this") {
+                    continue;
+                }
+                let type_expr =
+                    annotated_type(param.child_by_field_name("type"), ctx).unwrap_or_else(|| "any".to_string());
+                let is_rest = param
+                    .child_by_field_name("pattern")
+                    .is_some_and(|p| p.kind() == "rest_pattern");
+                let mut param_type =
+                    TypeInfo::simple(if is_rest { format!("...{}", type_expr) } else { type_expr });
+                // Callers may leave out both `x?: T` and `x: T = v`
+                param_type.nullable = param.kind() == "optional_parameter"
+                    || param.child_by_field_name("value").is_some();
+                param_types.push(param_type);
+            }
+        }
+    } else if node.child_by_field_name("parameter").is_some() {
+        param_types.push(TypeInfo::simple("any"));
+    }
+
+    let return_type = annotated_type(node.child_by_field_name("return_type"), ctx);
+
+    TypeInfo {
+        type_expr: format!("{} => {}", params, return_type.as_deref().unwrap_or("void")),
+        nullable: false,
+        type_params: extract_type_params(node, ctx),
+        return_type: return_type.map(|r| Box::new(TypeInfo::simple(r))),
+        param_types,
+    }
+}
+
+/// The type inside a `: T` annotation
+fn annotated_type(annotation: Option<Node>, ctx: &AnalysisContext) -> Option<String> {
+    let annotation = annotation?;
+    let type_node = if annotation.kind() == "type_annotation" {
+        annotation.named_child(0)?
+    } else {
+        annotation
+    };
+    Some(ctx.get_text(&type_node))
+}
+
 fn extract_type_params(node: &Node, ctx: &AnalysisContext) -> Vec<String> {
     let mut params = Vec::new();
     if let Some(type_params) = node.child_by_field_name("type_parameters") {
@@ -1067,6 +1274,18 @@ fn node_to_range(node: &Node) -> Range {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_very_deep_nesting_is_cut_off() {
+        let adapter = TypeScriptAdapter::new().unwrap();
+        let depth = 10_000;
+        let source = format!("function outer() {{}}\nconst f = {}0;\n", "() => ".repeat(depth));
+        let result = adapter.analyze("file:///deep.ts", &source);
+
+        assert!(result.symbols.iter().any(|s| s.name == "outer"));
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains(&crate::adapter::MAX_NESTING_DEPTH.to_string()));
+    }
+
     #[test]
     fn test_simple_function() {
         let adapter = TypeScriptAdapter::new().unwrap();
@@ -1086,6 +1305,56 @@ export function greet(name: string): string {
         assert_eq!(result.exports[0].name, "greet");
     }
 
+    #[test]
+    fn test_export_forms() {
+        let adapter = TypeScriptAdapter::new().unwrap();
+        let source = r#"
+export function run() {}
+export class Service {}
+export const VERSION = 1;
+export interface Options {}
+const a = 1, b = 2;
+export { a, b as c };
+export type { Options as Opts };
+export default run;
+export { User as Person } from './user';
+export * from './util';
+export * as helpers from './helpers';
+"#;
+        let result = adapter.analyze("file:///test.ts", source);
+        let find = |name: &str| result.exports.iter().find(|e| e.name == name).unwrap();
+
+        for name in ["run", "Service", "VERSION", "a"] {
+            let export = find(name);
+            assert!(!export.is_default && !export.is_type_only && export.from_module.is_none());
+        }
+        assert!(find("Options").is_type_only);
+
+        let aliased = find("c");
+        assert_eq!(aliased.original_name.as_deref(), Some("b"));
+
+        let type_alias = find("Opts");
+        assert!(type_alias.is_type_only);
+        assert_eq!(type_alias.original_name.as_deref(), Some("Options"));
+
+        let default = find("default");
+        assert!(default.is_default);
+        assert_eq!(default.original_name.as_deref(), Some("run"));
+
+        let reexport = find("Person");
+        assert_eq!(reexport.original_name.as_deref(), Some("User"));
+        assert_eq!(reexport.from_module.as_deref(), Some("./user"));
+
+        assert_eq!(find("*").from_module.as_deref(), Some("./util"));
+        assert_eq!(find("helpers").from_module.as_deref(), Some("./helpers"));
+
+        let source = "export default class App {}\n";
+        let result = adapter.analyze("file:///app.ts", source);
+        assert_eq!(result.exports.len(), 1);
+        assert!(result.exports[0].is_default);
+        assert_eq!(result.exports[0].name, "App");
+    }
+
     #[test]
     fn test_class_with_members() {
         let adapter = TypeScriptAdapter::new().unwrap();
@@ -1118,6 +1387,17 @@ export class User {
         assert_eq!(greet_sym.visibility, Visibility::Public);
     }
 
+    #[test]
+    fn test_constructor_calls() {
+        let adapter = TypeScriptAdapter::new().unwrap();
+        let source = "const user = new User('ada');\nsave(user);\n";
+        let result = adapter.analyze("file:///test.js", source);
+        let user = result.calls.iter().find(|c| c.callee_name == "User").unwrap();
+        assert!(user.is_constructor);
+        let save = result.calls.iter().find(|c| c.callee_name == "save").unwrap();
+        assert!(!save.is_constructor);
+    }
+
     #[test]
     fn test_imports() {
         let adapter = TypeScriptAdapter::new().unwrap();
@@ -1168,4 +1448,59 @@ class Labrador implements Dog {
             .expect("Labrador should implement Dog");
         assert!(lab_implements.is_implements);
     }
+
+    #[test]
+    fn ts_function_param_and_return_types() {
+        let adapter = TypeScriptAdapter::new().unwrap();
+        let src = r#"
+function f(x: number, label?: string): string {
+    return label + x;
+}
+
+const double = (n: number): number => n * 2;
+const untyped = y => y;
+function scale(factor: number = 2, ...values: number[]) {}
+
+class Greeter {
+    greet(name: string): void {}
+}
+"#;
+        let result = adapter.analyze("file:///types.ts", src);
+        let type_info = |name: &str| {
+            result.symbols.iter().find(|s| s.name == name).unwrap().type_info.clone().unwrap()
+        };
+
+        let f = type_info("f");
+        let params: Vec<_> = f.param_types.iter().map(|p| (p.type_expr.as_str(), p.nullable)).collect();
+        assert_eq!(params, vec![("number", false), ("string", true)]);
+        assert_eq!(f.return_type.unwrap().type_expr, "string");
+        assert_eq!(f.type_expr, "(x: number, label?: string) => string");
+
+        let double = type_info("double");
+        assert_eq!(double.param_types[0].type_expr, "number");
+        assert_eq!(double.return_type.unwrap().type_expr, "number");
+
+        let untyped = type_info("untyped");
+        assert_eq!(untyped.param_types[0].type_expr, "any");
+        assert!(untyped.return_type.is_none());
+
+        let scale = type_info("scale");
+        let params: Vec<_> = scale.param_types.iter().map(|p| (p.type_expr.as_str(), p.nullable)).collect();
+        assert_eq!(params, vec![("number", true), ("...number[]", false)]);
+
+        let greet = type_info("greet");
+        assert_eq!(greet.param_types[0].type_expr, "string");
+        assert_eq!(greet.return_type.unwrap().type_expr, "void");
+    }
+
+    #[test]
+    fn test_this_parameter_is_not_a_parameter() {
+        let adapter = TypeScriptAdapter::new().unwrap();
+        let src = "function onClick(this: Window, count: number): void {}\n";
+        let result = adapter.analyze("file:///events.ts", src);
+        let type_info = result.symbols[0].type_info.clone().unwrap();
+
+        let params: Vec<_> = type_info.param_types.iter().map(|p| p.type_expr.as_str()).collect();
+        assert_eq!(params, vec!["number"]);
+    }
 }
@@ -120,7 +120,7 @@ fn extract_symbols_from_node(node: &Node, source: &str, symbols: &mut Vec<Symbol
 }
 
 fn find_identifier_info(node: &Node, source: &str) -> Option<(String, crate::Range)> {
-    if node.kind() == "identifier" {
+    if matches!(node.kind(), "identifier" | "field_identifier") {
         return Some((get_node_text(node, source), crate::node_to_range(node)));
     }
 
@@ -139,7 +139,9 @@ fn extract_struct_fields(node: &Node, source: &str, symbols: &mut Vec<Symbol>) {
     for i in 0..node.named_child_count() {
         if let Some(child) = node.named_child(i) {
             if child.kind() == "field_declaration" {
-                if let Some(declarator) = child.child_by_field_name("declarator") {
+                // `int x, y;` declares several fields
+                let mut cursor = child.walk();
+                for declarator in child.children_by_field_name("declarator", &mut cursor) {
                     if let Some((name, sel_range)) = find_identifier_info(&declarator, source) {
                         symbols.push(Symbol::new(
                             name,
@@ -164,9 +164,9 @@ fn extract_class_members(node: &Node, source: &str, symbols: &mut Vec<Symbol>) {
                 "function_definition" | "declaration" => {
                     if let Some(declarator) = child.child_by_field_name("declarator") {
                         if let Some((name, sel_range)) = find_function_name_info(&declarator, source) {
-                            let kind = if child.kind() == "function_definition" {
-                                SymbolKind::Method
-                            } else if declarator.kind() == "function_declarator" {
+                            let kind = if child.kind() == "function_definition"
+                                || declarator.kind() == "function_declarator"
+                            {
                                 SymbolKind::Method
                             } else {
                                 SymbolKind::Field
@@ -121,10 +121,17 @@ fn extract_symbols_from_node(node: &Node, source: &str, symbols: &mut Vec<Symbol
 }
 
 fn extract_struct_fields(node: &Node, source: &str, symbols: &mut Vec<Symbol>) {
-    for i in 0..node.named_child_count() {
-        if let Some(child) = node.named_child(i) {
+    // struct_type wraps the field_declaration_list
+    let fields = match node.named_child(0) {
+        Some(list) if list.kind() == "field_declaration_list" => list,
+        _ => return,
+    };
+    for i in 0..fields.named_child_count() {
+        if let Some(child) = fields.named_child(i) {
             if child.kind() == "field_declaration" {
-                if let Some(name_node) = child.child_by_field_name("name") {
+                // `X, Y int` declares several fields; embedded fields have no name
+                let mut cursor = child.walk();
+                for name_node in child.children_by_field_name("name", &mut cursor) {
                     let name = get_node_text(&name_node, source);
                     let mut symbol = Symbol::new(
                         name,
@@ -9,9 +9,11 @@ pub mod java;
 pub mod javascript;
 pub mod typescript;
 
-use logos_core::{Diagnostic, Position, Range};
+use logos_core::{Diagnostic, Position, Range, Symbol};
 use thiserror::Error;
-use tree_sitter::{Parser, Tree, Node, Language};
+use tree_sitter::{Parser, Node, Language};
+
+pub use tree_sitter::Tree;
 
 /// Parser errors
 #[derive(Debug, Error)]
@@ -38,6 +40,7 @@ pub enum LanguageId {
 }
 
 impl LanguageId {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "python" | "py" => Some(Self::Python),
@@ -152,32 +155,105 @@ impl Default for LanguageParser {
     }
 }
 
-/// Extract diagnostics from parse errors in the tree
+/// Extract symbols from a parsed tree using the extractor for `language`
+pub fn extract_symbols(language: LanguageId, tree: &Tree, source: &str) -> Vec<Symbol> {
+    match language {
+        LanguageId::Python => python::extract_symbols(tree, source),
+        LanguageId::Go => go::extract_symbols(tree, source),
+        LanguageId::Rust => rust_lang::extract_symbols(tree, source),
+        LanguageId::C => c::extract_symbols(tree, source),
+        LanguageId::Cpp => cpp::extract_symbols(tree, source),
+        LanguageId::Java => java::extract_symbols(tree, source),
+        LanguageId::JavaScript => javascript::extract_symbols(tree, source),
+        LanguageId::TypeScript => typescript::extract_symbols(tree, source),
+    }
+}
+
+/// Language keywords for `language`
+pub fn get_keywords(language: LanguageId) -> &'static [&'static str] {
+    match language {
+        LanguageId::Python => python::get_keywords(),
+        LanguageId::Go => go::get_keywords(),
+        LanguageId::Rust => rust_lang::get_keywords(),
+        LanguageId::C => c::get_keywords(),
+        LanguageId::Cpp => cpp::get_keywords(),
+        LanguageId::Java => java::get_keywords(),
+        LanguageId::JavaScript => javascript::get_keywords(),
+        LanguageId::TypeScript => typescript::get_keywords(),
+    }
+}
+
+/// Builtin functions and well-known globals for `language`, where known
+pub fn get_builtins(language: LanguageId) -> &'static [&'static str] {
+    match language {
+        LanguageId::Python => python::get_builtins(),
+        LanguageId::Go => go::get_builtins(),
+        LanguageId::JavaScript | LanguageId::TypeScript => javascript::get_globals(),
+        _ => &[],
+    }
+}
+
+/// Extract diagnostics from parse errors in the tree.
+///
+/// Errors nested inside an `ERROR` node, or overlapping one already
+/// reported, are dropped so one mistake yields one diagnostic.
 pub fn extract_parse_errors(tree: &Tree, source: &str) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
+    if !tree.root_node().has_error() {
+        return diagnostics;
+    }
     let mut cursor = tree.walk();
 
     extract_errors_recursive(&mut cursor, source, &mut diagnostics);
-    diagnostics
+
+    diagnostics.sort_by_key(|d| (d.range.start, d.range.end));
+    let mut deduped: Vec<Diagnostic> = Vec::with_capacity(diagnostics.len());
+    for diagnostic in diagnostics {
+        let overlaps = deduped.last().is_some_and(|last| {
+            last.range.start == diagnostic.range.start || last.range.overlaps(&diagnostic.range)
+        });
+        if !overlaps {
+            deduped.push(diagnostic);
+        }
+    }
+    deduped
 }
 
+/// Longest snippet of source quoted in a syntax error message
+const ERROR_SNIPPET_LEN: usize = 30;
+
 fn extract_errors_recursive(
     cursor: &mut tree_sitter::TreeCursor,
     source: &str,
     diagnostics: &mut Vec<Diagnostic>,
 ) {
     let node = cursor.node();
+    if !node.has_error() {
+        return;
+    }
 
     if node.is_error() || node.is_missing() {
         let range = node_to_range(&node);
         let message = if node.is_missing() {
             format!("Missing {}", node.kind())
         } else {
-            "Syntax error".to_string()
+            let text = source.get(node.byte_range()).unwrap_or("");
+            let line = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+            let snippet: String = line.chars().take(ERROR_SNIPPET_LEN).collect();
+            if snippet.is_empty() {
+                "Syntax error".to_string()
+            } else if snippet.len() < line.len() {
+                format!("Syntax error near '{}...'", snippet)
+            } else {
+                format!("Syntax error near '{}'", snippet)
+            }
         };
         diagnostics.push(
             Diagnostic::error(range, message).with_source("logos-parser".to_string()),
         );
+        if node.is_error() {
+            return;
+        }
     }
 
     if cursor.goto_first_child() {
@@ -191,6 +267,62 @@ fn extract_errors_recursive(
     }
 }
 
+/// Range of the identifier token at `position`, if there is one.
+///
+/// A cursor just past the end of an identifier still selects it. Keywords,
+/// literals, comments and string contents give `None`.
+pub fn identifier_range_at(tree: &Tree, position: Position) -> Option<Range> {
+    let root = tree.root_node();
+    let mut columns = vec![position.column];
+    if position.column > 0 {
+        columns.push(position.column - 1);
+    }
+
+    columns.into_iter().find_map(|column| {
+        let point = tree_sitter::Point::new(position.line as usize, column as usize);
+        let node = root.descendant_for_point_range(point, point)?;
+        is_identifier(&node).then(|| node_to_range(&node))
+    })
+}
+
+/// Call sites in the tree: the called name (the last segment of
+/// `a.b.f()` or `m::f()`) and the range of the whole call
+pub fn extract_calls(tree: &Tree, source: &str) -> Vec<(String, Range)> {
+    let mut calls = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let callee = match node.kind() {
+            "call" | "call_expression" => node.child_by_field_name("function"),
+            "method_invocation" => node.child_by_field_name("name"),
+            _ => None,
+        };
+        if let Some(name) = callee.and_then(|callee| callee_name_node(callee)) {
+            calls.push((source[name.byte_range()].to_string(), node_to_range(&node)));
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    calls.sort_by_key(|(_, range)| (range.start, range.end));
+    calls
+}
+
+/// The identifier naming the called function in a callee expression
+fn callee_name_node(node: Node) -> Option<Node> {
+    if is_identifier(&node) {
+        return Some(node);
+    }
+    // `obj.f`, `obj->f`, `m::f`, `f::<T>`
+    ["property", "field", "attribute", "name", "function"]
+        .iter()
+        .find_map(|field| node.child_by_field_name(field))
+        .and_then(callee_name_node)
+}
+
+/// Whether a node is an identifier token in any supported grammar
+fn is_identifier(node: &Node) -> bool {
+    node.is_named() && node.child_count() == 0 && node.kind().ends_with("identifier")
+}
+
 /// Convert tree-sitter node range to logos Range
 pub fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
@@ -236,4 +368,56 @@ mod tests {
         let tree = parser.parse("def hello(): pass", None).unwrap();
         assert!(!tree.root_node().has_error());
     }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_extract_calls() {
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Rust).unwrap();
+        let source = "fn main() {\n    helper(config::load());\n    self.run();\n}\n";
+        let tree = parser.parse(source, None).unwrap();
+
+        let calls = extract_calls(&tree, source);
+        let names: Vec<&str> = calls.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["helper", "load", "run"]);
+        assert_eq!(calls[0].1, Range::from_coords(1, 4, 1, 26));
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_identifier_range_at() {
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Rust).unwrap();
+
+        let source = "fn total(items: &[u32]) -> u32 {\n    items.len() as u32 // items\n}\n";
+        let tree = parser.parse(source, None).unwrap();
+
+        // Inside and just after `total`
+        assert_eq!(identifier_range_at(&tree, Position::new(0, 4)), Some(Range::from_coords(0, 3, 0, 8)));
+        assert_eq!(identifier_range_at(&tree, Position::new(0, 8)), Some(Range::from_coords(0, 3, 0, 8)));
+        // Field access selects only the field name
+        assert_eq!(identifier_range_at(&tree, Position::new(1, 11)), Some(Range::from_coords(1, 10, 1, 13)));
+        // `fn` keyword, primitive type, comment
+        assert_eq!(identifier_range_at(&tree, Position::new(0, 0)), None);
+        assert_eq!(identifier_range_at(&tree, Position::new(1, 21)), None);
+        assert_eq!(identifier_range_at(&tree, Position::new(1, 27)), None);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_parse_errors_are_deduplicated() {
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Python).unwrap();
+
+        let source = "def ok():\n    pass\n\nx = (1 +\n";
+        let tree = parser.parse(source, None).unwrap();
+        let errors = extract_parse_errors(&tree, source);
+
+        assert!(!errors.is_empty());
+        for pair in errors.windows(2) {
+            assert!(!pair[0].range.overlaps(&pair[1].range));
+            assert_ne!(pair[0].range.start, pair[1].range.start);
+        }
+        assert!(errors.iter().all(|d| d.range.start.line >= 3));
+    }
 }
@@ -111,6 +111,25 @@ fn extract_symbols_from_node(
                 }
             }
         }
+        "named_expression" => {
+            // `if (n := len(items)) > 10:` binds `n` in the enclosing
+            // function or module, even inside a comprehension. Rebinding a
+            // name already declared here adds nothing.
+            if let Some(name_node) = node.child_by_field_name("name") {
+                let name = get_node_text(&name_node, source);
+                if !symbols.iter().any(|s| s.name == name) {
+                    symbols.push(Symbol::new(
+                        name,
+                        SymbolKind::Variable,
+                        node_to_range(node),
+                        node_to_range(&name_node),
+                    ));
+                }
+            }
+            if let Some(value) = node.child_by_field_name("value") {
+                extract_symbols_from_node(&value, source, symbols, parent);
+            }
+        }
         _ => {
             // Recurse into children for other node types
             for i in 0..node.named_child_count() {
@@ -207,4 +226,24 @@ class MyClass:
         assert_eq!(symbols[0].kind, SymbolKind::Class);
         assert_eq!(symbols[0].children.len(), 2);
     }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_extract_walrus_bindings() {
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Python).unwrap();
+
+        let source = r#"
+def scan(lines):
+    while (line := next(lines)):
+        if (m := match(line)) or (m := fallback(line)):
+            return [y for x in m if (y := x.strip())]
+"#;
+        let tree = parser.parse(source, None).unwrap();
+        let symbols = extract_symbols(&tree, source);
+
+        let names: Vec<&str> = symbols[0].children.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["line", "m", "y"]);
+        assert!(symbols[0].children.iter().all(|s| s.kind == SymbolKind::Variable));
+    }
 }
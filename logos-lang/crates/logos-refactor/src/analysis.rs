@@ -163,18 +163,13 @@ pub fn has_balanced_delimiters(text: &str) -> bool {
                     string_char = ch;
                 }
                 '(' | '[' | '{' => stack.push(ch),
-                ')' => {
-                    if stack.pop() != Some('(') {
-                        return false;
-                    }
-                }
-                ']' => {
-                    if stack.pop() != Some('[') {
-                        return false;
-                    }
-                }
-                '}' => {
-                    if stack.pop() != Some('{') {
+                ')' | ']' | '}' => {
+                    let open = match ch {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    if stack.pop() != Some(open) {
                         return false;
                     }
                 }
@@ -188,77 +183,167 @@ pub fn has_balanced_delimiters(text: &str) -> bool {
 }
 
 /// Find all variable references in a code snippet
+///
+/// Identifiers inside strings and comments, and property names after a
+/// `.`, are not variable references.
 pub fn find_variable_references(text: &str, language: LanguageId) -> HashSet<String> {
-    let mut variables = HashSet::new();
-
-    // Basic identifier pattern (works for most languages)
-    let pattern = match language {
-        LanguageId::Python => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        LanguageId::Rust => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        LanguageId::Go => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        _ => r"\b([a-zA-Z_$][a-zA-Z0-9_$]*)\b",
-    };
-
-    let re = Regex::new(pattern).unwrap();
+    let code = mask_strings_and_comments(text, language);
 
     // Language keywords to exclude
     let keywords = get_language_keywords(language);
 
-    for cap in re.captures_iter(text) {
-        let name = cap.get(1).unwrap().as_str();
-        if !keywords.contains(name) && !is_builtin(name, language) {
-            variables.insert(name.to_string());
+    identifier_tokens(&code, language)
+        .into_iter()
+        .filter(|(start, _)| !is_member_access(&code, *start))
+        .map(|(_, name)| name)
+        .filter(|name| !keywords.contains(name) && !is_builtin(name, language))
+        .map(String::from)
+        .collect()
+}
+
+/// Split code into identifier tokens with their byte offsets. Tokens are
+/// whole identifiers, so `x` never matches inside `max`.
+pub(crate) fn identifier_tokens(code: &str, language: LanguageId) -> Vec<(usize, &str)> {
+    let allows_dollar = !matches!(
+        language,
+        LanguageId::Python | LanguageId::Rust | LanguageId::Go
+    );
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || (allows_dollar && c == '$');
+
+    let mut tokens = Vec::new();
+    let mut chars = code.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !is_ident(c) {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, next)) = chars.peek() {
+            if !is_ident(next) {
+                break;
+            }
+            end = i + next.len_utf8();
+            chars.next();
+        }
+        // Skip numeric literals such as `10` or `0x1f`
+        if !c.is_ascii_digit() {
+            tokens.push((start, &code[start..end]));
         }
     }
+    tokens
+}
 
-    variables
+/// Whether the identifier at `start` is a property access (`obj.name`)
+pub(crate) fn is_member_access(code: &str, start: usize) -> bool {
+    let before = code[..start].trim_end();
+    before.ends_with('.') && !before.ends_with("..")
+}
+
+/// Replace string literal and comment contents with spaces, keeping line
+/// breaks so that line numbers still match
+pub(crate) fn mask_strings_and_comments(text: &str, language: LanguageId) -> String {
+    let hash_comments = language == LanguageId::Python;
+    let chars: Vec<char> = text.chars().collect();
+    let mut masked = String::with_capacity(text.len());
+    let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // Line comments
+        if (hash_comments && c == '#') || (!hash_comments && c == '/' && next == Some('/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                masked.push(' ');
+                i += 1;
+            }
+            continue;
+        }
+
+        // Block comments
+        if !hash_comments && c == '/' && next == Some('*') {
+            masked.push_str("  ");
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                masked.push(blank(chars[i]));
+                i += 1;
+            }
+            if i < chars.len() {
+                masked.push_str("  ");
+                i += 2;
+            }
+            continue;
+        }
+
+        // Rust lifetimes and labels look like unterminated char literals
+        let is_quote = match c {
+            '"' | '`' => true,
+            '\'' if language == LanguageId::Rust => {
+                chars.get(i + 2) == Some(&'\'') || next == Some('\\')
+            }
+            '\'' => true,
+            _ => false,
+        };
+        if !is_quote {
+            masked.push(c);
+            i += 1;
+            continue;
+        }
+
+        // Python triple-quoted strings
+        let triple = hash_comments && next == Some(c) && chars.get(i + 2) == Some(&c);
+        let delimiter_len = if triple { 3 } else { 1 };
+        masked.push(c);
+        for _ in 1..delimiter_len {
+            masked.push(c);
+        }
+        i += delimiter_len;
+
+        while i < chars.len() {
+            if chars[i] == '\\' {
+                masked.push(' ');
+                if let Some(&escaped) = chars.get(i + 1) {
+                    masked.push(blank(escaped));
+                }
+                i += 2;
+                continue;
+            }
+            let closes = chars[i] == c
+                && (!triple || (chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c)));
+            if closes {
+                for _ in 0..delimiter_len {
+                    masked.push(c);
+                }
+                i += delimiter_len;
+                break;
+            }
+            // Only template literals and triple-quoted strings span lines
+            if chars[i] == '\n' && c != '`' && !triple {
+                break;
+            }
+            masked.push(blank(chars[i]));
+            i += 1;
+        }
+    }
+
+    masked
 }
 
 /// Get keywords for a language
 fn get_language_keywords(language: LanguageId) -> HashSet<&'static str> {
-    match language {
-        LanguageId::Python => [
-            "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
-            "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
-            "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
-            "raise", "return", "try", "while", "with", "yield",
-        ]
-        .iter()
-        .cloned()
-        .collect(),
-        LanguageId::JavaScript | LanguageId::TypeScript => [
-            "break", "case", "catch", "class", "const", "continue", "debugger", "default",
-            "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for",
-            "function", "if", "import", "in", "instanceof", "new", "null", "return", "super",
-            "switch", "this", "throw", "true", "try", "typeof", "undefined", "var", "void",
-            "while", "with", "yield", "let", "static", "async", "await",
-        ]
-        .iter()
-        .cloned()
-        .collect(),
-        LanguageId::Rust => [
-            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
-            "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
-            "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
-            "super", "trait", "true", "type", "unsafe", "use", "where", "while",
-        ]
-        .iter()
-        .cloned()
-        .collect(),
-        LanguageId::Go => [
-            "break", "case", "chan", "const", "continue", "default", "defer", "else",
-            "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map",
-            "package", "range", "return", "select", "struct", "switch", "type", "var",
-        ]
-        .iter()
-        .cloned()
-        .collect(),
-        _ => HashSet::new(),
-    }
+    logos_parser::get_keywords(language).iter().copied().collect()
+}
+
+/// Whether a name is a keyword or builtin rather than a user variable
+pub(crate) fn is_keyword_or_builtin(name: &str, language: LanguageId) -> bool {
+    logos_parser::get_keywords(language).contains(&name) || is_builtin(name, language)
 }
 
 /// Check if a name is a builtin
 fn is_builtin(name: &str, language: LanguageId) -> bool {
+    if logos_parser::get_builtins(language).contains(&name) {
+        return true;
+    }
     match language {
         LanguageId::Python => {
             matches!(
@@ -483,4 +568,16 @@ mod tests {
             "name"
         );
     }
+
+    #[test]
+    fn test_variable_references_skip_strings_comments_and_members() {
+        let code = "let total = max + \"x y\"; // uses z\nobj.count = total;";
+        let vars = find_variable_references(code, LanguageId::JavaScript);
+        assert!(vars.contains("total"));
+        assert!(vars.contains("max"));
+        assert!(vars.contains("obj"));
+        assert!(!vars.contains("x"));
+        assert!(!vars.contains("z"));
+        assert!(!vars.contains("count"));
+    }
 }
@@ -7,12 +7,16 @@
 //! 3. Checking for control flow issues (return, break, continue)
 //! 4. Generating the new method with appropriate parameters and return type
 
-use crate::analysis::{find_variable_references, has_balanced_delimiters};
+use crate::analysis::{
+    find_variable_references, has_balanced_delimiters, identifier_tokens, is_keyword_or_builtin,
+    is_member_access, mask_strings_and_comments,
+};
 use crate::{RefactorContext, RefactorError, RefactorResult, TextEdit};
-use logos_core::{Position, Range};
-use logos_parser::LanguageId;
+use logos_core::{LineIndex, Position, Range};
+use logos_parser::{LanguageId, LanguageParser};
 use regex::Regex;
 use std::collections::HashSet;
+use tree_sitter::{Node, Point};
 
 /// Information about variables used in the extracted code
 #[derive(Debug, Clone)]
@@ -32,9 +36,9 @@ pub struct ExtractMethodAnalysis {
     pub return_variables: Vec<String>,
     /// Whether the selection contains return statements
     pub has_return: bool,
-    /// Whether the selection contains break statements
+    /// Whether the selection contains a `break` whose loop isn't selected
     pub has_break: bool,
-    /// Whether the selection contains continue statements
+    /// Whether the selection contains a `continue` whose loop isn't selected
     pub has_continue: bool,
     /// Issues that prevent extraction
     pub issues: Vec<String>,
@@ -67,7 +71,7 @@ pub fn can_extract(ctx: &RefactorContext) -> Result<bool, RefactorError> {
 
     if !analysis.can_extract() {
         return Err(RefactorError::ControlFlowIssue(
-            analysis.issues.join(", "),
+            analysis.issues.join("; "),
         ));
     }
 
@@ -86,17 +90,7 @@ pub fn analyze(ctx: &RefactorContext) -> Result<ExtractMethodAnalysis, RefactorE
         issues: Vec::new(),
     };
 
-    // Check for control flow statements
-    analysis.has_return = Regex::new(r"\breturn\b").unwrap().is_match(selected);
-    analysis.has_break = Regex::new(r"\bbreak\b").unwrap().is_match(selected);
-    analysis.has_continue = Regex::new(r"\bcontinue\b").unwrap().is_match(selected);
-
-    if analysis.has_break {
-        analysis.issues.push("Selection contains 'break' statement".to_string());
-    }
-    if analysis.has_continue {
-        analysis.issues.push("Selection contains 'continue' statement".to_string());
-    }
+    check_control_flow(ctx, &mut analysis);
 
     // Find variables used in selection
     let selected_vars = find_variable_references(selected, ctx.language);
@@ -119,6 +113,9 @@ pub fn analyze(ctx: &RefactorContext) -> Result<ExtractMethodAnalysis, RefactorE
     // Determine return variables (modified in selection and used after)
     let modified_vars = find_modified_variables(selected, ctx.language);
     for var in modified_vars {
+        if is_keyword_or_builtin(&var, ctx.language) {
+            continue;
+        }
         if after_vars.contains(&var) && !analysis.parameters.contains(&var) {
             analysis.return_variables.push(var);
         }
@@ -127,74 +124,225 @@ pub fn analyze(ctx: &RefactorContext) -> Result<ExtractMethodAnalysis, RefactorE
     Ok(analysis)
 }
 
-fn get_text_before(source: &str, selection: Range) -> String {
-    let lines: Vec<&str> = source.lines().collect();
-    let mut result = String::new();
-
-    for (i, line) in lines.iter().enumerate() {
-        if i < selection.start.line as usize {
-            result.push_str(line);
-            result.push('\n');
-        } else if i == selection.start.line as usize {
-            let end = (selection.start.column as usize).min(line.len());
-            result.push_str(&line[..end]);
-            break;
+/// Find `return`, `break` and `continue` in the selection and record the
+/// ones that would jump out of the extracted method: a `break`/`continue`
+/// whose loop (or labeled statement, for `break outer`) isn't selected, and
+/// a `return` from a function whose body is only partly selected
+fn check_control_flow(ctx: &RefactorContext, analysis: &mut ExtractMethodAnalysis) {
+    let mut parser = LanguageParser::new();
+    let tree = match parser
+        .set_language(ctx.language)
+        .and_then(|_| parser.parse(ctx.source, None))
+    {
+        Ok(tree) => tree,
+        Err(_) => return check_control_flow_by_keyword(ctx, analysis),
+    };
+
+    let index = LineIndex::new(ctx.source);
+    let start = index.offset_of(ctx.selection.start).unwrap_or(0);
+    let end = index.offset_of(ctx.selection.end).unwrap_or(ctx.source.len());
+    let selected = |node: Node| start <= node.start_byte() && node.end_byte() <= end;
+
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if node.end_byte() <= start || node.start_byte() >= end {
+            continue;
+        }
+        let line = node.start_position().row + 1;
+        if selected(node) {
+            match node.kind() {
+                "return_statement" | "return_expression" => {
+                    analysis.has_return = true;
+                    let allowed = enclosing(node, is_function_node)
+                        .is_none_or(|function| selected(function) || body_selected(function, &selected));
+                    if !allowed {
+                        analysis.issues.push(format!(
+                            "'return' on line {} would only return from the extracted method",
+                            line
+                        ));
+                    }
+                }
+                "break_statement" | "break_expression"
+                    if jump_target(node, ctx.source, |kind| is_loop_node(kind) || is_switch_node(kind))
+                        .is_some_and(|target| !selected(target)) =>
+                {
+                    analysis.has_break = true;
+                    analysis.issues.push(format!(
+                        "'break' on line {} exits a loop outside the selection",
+                        line
+                    ));
+                }
+                "continue_statement" | "continue_expression"
+                    if jump_target(node, ctx.source, is_loop_node).is_some_and(|target| !selected(target)) =>
+                {
+                    analysis.has_continue = true;
+                    analysis.issues.push(format!(
+                        "'continue' on line {} continues a loop outside the selection",
+                        line
+                    ));
+                }
+                _ => {}
+            }
+        }
+        for i in (0..node.named_child_count()).rev() {
+            if let Some(child) = node.named_child(i) {
+                stack.push(child);
+            }
         }
     }
+}
+
+/// Keyword scan for when the source can't be parsed: any `break` or
+/// `continue` is reported, since their loops can't be located
+fn check_control_flow_by_keyword(ctx: &RefactorContext, analysis: &mut ExtractMethodAnalysis) {
+    let code = mask_strings_and_comments(ctx.selected_text(), ctx.language);
+    analysis.has_return = Regex::new(r"\breturn\b").unwrap().is_match(&code);
+    analysis.has_break = Regex::new(r"\bbreak\b").unwrap().is_match(&code);
+    analysis.has_continue = Regex::new(r"\bcontinue\b").unwrap().is_match(&code);
 
-    result
+    if analysis.has_break {
+        analysis.issues.push("Selection contains 'break' statement".to_string());
+    }
+    if analysis.has_continue {
+        analysis.issues.push("Selection contains 'continue' statement".to_string());
+    }
 }
 
-fn get_text_after(source: &str, selection: Range) -> String {
-    let lines: Vec<&str> = source.lines().collect();
-    let mut result = String::new();
-
-    for (i, line) in lines.iter().enumerate() {
-        if i > selection.end.line as usize {
-            result.push_str(line);
-            result.push('\n');
-        } else if i == selection.end.line as usize {
-            let start = (selection.end.column as usize).min(line.len());
-            result.push_str(&line[start..]);
-            result.push('\n');
+/// The nearest ancestor matching `is_target`, stopping at function
+/// boundaries (a `break` can't reach a loop outside its closure)
+fn enclosing<'t>(node: Node<'t>, is_target: impl Fn(&str) -> bool) -> Option<Node<'t>> {
+    let mut current = node.parent();
+    while let Some(ancestor) = current {
+        if is_target(ancestor.kind()) {
+            return Some(ancestor);
+        }
+        if is_function_node(ancestor.kind()) {
+            return None;
         }
+        current = ancestor.parent();
     }
+    None
+}
 
-    result
+/// Where a `break` or `continue` jumps to: the statement or loop carrying
+/// its label, or else the nearest ancestor matching `is_target`
+fn jump_target<'t>(node: Node<'t>, source: &str, is_target: impl Fn(&str) -> bool) -> Option<Node<'t>> {
+    let Some(label) = jump_label(node) else {
+        return enclosing(node, is_target);
+    };
+    let label = &source[label.byte_range()];
+
+    let mut current = node.parent();
+    while let Some(ancestor) = current {
+        let own_label = match ancestor.kind() {
+            "labeled_statement" => ancestor
+                .child_by_field_name("label")
+                .or_else(|| ancestor.named_child(0)),
+            // Rust puts `'outer:` on the loop or block itself
+            kind if is_loop_node(kind) || kind == "block" => {
+                let mut cursor = ancestor.walk();
+                let found = ancestor.named_children(&mut cursor).find(|c| c.kind() == "label");
+                found
+            }
+            _ => None,
+        };
+        if own_label.is_some_and(|own| &source[own.byte_range()] == label) {
+            return Some(ancestor);
+        }
+        if is_function_node(ancestor.kind()) {
+            return None;
+        }
+        current = ancestor.parent();
+    }
+    None
 }
 
-/// Find variables that are modified (assigned) in the code
-fn find_modified_variables(text: &str, language: LanguageId) -> HashSet<String> {
-    let mut modified = HashSet::new();
+/// The label of `break outer` / `continue 'rows`, if any. Rust's `break`
+/// may also carry a value, so only its `label` child counts.
+fn jump_label(node: Node) -> Option<Node> {
+    let rust = matches!(node.kind(), "break_expression" | "continue_expression");
+    let mut cursor = node.walk();
+    let label = node.named_children(&mut cursor).find(|child| {
+        if rust {
+            child.kind() == "label"
+        } else {
+            matches!(child.kind(), "statement_identifier" | "identifier" | "label_name")
+        }
+    });
+    label
+}
 
-    // Pattern for assignments: identifier = something (but not ==, ===, etc.)
-    let pattern = match language {
-        LanguageId::Python => r"(\w+)\s*(?<![=!<>])=(?![=])",
-        LanguageId::Go => r"(\w+)\s*:?=",
-        _ => r"(\w+)\s*(?<![=!<>])=(?![=])",
+/// Whether every statement in the function's body is selected
+fn body_selected(function: Node, selected: &impl Fn(Node) -> bool) -> bool {
+    let Some(body) = function.child_by_field_name("body") else {
+        return false;
     };
+    let mut cursor = body.walk();
+    let mut statements = body.named_children(&mut cursor).peekable();
+    statements.peek().is_some() && statements.all(selected)
+}
 
-    if let Ok(re) = Regex::new(pattern) {
-        for cap in re.captures_iter(text) {
-            if let Some(name) = cap.get(1) {
-                modified.insert(name.as_str().to_string());
-            }
-        }
+/// Source text up to the start of the selection
+fn get_text_before(source: &str, selection: Range) -> String {
+    let index = LineIndex::new(source);
+    let end = index.offset_of(selection.start).unwrap_or(source.len());
+    source[..floor_char_boundary(source, end)].to_string()
+}
+
+/// Source text from the end of the selection
+fn get_text_after(source: &str, selection: Range) -> String {
+    let index = LineIndex::new(source);
+    match index.offset_of(selection.end) {
+        Some(start) => source[floor_char_boundary(source, start)..].to_string(),
+        None => String::new(),
+    }
+}
+
+/// Round an offset down to a char boundary so slicing cannot panic
+fn floor_char_boundary(source: &str, mut offset: usize) -> usize {
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
     }
+    offset
+}
 
-    // Also check for increment/decrement
-    let inc_pattern = r"(\w+)\s*(?:\+\+|--)|(?:\+\+|--)\s*(\w+)";
-    if let Ok(re) = Regex::new(inc_pattern) {
-        for cap in re.captures_iter(text) {
-            if let Some(name) = cap.get(1).or_else(|| cap.get(2)) {
-                modified.insert(name.as_str().to_string());
-            }
+/// Find variables that are modified (assigned) in the code
+fn find_modified_variables(text: &str, language: LanguageId) -> HashSet<String> {
+    let code = mask_strings_and_comments(text, language);
+    let mut modified = HashSet::new();
+
+    for (start, name) in identifier_tokens(&code, language) {
+        if is_member_access(&code, start) {
+            continue;
+        }
+        let before = code[..start].trim_end();
+        let after = code[start + name.len()..].trim_start();
+
+        // Assignments, including compound operators like +=, but not
+        // comparisons (==, ===, !=, <=, >=)
+        let is_assignment = ASSIGNMENT_OPERATORS
+            .iter()
+            .any(|op| after.starts_with(op) && !after[op.len()..].starts_with('='));
+
+        // Increment/decrement
+        let is_step = after.starts_with("++")
+            || after.starts_with("--")
+            || before.ends_with("++")
+            || before.ends_with("--");
+
+        if is_assignment || is_step {
+            modified.insert(name.to_string());
         }
     }
 
     modified
 }
 
+/// Assignment operators, longest first
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "**=", "//=", "<<=", ">>=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", ":=", "=",
+];
+
 /// Extract the selection into a new method
 pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResult, RefactorError> {
     can_extract(ctx)?;
@@ -210,8 +358,7 @@ pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResul
         &analysis.parameters,
         &analysis.return_variables,
         analysis.has_return,
-        ctx.language,
-        &indent,
+        ctx,
     );
 
     // Generate the call to the new method
@@ -226,6 +373,10 @@ pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResul
     // Find insertion point for the new method (after current function or at end of file)
     let method_insert_pos = find_method_insertion_point(ctx);
 
+    // Code is generated with `\n`; keep CRLF documents CRLF
+    let method_code = ctx.with_line_endings(&method_code);
+    let call_code = ctx.with_line_endings(&call_code);
+
     // Create edits
     let mut edits = Vec::new();
 
@@ -242,21 +393,21 @@ pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResul
     .with_generated_code(method_code))
 }
 
-/// Generate the new method code
+/// Generate the new method code, indented like the selection's first line
 fn generate_method(
     name: &str,
     body: &str,
     params: &[String],
     return_vars: &[String],
     has_explicit_return: bool,
-    language: LanguageId,
-    base_indent: &str,
+    ctx: &RefactorContext,
 ) -> String {
     let param_list = params.join(", ");
-    let body_indent = format!("{}    ", base_indent);
-    let indented_body = indent_code(body.trim(), &body_indent);
+    let base_indent = &ctx.indentation_at(ctx.selection.start.line);
+    let body_indent = format!("{}{}", base_indent, ctx.indent_unit());
+    let indented_body = indent_code(body.trim(), base_indent, &body_indent);
 
-    match language {
+    match ctx.language {
         LanguageId::Python => {
             let mut code = format!("\n{}def {}({}):\n", base_indent, name, param_list);
             code.push_str(&indented_body);
@@ -401,22 +552,106 @@ fn generate_call(
     }
 }
 
-/// Indent code with the given prefix
-fn indent_code(code: &str, indent: &str) -> String {
+/// Re-indent code from `from` to `to`, keeping any deeper indentation
+/// of nested lines as written
+fn indent_code(code: &str, from: &str, to: &str) -> String {
     code.lines()
         .map(|line| {
             if line.trim().is_empty() {
                 String::new()
             } else {
-                format!("{}{}", indent, line.trim())
+                let relative = line.strip_prefix(from).unwrap_or_else(|| line.trim_start());
+                format!("{}{}", to, relative.trim_end())
             }
         })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-/// Find the insertion point for the new method
+/// Find the insertion point for the new method: the line after the
+/// function enclosing the selection, or the end of the file
 fn find_method_insertion_point(ctx: &RefactorContext) -> Position {
+    let mut parser = LanguageParser::new();
+    let tree = match parser
+        .set_language(ctx.language)
+        .and_then(|_| parser.parse(ctx.source, None))
+    {
+        Ok(tree) => tree,
+        Err(_) => return heuristic_insertion_point(ctx),
+    };
+
+    let start = Point::new(
+        ctx.selection.start.line as usize,
+        ctx.selection.start.column as usize,
+    );
+    let end = Point::new(
+        ctx.selection.end.line as usize,
+        ctx.selection.end.column as usize,
+    );
+
+    // The outermost function, so nested closures don't receive the method
+    let mut enclosing = None;
+    let mut node = tree.root_node().descendant_for_point_range(start, end);
+    while let Some(current) = node {
+        if is_function_node(current.kind()) {
+            enclosing = Some(current);
+        }
+        node = current.parent();
+    }
+
+    match enclosing {
+        Some(function) => Position::new(function.end_position().row as u32 + 1, 0),
+        None => Position::new(ctx.source.lines().count() as u32, 0),
+    }
+}
+
+/// Node kinds for functions and methods across the supported grammars
+fn is_function_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_declaration"
+            | "function_definition"
+            | "function_item"
+            | "function_expression"
+            | "generator_function_declaration"
+            | "arrow_function"
+            | "method_definition"
+            | "method_declaration"
+            | "constructor_declaration"
+            | "func_literal"
+    )
+}
+
+/// Node kinds for loops across the supported grammars
+fn is_loop_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "for_statement"
+            | "for_in_statement"
+            | "while_statement"
+            | "do_statement"
+            | "enhanced_for_statement"
+            | "for_range_loop"
+            | "for_expression"
+            | "while_expression"
+            | "loop_expression"
+    )
+}
+
+/// Node kinds a `break` can leave besides loops
+fn is_switch_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "switch_statement"
+            | "switch_expression"
+            | "expression_switch_statement"
+            | "type_switch_statement"
+            | "select_statement"
+    )
+}
+
+/// Brace/indentation based fallback for when the source can't be parsed
+fn heuristic_insertion_point(ctx: &RefactorContext) -> Position {
     let lines: Vec<&str> = ctx.source.lines().collect();
     let current_line = ctx.selection.start.line as usize;
 
@@ -475,6 +710,44 @@ mod tests {
         RefactorContext::new(source, "test.js", selection, language)
     }
 
+    #[test]
+    fn test_text_around_selection() {
+        let source = "let a = 1;\r\nlet b = a + 1;\r\nlog(b);\r\n";
+        let selection = Range::from_coords(1, 0, 1, 14);
+        assert_eq!(get_text_before(source, selection), "let a = 1;\r\n");
+        assert_eq!(get_text_after(source, selection), "\r\nlog(b);\r\n");
+
+        // Columns past the line end clamp instead of spilling into the next line
+        let selection = Range::from_coords(0, 4, 0, 99);
+        assert_eq!(get_text_before(source, selection), "let ");
+        assert_eq!(get_text_after(source, selection), "\r\nlet b = a + 1;\r\nlog(b);\r\n");
+    }
+
+    #[test]
+    fn test_extract_preserves_crlf() {
+        let source = "function main() {\r\n    let a = 1;\r\n    let b = a + 2;\r\n    console.log(b);\r\n}\r\n";
+        let selection = Range::from_coords(2, 4, 2, 18);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "computeB").unwrap();
+        for edit in &result.edits {
+            assert!(!edit.new_text.replace("\r\n", "").contains('\n'), "bare LF in {:?}", edit.new_text);
+        }
+        let method = result.edits.iter().find(|e| e.new_text.contains("function computeB")).unwrap();
+        assert!(method.new_text.contains("\r\n"));
+    }
+
+    #[test]
+    fn test_extract_keeps_tab_indentation() {
+        let source = "function main() {\n\tlet a = 1;\n\tif (a) {\n\t\tlog(a);\n\t}\n}\n";
+        let selection = Range::from_coords(2, 1, 4, 2);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "logA").unwrap();
+        let method = result.generated_code.unwrap();
+        assert_eq!(method, "\n\tfunction logA(a) {\n\t\tif (a) {\n\t\t\tlog(a);\n\t\t}\n\t}\n");
+    }
+
     #[test]
     fn test_find_modified_variables() {
         let code = "x = 1; y += 2; z++;";
@@ -484,6 +757,22 @@ mod tests {
         assert!(modified.contains("z"));
     }
 
+    #[test]
+    fn test_modified_variables_use_whole_identifiers() {
+        let code = "maximum = 1;\nif (max == x) { log(\"x = 2\"); } // y = 3\nthis.count += 1;";
+        let modified = find_modified_variables(code, LanguageId::JavaScript);
+        assert!(modified.contains("maximum"));
+        assert!(!modified.contains("max"));
+        assert!(!modified.contains("x"));
+        assert!(!modified.contains("y"));
+        assert!(!modified.contains("count"));
+
+        let modified = find_modified_variables("total //= 2\nlabel = '#'  # n = 1", LanguageId::Python);
+        assert!(modified.contains("total"));
+        assert!(modified.contains("label"));
+        assert!(!modified.contains("n"));
+    }
+
     #[test]
     fn test_analyze_parameters() {
         let source = "let x = 1;\nlet y = x + 2;\nconsole.log(y);";
@@ -494,27 +783,102 @@ mod tests {
         assert!(analysis.parameters.contains(&"x".to_string()));
     }
 
+    #[test]
+    fn test_keywords_are_not_parameters() {
+        let source = "let items = [1, 2];\nlet total = 0;\nfor (const item of items) {\n    if (item > 1) { total += item; }\n}\nconsole.log(total);";
+        let selection = Range::from_coords(2, 0, 4, 1);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let analysis = analyze(&ctx).unwrap();
+        for keyword in ["for", "if", "const", "of", "let"] {
+            assert!(!analysis.parameters.iter().any(|p| p == keyword), "{} leaked", keyword);
+            assert!(!analysis.return_variables.iter().any(|p| p == keyword), "{} leaked", keyword);
+        }
+        assert!(analysis.parameters.contains(&"items".to_string()));
+        assert!(analysis.parameters.contains(&"total".to_string()));
+    }
+
     #[test]
     fn test_cannot_extract_with_break() {
         let source = "for(;;) { break; }";
         let selection = Range::from_coords(0, 10, 0, 16); // "break;"
         let ctx = make_ctx(source, selection, LanguageId::JavaScript);
 
-        assert!(can_extract(&ctx).is_err());
+        match can_extract(&ctx) {
+            Err(RefactorError::ControlFlowIssue(message)) => {
+                assert_eq!(message, "'break' on line 1 exits a loop outside the selection");
+            }
+            other => panic!("expected a control flow issue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_break_inside_selected_loop() {
+        let source = "function find(items) {\n    let hit = null;\n    for (const item of items) {\n        if (item.ok) { hit = item; break; }\n        if (!item) continue;\n    }\n    return hit;\n}\n";
+        let selection = Range::from_coords(2, 4, 5, 5);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        assert!(can_extract(&ctx).unwrap());
+        let analysis = analyze(&ctx).unwrap();
+        assert!(!analysis.has_break && !analysis.has_continue);
+    }
+
+    #[test]
+    fn test_labeled_jump_to_outer_loop() {
+        // The inner loop is selected, but `break outer` leaves the loop around it
+        let source = "function scan(rows) {\n    outer: for (const row of rows) {\n        for (const cell of row) {\n            if (!cell) break outer;\n        }\n    }\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 8, 4, 9), LanguageId::JavaScript);
+        match can_extract(&ctx) {
+            Err(RefactorError::ControlFlowIssue(message)) => {
+                assert_eq!(message, "'break' on line 4 exits a loop outside the selection");
+            }
+            other => panic!("expected a control flow issue, got {:?}", other),
+        }
+
+        // Selecting the labeled loop keeps the jump inside
+        let ctx = make_ctx(source, Range::from_coords(1, 4, 5, 5), LanguageId::JavaScript);
+        assert!(can_extract(&ctx).unwrap());
+
+        let source = "fn scan(rows: &[Vec<u8>]) {\n    'rows: for row in rows {\n        for cell in row {\n            if *cell == 0 { continue 'rows; }\n        }\n    }\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 8, 4, 9), LanguageId::Rust);
+        let analysis = analyze(&ctx).unwrap();
+        assert!(analysis.has_continue);
+        assert_eq!(analysis.issues, vec!["'continue' on line 4 continues a loop outside the selection"]);
+    }
+
+    #[test]
+    fn test_return_needs_whole_body() {
+        let source = "def total(items):\n    s = sum(items)\n    return s\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 4, 2, 12), LanguageId::Python);
+        assert!(can_extract(&ctx).unwrap());
+
+        let ctx = make_ctx(source, Range::from_coords(2, 4, 2, 12), LanguageId::Python);
+        match can_extract(&ctx) {
+            Err(RefactorError::ControlFlowIssue(message)) => {
+                assert_eq!(message, "'return' on line 3 would only return from the extracted method");
+            }
+            other => panic!("expected a control flow issue, got {:?}", other),
+        }
     }
 
     #[test]
     fn test_generate_method_javascript() {
-        let code = generate_method(
-            "extracted",
-            "console.log(x);",
-            &["x".to_string()],
-            &[],
-            false,
-            LanguageId::JavaScript,
-            "",
-        );
+        let ctx = make_ctx("console.log(x);\n", Range::from_coords(0, 0, 0, 15), LanguageId::JavaScript);
+        let code = generate_method("extracted", "console.log(x);", &["x".to_string()], &[], false, &ctx);
         assert!(code.contains("function extracted(x)"));
         assert!(code.contains("console.log(x)"));
     }
+
+    #[test]
+    fn test_insertion_point_ignores_braces_in_strings() {
+        let source = "function format(items) {\n    const close = \"}\";\n    const total = items.length;\n    return total + close;\n}\n\nfunction other() {}\n";
+        let selection = Range::from_coords(2, 4, 2, 31);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+        assert_eq!(find_method_insertion_point(&ctx), Position::new(5, 0));
+
+        let source = "def outer():\n    s = \"}\"\n    x = 1\n    return x\n\nprint(outer())\n";
+        let selection = Range::from_coords(2, 4, 2, 9);
+        let ctx = make_ctx(source, selection, LanguageId::Python);
+        assert_eq!(find_method_insertion_point(&ctx), Position::new(4, 0));
+    }
 }
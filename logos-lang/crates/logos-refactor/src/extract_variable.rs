@@ -9,7 +9,7 @@ use crate::analysis::{
     suggest_variable_name,
 };
 use crate::{RefactorContext, RefactorError, RefactorResult, TextEdit};
-use logos_core::{Position, Range};
+use logos_core::{LineIndex, Range};
 use logos_parser::LanguageId;
 use regex::Regex;
 
@@ -48,22 +48,11 @@ pub fn find_occurrences(ctx: &RefactorContext) -> Vec<Range> {
     let pattern = format!(r"(?m){}", escaped);
 
     if let Ok(re) = Regex::new(&pattern) {
-        let lines: Vec<&str> = ctx.source.lines().collect();
-        let mut line_offsets: Vec<usize> = Vec::new();
-        let mut offset = 0;
-
-        for line in &lines {
-            line_offsets.push(offset);
-            offset += line.len() + 1; // +1 for newline
-        }
+        let index = LineIndex::new(ctx.source);
 
         for m in re.find_iter(ctx.source) {
-            let start_offset = m.start();
-            let end_offset = m.end();
-
-            // Convert byte offsets to line/column
-            let start_pos = offset_to_position(&line_offsets, &lines, start_offset);
-            let end_pos = offset_to_position(&line_offsets, &lines, end_offset);
+            let start_pos = index.position_of(m.start());
+            let end_pos = index.position_of(m.end());
 
             occurrences.push(Range::new(start_pos, end_pos));
         }
@@ -76,23 +65,6 @@ pub fn find_occurrences(ctx: &RefactorContext) -> Vec<Range> {
     occurrences
 }
 
-fn offset_to_position(line_offsets: &[usize], lines: &[&str], offset: usize) -> Position {
-    for (i, &line_offset) in line_offsets.iter().enumerate() {
-        let line_end = if i + 1 < line_offsets.len() {
-            line_offsets[i + 1] - 1
-        } else {
-            line_offset + lines.get(i).map(|l| l.len()).unwrap_or(0)
-        };
-
-        if offset <= line_end {
-            let column = offset - line_offset;
-            return Position::new(i as u32, column as u32);
-        }
-    }
-
-    Position::new(0, 0)
-}
-
 /// Extract the selected expression into a variable
 pub fn extract(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorResult, RefactorError> {
     can_extract(ctx)?;
@@ -107,7 +79,7 @@ pub fn extract(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorRes
     let indent = ctx.indentation_at(insert_pos.line);
 
     // Generate the declaration statement
-    let declaration = generate_declaration(variable_name, trimmed, ctx.language, &indent);
+    let declaration = ctx.with_line_endings(&generate_declaration(variable_name, trimmed, ctx.language, &indent));
 
     // Find all occurrences to replace (currently just the selected one)
     let occurrences = vec![ctx.selection]; // Could use find_occurrences for replace all
@@ -117,7 +89,7 @@ pub fn extract(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorRes
 
     // First, add replacements for all occurrences (in reverse order)
     let mut sorted_occurrences = occurrences.clone();
-    sorted_occurrences.sort_by(|a, b| b.start.cmp(&a.start));
+    sorted_occurrences.sort_by_key(|r| std::cmp::Reverse(r.start));
 
     for occurrence in sorted_occurrences {
         edits.push(TextEdit::replace(occurrence, variable_name.to_string()));
@@ -176,6 +148,22 @@ mod tests {
         RefactorContext::new(source, "test.js", selection, language)
     }
 
+    #[test]
+    fn test_crlf_declaration_and_occurrences() {
+        let source = "let x = 1;\r\nlet y = a + b;\r\nlet z = a + b;\r\n";
+        let selection = Range::from_coords(1, 8, 1, 13);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "sum").unwrap();
+        let declaration = result.edits.last().unwrap();
+        assert!(declaration.new_text.ends_with(";\r\n"));
+
+        assert_eq!(
+            find_occurrences(&ctx),
+            vec![Range::from_coords(1, 8, 1, 13), Range::from_coords(2, 8, 2, 13)]
+        );
+    }
+
     #[test]
     fn test_can_extract_simple_expression() {
         let source = "let x = a + b;";
@@ -241,12 +241,30 @@ impl<'a> RefactorContext<'a> {
         }
     }
 
+    /// The document's line ending: `\r\n` if it uses CRLF, else `\n`
+    pub fn line_ending(&self) -> &'static str {
+        if self.source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    /// Convert generated text (built with `\n`) to the document's line ending
+    pub fn with_line_endings(&self, text: &str) -> String {
+        let normalized = text.replace("\r\n", "\n");
+        match self.line_ending() {
+            "\n" => normalized,
+            eol => normalized.replace('\n', eol),
+        }
+    }
+
     /// Get the line text at a given line number
     pub fn line_at(&self, line: u32) -> Option<&str> {
         self.source.lines().nth(line as usize)
     }
 
-    /// Get indentation at a given line
+    /// Get the leading whitespace of a line, tabs and spaces as written
     pub fn indentation_at(&self, line: u32) -> String {
         if let Some(text) = self.line_at(line) {
             let indent_len = text.len() - text.trim_start().len();
@@ -255,6 +273,19 @@ impl<'a> RefactorContext<'a> {
             String::new()
         }
     }
+
+    /// One level of indentation in this document: a tab if its first
+    /// indented line starts with one, four spaces otherwise
+    pub fn indent_unit(&self) -> &'static str {
+        let first_indented = self
+            .source
+            .lines()
+            .find(|line| line.starts_with([' ', '\t']) && !line.trim().is_empty());
+        match first_indented {
+            Some(line) if line.starts_with('\t') => "\t",
+            _ => "    ",
+        }
+    }
 }
 
 /// Main refactoring engine
@@ -0,0 +1,155 @@
+//! Code complexity metrics
+
+use logos_core::Symbol;
+use tree_sitter::{Node, Point, Tree};
+
+/// Cyclomatic complexity of a function: one plus the number of decision
+/// points (`if`, loops, `case`, `catch`, `&&`, `||` and `?:`) in its body.
+/// A Rust `match` with n arms adds n - 1, like a `switch` whose last case
+/// is the `default`.
+///
+/// Functions, closures and classes nested inside the symbol are measured on
+/// their own and don't add to its count. Returns 1 when the symbol's range
+/// holds no syntax node.
+pub fn cyclomatic(symbol: &Symbol, tree: &Tree, source: &str) -> u32 {
+    let start = Point::new(symbol.range.start.line as usize, symbol.range.start.column as usize);
+    let end = Point::new(symbol.range.end.line as usize, symbol.range.end.column as usize);
+    let node = match tree.root_node().descendant_for_point_range(start, end) {
+        Some(node) => node,
+        None => return 1,
+    };
+    // `const f = () => ...` is measured on the function, not skipped as a
+    // closure nested in the declaration
+    let node = assigned_function(symbol, tree).unwrap_or(node);
+
+    let mut decisions = 0;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        count_decisions(child, source, &mut decisions);
+    }
+    1 + decisions
+}
+
+/// The function or lambda assigned to the name a symbol declares
+fn assigned_function<'a>(symbol: &Symbol, tree: &'a Tree) -> Option<Node<'a>> {
+    let start = Point::new(symbol.selection_range.start.line as usize, symbol.selection_range.start.column as usize);
+    let end = Point::new(symbol.selection_range.end.line as usize, symbol.selection_range.end.column as usize);
+    let binding = tree.root_node().descendant_for_point_range(start, end)?.parent()?;
+    binding
+        .child_by_field_name("value")
+        .or_else(|| binding.child_by_field_name("right"))
+        .filter(is_nested_definition)
+}
+
+fn count_decisions(node: Node, source: &str, decisions: &mut u32) {
+    if is_nested_definition(&node) {
+        return;
+    }
+    if is_decision_point(&node, source) {
+        *decisions += 1;
+    }
+    if node.kind() == "match_block" {
+        let mut cursor = node.walk();
+        let arms = node.named_children(&mut cursor).filter(|n| n.kind() == "match_arm").count() as u32;
+        *decisions += arms.saturating_sub(1);
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        count_decisions(child, source, decisions);
+    }
+}
+
+fn is_decision_point(node: &Node, source: &str) -> bool {
+    match node.kind() {
+        // Branches
+        "if_statement" | "if_expression" | "elif_clause" | "conditional_expression"
+        | "ternary_expression" => true,
+        // Loops
+        "for_statement" | "for_in_statement" | "enhanced_for_statement" | "for_range_loop"
+        | "while_statement" | "do_statement" | "for_expression" | "while_expression" => true,
+        // Cases; `default` is the fall-through path, not a decision
+        "case_statement" | "switch_case" | "switch_label" | "expression_case" | "type_case"
+        | "communication_case" | "case_clause" => {
+            !source[node.byte_range()].trim_start().starts_with("default")
+        }
+        // Handlers
+        "catch_clause" | "except_clause" => true,
+        // Short-circuit operators
+        "boolean_operator" => true,
+        "binary_expression" => node
+            .child_by_field_name("operator")
+            .map(|op| matches!(&source[op.byte_range()], "&&" | "||"))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Definitions inside a function that have a complexity of their own
+fn is_nested_definition(node: &Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_definition"
+            | "function_declaration"
+            | "function_item"
+            | "method_definition"
+            | "method_declaration"
+            | "class_definition"
+            | "class_declaration"
+            | "closure_expression"
+            | "arrow_function"
+            | "function_expression"
+            | "lambda"
+            | "lambda_expression"
+            | "func_literal"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use logos_core::SymbolKind;
+    use logos_parser::LanguageId;
+
+    /// Complexity of the function called `name`
+    fn complexity(name: &str, source: &str, language: LanguageId) -> u32 {
+        let tree = parse(source, language).unwrap();
+        let symbol = logos_parser::extract_symbols(language, &tree, source)
+            .into_iter()
+            .find(|s| s.name == name && matches!(s.kind, SymbolKind::Function | SymbolKind::Method))
+            .unwrap();
+        cyclomatic(&symbol, &tree, source)
+    }
+
+    #[test]
+    fn test_two_ifs_and_a_loop() {
+        let source = "def check(items, limit):\n    if not items:\n        return 0\n    total = 0\n    for item in items:\n        if item > limit and limit > 0:\n            total += item\n    return total\n";
+        // 1 + if + for + if + and
+        assert_eq!(complexity("check", source, LanguageId::Python), 5);
+    }
+
+    #[test]
+    fn test_cases_and_nested_functions() {
+        let source = "function kind(x) {\n  switch (x) {\n    case 1: return 'one';\n    case 2: return 'two';\n    default: return x > 2 ? 'many' : 'none';\n  }\n  function inner(y) { if (y) { return 1; } }\n}\n";
+        // 1 + two cases + ternary; `default` and `inner` don't count
+        assert_eq!(complexity("kind", source, LanguageId::JavaScript), 4);
+
+        let straight = "fn id(x: u32) -> u32 {\n    x\n}\n";
+        assert_eq!(complexity("id", straight, LanguageId::Rust), 1);
+    }
+
+    #[test]
+    fn test_match_arms_and_closures() {
+        let source = "fn sign(x: i32, xs: &[i32]) -> i32 {\n    let any = xs.iter().any(|y| if *y > 0 { true } else { false });\n    match x {\n        0 => 0,\n        n if n > 0 => 1,\n        _ => -1,\n    }\n}\n";
+        // 1 + (3 arms - 1); the closure's `if` is its own
+        assert_eq!(complexity("sign", source, LanguageId::Rust), 3);
+
+        let source = "const f = (xs) => {\n  xs.forEach((x) => { if (x) { use(x); } });\n};\nfunction g(xs) {\n  return xs.map(function (x) { return x ? 1 : 0; });\n}\n";
+        assert_eq!(complexity("g", source, LanguageId::JavaScript), 1);
+        assert_eq!(complexity("f", source, LanguageId::JavaScript), 1);
+
+        let source = "const h = (x) => x ? 1 : 0;\n";
+        assert_eq!(complexity("h", source, LanguageId::JavaScript), 2);
+    }
+}
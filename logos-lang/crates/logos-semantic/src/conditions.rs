@@ -0,0 +1,92 @@
+//! Suspicious conditions
+//!
+//! Flags assignments used as the condition of an `if`, `while` or
+//! `do ... while`, the classic `if (x = 1)` typo for `if (x == 1)`.
+
+use logos_core::Diagnostic;
+use logos_parser::{node_to_range, LanguageId};
+use tree_sitter::{Node, Tree};
+
+/// Warn about assignments used directly as a condition.
+///
+/// Only C-family languages and JavaScript/TypeScript are checked: Go and
+/// Rust reject the pattern at compile time, and Python's `:=` is always
+/// deliberate. Wrapping the assignment in a second pair of parentheses,
+/// as in `while ((line = next()))`, marks it as intended.
+pub fn assignment_in_condition(tree: &Tree, language: LanguageId) -> Vec<Diagnostic> {
+    if !matches!(
+        language,
+        LanguageId::C | LanguageId::Cpp | LanguageId::Java | LanguageId::JavaScript | LanguageId::TypeScript
+    ) {
+        return Vec::new();
+    }
+
+    let mut diagnostics = Vec::new();
+    check_node(tree.root_node(), &mut diagnostics);
+    diagnostics
+}
+
+fn check_node(node: Node, diagnostics: &mut Vec<Diagnostic>) {
+    if matches!(node.kind(), "if_statement" | "while_statement" | "do_statement") {
+        if let Some(assignment) = node.child_by_field_name("condition").and_then(condition_assignment) {
+            diagnostics.push(
+                Diagnostic::warning(
+                    node_to_range(&assignment),
+                    "Assignment used as a condition; did you mean '=='?".to_string(),
+                )
+                .with_code("assignment-in-condition".to_string())
+                .with_source("logos-semantic".to_string()),
+            );
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        check_node(child, diagnostics);
+    }
+}
+
+/// The assignment directly inside a condition's parentheses, if any
+fn condition_assignment(condition: Node) -> Option<Node> {
+    let inner = match condition.kind() {
+        "parenthesized_expression" => condition.named_child(0)?,
+        // C++ wraps conditions that may declare a variable
+        "condition_clause" => condition.child_by_field_name("value")?,
+        _ => condition,
+    };
+    matches!(inner.kind(), "assignment_expression" | "augmented_assignment_expression").then_some(inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn check(source: &str, language: LanguageId) -> Vec<Diagnostic> {
+        assignment_in_condition(&parse(source, language).unwrap(), language)
+    }
+
+    #[test]
+    fn test_assignment_in_if_condition() {
+        let source = "if (x = 1) {\n  go();\n}\nif (x == 1) {\n  go();\n}\n";
+        let diagnostics = check(source, LanguageId::JavaScript);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 0);
+        assert_eq!(diagnostics[0].range.start.column, 4);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("assignment-in-condition"));
+    }
+
+    #[test]
+    fn test_intentional_and_unchecked_assignments() {
+        // Doubled parentheses mark the assignment as intended
+        let js = "while ((line = next())) {\n  use(line);\n}\n";
+        assert!(check(js, LanguageId::JavaScript).is_empty());
+
+        let c = "int f(int x) {\n    while (x -= 1) {}\n    return x;\n}\n";
+        assert_eq!(check(c, LanguageId::C).len(), 1);
+
+        let python = "if (n := len(items)) > 10:\n    pass\n";
+        assert!(check(python, LanguageId::Python).is_empty());
+    }
+}
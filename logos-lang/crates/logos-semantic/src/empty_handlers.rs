@@ -0,0 +1,125 @@
+//! Swallowed errors
+//!
+//! Flags exception handlers that do nothing (`except: pass`, `catch (e) {}`)
+//! and Go error checks with an empty body (`if err != nil {}`).
+
+use logos_core::Diagnostic;
+use logos_parser::node_to_range;
+use tree_sitter::{Node, Tree};
+
+/// Warn about handlers that silently discard an error.
+///
+/// A handler whose body holds only a comment is left alone, since the
+/// comment usually explains why the error is ignored.
+pub fn empty_handlers(tree: &Tree, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_node(tree.root_node(), source, &mut diagnostics);
+    diagnostics
+}
+
+fn check_node(node: Node, source: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let message = match node.kind() {
+        "except_clause" | "except_group_clause" if is_empty_python_block(node) => {
+            Some("Empty except block swallows the error")
+        }
+        "catch_clause" if node.child_by_field_name("body").is_some_and(|body| body.named_child_count() == 0) => {
+            Some("Empty catch block swallows the error")
+        }
+        "if_statement" if is_go_error_check(node, source) => {
+            Some("Error check with an empty body ignores the error")
+        }
+        _ => None,
+    };
+    if let Some(message) = message {
+        diagnostics.push(
+            Diagnostic::warning(node_to_range(&node), message.to_string())
+                .with_code("empty-error-handler".to_string())
+                .with_source("logos-semantic".to_string()),
+        );
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        check_node(child, source, diagnostics);
+    }
+}
+
+/// Whether an `except` clause's block holds nothing but `pass`. Comments
+/// may end up beside the block rather than in it, so the clause is
+/// searched for them too.
+fn is_empty_python_block(clause: Node) -> bool {
+    let mut cursor = clause.walk();
+    let children: Vec<Node> = clause.named_children(&mut cursor).collect();
+    let block = match children.iter().find(|child| child.kind() == "block") {
+        Some(block) => *block,
+        None => return false,
+    };
+    if children.iter().any(|child| child.kind() == "comment") {
+        return false;
+    }
+    let mut cursor = block.walk();
+    let only_pass = block
+        .named_children(&mut cursor)
+        .all(|statement| statement.kind() == "pass_statement");
+    only_pass
+}
+
+/// `if err != nil {}`: a Go error comparison whose body is empty
+fn is_go_error_check(node: Node, source: &str) -> bool {
+    let (condition, body) = match (
+        node.child_by_field_name("condition"),
+        node.child_by_field_name("consequence"),
+    ) {
+        (Some(condition), Some(body)) => (condition, body),
+        _ => return false,
+    };
+    if condition.kind() != "binary_expression" || body.named_child_count() != 0 {
+        return false;
+    }
+
+    let text = |field: &str| condition.child_by_field_name(field).map(|n| &source[n.byte_range()]);
+    let operator = condition.child_by_field_name("operator").map(|n| n.kind());
+    operator == Some("!=")
+        && text("right") == Some("nil")
+        && text("left").is_some_and(|left| left == "err" || left.ends_with("Err"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use logos_parser::LanguageId;
+
+    fn check(source: &str, language: LanguageId) -> Vec<Diagnostic> {
+        empty_handlers(&parse(source, language).unwrap(), source)
+    }
+
+    #[test]
+    fn test_python_except_pass() {
+        let source = "try:\n    load()\nexcept ValueError:\n    pass\nexcept KeyError:\n    # missing keys are fine\n    pass\nexcept OSError as e:\n    log(e)\n";
+        let diagnostics = check(source, LanguageId::Python);
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("empty-error-handler"));
+    }
+
+    #[test]
+    fn test_javascript_empty_catch() {
+        let source = "try {\n  load();\n} catch (e) {}\ntry {\n  load();\n} catch (e) {\n  // best effort\n}\n";
+        let diagnostics = check(source, LanguageId::JavaScript);
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert_eq!(diagnostics[0].range.start.column, 2);
+    }
+
+    #[test]
+    fn test_go_empty_error_check() {
+        let source = "package main\n\nfunc run() {\n\tif err != nil {\n\t}\n\tif err != nil {\n\t\treturn\n\t}\n\tif n != nil {\n\t}\n}\n";
+        let diagnostics = check(source, LanguageId::Go);
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].range.start.line, 3);
+    }
+}
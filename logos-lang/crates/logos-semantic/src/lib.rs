@@ -1,18 +1,24 @@
 //! Logos Semantic - Semantic analysis for the language service
 
+pub mod complexity;
+pub mod conditions;
+pub mod empty_handlers;
 pub mod resolver;
 pub mod scope;
 pub mod type_check;
 pub mod type_infer;
 pub mod unused;
 
-pub use type_check::{TypeCheckConfig, TypeCheckError, TypeCheckErrorKind, TypeChecker};
-pub use type_infer::{LiteralType, Type, TypeContext, TypeError};
+pub use type_check::{
+    FunctionSignature, TypeCheckConfig, TypeCheckError, TypeCheckErrorKind, TypeChecker,
+};
+pub use type_infer::{infer_literal, LiteralType, Type, TypeContext, TypeError};
 pub use unused::{UnusedDetector, UnusedItem, UnusedKind};
 
-use logos_core::{Diagnostic, Position, Range, Symbol, SymbolKind};
-use logos_parser::LanguageId;
+use logos_core::{Diagnostic, DiagnosticSeverity, Position, Range, Symbol, SymbolKind};
+use logos_parser::{LanguageId, LanguageParser};
 use std::collections::HashMap;
+use tree_sitter::Tree;
 
 /// Semantic analysis result
 #[derive(Debug, Default)]
@@ -24,41 +30,122 @@ pub struct SemanticInfo {
     pub unused_items: Vec<UnusedItem>,
 }
 
+/// Which checks semantic analysis runs
+#[derive(Debug, Clone)]
+pub struct AnalysisConfig {
+    /// Run unused code detection (unused symbols and unreachable code)
+    pub detect_unused: bool,
+    /// Report unused symbols; unreachable code is still reported when
+    /// `detect_unused` is on
+    pub report_unused: bool,
+    /// Strict type checking (report implicit any)
+    pub strict_types: bool,
+    /// Null safety checks
+    pub null_safety: bool,
+    /// Severity to report diagnostics with, keyed by diagnostic code
+    /// (`unused-variable`, `duplicate-definition`, ...)
+    pub severity_overrides: HashMap<String, DiagnosticSeverity>,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            detect_unused: true,
+            report_unused: true,
+            strict_types: false,
+            null_safety: true,
+            severity_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl AnalysisConfig {
+    /// The type checker settings this configuration implies
+    pub fn type_check_config(&self) -> TypeCheckConfig {
+        TypeCheckConfig {
+            strict: self.strict_types,
+            null_safety: self.null_safety,
+            report_unused: self.detect_unused && self.report_unused,
+        }
+    }
+
+    /// Give each diagnostic whose code has an override the configured severity
+    pub fn apply_severity_overrides(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics {
+            let severity = diagnostic.code.as_ref().and_then(|code| self.severity_overrides.get(code));
+            if let Some(&severity) = severity {
+                diagnostic.severity = severity;
+            }
+        }
+    }
+}
+
 /// Semantic analyzer for a document
 pub struct SemanticAnalyzer {
     language: LanguageId,
-    detect_unused: bool,
+    config: AnalysisConfig,
 }
 
 impl SemanticAnalyzer {
     pub fn new(language: LanguageId) -> Self {
         Self {
             language,
-            detect_unused: true,
+            config: AnalysisConfig::default(),
         }
     }
 
+    /// Use the given analysis configuration
+    pub fn with_config(mut self, config: AnalysisConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Enable or disable unused code detection
     pub fn with_unused_detection(mut self, enabled: bool) -> Self {
-        self.detect_unused = enabled;
+        self.config.detect_unused = enabled;
         self
     }
 
     pub fn analyze(&self, symbols: &[Symbol], source: &str) -> SemanticInfo {
-        let mut info = SemanticInfo::default();
-        info.scope_tree = scope::ScopeTree::from_symbols(symbols);
-        info.symbols = symbols.to_vec();
+        // Every syntax check shares one parse
+        let syntax = parse(source, self.language);
+        let mut info = SemanticInfo {
+            scope_tree: match &syntax {
+                Some(syntax) => scope::ScopeTree::from_syntax(symbols, syntax, source, self.language),
+                None => scope::ScopeTree::from_symbols(symbols),
+            },
+            symbols: symbols.to_vec(),
+            ..Default::default()
+        };
         self.check_duplicates(&info.symbols, &mut info.diagnostics);
 
+        let mut checker = TypeChecker::with_config(self.config.type_check_config());
+        checker.analyze_symbols(symbols);
+        if let Some(syntax) = &syntax {
+            info.diagnostics
+                .extend(conditions::assignment_in_condition(syntax, self.language));
+            info.diagnostics
+                .extend(empty_handlers::empty_handlers(syntax, source));
+            checker.check_returns(syntax, source);
+        }
+        info.diagnostics.extend(checker.diagnostics());
+
         // Detect unused code
-        if self.detect_unused {
+        if self.config.detect_unused {
             let mut detector = UnusedDetector::new();
-            info.unused_items = detector.analyze(symbols, source);
+            if self.config.report_unused {
+                info.unused_items = detector.analyze(symbols, source);
+            }
+            if let Some(syntax) = &syntax {
+                info.unused_items
+                    .extend(detector.detect_unreachable(syntax, source));
+            }
             // Add unused diagnostics
             for item in &info.unused_items {
                 info.diagnostics.push(item.to_diagnostic());
             }
         }
+        self.config.apply_severity_overrides(&mut info.diagnostics);
 
         info
     }
@@ -75,6 +162,7 @@ impl SemanticAnalyzer {
                         symbol.selection_range,
                         format!("Duplicate definition of '{}'", symbol.name),
                     )
+                    .with_code("duplicate-definition".to_string())
                     .with_source("logos-semantic".to_string()),
                 );
             }
@@ -85,4 +173,16 @@ impl SemanticAnalyzer {
     pub fn language(&self) -> LanguageId {
         self.language
     }
+
+    pub fn config(&self) -> &AnalysisConfig {
+        &self.config
+    }
+}
+
+/// Parse `source` once for the syntax checks, or `None` if the grammar
+/// can't be loaded
+pub(crate) fn parse(source: &str, language: LanguageId) -> Option<Tree> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    parser.parse(source, None).ok()
 }
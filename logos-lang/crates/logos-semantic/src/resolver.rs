@@ -52,6 +52,11 @@ impl<'a> SymbolResolver<'a> {
                 return Some(symbol);
             }
         }
+        // An unnamed scope below the root is a comprehension; its bindings
+        // hide outer declarations but have no symbol of their own
+        if scope.name.is_none() && scope.parent.is_some() && scope.bindings.iter().any(|b| b == name) {
+            return None;
+        }
         if let Some(parent_id) = scope.parent {
             return self.search_scopes_for_definition(name, Some(parent_id));
         }
@@ -1,6 +1,8 @@
 //! Scope analysis
 
 use logos_core::{Position, Range, Symbol};
+use logos_parser::{node_to_range, LanguageId};
+use tree_sitter::{Node, Tree};
 
 #[derive(Debug, Clone)]
 pub struct Scope {
@@ -9,11 +11,13 @@ pub struct Scope {
     pub range: Range,
     pub name: Option<String>,
     pub children: Vec<usize>,
+    /// Names declared directly in this scope
+    pub bindings: Vec<String>,
 }
 
 impl Scope {
     pub fn new(id: usize, range: Range) -> Self {
-        Self { id, parent: None, range, name: None, children: Vec::new() }
+        Self { id, parent: None, range, name: None, children: Vec::new(), bindings: Vec::new() }
     }
 
     pub fn with_parent(mut self, parent: usize) -> Self {
@@ -54,8 +58,56 @@ impl ScopeTree {
         tree
     }
 
+    /// `from_symbols`, plus the scopes only the syntax tree shows: each
+    /// Python comprehension gets a scope binding its `for` targets, so the
+    /// `x` in `[x * 2 for x in xs]` is not the `x` of the enclosing function
+    pub fn from_syntax(symbols: &[Symbol], syntax: &Tree, source: &str, language: LanguageId) -> Self {
+        let mut tree = Self::from_symbols(symbols);
+        if language != LanguageId::Python {
+            return tree;
+        }
+
+        let root = syntax.root_node();
+        if tree.root.is_none() {
+            tree.root = Some(tree.add_scope(Scope::new(0, node_to_range(&root))));
+        }
+        tree.add_comprehension_scopes(root, source);
+        tree
+    }
+
+    fn add_comprehension_scopes(&mut self, node: Node, source: &str) {
+        if matches!(
+            node.kind(),
+            "list_comprehension" | "set_comprehension" | "dictionary_comprehension" | "generator_expression"
+        ) {
+            let range = node_to_range(&node);
+            let mut bindings = Vec::new();
+            let mut cursor = node.walk();
+            for clause in node.named_children(&mut cursor) {
+                if clause.kind() == "for_in_clause" {
+                    if let Some(left) = clause.child_by_field_name("left") {
+                        collect_identifiers(left, source, &mut bindings);
+                    }
+                }
+            }
+
+            if let Some(parent_id) = self.scope_at(range.start).or(self.root) {
+                let mut scope = Scope::new(self.scopes.len(), range).with_parent(parent_id);
+                scope.bindings = bindings;
+                let scope_id = self.add_scope(scope);
+                self.scopes[parent_id].children.push(scope_id);
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.add_comprehension_scopes(child, source);
+        }
+    }
+
     fn add_scopes_from_symbols(&mut self, symbols: &[Symbol], parent_id: usize) {
         for symbol in symbols {
+            self.scopes[parent_id].bindings.push(symbol.name.clone());
             if !symbol.children.is_empty() {
                 let scope_id = self.add_scope(
                     Scope::new(self.scopes.len(), symbol.range)
@@ -92,5 +144,106 @@ impl ScopeTree {
         Some(scope_id)
     }
 
+    /// Scopes enclosing a position, innermost first and ending at the root
+    pub fn scope_chain(&self, position: Position) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut current = self.scope_at(position).or(self.root);
+        while let Some(id) = current {
+            chain.push(id);
+            current = self.get_scope(id).and_then(|s| s.parent);
+        }
+        chain
+    }
+
     pub fn root(&self) -> Option<usize> { self.root }
+
+    /// Walk the symbols this tree was built from alongside their scopes,
+    /// calling `visit(symbol, scope_id, distance)` for each symbol declared
+    /// in a scope on `chain`; `distance` is that scope's index in the chain,
+    /// so 0 is the innermost. Symbols in scopes off the chain (another
+    /// function's locals) are skipped.
+    pub fn visit_visible<'a>(
+        &self,
+        symbols: &'a [Symbol],
+        chain: &[usize],
+        visit: &mut impl FnMut(&'a Symbol, usize, usize),
+    ) {
+        if let Some(root) = self.root {
+            self.visit_scope(symbols, root, chain, visit);
+        }
+    }
+
+    fn visit_scope<'a>(
+        &self,
+        symbols: &'a [Symbol],
+        scope_id: usize,
+        chain: &[usize],
+        visit: &mut impl FnMut(&'a Symbol, usize, usize),
+    ) {
+        let distance = chain.iter().position(|&id| id == scope_id);
+
+        for symbol in symbols {
+            if let Some(distance) = distance {
+                visit(symbol, scope_id, distance);
+            }
+
+            if symbol.children.is_empty() {
+                continue;
+            }
+
+            // `from_symbols` creates one child scope per symbol with children
+            let child_scope = self.get_scope(scope_id).and_then(|scope| {
+                scope.children.iter().copied().find(|&child| {
+                    self.get_scope(child).is_some_and(|s| {
+                        s.range == symbol.range && s.name.as_deref() == Some(symbol.name.as_str())
+                    })
+                })
+            });
+            if let Some(child_scope) = child_scope {
+                self.visit_scope(&symbol.children, child_scope, chain, visit);
+            }
+        }
+    }
+}
+
+/// Names bound by an assignment target such as `x`, `k, v` or `(a, (b, c))`
+fn collect_identifiers(node: Node, source: &str, names: &mut Vec<String>) {
+    if node.kind() == "identifier" {
+        names.push(source[node.byte_range()].to_string());
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_identifiers(child, source, names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::SymbolResolver;
+    use logos_parser::LanguageParser;
+
+    #[test]
+    fn test_comprehension_targets_are_scoped() {
+        let source = "x = 10\n\ndef pairs(items):\n    return {k: v for k, v in items if (n := v)}\n\nsquares = [x * x for x in range(3)]\n";
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Python).unwrap();
+        let syntax = parser.parse(source, None).unwrap();
+        let symbols = logos_parser::extract_symbols(LanguageId::Python, &syntax, source);
+        let tree = ScopeTree::from_syntax(&symbols, &syntax, source, LanguageId::Python);
+
+        // Inside `pairs`, the dict comprehension binds `k` and `v`; the
+        // walrus binds `n` in the function itself
+        let in_dict = tree.get_scope(tree.scope_at(Position::new(3, 15)).unwrap()).unwrap();
+        assert_eq!(in_dict.bindings, vec!["k", "v"]);
+        let function = tree.get_scope(in_dict.parent.unwrap()).unwrap();
+        assert_eq!(function.name.as_deref(), Some("pairs"));
+        assert_eq!(function.bindings, vec!["n"]);
+
+        // The comprehension's `x` hides the module-level `x`
+        let resolver = SymbolResolver::new(&tree, &symbols);
+        assert!(resolver.find_definition("x", Position::new(5, 12)).is_none());
+        assert_eq!(resolver.find_definition("x", Position::new(5, 0)).unwrap().selection_range.start, Position::new(0, 0));
+    }
 }
@@ -3,10 +3,12 @@
 //! Provides type checking capabilities for detecting type mismatches,
 //! undefined variables, incorrect function calls, etc.
 
-use crate::type_infer::{Type, TypeContext};
+use crate::type_infer::{infer_literal, Type, TypeContext};
 use logos_core::{Diagnostic, Range, Symbol, SymbolKind};
+use logos_parser::node_to_range;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tree_sitter::{Node, Tree};
 
 /// Type check result for a single expression or statement
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,6 +134,8 @@ pub struct FunctionSignature {
     pub return_type: Type,
     /// Whether the function is variadic
     pub variadic: bool,
+    /// Number of trailing parameters callers may leave out
+    pub optional_params: usize,
 }
 
 impl TypeChecker {
@@ -197,14 +201,23 @@ impl TypeChecker {
     ) -> Type {
         if let Some(sig) = self.functions.get(name).cloned() {
             // Check argument count
-            if !sig.variadic && args.len() != sig.params.len() {
+            let max = sig.params.len();
+            let min = max.saturating_sub(sig.optional_params);
+            if args.len() < min || (!sig.variadic && args.len() > max) {
+                let expected = if sig.variadic {
+                    format!("at least {}", min)
+                } else if min == max {
+                    max.to_string()
+                } else {
+                    format!("{} to {}", min, max)
+                };
                 self.errors.push(TypeCheckError {
                     kind: TypeCheckErrorKind::ArgumentCount,
                     range,
                     message: format!(
                         "Function '{}' expects {} arguments, but {} were provided",
                         name,
-                        sig.params.len(),
+                        expected,
                         args.len()
                     ),
                     expected: None,
@@ -217,13 +230,18 @@ impl TypeChecker {
                 args.iter().zip(sig.params.iter()).enumerate()
             {
                 if !arg_type.is_subtype_of(param_type) {
+                    // Signatures taken from an index may not know parameter names
+                    let argument = if param_name.is_empty() {
+                        format!("Argument {}", i + 1)
+                    } else {
+                        format!("Argument {} '{}'", i + 1, param_name)
+                    };
                     self.errors.push(TypeCheckError {
                         kind: TypeCheckErrorKind::TypeMismatch,
                         range,
                         message: format!(
-                            "Argument {} '{}': expected '{}', found '{}'",
-                            i + 1,
-                            param_name,
+                            "{}: expected '{}', found '{}'",
+                            argument,
                             param_type.display_name(),
                             arg_type.display_name()
                         ),
@@ -331,12 +349,13 @@ impl TypeChecker {
                     self.context.bind(symbol.name.clone(), Type::Unknown);
                 }
                 SymbolKind::Function | SymbolKind::Method => {
-                    // Register function signature (simplified)
+                    // Symbols don't carry parameters, so accept any arguments
                     self.register_function(FunctionSignature {
                         name: symbol.name.clone(),
                         params: Vec::new(),
                         return_type: Type::Unknown,
-                        variadic: false,
+                        variadic: true,
+                        optional_params: 0,
                     });
                 }
                 SymbolKind::Class | SymbolKind::Struct => {
@@ -349,6 +368,111 @@ impl TypeChecker {
         }
     }
 
+    /// Check every `return` in functions with a declared return type.
+    ///
+    /// Returned literals are typed directly and identifiers through the
+    /// context, including locals assigned a literal earlier in the same
+    /// function; other expressions are not checked.
+    pub fn check_returns(&mut self, tree: &Tree, source: &str) {
+        self.check_returns_in(tree.root_node(), source);
+    }
+
+    fn check_returns_in(&mut self, node: Node, source: &str) {
+        if is_function(&node) {
+            if let (Some(declared), Some(body)) =
+                (declared_return_type(&node, source), node.child_by_field_name("body"))
+            {
+                let outer = self.context.clone();
+                self.check_function_body(body, source, &declared);
+                self.context = outer;
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.check_returns_in(child, source);
+        }
+    }
+
+    /// Check the returns of one function body, skipping nested functions
+    fn check_function_body(&mut self, node: Node, source: &str, declared: &Type) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if is_function(&child) {
+                continue;
+            }
+            match child.kind() {
+                "return_statement" | "return_expression" => {
+                    if let Some(value) = returned_value(&child) {
+                        let actual = self.expression_type(&value, source);
+                        if !actual.is_unknown() {
+                            self.check_return(&actual, declared, node_to_range(&value));
+                        }
+                    }
+                }
+                _ => {
+                    if let Some((name, ty)) = value_binding(&child, source) {
+                        self.context.bind(name, ty);
+                    }
+                    self.check_function_body(child, source, declared);
+                }
+            }
+        }
+    }
+
+    /// Check calls to plain function names against their signatures.
+    ///
+    /// Names without a registered signature are passed to `resolve` once,
+    /// so callers can supply signatures from a project index on demand;
+    /// names neither registered nor resolved are skipped. Calls with
+    /// keyword or spread arguments are skipped, since their arguments can't
+    /// be matched to parameters by position.
+    pub fn check_calls(
+        &mut self,
+        tree: &Tree,
+        source: &str,
+        mut resolve: impl FnMut(&str) -> Option<FunctionSignature>,
+    ) {
+        let mut calls = Vec::new();
+        collect_calls(tree.root_node(), &mut calls);
+
+        let mut unresolved = std::collections::HashSet::new();
+        for call in calls {
+            let Some((name, args)) = call_parts(&call, source) else {
+                continue;
+            };
+            if !self.functions.contains_key(name) {
+                if unresolved.contains(name) {
+                    continue;
+                }
+                match resolve(name) {
+                    Some(sig) => self.register_function(sig),
+                    None => {
+                        unresolved.insert(name);
+                        continue;
+                    }
+                }
+            }
+            let arg_types: Vec<Type> = args.iter().map(|arg| self.expression_type(arg, source)).collect();
+            self.check_function_call(name, &arg_types, node_to_range(&call));
+        }
+    }
+
+    /// Type of a returned expression: literals and known variables only
+    fn expression_type(&self, node: &Node, source: &str) -> Type {
+        if let Some(ty) = infer_literal(node, source) {
+            return ty;
+        }
+        match node.kind() {
+            "identifier" => self.context.get_or_unknown(&source[node.byte_range()]),
+            "parenthesized_expression" => node
+                .named_child(0)
+                .map(|inner| self.expression_type(&inner, source))
+                .unwrap_or_default(),
+            _ => Type::Unknown,
+        }
+    }
+
     /// Get all type errors
     pub fn errors(&self) -> &[TypeCheckError] {
         &self.errors
@@ -376,10 +500,116 @@ impl Default for TypeChecker {
     }
 }
 
+fn is_function(node: &Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_definition"
+            | "function_item"
+            | "function_declaration"
+            | "generator_function_declaration"
+            | "function_expression"
+            | "arrow_function"
+            | "method_definition"
+            | "method_declaration"
+            | "constructor_declaration"
+            | "func_literal"
+            | "lambda"
+            | "lambda_expression"
+            | "closure_expression"
+    )
+}
+
+/// The declared return type of a function node (`-> int`, `: string`,
+/// Go results, Java method types)
+fn declared_return_type(node: &Node, source: &str) -> Option<Type> {
+    let annotation = node
+        .child_by_field_name("return_type")
+        .or_else(|| node.child_by_field_name("result"))
+        .or_else(|| {
+            // Java puts the return type in `type`; C's `type` is not checked
+            // because the declarator can still make it a pointer
+            (node.kind() == "method_declaration")
+                .then(|| node.child_by_field_name("type"))
+                .flatten()
+        })?;
+    Some(Type::from_annotation(&source[annotation.byte_range()])).filter(|ty| !ty.is_unknown())
+}
+
+fn collect_calls<'a>(node: Node<'a>, out: &mut Vec<Node<'a>>) {
+    if matches!(node.kind(), "call" | "call_expression" | "method_invocation") {
+        out.push(node);
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_calls(child, out);
+    }
+}
+
+/// Callee name and positional arguments of a call to a plain name
+fn call_parts<'a, 's>(call: &Node<'a>, source: &'s str) -> Option<(&'s str, Vec<Node<'a>>)> {
+    // Java: `name(args)` without a receiver
+    let callee = if call.kind() == "method_invocation" {
+        if call.child_by_field_name("object").is_some() {
+            return None;
+        }
+        call.child_by_field_name("name")?
+    } else {
+        call.child_by_field_name("function")?
+    };
+    if callee.kind() != "identifier" {
+        return None;
+    }
+
+    let arguments = call.child_by_field_name("arguments")?;
+    let mut cursor = arguments.walk();
+    let args: Vec<Node> = arguments
+        .named_children(&mut cursor)
+        .filter(|arg| !matches!(arg.kind(), "comment" | "line_comment" | "block_comment"))
+        .collect();
+    let by_position = args.iter().all(|arg| {
+        !matches!(
+            arg.kind(),
+            "keyword_argument" | "list_splat" | "dictionary_splat" | "spread_element"
+        )
+    });
+    by_position.then(|| (&source[callee.byte_range()], args))
+}
+
+/// The expression a return statement returns, if any
+fn returned_value<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    let value = node
+        .named_children(&mut cursor)
+        .find(|child| !matches!(child.kind(), "comment" | "line_comment" | "block_comment"))?;
+    // Go: `return x` wraps a single value in an expression list
+    if value.kind() == "expression_list" {
+        return (value.named_child_count() == 1).then(|| value.named_child(0)).flatten();
+    }
+    Some(value)
+}
+
+/// `name = <value>` style declarations and assignments, typed by the
+/// value if it is a literal and `Unknown` otherwise, so a later
+/// `x = compute()` forgets an earlier `x = 1`
+fn value_binding(node: &Node, source: &str) -> Option<(String, Type)> {
+    let target = node
+        .child_by_field_name("left")
+        .or_else(|| node.child_by_field_name("name"))
+        .or_else(|| node.child_by_field_name("pattern"))
+        .filter(|n| n.kind() == "identifier")?;
+    let value = node
+        .child_by_field_name("right")
+        .or_else(|| node.child_by_field_name("value"))?;
+    let ty = infer_literal(&value, source).unwrap_or(Type::Unknown);
+    Some((source[target.byte_range()].to_string(), ty))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse;
     use logos_core::Position;
+    use logos_parser::LanguageId;
 
     fn test_range() -> Range {
         Range {
@@ -414,6 +644,7 @@ mod tests {
             ],
             return_type: Type::Int,
             variadic: false,
+            optional_params: 0,
         });
 
         // Valid call
@@ -426,6 +657,85 @@ mod tests {
         assert_eq!(checker.errors().len(), 1);
     }
 
+    #[test]
+    fn test_return_type_mismatch() {
+        let mut checker = TypeChecker::new();
+        let source = "def count() -> int:\n    return \"many\"\n";
+        checker.check_returns(&parse(source, LanguageId::Python).unwrap(), source);
+
+        assert_eq!(checker.errors().len(), 1);
+        let error = &checker.errors()[0];
+        assert_eq!(error.kind, TypeCheckErrorKind::ReturnTypeMismatch);
+        assert_eq!(error.expected, Some(Type::Int));
+        assert_eq!(error.actual, Some(Type::String));
+        assert_eq!(error.range.start, Position { line: 1, column: 11 });
+    }
+
+    #[test]
+    fn test_return_checks_use_local_literals() {
+        let mut checker = TypeChecker::new();
+        let source = "fn label() -> i32 {\n    let name = \"x\";\n    return name;\n}\n\nfn ratio() -> f64 {\n    return 1;\n}\n";
+        checker.check_returns(&parse(source, LanguageId::Rust).unwrap(), source);
+
+        assert_eq!(checker.errors().len(), 1);
+        assert_eq!(checker.errors()[0].actual, Some(Type::String));
+        assert_eq!(checker.errors()[0].range.start.line, 2);
+    }
+
+    #[test]
+    fn test_reassignment_clears_literal_type() {
+        let mut checker = TypeChecker::new();
+        let source = "function label(): string {\n    let x = 1;\n    x = compute();\n    return x;\n}\n";
+        checker.check_returns(&parse(source, LanguageId::TypeScript).unwrap(), source);
+        assert!(checker.errors().is_empty(), "{:?}", checker.errors());
+
+        let mut checker = TypeChecker::new();
+        let source = "def label() -> str:\n    x = compute()\n    x = 1\n    return x\n";
+        checker.check_returns(&parse(source, LanguageId::Python).unwrap(), source);
+        assert_eq!(checker.errors().len(), 1);
+        assert_eq!(checker.errors()[0].actual, Some(Type::Int));
+    }
+
+    #[test]
+    fn test_call_argument_types() {
+        let mut checker = TypeChecker::new();
+        let source = "add(1, \"two\")\nadd(1, 2)\nprint(\"unchecked\")\n";
+        checker.check_calls(&parse(source, LanguageId::Python).unwrap(), source, |name| {
+            (name == "add").then(|| FunctionSignature {
+                name: "add".to_string(),
+                params: vec![("a".to_string(), Type::Int), ("b".to_string(), Type::Int)],
+                return_type: Type::Int,
+                variadic: false,
+                optional_params: 0,
+            })
+        });
+
+        assert_eq!(checker.errors().len(), 1);
+        assert_eq!(checker.errors()[0].kind, TypeCheckErrorKind::TypeMismatch);
+        assert_eq!(checker.errors()[0].range.start, Position { line: 0, column: 0 });
+    }
+
+    #[test]
+    fn test_optional_params_arity() {
+        let mut checker = TypeChecker::new();
+        checker.register_function(FunctionSignature {
+            name: "pad".to_string(),
+            params: vec![("s".to_string(), Type::String), ("width".to_string(), Type::Int)],
+            return_type: Type::String,
+            variadic: false,
+            optional_params: 1,
+        });
+
+        checker.check_function_call("pad", &[Type::String], test_range());
+        checker.check_function_call("pad", &[Type::String, Type::Int], test_range());
+        assert!(checker.errors().is_empty());
+
+        checker.check_function_call("pad", &[], test_range());
+        assert_eq!(checker.errors().len(), 1);
+        assert_eq!(checker.errors()[0].kind, TypeCheckErrorKind::ArgumentCount);
+        assert!(checker.errors()[0].message.contains("1 to 2"));
+    }
+
     #[test]
     fn test_undefined_variable() {
         let mut checker = TypeChecker::new();
@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tree_sitter::Node;
 
 /// Represents a type in the type system
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -220,6 +221,51 @@ impl Type {
             other => other,
         }
     }
+
+    /// Map a written type name (`int`, `f64`, `string`, `boolean`, ...) to
+    /// a primitive type. Anything else, including generics and unions, is
+    /// `Unknown`, so checks against it never fail.
+    pub fn from_annotation(text: &str) -> Type {
+        match text.trim().trim_start_matches(':').trim() {
+            "int" | "long" | "short" | "byte" | "Integer" | "Long" | "Short" | "Byte" | "i8"
+            | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize" | "int8" | "int16" | "int32" | "int64" | "uint" | "uint8" | "uint16"
+            | "uint32" | "uint64" => Type::Int,
+            "float" | "double" | "Float" | "Double" | "f32" | "f64" | "float32" | "float64"
+            | "number" => Type::Float,
+            "str" | "String" | "string" | "&str" | "&'static str" => Type::String,
+            "bool" | "boolean" | "Boolean" => Type::Bool,
+            "None" | "void" | "()" => Type::Void,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+/// The type of a literal expression node, if it is one.
+///
+/// `null`/`None`/`nil` are not given a type, since whether they fit
+/// depends on nullability the checker doesn't track.
+pub fn infer_literal(node: &Node, source: &str) -> Option<Type> {
+    let ty = match node.kind() {
+        "string" | "string_literal" | "raw_string_literal" | "interpreted_string_literal"
+        | "template_string" | "concatenated_string" => Type::String,
+        "integer" | "integer_literal" | "int_literal" | "decimal_integer_literal"
+        | "hex_integer_literal" | "octal_integer_literal" | "binary_integer_literal" => Type::Int,
+        "float" | "float_literal" | "decimal_floating_point_literal" => Type::Float,
+        // JavaScript/TypeScript have a single number literal
+        "number" => {
+            let text = &source[node.byte_range()];
+            let is_hex = text.starts_with("0x") || text.starts_with("0X");
+            if !is_hex && text.contains(['.', 'e', 'E']) {
+                Type::Float
+            } else {
+                Type::Int
+            }
+        }
+        "true" | "false" | "boolean_literal" => Type::Bool,
+        _ => return None,
+    };
+    Some(ty)
 }
 
 
@@ -343,6 +389,13 @@ mod tests {
         assert!(!Type::Float.is_subtype_of(&union));
     }
 
+    #[test]
+    fn test_from_annotation() {
+        assert_eq!(Type::from_annotation("i32"), Type::Int);
+        assert_eq!(Type::from_annotation(": string"), Type::String);
+        assert_eq!(Type::from_annotation("Optional[int]"), Type::Unknown);
+    }
+
     #[test]
     fn test_context_scoping() {
         let mut ctx = TypeContext::new();
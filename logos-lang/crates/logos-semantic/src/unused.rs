@@ -1,10 +1,13 @@
 //! Unused code detection
 //!
-//! Detects unused variables, functions, imports, and parameters in source code.
+//! Detects unused variables, functions, imports, and parameters in source code,
+//! and statements that can never run.
 
 use logos_core::{Diagnostic, DiagnosticSeverity, Range, Symbol, SymbolKind};
+use logos_parser::node_to_range;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use tree_sitter::{Node, Tree};
 
 /// The kind of unused item
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -17,6 +20,7 @@ pub enum UnusedKind {
     Class,
     Constant,
     TypeAlias,
+    UnreachableCode,
 }
 
 impl UnusedKind {
@@ -29,6 +33,7 @@ impl UnusedKind {
             UnusedKind::Class => "class",
             UnusedKind::Constant => "constant",
             UnusedKind::TypeAlias => "type alias",
+            UnusedKind::UnreachableCode => "unreachable code",
         }
     }
 
@@ -36,7 +41,7 @@ impl UnusedKind {
     pub fn severity(&self) -> DiagnosticSeverity {
         match self {
             UnusedKind::Variable | UnusedKind::Parameter => DiagnosticSeverity::Hint,
-            UnusedKind::Import => DiagnosticSeverity::Warning,
+            UnusedKind::Import | UnusedKind::UnreachableCode => DiagnosticSeverity::Warning,
             UnusedKind::Function | UnusedKind::Class | UnusedKind::TypeAlias => {
                 DiagnosticSeverity::Hint
             }
@@ -63,7 +68,11 @@ pub struct UnusedItem {
 impl UnusedItem {
     /// Create a diagnostic for this unused item
     pub fn to_diagnostic(&self) -> Diagnostic {
-        let message = format!("Unused {}: '{}'", self.kind.as_str(), self.name);
+        let message = if self.kind == UnusedKind::UnreachableCode {
+            "Unreachable code".to_string()
+        } else {
+            format!("Unused {}: '{}'", self.kind.as_str(), self.name)
+        };
         let mut diagnostic = match self.kind.severity() {
             DiagnosticSeverity::Error => Diagnostic::error(self.range, message),
             DiagnosticSeverity::Warning => Diagnostic::warning(self.range, message),
@@ -71,7 +80,10 @@ impl UnusedItem {
             DiagnosticSeverity::Hint => Diagnostic::hint(self.range, message),
         };
         diagnostic.source = Some("logos-semantic".to_string());
-        diagnostic.code = Some(format!("unused-{}", self.kind.as_str()));
+        diagnostic.code = Some(match self.kind {
+            UnusedKind::UnreachableCode => "unreachable-code".to_string(),
+            kind => format!("unused-{}", kind.as_str()),
+        });
         diagnostic
     }
 }
@@ -197,16 +209,17 @@ impl UnusedDetector {
     fn collect_references(&mut self, source: &str) {
         // Simple word-based reference detection
         // A more accurate approach would use the AST
+        let mut counts: HashMap<&str, usize> = HashMap::new();
         for word in source.split(|c: char| !c.is_alphanumeric() && c != '_') {
             if !word.is_empty() && !self.should_ignore(word) {
-                // Check if this word is a defined symbol
-                if self.defined_symbols.contains_key(word) {
-                    // Count occurrences - if more than 1, it's used
-                    let count = source.matches(word).count();
-                    if count > 1 {
-                        self.mark_used(word);
-                    }
-                }
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+
+        for (word, count) in counts {
+            // A defined symbol that occurs more than once is used
+            if count > 1 && self.defined_symbols.contains_key(word) {
+                self.mark_used(word);
             }
         }
     }
@@ -246,6 +259,19 @@ impl UnusedDetector {
         unused
     }
 
+    /// Find statements that follow an unconditional `return`, `raise`,
+    /// `throw`, `break` or `continue` in the same block.
+    ///
+    /// Only the first unreachable statement of each block is reported.
+    /// Returns inside an `if` or loop don't make the code after the `if`
+    /// unreachable, and declarations that are hoisted or usable from
+    /// elsewhere (JS function declarations, Rust items, labels) are skipped.
+    pub fn detect_unreachable(&self, tree: &Tree, source: &str) -> Vec<UnusedItem> {
+        let mut unreachable = Vec::new();
+        collect_unreachable(tree.root_node(), source, &mut unreachable);
+        unreachable
+    }
+
     /// Generate diagnostics from analysis
     pub fn analyze_to_diagnostics(&mut self, symbols: &[Symbol], source: &str) -> Vec<Diagnostic> {
         self.analyze(symbols, source)
@@ -255,10 +281,89 @@ impl UnusedDetector {
     }
 }
 
+fn collect_unreachable(node: Node, source: &str, out: &mut Vec<UnusedItem>) {
+    if node.is_error() {
+        return;
+    }
+
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node
+        .named_children(&mut cursor)
+        .filter(|child| !is_comment(child))
+        .collect();
+
+    // Only statement lists run their children in order; an `if` whose
+    // consequence returns still runs its `else`
+    let is_statement_list = matches!(node.kind(), "statement_block" | "compound_statement" | "block");
+    let exit = children.iter().position(is_unconditional_exit).filter(|_| is_statement_list);
+    if let Some(exit) = exit {
+        if let Some(statement) = children[exit + 1..].iter().find(|c| !is_reachable_declaration(c)) {
+            out.push(UnusedItem {
+                kind: UnusedKind::UnreachableCode,
+                name: first_line(&source[statement.byte_range()]),
+                range: node_to_range(statement),
+                can_remove: true,
+                fix_action: Some("Remove unreachable code".to_string()),
+            });
+        }
+    }
+
+    for child in children {
+        collect_unreachable(child, source, out);
+    }
+}
+
+/// A statement that always leaves the enclosing block
+fn is_unconditional_exit(node: &Node) -> bool {
+    match node.kind() {
+        "return_statement" | "raise_statement" | "throw_statement" | "break_statement"
+        | "continue_statement" | "return_expression" | "break_expression" | "continue_expression" => true,
+        // Rust: `return x;`
+        "expression_statement" => node
+            .named_child(0)
+            .map(|expr| matches!(expr.kind(), "return_expression" | "break_expression" | "continue_expression"))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Statements that stay reachable after an exit: hoisted declarations,
+/// nested items and jump targets
+fn is_reachable_declaration(node: &Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_declaration"
+            | "generator_function_declaration"
+            | "function_item"
+            | "struct_item"
+            | "enum_item"
+            | "impl_item"
+            | "trait_item"
+            | "mod_item"
+            | "use_declaration"
+            | "const_item"
+            | "static_item"
+            | "type_item"
+            | "macro_definition"
+            | "labeled_statement"
+            | "empty_statement"
+    )
+}
+
+fn is_comment(node: &Node) -> bool {
+    matches!(node.kind(), "comment" | "line_comment" | "block_comment")
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or("").trim().to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse;
     use logos_core::Position;
+    use logos_parser::{LanguageId, LanguageParser};
 
     fn make_symbol(name: &str, kind: SymbolKind, line: u32) -> Symbol {
         Symbol {
@@ -317,4 +422,70 @@ mod tests {
         let unused = detector.analyze(&symbols, source);
         assert!(unused.is_empty());
     }
+
+    #[test]
+    fn test_walrus_binding_used_later() {
+        let source = "def first_long(lines):\n    if (n := len(lines)) > 10:\n        return lines[n - 1]\n    while (chunk := read()):\n        pass\n";
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Python).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let symbols = logos_parser::extract_symbols(LanguageId::Python, &tree, source);
+
+        let unused = UnusedDetector::new().analyze(&symbols, source);
+        let names: Vec<&str> = unused.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["first_long", "chunk"]);
+    }
+
+    #[test]
+    fn test_statement_after_return_is_unreachable() {
+        let detector = UnusedDetector::new();
+        let source = "def area(r):\n    return 3.14 * r * r\n    print('done')\n    r += 1\n";
+
+        let unreachable = detector.detect_unreachable(&parse(source, LanguageId::Python).unwrap(), source);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].kind, UnusedKind::UnreachableCode);
+        assert_eq!(unreachable[0].range.start, Position { line: 2, column: 4 });
+        let diagnostic = unreachable[0].to_diagnostic();
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostic.message, "Unreachable code");
+    }
+
+    #[test]
+    fn test_conditional_return_is_not_flagged() {
+        let detector = UnusedDetector::new();
+        let python = "def f(x):\n    if x:\n        return 1\n    return 2\n";
+        assert!(detector.detect_unreachable(&parse(python, LanguageId::Python).unwrap(), python).is_empty());
+
+        // Hoisted function declarations are callable from above the return
+        let js = "function f() {\n  return helper();\n  // helper\n  function helper() { return 1; }\n}\n";
+        assert!(detector.detect_unreachable(&parse(js, LanguageId::JavaScript).unwrap(), js).is_empty());
+
+        let js = "function g(xs) {\n  for (const x of xs) {\n    continue;\n    use(x);\n  }\n  throw new Error();\n}\n";
+        let unreachable = detector.detect_unreachable(&parse(js, LanguageId::JavaScript).unwrap(), js);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].range.start.line, 3);
+    }
+
+    #[test]
+    fn test_else_after_returning_if_is_reachable() {
+        let detector = UnusedDetector::new();
+        let cases = [
+            ("function f(x) {\n  if (x) return 1; else foo();\n  return 2;\n}\n", LanguageId::JavaScript),
+            ("int f(int x) {\n  if (x) return 1; else foo();\n  return 2;\n}\n", LanguageId::C),
+            ("class A {\n  int f(int x) {\n    if (x > 0) return 1; else foo();\n    return 2;\n  }\n}\n", LanguageId::Java),
+        ];
+        for (source, language) in cases {
+            let unreachable = detector.detect_unreachable(&parse(source, language).unwrap(), source);
+            assert!(unreachable.is_empty(), "{:?}: {:?}", language, unreachable);
+        }
+    }
+
+    #[test]
+    fn test_rust_return_expression() {
+        let detector = UnusedDetector::new();
+        let source = "fn f() -> u32 {\n    return 1;\n    let x = 2;\n}\n";
+        let unreachable = detector.detect_unreachable(&parse(source, LanguageId::Rust).unwrap(), source);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].name, "let x = 2;");
+    }
 }